@@ -0,0 +1,22 @@
+-- 账号备注
+ALTER TABLE emails ADD COLUMN notes TEXT;
+
+-- 创建标签（分组）表
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    color TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建账号与标签关联表（多对多）
+CREATE TABLE IF NOT EXISTS email_tags (
+    email_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (email_id, tag_id),
+    FOREIGN KEY (email_id) REFERENCES emails (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_tags_tag_id ON email_tags (tag_id);
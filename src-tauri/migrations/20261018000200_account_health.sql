@@ -0,0 +1,7 @@
+-- 账号健康状态
+ALTER TABLE emails ADD COLUMN health_status TEXT DEFAULT 'unknown';
+ALTER TABLE emails ADD COLUMN last_error TEXT;
+ALTER TABLE emails ADD COLUMN last_error_at TIMESTAMP;
+ALTER TABLE emails ADD COLUMN health_checked_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS idx_emails_health_status ON emails (health_status);
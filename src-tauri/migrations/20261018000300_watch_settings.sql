@@ -0,0 +1,3 @@
+-- 后台监听设置（enable_realtime_check 表示是否在启动时恢复监听）
+ALTER TABLE emails ADD COLUMN watch_interval_secs INTEGER;
+ALTER TABLE emails ADD COLUMN watch_folders TEXT;
@@ -0,0 +1,20 @@
+-- 邮件文件夹表：记录每个账号在服务器上的文件夹标识
+-- folder_key 为统一标识：常用文件夹为 inbox/junk/sent/drafts/deleted/archive，其余为小写路径
+CREATE TABLE IF NOT EXISTS mail_folders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    email_id INTEGER NOT NULL,
+    folder_key TEXT NOT NULL,
+    display_name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    parent_key TEXT,
+    graph_id TEXT,
+    imap_name TEXT,
+    special_use TEXT,
+    unread_count INTEGER,
+    total_count INTEGER,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (email_id, folder_key),
+    FOREIGN KEY (email_id) REFERENCES emails (id) ON DELETE CASCADE
+);
+
+-- 已有邮件的文件夹名称由 folders::backfill_folder_keys 在启动时统一为 folder_key（与 canonical_folder_key 使用同一份别名表）
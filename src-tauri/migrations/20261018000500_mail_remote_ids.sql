@@ -0,0 +1,8 @@
+-- 邮件的服务器标识与状态，用于在服务器上执行标记、移动、删除等操作
+ALTER TABLE mail_records ADD COLUMN graph_id TEXT;
+ALTER TABLE mail_records ADD COLUMN imap_uid INTEGER;
+ALTER TABLE mail_records ADD COLUMN message_id TEXT;
+ALTER TABLE mail_records ADD COLUMN is_read INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE mail_records ADD COLUMN is_flagged INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_mail_records_email_folder ON mail_records (email_id, folder);
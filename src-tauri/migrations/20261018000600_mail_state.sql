@@ -0,0 +1,6 @@
+-- 邮件状态：已答复、重要性与类别（已读/旗标见 20261018000500）
+ALTER TABLE mail_records ADD COLUMN is_answered INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE mail_records ADD COLUMN importance TEXT;
+ALTER TABLE mail_records ADD COLUMN categories TEXT NOT NULL DEFAULT '[]';
+
+CREATE INDEX IF NOT EXISTS idx_mail_records_unread ON mail_records (email_id, folder, is_read);
@@ -0,0 +1,35 @@
+-- 附件内容按 SHA-256 存储在应用数据目录的 blobs 中，相同内容只保存一份
+-- ref_count 由 attachments 表上的触发器维护，降为 0 后由垃圾回收删除文件
+CREATE TABLE IF NOT EXISTS attachment_blobs (
+    hash TEXT PRIMARY KEY,
+    size INTEGER NOT NULL,
+    ref_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE attachments ADD COLUMN blob_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_attachments_blob_hash ON attachments (blob_hash);
+CREATE INDEX IF NOT EXISTS idx_attachment_blobs_ref_count ON attachment_blobs (ref_count);
+
+CREATE TRIGGER IF NOT EXISTS trg_attachments_blob_insert
+AFTER INSERT ON attachments
+WHEN NEW.blob_hash IS NOT NULL
+BEGIN
+    UPDATE attachment_blobs SET ref_count = ref_count + 1 WHERE hash = NEW.blob_hash;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_attachments_blob_delete
+AFTER DELETE ON attachments
+WHEN OLD.blob_hash IS NOT NULL
+BEGIN
+    UPDATE attachment_blobs SET ref_count = ref_count - 1 WHERE hash = OLD.blob_hash;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_attachments_blob_update
+AFTER UPDATE OF blob_hash ON attachments
+WHEN OLD.blob_hash IS NOT NEW.blob_hash
+BEGIN
+    UPDATE attachment_blobs SET ref_count = ref_count - 1 WHERE hash = OLD.blob_hash;
+    UPDATE attachment_blobs SET ref_count = ref_count + 1 WHERE hash = NEW.blob_hash;
+END;
@@ -0,0 +1,7 @@
+-- 附件按需下载：收件时只保存元数据与服务器引用，内容在首次查看时下载并缓存
+-- remote_source 为 graph / imap；remote_ref 为 Graph 附件 ID 或 IMAP 段号（TEXT 表示单段邮件正文）
+ALTER TABLE attachments ADD COLUMN remote_source TEXT;
+ALTER TABLE attachments ADD COLUMN remote_ref TEXT;
+
+-- 账号级自动下载策略：小于该大小（MB）的附件在收件后自动下载，NULL 表示不自动下载
+ALTER TABLE emails ADD COLUMN attachment_auto_download_mb INTEGER;
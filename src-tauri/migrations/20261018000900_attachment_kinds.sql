@@ -0,0 +1,4 @@
+-- 附件类型：file（文件）、item（嵌入的邮件/日程，保存为 .eml）、reference（云附件链接）
+ALTER TABLE attachments ADD COLUMN kind TEXT NOT NULL DEFAULT 'file';
+-- 云附件的原始链接
+ALTER TABLE attachments ADD COLUMN source_url TEXT;
@@ -0,0 +1,22 @@
+-- 审计日志：记录操作用户与受影响的 ID（JSON 数组）
+ALTER TABLE audit_log ADD COLUMN user_id INTEGER;
+ALTER TABLE audit_log ADD COLUMN target_ids TEXT;
+ALTER TABLE audit_log ADD COLUMN target_count INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_user_id ON audit_log (user_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_email_id ON audit_log (email_id);
+
+-- 审计日志只允许追加：禁止修改，只允许删除超过保留期限的记录（保留天数为 0 表示永久保留）
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update
+BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_retention_only
+BEFORE DELETE ON audit_log
+WHEN COALESCE((SELECT CAST(value AS INTEGER) FROM system_config WHERE key = 'audit.retention_days'), 365) <= 0
+    OR old.created_at >= datetime('now', '-' || COALESCE((SELECT CAST(value AS INTEGER) FROM system_config WHERE key = 'audit.retention_days'), 365) || ' days')
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
@@ -0,0 +1,9 @@
+-- 本地回收站：用户删除与同步删除只标记删除时间与原因，保留期满后再由定时任务彻底清除
+ALTER TABLE emails ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE emails ADD COLUMN deleted_reason TEXT;
+
+ALTER TABLE mail_records ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE mail_records ADD COLUMN deleted_reason TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_emails_deleted_at ON emails (deleted_at);
+CREATE INDEX IF NOT EXISTS idx_mail_records_deleted_at ON mail_records (deleted_at);
@@ -0,0 +1,33 @@
+-- 收件规则：新邮件入库后按优先级依次匹配条件并执行动作（条件与动作以 JSON 保存）
+CREATE TABLE IF NOT EXISTS mail_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    priority INTEGER NOT NULL DEFAULT 0,
+    match_all INTEGER NOT NULL DEFAULT 1,
+    stop_processing INTEGER NOT NULL DEFAULT 0,
+    conditions TEXT NOT NULL DEFAULT '[]',
+    actions TEXT NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_mail_rules_user_id ON mail_rules (user_id, priority);
+
+-- 规则命中记录：提取到的验证码与各动作的执行结果
+CREATE TABLE IF NOT EXISTS mail_rule_hits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rule_id INTEGER NOT NULL,
+    mail_id INTEGER NOT NULL,
+    email_id INTEGER NOT NULL,
+    extracted_code TEXT,
+    results TEXT NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (rule_id) REFERENCES mail_rules (id) ON DELETE CASCADE,
+    FOREIGN KEY (mail_id) REFERENCES mail_records (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_mail_rule_hits_rule_id ON mail_rule_hits (rule_id);
+CREATE INDEX IF NOT EXISTS idx_mail_rule_hits_mail_id ON mail_rule_hits (mail_id);
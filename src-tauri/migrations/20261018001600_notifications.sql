@@ -0,0 +1,25 @@
+-- 桌面通知设置（每个用户一行，缺省时使用默认值）；免打扰时段为本地时间 HH:MM，可跨零点
+CREATE TABLE IF NOT EXISTS notification_settings (
+    user_id INTEGER PRIMARY KEY,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    quiet_start TEXT,
+    quiet_end TEXT,
+    group_threshold INTEGER NOT NULL DEFAULT 3,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+-- 通知静音：按账号或按发件人（完整地址或 @域名），muted_until 为空表示一直静音
+CREATE TABLE IF NOT EXISTS notification_mutes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    email_id INTEGER,
+    sender TEXT,
+    muted_until TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (email_id) REFERENCES emails (id) ON DELETE CASCADE,
+    CHECK ((email_id IS NULL) != (sender IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_mutes_user_id ON notification_mutes (user_id);
@@ -0,0 +1,103 @@
+-- 标签归属用户：名称改为按用户唯一（SQLite 无法删除 UNIQUE 约束，需重建表）
+CREATE TABLE tags_owned (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER REFERENCES users (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    color TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, name)
+);
+
+-- 各用户对已有标签的引用：账号关联、按标签限定的 Webhook、按标签匹配的收件规则
+CREATE TEMP TABLE tag_refs_migrating AS
+SELECT et.tag_id, e.user_id, 0 AS via_rule
+FROM email_tags et
+JOIN emails e ON e.id = et.email_id
+UNION
+SELECT w.tag_id, w.user_id, 1 FROM webhooks w WHERE w.tag_id IS NOT NULL
+UNION
+SELECT json_extract(c.value, '$.tag_id'), r.user_id, 1
+FROM mail_rules r, json_each(r.conditions) c
+WHERE json_extract(c.value, '$.type') = 'account_tag';
+
+-- 已有标签归属于使用它的账号所属用户（其次是引用它的 Webhook 或规则所属用户，都没有时归第一个用户），
+-- 其他用户也引用时为其复制一份
+INSERT INTO tags_owned (id, user_id, name, color, created_at)
+SELECT t.id,
+       COALESCE(
+           (SELECT MIN(user_id) FROM tag_refs_migrating WHERE tag_id = t.id AND via_rule = 0),
+           (SELECT MIN(user_id) FROM tag_refs_migrating WHERE tag_id = t.id),
+           (SELECT MIN(id) FROM users)
+       ),
+       t.name, t.color, t.created_at
+FROM tags t;
+
+INSERT INTO tags_owned (user_id, name, color, created_at)
+SELECT DISTINCT r.user_id, t.name, t.color, t.created_at
+FROM tag_refs_migrating r
+JOIN tags t ON t.id = r.tag_id
+JOIN tags_owned o ON o.id = t.id
+WHERE r.user_id IS NOT NULL AND r.user_id IS NOT o.user_id;
+
+-- 规则条件中的标签 ID 改为规则所属用户的那一份
+UPDATE mail_rules
+SET conditions = (
+    SELECT json_group_array(
+        CASE
+            WHEN json_extract(c.value, '$.type') = 'account_tag' THEN json_set(
+                c.value,
+                '$.tag_id',
+                COALESCE(
+                    (SELECT o.id
+                     FROM tags t
+                     JOIN tags_owned o ON o.name = t.name AND o.user_id = mail_rules.user_id
+                     WHERE t.id = json_extract(c.value, '$.tag_id')),
+                    json_extract(c.value, '$.tag_id')
+                )
+            )
+            ELSE json(c.value)
+        END
+    )
+    FROM json_each(mail_rules.conditions) c
+)
+WHERE EXISTS (
+    SELECT 1 FROM json_each(mail_rules.conditions) c
+    WHERE json_extract(c.value, '$.type') = 'account_tag'
+);
+
+-- 删除旧表会级联清空 email_tags 并删除按标签限定的 Webhook，先按标签名称保存关联
+CREATE TEMP TABLE email_tags_migrating AS
+SELECT et.email_id, e.user_id, t.name, et.created_at
+FROM email_tags et
+JOIN emails e ON e.id = et.email_id
+JOIN tags t ON t.id = et.tag_id;
+
+CREATE TEMP TABLE webhook_tags_migrating AS
+SELECT w.id AS webhook_id, w.user_id, t.name
+FROM webhooks w
+JOIN tags t ON t.id = w.tag_id;
+
+UPDATE webhooks SET tag_id = NULL WHERE tag_id IS NOT NULL;
+
+DROP TABLE tags;
+ALTER TABLE tags_owned RENAME TO tags;
+
+INSERT OR IGNORE INTO email_tags (email_id, tag_id, created_at)
+SELECT m.email_id, t.id, m.created_at
+FROM email_tags_migrating m
+JOIN tags t ON t.name = m.name AND t.user_id IS m.user_id;
+
+UPDATE webhooks
+SET tag_id = (
+    SELECT t.id
+    FROM webhook_tags_migrating m
+    JOIN tags t ON t.name = m.name AND t.user_id = m.user_id
+    WHERE m.webhook_id = webhooks.id
+)
+WHERE id IN (SELECT webhook_id FROM webhook_tags_migrating);
+
+DROP TABLE email_tags_migrating;
+DROP TABLE webhook_tags_migrating;
+DROP TABLE tag_refs_migrating;
+
+CREATE INDEX IF NOT EXISTS idx_tags_user_id ON tags (user_id);
@@ -0,0 +1,4 @@
+-- 管理员：可备份/恢复/整理数据库、管理保险库与全局设置；已有安装中第一个注册的用户成为管理员
+ALTER TABLE users ADD COLUMN is_admin INTEGER NOT NULL DEFAULT 0;
+
+UPDATE users SET is_admin = 1 WHERE id = (SELECT MIN(id) FROM users);
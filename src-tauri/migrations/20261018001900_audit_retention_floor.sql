@@ -0,0 +1,14 @@
+-- 审计日志至少保留 30 天：已有的更短设置提升到 30 天，触发器同样按不少于 30 天判断
+UPDATE system_config
+SET value = '30', updated_at = CURRENT_TIMESTAMP
+WHERE key = 'audit.retention_days' AND CAST(value AS INTEGER) BETWEEN 1 AND 29;
+
+DROP TRIGGER IF EXISTS audit_log_retention_only;
+
+CREATE TRIGGER audit_log_retention_only
+BEFORE DELETE ON audit_log
+WHEN COALESCE((SELECT CAST(value AS INTEGER) FROM system_config WHERE key = 'audit.retention_days'), 365) <= 0
+    OR old.created_at >= datetime('now', '-' || MAX(COALESCE((SELECT CAST(value AS INTEGER) FROM system_config WHERE key = 'audit.retention_days'), 365), 30) || ' days')
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
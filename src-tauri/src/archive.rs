@@ -0,0 +1,928 @@
+//! 邮箱归档模块
+//! 将本地邮件（mail_records + attachments）导出为 mbox / Maildir，没有原始邮件时按记录重建 MIME；
+//! 也可将 mbox / Maildir / .eml 导入到本地账号，使归档邮件仍可在应用中检索
+
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use mailparse::MailHeaderMap;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::attachments;
+use crate::audit::{self, AuditEvent};
+use crate::email;
+use crate::folders;
+use crate::mail_state::MailState;
+
+/// 本地账号类型（导入的归档，不连接服务器）
+pub const MAIL_TYPE_LOCAL: &str = "local";
+
+/// 结果中保留的错误信息条数
+const MAX_REPORTED_ERRORS: usize = 50;
+
+/// 导入时解析线程与写库之间的缓冲邮件数
+const IMPORT_CHANNEL_SIZE: usize = 16;
+
+/// 归档格式
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailboxFormat {
+    /// 每个文件夹一个 .mbox 文件（mboxrd）
+    Mbox,
+    /// 每个文件夹一个 Maildir 目录
+    Maildir,
+}
+
+/// 导出范围，均为空时导出全部邮件
+#[derive(Debug, Default, Deserialize)]
+pub struct MailboxSelection {
+    #[serde(default)]
+    pub email_ids: Vec<i64>,
+    /// 文件夹统一标识（如 inbox、sent）
+    #[serde(default)]
+    pub folders: Vec<String>,
+    /// 仅包含该用户的账号（由后端根据会话设置）
+    #[serde(skip)]
+    pub user_id: Option<i64>,
+}
+
+/// 导出结果
+#[derive(Debug, Serialize)]
+pub struct MailboxExportResult {
+    pub exported: usize,
+    pub failed: usize,
+    pub target: String,
+    pub errors: Vec<String>,
+}
+
+/// 导入结果
+#[derive(Debug, Serialize)]
+pub struct MailboxImportResult {
+    pub email_id: i64,
+    pub imported: usize,
+    /// 已存在而跳过的邮件数
+    pub skipped: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+}
+
+/// 待导出的邮件记录
+#[derive(Debug, sqlx::FromRow)]
+struct ExportMail {
+    id: i64,
+    email_id: i64,
+    account: String,
+    subject: Option<String>,
+    sender: Option<String>,
+    received_time: Option<String>,
+    content: Option<String>,
+    folder: String,
+    message_id: Option<String>,
+    is_read: bool,
+    is_flagged: bool,
+    is_answered: bool,
+    importance: Option<String>,
+}
+
+/// 重建 MIME 时使用的附件
+struct ExportAttachment {
+    filename: String,
+    content_type: String,
+    content: Vec<u8>,
+}
+
+/// 导出选中的邮件到目标目录：mbox 为 <账号>/<文件夹>.mbox，Maildir 为 <账号>/<文件夹>/{cur,new,tmp}
+pub async fn export_mailbox(
+    pool: &Pool<Sqlite>,
+    selection: &MailboxSelection,
+    target: &Path,
+    format: MailboxFormat,
+) -> Result<MailboxExportResult> {
+    let mails = query_export_mails(pool, selection).await?;
+    if mails.is_empty() {
+        return Err(anyhow!("没有符合条件的邮件"));
+    }
+    tokio::fs::create_dir_all(target).await?;
+
+    let mut exported_ids = Vec::new();
+    let mut errors = Vec::new();
+    // 当前写入的 mbox 文件（按账号、文件夹分组）
+    let mut current: Option<((i64, String), tokio::fs::File)> = None;
+
+    for mail in &mails {
+        let mail_attachments = match load_attachments(pool, mail.id).await {
+            Ok(attachments) => attachments,
+            Err(e) => {
+                push_error(&mut errors, format!("{}: {}", display_subject(mail), e));
+                continue;
+            }
+        };
+        let message = build_mime(mail, &mail_attachments);
+        let account_dir = target.join(attachments::sanitize_filename(&mail.account));
+        let folder_name = attachments::sanitize_filename(&mail.folder);
+
+        let written = match format {
+            MailboxFormat::Mbox => {
+                let group = (mail.email_id, mail.folder.clone());
+                if current.as_ref().map(|(key, _)| key) != Some(&group) {
+                    if let Some((_, mut file)) = current.take() {
+                        file.flush().await?;
+                    }
+                    tokio::fs::create_dir_all(&account_dir).await?;
+                    let path = account_dir.join(format!("{}.mbox", folder_name));
+                    current = Some((group, tokio::fs::File::create(&path).await?));
+                }
+                let (_, file) = current.as_mut().expect("mbox 文件已打开");
+                file.write_all(&mbox_entry(mail, &message)).await
+            }
+            MailboxFormat::Maildir => {
+                write_maildir_message(&account_dir.join(&folder_name), mail, &message).await
+            }
+        };
+
+        match written {
+            Ok(()) => exported_ids.push(mail.id),
+            Err(e) => push_error(&mut errors, format!("{}: {}", display_subject(mail), e)),
+        }
+    }
+
+    if let Some((_, mut file)) = current.take() {
+        file.flush().await?;
+    }
+
+    let exported = exported_ids.len();
+    audit::record(
+        pool,
+        audit::ACTION_MAILBOX_EXPORT,
+        AuditEvent {
+            user_id: selection.user_id,
+            target_ids: &exported_ids,
+            detail: Some(format!("format={:?}", format).to_lowercase()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(MailboxExportResult {
+        exported,
+        failed: mails.len() - exported,
+        target: target.to_string_lossy().into_owned(),
+        errors,
+    })
+}
+
+/// 查询待导出的邮件，按账号、文件夹、时间排序
+async fn query_export_mails(
+    pool: &Pool<Sqlite>,
+    selection: &MailboxSelection,
+) -> Result<Vec<ExportMail>> {
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT m.id, m.email_id, e.email AS account, m.subject, m.sender, m.received_time, m.content, COALESCE(m.folder, 'inbox') AS folder, m.message_id, m.is_read, m.is_flagged, m.is_answered, m.importance FROM mail_records m JOIN emails e ON e.id = m.email_id WHERE m.deleted_at IS NULL AND e.deleted_at IS NULL",
+    );
+    if !selection.email_ids.is_empty() {
+        qb.push(" AND m.email_id IN (");
+        let mut separated = qb.separated(", ");
+        for id in &selection.email_ids {
+            separated.push_bind(id);
+        }
+        qb.push(")");
+    }
+    if let Some(user_id) = selection.user_id {
+        qb.push(" AND e.user_id = ");
+        qb.push_bind(user_id);
+    }
+    if !selection.folders.is_empty() {
+        qb.push(" AND COALESCE(m.folder, 'inbox') IN (");
+        let mut separated = qb.separated(", ");
+        for folder in &selection.folders {
+            separated.push_bind(folders::canonical_folder_key(folder));
+        }
+        qb.push(")");
+    }
+    qb.push(" ORDER BY m.email_id, folder, m.received_time, m.id");
+
+    let mails = qb.build_query_as::<ExportMail>().fetch_all(pool).await?;
+    Ok(mails)
+}
+
+/// 读取邮件的附件内容，未下载的附件先按需下载
+async fn load_attachments(pool: &Pool<Sqlite>, mail_id: i64) -> Result<Vec<ExportAttachment>> {
+    let rows = sqlx::query_as::<_, (i64, Option<String>, Option<String>)>(
+        "SELECT id, filename, content_type FROM attachments WHERE mail_id = ? ORDER BY id",
+    )
+    .bind(mail_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut attachments = Vec::with_capacity(rows.len());
+    for (attachment_id, filename, content_type) in rows {
+        let (_, path) = attachments::ensure_local(pool, attachment_id).await?;
+        attachments.push(ExportAttachment {
+            filename: filename.unwrap_or_else(|| "attachment".to_string()),
+            content_type: content_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+            content: tokio::fs::read(&path).await?,
+        });
+    }
+    Ok(attachments)
+}
+
+/// 按邮件记录重建 MIME 邮件（LF 换行）
+fn build_mime(mail: &ExportMail, attachments: &[ExportAttachment]) -> Vec<u8> {
+    let mut out = String::new();
+    let sender = mail.sender.as_deref().unwrap_or(&mail.account);
+    out.push_str(&format!("From: {}\n", encode_address(sender)));
+    out.push_str(&format!("To: {}\n", mail.account));
+    if let Some(subject) = &mail.subject {
+        out.push_str(&format!("Subject: {}\n", encode_header_text(subject)));
+    }
+    if let Some(date) = mail.received_time.as_deref().and_then(parse_time) {
+        out.push_str(&format!("Date: {}\n", date.to_rfc2822()));
+    }
+    match &mail.message_id {
+        Some(message_id) => out.push_str(&format!("Message-ID: {}\n", message_id)),
+        None => out.push_str(&format!("Message-ID: <flaremail.{}@localhost>\n", mail.id)),
+    }
+    if let Some(importance) = &mail.importance {
+        out.push_str(&format!("Importance: {}\n", importance));
+    }
+    out.push_str(&format!("X-FlareMail-Folder: {}\n", mail.folder));
+    out.push_str("MIME-Version: 1.0\n");
+
+    let content = mail.content.as_deref().unwrap_or("");
+    let subtype = if looks_like_html(content) {
+        "html"
+    } else {
+        "plain"
+    };
+    let body_part = format!(
+        "Content-Type: text/{}; charset=utf-8\nContent-Transfer-Encoding: base64\n\n{}",
+        subtype,
+        wrap_base64(content.as_bytes())
+    );
+
+    if attachments.is_empty() {
+        out.push_str(&body_part);
+        return out.into_bytes();
+    }
+
+    let boundary = format!("----=_FlareMail_{}_{:08x}", mail.id, rand::random::<u32>());
+    out.push_str(&format!(
+        "Content-Type: multipart/mixed; boundary=\"{}\"\n\n",
+        boundary
+    ));
+    out.push_str("This is a multi-part message in MIME format.\n");
+    out.push_str(&format!("\n--{}\n{}", boundary, body_part));
+    for attachment in attachments {
+        out.push_str(&format!("\n--{}\n", boundary));
+        out.push_str(&format!(
+            "Content-Type: {}; {}\n",
+            attachment.content_type,
+            mime_param("name", &attachment.filename)
+        ));
+        out.push_str(&format!(
+            "Content-Disposition: attachment; {}\n",
+            mime_param("filename", &attachment.filename)
+        ));
+        out.push_str("Content-Transfer-Encoding: base64\n\n");
+        out.push_str(&wrap_base64(&attachment.content));
+    }
+    out.push_str(&format!("\n--{}--\n", boundary));
+    out.into_bytes()
+}
+
+/// mbox 条目：From_ 分隔行、状态头部与 mboxrd 转义后的邮件
+fn mbox_entry(mail: &ExportMail, message: &[u8]) -> Vec<u8> {
+    let envelope = mail
+        .sender
+        .as_deref()
+        .and_then(extract_address)
+        .unwrap_or_else(|| "MAILER-DAEMON".to_string());
+    let date = mail
+        .received_time
+        .as_deref()
+        .and_then(parse_time)
+        .unwrap_or_else(Utc::now);
+
+    let mut entry = format!(
+        "From {} {}\n",
+        envelope,
+        date.format("%a %b %e %H:%M:%S %Y")
+    );
+    entry.push_str(if mail.is_read {
+        "Status: RO\n"
+    } else {
+        "Status: O\n"
+    });
+    let mut x_status = String::new();
+    if mail.is_answered {
+        x_status.push('A');
+    }
+    if mail.is_flagged {
+        x_status.push('F');
+    }
+    if !x_status.is_empty() {
+        entry.push_str(&format!("X-Status: {}\n", x_status));
+    }
+
+    let mut entry = entry.into_bytes();
+    entry.extend_from_slice(&mboxrd_escape(message));
+    if !entry.ends_with(b"\n") {
+        entry.push(b'\n');
+    }
+    entry.push(b'\n');
+    entry
+}
+
+/// 写入 Maildir：已读邮件放入 cur 并带 :2, 标志，未读邮件放入 new
+async fn write_maildir_message(
+    dir: &Path,
+    mail: &ExportMail,
+    message: &[u8],
+) -> std::io::Result<()> {
+    for sub in ["cur", "new", "tmp"] {
+        tokio::fs::create_dir_all(dir.join(sub)).await?;
+    }
+
+    let timestamp = mail
+        .received_time
+        .as_deref()
+        .and_then(parse_time)
+        .unwrap_or_else(Utc::now)
+        .timestamp();
+    let unique = format!(
+        "{}.M{}P{}.flaremail",
+        timestamp,
+        mail.id,
+        std::process::id()
+    );
+    let flags = maildir_flags(&MailState {
+        is_read: mail.is_read,
+        is_flagged: mail.is_flagged,
+        is_answered: mail.is_answered,
+        ..MailState::default()
+    });
+    let (sub, name) = if mail.is_read || !flags.is_empty() {
+        ("cur", format!("{}:2,{}", unique, flags))
+    } else {
+        ("new", unique.clone())
+    };
+
+    // 先写入 tmp 再移动，避免其他程序读到不完整的邮件
+    let tmp_path = dir.join("tmp").join(&unique);
+    tokio::fs::write(&tmp_path, message).await?;
+    tokio::fs::rename(&tmp_path, dir.join(sub).join(name)).await
+}
+
+/// 导入 mbox / Maildir / .eml 到本地账号；path 可以是文件或包含归档的目录
+pub async fn import_mailbox(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    path: &Path,
+    account: &str,
+) -> Result<MailboxImportResult> {
+    if !path.exists() {
+        return Err(anyhow!("路径不存在: {}", path.display()));
+    }
+    let email_id = local_account_id(pool, user_id, account).await?;
+
+    // 解析在阻塞线程中进行，逐封邮件交给异步任务写库，避免一次读入整个归档
+    let (sender, mut receiver) = mpsc::channel::<Result<ImportItem, String>>(IMPORT_CHANNEL_SIZE);
+    let root = path.to_path_buf();
+    let producer = tokio::task::spawn_blocking(move || scan_archive(&root, &sender));
+
+    let mut result = MailboxImportResult {
+        email_id,
+        imported: 0,
+        skipped: 0,
+        failed: 0,
+        errors: Vec::new(),
+    };
+    while let Some(item) = receiver.recv().await {
+        let outcome = match item {
+            Ok(item) => email::import_raw_mail(pool, email_id, &item.raw, &item.folder, item.state)
+                .await
+                .map_err(|e| format!("{}: {}", item.source, e)),
+            Err(e) => Err(e),
+        };
+        match outcome {
+            Ok(true) => result.imported += 1,
+            Ok(false) => result.skipped += 1,
+            Err(e) => {
+                result.failed += 1;
+                push_error(&mut result.errors, e);
+            }
+        }
+    }
+    producer.await?;
+
+    audit::record(
+        pool,
+        audit::ACTION_MAILBOX_IMPORT,
+        AuditEvent {
+            user_id: Some(user_id),
+            email_id: Some(email_id),
+            target_ids: &[email_id],
+            detail: Some(format!(
+                "imported={}, skipped={}, failed={}",
+                result.imported, result.skipped, result.failed
+            )),
+        },
+    )
+    .await?;
+
+    log::info!(
+        "邮箱归档导入完成: account={}, imported={}, skipped={}, failed={}",
+        account,
+        result.imported,
+        result.skipped,
+        result.failed
+    );
+    Ok(result)
+}
+
+/// 是否为本地账号（本地账号没有服务器，不参与收件、监听与验证）
+pub async fn is_local_account(pool: &Pool<Sqlite>, email_id: i64) -> Result<bool> {
+    let mail_type =
+        sqlx::query_scalar::<_, Option<String>>("SELECT mail_type FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_optional(pool)
+            .await?
+            .flatten();
+    Ok(mail_type.as_deref() == Some(MAIL_TYPE_LOCAL))
+}
+
+/// 获取或创建本地账号；同名的非本地账号或其他用户的账号不允许导入
+async fn local_account_id(pool: &Pool<Sqlite>, user_id: i64, account: &str) -> Result<i64> {
+    let account = account.trim();
+    if account.is_empty() {
+        return Err(anyhow!("账号名称不能为空"));
+    }
+
+    let existing = sqlx::query_as::<_, (i64, Option<String>, Option<i64>, bool)>(
+        "SELECT id, mail_type, user_id, deleted_at IS NOT NULL FROM emails WHERE email = ?",
+    )
+    .bind(account)
+    .fetch_optional(pool)
+    .await?;
+
+    match existing {
+        Some((_, _, owner, true)) if owner == Some(user_id) => {
+            Err(anyhow!("账号 {} 在回收站中，请先恢复", account))
+        }
+        Some((id, Some(mail_type), owner, false))
+            if mail_type == MAIL_TYPE_LOCAL && owner == Some(user_id) =>
+        {
+            Ok(id)
+        }
+        Some(_) => Err(anyhow!("账号 {} 已存在且不是当前用户的本地账号", account)),
+        None => email::add_email(pool, user_id, account, "", "", "", Some(MAIL_TYPE_LOCAL)).await,
+    }
+}
+
+/// 待导入的一封邮件
+struct ImportItem {
+    raw: Vec<u8>,
+    folder: String,
+    state: MailState,
+    /// 来源（文件路径），用于错误信息
+    source: String,
+}
+
+type ImportSender = mpsc::Sender<Result<ImportItem, String>>;
+
+/// 扫描归档路径并逐封发送；接收端关闭时提前结束
+fn scan_archive(path: &Path, sender: &ImportSender) {
+    if path.is_file() {
+        let default_folder = if is_eml(path) {
+            "inbox".to_string()
+        } else {
+            file_stem(path)
+        };
+        let sent = if is_eml(path) {
+            send_eml(path, &default_folder, sender)
+        } else {
+            send_mbox(path, &default_folder, sender)
+        };
+        if let Err(e) = sent {
+            let _ = sender.blocking_send(Err(format!("{}: {}", path.display(), e)));
+        }
+        return;
+    }
+
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if is_maildir(&dir) {
+            let folder = maildir_folder(path, &dir);
+            if let Err(e) = send_maildir(&dir, &folder, sender) {
+                let _ = sender.blocking_send(Err(format!("{}: {}", dir.display(), e)));
+            }
+        }
+
+        let entries = match read_dir_sorted(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                let _ = sender.blocking_send(Err(format!("{}: {}", dir.display(), e)));
+                continue;
+            }
+        };
+        for entry in entries {
+            if entry.is_dir() {
+                // 不跟随指向目录的符号链接，避免链接成环时无限遍历
+                if entry.is_symlink() {
+                    continue;
+                }
+                let name = entry.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                if !matches!(name, "cur" | "new" | "tmp") {
+                    pending.push(entry);
+                }
+                continue;
+            }
+            let sent = match extension(&entry).as_str() {
+                "mbox" | "mbx" => send_mbox(&entry, &file_stem(&entry), sender),
+                "eml" => send_eml(&entry, &parent_folder(path, &entry), sender),
+                _ => Ok(()),
+            };
+            if let Err(e) = sent {
+                let _ = sender.blocking_send(Err(format!("{}: {}", entry.display(), e)));
+            }
+        }
+        if sender.is_closed() {
+            return;
+        }
+    }
+}
+
+/// 发送 Maildir 中的邮件，标志从文件名 :2, 后缀解析
+fn send_maildir(dir: &Path, folder: &str, sender: &ImportSender) -> Result<()> {
+    for sub in ["new", "cur"] {
+        let sub_dir = dir.join(sub);
+        if !sub_dir.is_dir() {
+            continue;
+        }
+        for file in read_dir_sorted(&sub_dir)? {
+            if !file.is_file() {
+                continue;
+            }
+            let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let state = parse_maildir_flags(name);
+            let raw = std::fs::read(&file)?;
+            send_item(sender, raw, folder, state, &file)?;
+        }
+    }
+    Ok(())
+}
+
+/// 发送 mbox 中的邮件，按 From_ 行切分并还原 mboxrd 转义
+fn send_mbox(path: &Path, folder: &str, sender: &ImportSender) -> Result<()> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    let mut message: Option<Vec<u8>> = None;
+    let mut previous_blank = true;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if previous_blank && line.starts_with(b"From ") {
+            if let Some(raw) = message.take() {
+                send_mbox_message(raw, folder, sender, path)?;
+            }
+            message = Some(Vec::new());
+            previous_blank = false;
+            continue;
+        }
+        previous_blank = line == b"\n" || line == b"\r\n";
+        if let Some(raw) = message.as_mut() {
+            raw.extend_from_slice(mboxrd_unescape_line(&line));
+        }
+    }
+    if let Some(raw) = message {
+        send_mbox_message(raw, folder, sender, path)?;
+    }
+    Ok(())
+}
+
+fn send_mbox_message(
+    mut raw: Vec<u8>,
+    folder: &str,
+    sender: &ImportSender,
+    path: &Path,
+) -> Result<()> {
+    // 去掉条目之间的分隔空行
+    if raw.ends_with(b"\r\n") {
+        raw.truncate(raw.len() - 2);
+    } else if raw.ends_with(b"\n") {
+        raw.truncate(raw.len() - 1);
+    }
+    let state = match mailparse::parse_headers(&raw) {
+        Ok((headers, _)) => mbox_state(
+            headers.get_first_value("Status").as_deref(),
+            headers.get_first_value("X-Status").as_deref(),
+        ),
+        Err(_) => MailState::default(),
+    };
+    send_item(sender, raw, folder, state, path)
+}
+
+/// 发送单个 .eml 文件
+fn send_eml(path: &Path, folder: &str, sender: &ImportSender) -> Result<()> {
+    let raw = std::fs::read(path)?;
+    send_item(sender, raw, folder, MailState::default(), path)
+}
+
+/// 发送一封邮件；X-FlareMail-Folder 头优先于归档路径推断的文件夹
+fn send_item(
+    sender: &ImportSender,
+    raw: Vec<u8>,
+    folder: &str,
+    state: MailState,
+    source: &Path,
+) -> Result<()> {
+    let folder = mailparse::parse_headers(&raw)
+        .ok()
+        .and_then(|(headers, _)| headers.get_first_value("X-FlareMail-Folder"))
+        .filter(|f| !f.trim().is_empty())
+        .unwrap_or_else(|| folder.to_string());
+    let item = ImportItem {
+        raw,
+        folder,
+        state,
+        source: source.display().to_string(),
+    };
+    sender
+        .blocking_send(Ok(item))
+        .map_err(|_| anyhow!("导入已中止"))
+}
+
+/// Maildir 文件名标志：S 已读、F 旗标、R 已答复
+fn maildir_flags(state: &MailState) -> String {
+    let mut flags = String::new();
+    if state.is_flagged {
+        flags.push('F');
+    }
+    if state.is_answered {
+        flags.push('R');
+    }
+    if state.is_read {
+        flags.push('S');
+    }
+    flags
+}
+
+/// 从 Maildir 文件名解析状态
+fn parse_maildir_flags(name: &str) -> MailState {
+    let flags = name
+        .rsplit_once(":2,")
+        .map(|(_, flags)| flags)
+        .unwrap_or("");
+    MailState {
+        is_read: flags.contains('S'),
+        is_flagged: flags.contains('F'),
+        is_answered: flags.contains('R'),
+        ..MailState::default()
+    }
+}
+
+/// 从 mbox 的 Status / X-Status 头解析状态
+fn mbox_state(status: Option<&str>, x_status: Option<&str>) -> MailState {
+    let x_status = x_status.unwrap_or("");
+    MailState {
+        is_read: status.unwrap_or("").contains('R'),
+        is_flagged: x_status.contains('F'),
+        is_answered: x_status.contains('A'),
+        ..MailState::default()
+    }
+}
+
+/// mboxrd 转义：以零个或多个 > 加 "From " 开头的行前加 >
+fn mboxrd_escape(message: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(message.len() + 16);
+    for line in message.split_inclusive(|b| *b == b'\n') {
+        let unquoted = &line[line.iter().take_while(|b| **b == b'>').count()..];
+        if unquoted.starts_with(b"From ") {
+            out.push(b'>');
+        }
+        out.extend_from_slice(line);
+    }
+    out
+}
+
+/// 还原 mboxrd 转义的行
+fn mboxrd_unescape_line(line: &[u8]) -> &[u8] {
+    let quotes = line.iter().take_while(|b| **b == b'>').count();
+    if quotes > 0 && line[quotes..].starts_with(b"From ") {
+        &line[1..]
+    } else {
+        line
+    }
+}
+
+fn is_maildir(dir: &Path) -> bool {
+    dir.join("cur").is_dir() && dir.join("new").is_dir()
+}
+
+fn is_eml(path: &Path) -> bool {
+    extension(path) == "eml"
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("inbox")
+        .to_string()
+}
+
+/// Maildir 目录对应的文件夹：导入根目录本身为收件箱，Maildir++ 子目录去掉前导 "."
+fn maildir_folder(root: &Path, dir: &Path) -> String {
+    if dir == root {
+        return "inbox".to_string();
+    }
+    dir.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.trim_start_matches('.').to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "inbox".to_string())
+}
+
+/// .eml 文件所在目录对应的文件夹
+fn parent_folder(root: &Path, file: &Path) -> String {
+    match file.parent() {
+        Some(parent) if parent != root => file_stem(parent),
+        _ => "inbox".to_string(),
+    }
+}
+
+fn read_dir_sorted(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn looks_like_html(content: &str) -> bool {
+    let head = content
+        .trim_start()
+        .get(..512)
+        .unwrap_or(content)
+        .to_lowercase();
+    [
+        "<html",
+        "<!doctype",
+        "<body",
+        "<div",
+        "<p>",
+        "<br",
+        "<table",
+    ]
+    .iter()
+    .any(|tag| head.contains(tag))
+}
+
+/// 提取 "Name <addr>" 中的邮件地址
+fn extract_address(sender: &str) -> Option<String> {
+    let address = match (sender.rfind('<'), sender.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &sender[start + 1..end],
+        _ => sender,
+    };
+    let address = address.trim();
+    (address.contains('@') && !address.contains(char::is_whitespace)).then(|| address.to_string())
+}
+
+/// 非 ASCII 头部按 RFC 2047 编码
+fn encode_header_text(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(value.as_bytes()))
+    }
+}
+
+/// 编码 "Name <addr>" 中的显示名称
+fn encode_address(sender: &str) -> String {
+    match sender.rfind('<') {
+        Some(start) if start > 0 => {
+            let name = sender[..start].trim().trim_matches('"');
+            format!("{} {}", encode_header_text(name), &sender[start..])
+        }
+        _ => encode_header_text(sender),
+    }
+}
+
+/// MIME 参数，非 ASCII 文件名按 RFC 2231 编码
+fn mime_param(key: &str, value: &str) -> String {
+    if value.is_ascii() {
+        return format!(
+            "{}=\"{}\"",
+            key,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+    }
+    let encoded: String = value
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect();
+    format!("{}*=UTF-8''{}", key, encoded)
+}
+
+/// base64 编码并按 76 字符换行
+fn wrap_base64(content: &[u8]) -> String {
+    let encoded = STANDARD.encode(content);
+    let mut out = String::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+    for chunk in encoded.as_bytes().chunks(76) {
+        out.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        out.push('\n');
+    }
+    out
+}
+
+fn display_subject(mail: &ExportMail) -> String {
+    mail.subject
+        .clone()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| format!("邮件 {}", mail.id))
+}
+
+fn push_error(errors: &mut Vec<String>, error: String) {
+    if errors.len() < MAX_REPORTED_ERRORS {
+        errors.push(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mboxrd_escape_roundtrip() {
+        let message = b"Subject: hi\n\nFrom here\n>From there\nFromage\n";
+        let escaped = mboxrd_escape(message);
+        assert_eq!(
+            escaped,
+            b"Subject: hi\n\n>From here\n>>From there\nFromage\n".to_vec()
+        );
+        let restored: Vec<u8> = escaped
+            .split_inclusive(|b| *b == b'\n')
+            .flat_map(|line| mboxrd_unescape_line(line).to_vec())
+            .collect();
+        assert_eq!(restored, message.to_vec());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_archive_skips_symlinked_dirs() {
+        let root = std::env::temp_dir().join(format!("archive-scan-{}", rand::random::<u32>()));
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub").join("a.eml"), b"Subject: hi\n\nbody\n").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("sub").join("loop")).unwrap();
+
+        let (sender, mut receiver) = mpsc::channel(IMPORT_CHANNEL_SIZE);
+        scan_archive(&root, &sender);
+        drop(sender);
+        let mut items = Vec::new();
+        while let Ok(item) = receiver.try_recv() {
+            items.push(item.unwrap());
+        }
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].folder, "sub");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_maildir_flags() {
+        let state = parse_maildir_flags("1700000000.M1P2.host:2,FS");
+        assert!(state.is_read && state.is_flagged && !state.is_answered);
+        assert_eq!(maildir_flags(&state), "FS");
+        assert_eq!(
+            parse_maildir_flags("1700000000.M1P2.host"),
+            MailState::default()
+        );
+    }
+}
@@ -0,0 +1,590 @@
+//! 附件下载模块
+//! 收件时只保存附件元数据与服务器引用，内容在首次查看时通过 Graph API / IMAP 下载并写入附件存储；
+//! 账号可设置自动下载小于指定大小的附件；支持保存到指定路径与批量导出到文件夹或 ZIP
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::audit::{self, AuditEvent};
+use crate::blob_store;
+use crate::email::{self, OutlookSession};
+use crate::folders;
+use crate::graph_api;
+
+/// 附件来源：Graph API 附件 ID
+pub const SOURCE_GRAPH: &str = "graph";
+/// 附件来源：IMAP 段号
+pub const SOURCE_IMAP: &str = "imap";
+
+/// 附件类型：文件
+pub const KIND_FILE: &str = "file";
+/// 附件类型：嵌入的邮件/日程（保存为 .eml）
+pub const KIND_ITEM: &str = "item";
+/// 附件类型：云附件链接（内容为 .url 快捷方式）
+pub const KIND_REFERENCE: &str = "reference";
+
+/// 每次自动下载的附件数量上限
+const AUTO_DOWNLOAD_BATCH: i64 = 50;
+
+/// 待下载附件及其所属邮件的服务器标识
+#[derive(Debug, sqlx::FromRow)]
+struct PendingAttachment {
+    id: i64,
+    email_id: i64,
+    kind: String,
+    remote_source: Option<String>,
+    remote_ref: Option<String>,
+    graph_id: Option<String>,
+    imap_uid: Option<i64>,
+    folder: Option<String>,
+}
+
+const PENDING_SELECT: &str = "SELECT a.id, m.email_id, a.kind, a.remote_source, a.remote_ref, m.graph_id, m.imap_uid, m.folder FROM attachments a JOIN mail_records m ON m.id = a.mail_id";
+
+/// 下载附件内容并缓存，返回内容
+pub async fn download_attachment(pool: &Pool<Sqlite>, attachment_id: i64) -> Result<Vec<u8>> {
+    let attachment =
+        sqlx::query_as::<_, PendingAttachment>(&format!("{PENDING_SELECT} WHERE a.id = ?"))
+            .bind(attachment_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| anyhow!("附件不存在"))?;
+
+    let session = email::open_outlook_session(pool, attachment.email_id).await?;
+    download_and_store(pool, &session, &attachment).await
+}
+
+/// 自动下载账号中小于 limit_mb 的未下载附件，返回下载数量
+pub async fn auto_download(
+    pool: &Pool<Sqlite>,
+    session: &OutlookSession,
+    email_id: i64,
+    limit_mb: i64,
+) -> Result<usize> {
+    let pending = sqlx::query_as::<_, PendingAttachment>(&format!(
+        "{PENDING_SELECT} WHERE m.email_id = ? AND a.blob_hash IS NULL AND a.content IS NULL AND a.remote_ref IS NOT NULL AND a.size <= ? ORDER BY a.id DESC LIMIT ?"
+    ))
+    .bind(email_id)
+    .bind(limit_mb.saturating_mul(1024 * 1024))
+    .bind(AUTO_DOWNLOAD_BATCH)
+    .fetch_all(pool)
+    .await?;
+
+    let mut downloaded = 0usize;
+    for attachment in &pending {
+        match download_and_store(pool, session, attachment).await {
+            Ok(_) => downloaded += 1,
+            Err(e) => log::warn!("自动下载附件失败: id={}, error={}", attachment.id, e),
+        }
+    }
+
+    Ok(downloaded)
+}
+
+/// 设置账号的自动下载大小（MB），None 表示不自动下载
+pub async fn set_auto_download_limit(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    limit_mb: Option<i64>,
+) -> Result<bool> {
+    if limit_mb.is_some_and(|limit| limit <= 0) {
+        return Err(anyhow!("自动下载大小必须大于 0"));
+    }
+
+    let result = sqlx::query("UPDATE emails SET attachment_auto_download_mb = ? WHERE id = ?")
+        .bind(limit_mb)
+        .bind(email_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// 云附件的本地内容：指向原始链接的 Internet 快捷方式
+pub fn reference_shortcut(url: &str) -> Vec<u8> {
+    format!("[InternetShortcut]\r\nURL={url}\r\n").into_bytes()
+}
+
+/// 从服务器下载附件并写入附件存储
+async fn download_and_store(
+    pool: &Pool<Sqlite>,
+    session: &OutlookSession,
+    attachment: &PendingAttachment,
+) -> Result<Vec<u8>> {
+    if attachment.kind == KIND_REFERENCE {
+        return Err(anyhow!("云附件链接不可用，无法下载"));
+    }
+
+    let remote_ref = attachment
+        .remote_ref
+        .as_deref()
+        .ok_or_else(|| anyhow!("附件缺少服务器引用"))?;
+
+    let content = match attachment.remote_source.as_deref() {
+        Some(SOURCE_GRAPH) => {
+            let message_id = attachment
+                .graph_id
+                .as_deref()
+                .ok_or_else(|| anyhow!("邮件缺少 Graph ID，请重新收件后再试"))?;
+            graph_api::download_attachment(
+                &session.access_token,
+                message_id,
+                remote_ref,
+                &session.proxy_config,
+            )
+            .await?
+        }
+        Some(SOURCE_IMAP) => download_via_imap(pool, session, attachment, remote_ref).await?,
+        _ => return Err(anyhow!("未知的附件来源")),
+    };
+
+    let _guard = blob_store::lock().await;
+    let blob_hash = blob_store::put(pool, &content).await?;
+    sqlx::query(
+        "UPDATE attachments SET blob_hash = ?, size = ? WHERE id = ? AND blob_hash IS NULL",
+    )
+    .bind(&blob_hash)
+    .bind(content.len() as i64)
+    .bind(attachment.id)
+    .execute(pool)
+    .await?;
+
+    Ok(content)
+}
+
+/// 通过 IMAP UID FETCH 下载附件所在的段
+async fn download_via_imap(
+    pool: &Pool<Sqlite>,
+    session: &OutlookSession,
+    attachment: &PendingAttachment,
+    section: &str,
+) -> Result<Vec<u8>> {
+    // 邮件移动后 UID 失效，需重新收取目标文件夹
+    let uid = attachment
+        .imap_uid
+        .ok_or_else(|| anyhow!("邮件缺少 IMAP UID，请重新收件后再试"))?;
+    let folder = folders::resolve_folder(
+        pool,
+        attachment.email_id,
+        attachment.folder.as_deref().unwrap_or("inbox"),
+    )
+    .await?;
+
+    let email_address = session.email.clone();
+    let access_token = session.access_token.clone();
+    let section = section.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut imap = email::connect_outlook_imap(&email_address, &access_token)?;
+        imap.select(&folder.imap_name)?;
+        let fetches = imap.uid_fetch(uid.to_string(), email::imap_part_query(&section))?;
+        let raw = fetches
+            .iter()
+            .find_map(|fetch| email::imap_part_content(fetch, &section))
+            .ok_or_else(|| anyhow!("服务器未返回附件内容"))?;
+        imap.logout()?;
+
+        let part = mailparse::parse_mail(&raw)?;
+        Ok::<_, anyhow::Error>(part.get_body_raw()?)
+    })
+    .await?
+}
+
+/// 附件导出格式
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// 复制到文件夹
+    Folder,
+    /// 打包为 ZIP
+    Zip,
+}
+
+/// 附件导出筛选条件（日期为 YYYY-MM-DD，包含首尾两天）
+#[derive(Debug, Default, Deserialize)]
+pub struct AttachmentExportFilter {
+    #[serde(default)]
+    pub email_ids: Vec<i64>,
+    /// 发件人包含的关键字
+    pub sender: Option<String>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    /// 扩展名（不区分大小写，可带或不带点）
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// 仅包含该用户的账号（由后端根据会话设置）
+    #[serde(skip)]
+    pub user_id: Option<i64>,
+}
+
+/// 附件导出结果
+#[derive(Debug, Serialize)]
+pub struct AttachmentExportResult {
+    pub exported: usize,
+    pub failed: usize,
+    /// 导出的文件夹或 ZIP 路径
+    pub target: String,
+    pub errors: Vec<String>,
+}
+
+/// 将附件保存到指定路径；路径为文件夹时使用附件文件名，重名时自动编号
+pub async fn save_attachment(
+    pool: &Pool<Sqlite>,
+    attachment_id: i64,
+    path: &Path,
+) -> Result<String> {
+    let (filename, source) = ensure_local(pool, attachment_id).await?;
+
+    let dest = if path.is_dir() {
+        let name = sanitize_filename(filename.as_deref().unwrap_or(""));
+        path.join(unique_name(&name, |candidate| {
+            path.join(candidate).exists()
+        }))
+    } else {
+        path.to_path_buf()
+    };
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::copy(&source, &dest).await?;
+
+    Ok(dest.to_string_lossy().into_owned())
+}
+
+/// 批量导出符合条件的附件到文件夹或 ZIP
+pub async fn export_attachments(
+    pool: &Pool<Sqlite>,
+    filter: &AttachmentExportFilter,
+    target: &Path,
+    format: ExportFormat,
+) -> Result<AttachmentExportResult> {
+    let candidates = query_export_candidates(pool, filter).await?;
+    if candidates.is_empty() {
+        return Err(anyhow!("没有符合条件的附件"));
+    }
+
+    let mut errors = Vec::new();
+    let mut files: Vec<(String, PathBuf)> = Vec::new();
+    let mut file_ids = Vec::new();
+    let mut used_names: HashSet<String> = HashSet::new();
+    for (attachment_id, filename) in candidates {
+        let source = match ensure_local(pool, attachment_id).await {
+            Ok((_, source)) => source,
+            Err(e) => {
+                errors.push(format!(
+                    "{}: {}",
+                    filename.as_deref().unwrap_or("attachment"),
+                    e
+                ));
+                continue;
+            }
+        };
+        let name = sanitize_filename(filename.as_deref().unwrap_or(""));
+        let name = unique_name(&name, |candidate| {
+            used_names.contains(&candidate.to_lowercase())
+                || (matches!(format, ExportFormat::Folder) && target.join(candidate).exists())
+        });
+        used_names.insert(name.to_lowercase());
+        files.push((name, source));
+        file_ids.push(attachment_id);
+    }
+
+    let (exported, target) = match format {
+        ExportFormat::Folder => {
+            tokio::fs::create_dir_all(target).await?;
+            let mut exported = 0usize;
+            for (name, source) in &files {
+                match tokio::fs::copy(source, target.join(name)).await {
+                    Ok(_) => exported += 1,
+                    Err(e) => errors.push(format!("{}: {}", name, e)),
+                }
+            }
+            (exported, target.to_path_buf())
+        }
+        ExportFormat::Zip => {
+            let zip_path = if target.is_dir() {
+                let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                target.join(format!("attachments-{stamp}.zip"))
+            } else {
+                target.to_path_buf()
+            };
+            let path = zip_path.clone();
+            let (exported, zip_errors) =
+                tokio::task::spawn_blocking(move || write_zip(&path, &files)).await??;
+            errors.extend(zip_errors);
+            (exported, zip_path)
+        }
+    };
+
+    audit::record(
+        pool,
+        audit::ACTION_ATTACHMENT_EXPORT,
+        AuditEvent {
+            user_id: filter.user_id,
+            target_ids: &file_ids,
+            detail: Some(format!("exported={exported}")),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(AttachmentExportResult {
+        exported,
+        failed: errors.len(),
+        target: target.to_string_lossy().into_owned(),
+        errors,
+    })
+}
+
+/// 确保附件内容已在本地附件存储中，返回文件名与内容文件路径
+pub async fn ensure_local(
+    pool: &Pool<Sqlite>,
+    attachment_id: i64,
+) -> Result<(Option<String>, PathBuf)> {
+    let (filename, blob_hash) = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        "SELECT filename, blob_hash FROM attachments WHERE id = ?",
+    )
+    .bind(attachment_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("附件不存在"))?;
+
+    let blob_hash = match blob_hash {
+        Some(hash) => hash,
+        None => {
+            download_attachment(pool, attachment_id).await?;
+            sqlx::query_scalar::<_, Option<String>>(
+                "SELECT blob_hash FROM attachments WHERE id = ?",
+            )
+            .bind(attachment_id)
+            .fetch_one(pool)
+            .await?
+            .ok_or_else(|| anyhow!("附件内容不可用"))?
+        }
+    };
+
+    Ok((filename, blob_store::path_of(&blob_hash)?))
+}
+
+/// 查询符合条件的附件（附件 ID 与文件名）
+async fn query_export_candidates(
+    pool: &Pool<Sqlite>,
+    filter: &AttachmentExportFilter,
+) -> Result<Vec<(i64, Option<String>)>> {
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT a.id, a.filename FROM attachments a JOIN mail_records m ON m.id = a.mail_id WHERE m.deleted_at IS NULL",
+    );
+    if !filter.email_ids.is_empty() {
+        qb.push(" AND m.email_id IN (");
+        let mut separated = qb.separated(", ");
+        for id in &filter.email_ids {
+            separated.push_bind(id);
+        }
+        qb.push(")");
+    }
+    if let Some(user_id) = filter.user_id {
+        qb.push(" AND m.email_id IN (SELECT id FROM emails WHERE user_id = ");
+        qb.push_bind(user_id);
+        qb.push(")");
+    }
+    if let Some(sender) = filter
+        .sender
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        qb.push(" AND lower(m.sender) LIKE ");
+        qb.push_bind(format!("%{}%", sender.to_lowercase()));
+    }
+    if let Some(date_from) = filter.date_from.as_deref().filter(|d| !d.is_empty()) {
+        qb.push(" AND substr(m.received_time, 1, 10) >= ");
+        qb.push_bind(date_from);
+    }
+    if let Some(date_to) = filter.date_to.as_deref().filter(|d| !d.is_empty()) {
+        qb.push(" AND substr(m.received_time, 1, 10) <= ");
+        qb.push_bind(date_to);
+    }
+    qb.push(" ORDER BY m.received_time DESC, a.id");
+
+    let rows = qb
+        .build_query_as::<(i64, Option<String>)>()
+        .fetch_all(pool)
+        .await?;
+
+    let extensions: Vec<String> = filter
+        .extensions
+        .iter()
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    if extensions.is_empty() {
+        return Ok(rows);
+    }
+
+    Ok(rows
+        .into_iter()
+        .filter(|(_, filename)| {
+            let extension = filename
+                .as_deref()
+                .and_then(|name| Path::new(name).extension())
+                .map(|ext| ext.to_string_lossy().to_lowercase());
+            extension.is_some_and(|ext| extensions.contains(&ext))
+        })
+        .collect())
+}
+
+/// 写入 ZIP（逐个文件流式复制，不整体读入内存），单个文件失败时跳过并记录错误
+/// 返回写入的文件数与错误信息
+fn write_zip(path: &Path, entries: &[(String, PathBuf)]) -> Result<(usize, Vec<String>)> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut zip = ZipWriter::new(std::fs::File::create(path)?);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+
+    let mut exported = 0usize;
+    let mut errors = Vec::new();
+    for (name, source) in entries {
+        let mut file = match std::fs::File::open(source) {
+            Ok(file) => file,
+            Err(e) => {
+                errors.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+        zip.start_file(name.as_str(), options)?;
+        match std::io::copy(&mut file, &mut zip) {
+            Ok(_) => exported += 1,
+            Err(e) => {
+                // 丢弃写了一半的条目，继续写入其他文件
+                zip.abort_file()?;
+                errors.push(format!("{}: {}", name, e));
+            }
+        }
+    }
+    zip.finish()?;
+
+    Ok((exported, errors))
+}
+
+/// 清理文件名：移除路径分隔符与 Windows 不允许的字符，避开保留名称并限制长度
+pub fn sanitize_filename(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    let mut cleaned = if cleaned.is_empty() {
+        "attachment".to_string()
+    } else {
+        cleaned.to_string()
+    };
+
+    let stem = cleaned.split('.').next().unwrap_or("").to_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.as_bytes()[3].is_ascii_digit());
+    if reserved {
+        cleaned.insert(0, '_');
+    }
+
+    // 保留扩展名截断过长的文件名
+    if cleaned.len() > MAX_FILENAME_BYTES {
+        let (base, ext) = split_extension(&cleaned);
+        let keep = MAX_FILENAME_BYTES.saturating_sub(ext.len());
+        let mut end = keep.min(base.len());
+        while !base.is_char_boundary(end) {
+            end -= 1;
+        }
+        cleaned = format!("{}{}", &base[..end], ext);
+    }
+
+    cleaned
+}
+
+/// 文件名最大字节数
+const MAX_FILENAME_BYTES: usize = 200;
+
+/// 拆分文件名与扩展名（扩展名包含点）
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(pos) if pos > 0 && name.len() - pos <= 16 => name.split_at(pos),
+        _ => (name, ""),
+    }
+}
+
+/// 生成不冲突的文件名：name.ext → name (1).ext → name (2).ext …
+fn unique_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+    let (base, ext) = split_extension(name);
+    (1..)
+        .map(|n| format!("{base} ({n}){ext}"))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("../etc/passwd"), "_etc_passwd");
+        assert_eq!(sanitize_filename("a:b*c?.pdf"), "a_b_c_.pdf");
+        assert_eq!(sanitize_filename("  "), "attachment");
+        assert_eq!(sanitize_filename("CON.txt"), "_CON.txt");
+        assert_eq!(sanitize_filename("COM1"), "_COM1");
+        let long = format!("{}.pdf", "报".repeat(100));
+        let sanitized = sanitize_filename(&long);
+        assert!(sanitized.len() <= MAX_FILENAME_BYTES);
+        assert!(sanitized.ends_with(".pdf"));
+    }
+
+    #[test]
+    fn test_unique_name() {
+        let taken = ["a.pdf", "a (1).pdf"];
+        assert_eq!(unique_name("a.pdf", |n| taken.contains(&n)), "a (2).pdf");
+        assert_eq!(unique_name("b.pdf", |n| taken.contains(&n)), "b.pdf");
+        assert_eq!(unique_name("README", |n| n == "README"), "README (1)");
+    }
+
+    #[test]
+    fn test_write_zip_skips_failed_entries() {
+        let dir = std::env::temp_dir().join(format!("zip-export-{}", rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("a.txt");
+        std::fs::write(&source, b"hello").unwrap();
+        let entries = vec![
+            ("missing.txt".to_string(), dir.join("missing.txt")),
+            ("a.txt".to_string(), source),
+        ];
+
+        let zip_path = dir.join("out.zip");
+        let (exported, errors) = write_zip(&zip_path, &entries).unwrap();
+        assert_eq!(exported, 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("missing.txt"));
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 1);
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("a.txt").unwrap(), &mut content)
+            .unwrap();
+        assert_eq!(content, "hello");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
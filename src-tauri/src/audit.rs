@@ -0,0 +1,432 @@
+//! 审计日志模块
+//! 记录账号增删改、凭据查看、导出、服务器邮件操作与同步删除等敏感操作。
+//! 日志只允许追加（由数据库触发器保证），超过保留期限的记录定期清理
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+
+use crate::db;
+
+/// 添加邮箱账号
+pub const ACTION_ACCOUNT_ADD: &str = "account.add";
+/// 批量导入邮箱账号
+pub const ACTION_ACCOUNT_IMPORT: &str = "account.import";
+/// 修改账号凭据
+pub const ACTION_ACCOUNT_UPDATE: &str = "account.update";
+/// 删除邮箱账号
+pub const ACTION_ACCOUNT_DELETE: &str = "account.delete";
+/// 从回收站恢复邮箱账号
+pub const ACTION_ACCOUNT_RESTORE: &str = "account.restore";
+/// 导出账号（含凭据）
+pub const ACTION_ACCOUNT_EXPORT: &str = "account.export";
+/// 查看账号凭据
+pub const ACTION_CREDENTIAL_REVEAL: &str = "credential.reveal";
+/// 复制账号凭据
+pub const ACTION_CREDENTIAL_COPY: &str = "credential.copy";
+/// 导出邮件归档
+pub const ACTION_MAILBOX_EXPORT: &str = "mailbox.export";
+/// 导入邮件归档
+pub const ACTION_MAILBOX_IMPORT: &str = "mailbox.import";
+/// 导出附件
+pub const ACTION_ATTACHMENT_EXPORT: &str = "attachment.export";
+/// 在服务器上执行邮件操作
+pub const ACTION_MAIL_ACTION: &str = "mail.action";
+/// 同步时删除服务器上已不存在的本地邮件
+pub const ACTION_MAIL_SYNC_DELETE: &str = "mail.sync_delete";
+/// 从回收站恢复邮件
+pub const ACTION_MAIL_RESTORE: &str = "mail.restore";
+/// 彻底清除回收站中的账号与邮件
+pub const ACTION_TRASH_PURGE: &str = "trash.purge";
+/// 修改回收站保留期限
+pub const ACTION_TRASH_RETENTION: &str = "trash.retention";
+/// 新建 Webhook 端点
+pub const ACTION_WEBHOOK_CREATE: &str = "webhook.create";
+/// 修改 Webhook 端点
+pub const ACTION_WEBHOOK_UPDATE: &str = "webhook.update";
+/// 删除 Webhook 端点
+pub const ACTION_WEBHOOK_DELETE: &str = "webhook.delete";
+/// 重新生成 Webhook 签名密钥
+pub const ACTION_WEBHOOK_SECRET: &str = "webhook.secret";
+/// 修改应用设置
+pub const ACTION_SETTINGS_UPDATE: &str = "settings.update";
+/// 从文件导入应用设置
+pub const ACTION_SETTINGS_IMPORT: &str = "settings.import";
+/// 导出审计日志
+pub const ACTION_AUDIT_EXPORT: &str = "audit.export";
+/// 修改审计日志保留期限
+pub const ACTION_AUDIT_RETENTION: &str = "audit.retention";
+
+const CONFIG_RETENTION_DAYS: &str = "audit.retention_days";
+
+/// 默认保留天数（与迁移脚本中的触发器保持一致）
+const DEFAULT_RETENTION_DAYS: i64 = 365;
+
+/// 最短保留天数（与迁移脚本中的触发器保持一致，0 表示永久保留不受此限制）
+const MIN_RETENTION_DAYS: i64 = 30;
+
+/// 最长保留天数
+const MAX_RETENTION_DAYS: i64 = 3650;
+
+/// 单次查询默认返回的条数
+const DEFAULT_QUERY_LIMIT: i64 = 200;
+
+/// 清理过期日志的间隔（秒）
+const RETENTION_CHECK_SECS: u64 = 6 * 3600;
+
+/// 审计事件
+#[derive(Debug, Default)]
+pub struct AuditEvent<'a> {
+    /// 操作用户，后台同步等系统操作为空
+    pub user_id: Option<i64>,
+    /// 相关的邮箱账号
+    pub email_id: Option<i64>,
+    /// 受影响的记录 ID（账号、邮件或附件，取决于操作类型）
+    pub target_ids: &'a [i64],
+    pub detail: Option<String>,
+}
+
+/// 审计日志查询条件（日期为 YYYY-MM-DD，包含首尾两天）
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    /// 操作类型，可只写前缀（如 account）
+    pub action: Option<String>,
+    pub email_id: Option<i64>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// 仅包含该用户的操作及其账号上的系统操作（由后端根据会话设置）
+    #[serde(skip)]
+    pub user_id: Option<i64>,
+}
+
+/// 审计日志记录
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub action: String,
+    pub user_id: Option<i64>,
+    pub username: Option<String>,
+    pub email_id: Option<i64>,
+    pub email: Option<String>,
+    pub target_ids: Vec<i64>,
+    pub target_count: i64,
+    pub detail: Option<String>,
+    pub created_at: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct AuditRow {
+    id: i64,
+    action: String,
+    user_id: Option<i64>,
+    username: Option<String>,
+    email_id: Option<i64>,
+    email: Option<String>,
+    target_ids: Option<String>,
+    target_count: i64,
+    detail: Option<String>,
+    created_at: Option<String>,
+}
+
+impl From<AuditRow> for AuditEntry {
+    fn from(row: AuditRow) -> Self {
+        AuditEntry {
+            id: row.id,
+            action: row.action,
+            user_id: row.user_id,
+            username: row.username,
+            email_id: row.email_id,
+            email: row.email,
+            target_ids: row
+                .target_ids
+                .and_then(|ids| serde_json::from_str(&ids).ok())
+                .unwrap_or_default(),
+            target_count: row.target_count,
+            detail: row.detail,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// 审计日志导出格式
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditExportFormat {
+    Csv,
+    Json,
+}
+
+/// 写入一条审计日志
+pub async fn record(pool: &Pool<Sqlite>, action: &str, event: AuditEvent<'_>) -> Result<()> {
+    let target_ids = if event.target_ids.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(event.target_ids)?)
+    };
+
+    sqlx::query(
+        "INSERT INTO audit_log (action, user_id, email_id, target_ids, target_count, detail) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(action)
+    .bind(event.user_id)
+    .bind(event.email_id)
+    .bind(target_ids)
+    .bind(event.target_ids.len() as i64)
+    .bind(event.detail)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 查询审计日志（按时间倒序）
+pub async fn query(pool: &Pool<Sqlite>, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+    let mut qb = build_query(query);
+    qb.push(" LIMIT ");
+    qb.push_bind(
+        query
+            .limit
+            .filter(|l| *l > 0)
+            .unwrap_or(DEFAULT_QUERY_LIMIT),
+    );
+    qb.push(" OFFSET ");
+    qb.push_bind(query.offset.unwrap_or(0).max(0));
+
+    let rows = qb.build_query_as::<AuditRow>().fetch_all(pool).await?;
+    Ok(rows.into_iter().map(AuditEntry::from).collect())
+}
+
+/// 导出符合条件的全部审计日志到文件，返回导出的条数
+pub async fn export(
+    pool: &Pool<Sqlite>,
+    query: &AuditQuery,
+    target: &Path,
+    format: AuditExportFormat,
+) -> Result<usize> {
+    let entries: Vec<AuditEntry> = build_query(query)
+        .build_query_as::<AuditRow>()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(AuditEntry::from)
+        .collect();
+
+    let content = match format {
+        AuditExportFormat::Json => serde_json::to_string_pretty(&entries)?,
+        AuditExportFormat::Csv => to_csv(&entries),
+    };
+    tokio::fs::write(target, content).await?;
+
+    record(
+        pool,
+        ACTION_AUDIT_EXPORT,
+        AuditEvent {
+            user_id: query.user_id,
+            detail: Some(format!("count={}", entries.len())),
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(entries.len())
+}
+
+/// 读取审计日志保留天数，0 表示永久保留
+pub async fn get_retention_days(pool: &Pool<Sqlite>) -> Result<i64> {
+    Ok(db::get_config(pool, CONFIG_RETENTION_DAYS)
+        .await?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS))
+}
+
+/// 设置审计日志保留天数，0 表示永久保留；先记录本次修改，再写入配置
+pub async fn set_retention_days(pool: &Pool<Sqlite>, user_id: i64, days: i64) -> Result<()> {
+    validate_retention_days(days)?;
+
+    record(
+        pool,
+        ACTION_AUDIT_RETENTION,
+        AuditEvent {
+            user_id: Some(user_id),
+            detail: Some(format!("days={days}")),
+            ..Default::default()
+        },
+    )
+    .await?;
+    db::set_config(
+        pool,
+        CONFIG_RETENTION_DAYS,
+        &days.to_string(),
+        Some("审计日志保留天数（0 表示永久保留）"),
+    )
+    .await
+}
+
+fn validate_retention_days(days: i64) -> Result<()> {
+    if days != 0 && !(MIN_RETENTION_DAYS..=MAX_RETENTION_DAYS).contains(&days) {
+        return Err(anyhow!(
+            "保留天数需为 0（永久保留）或 {} 到 {} 之间",
+            MIN_RETENTION_DAYS,
+            MAX_RETENTION_DAYS
+        ));
+    }
+    Ok(())
+}
+
+/// 删除超过保留期限的审计日志，返回删除的条数
+pub async fn purge_expired(pool: &Pool<Sqlite>) -> Result<u64> {
+    let days = get_retention_days(pool).await?;
+    if days <= 0 {
+        return Ok(0);
+    }
+    let days = days.max(MIN_RETENTION_DAYS);
+
+    let deleted = sqlx::query("DELETE FROM audit_log WHERE created_at < datetime('now', ?)")
+        .bind(format!("-{days} days"))
+        .execute(pool)
+        .await?
+        .rows_affected();
+    if deleted > 0 {
+        log::info!("已清理过期审计日志: {}", deleted);
+    }
+    Ok(deleted)
+}
+
+/// 后台定期清理过期审计日志
+pub async fn run_retention(pool: Pool<Sqlite>) {
+    loop {
+        if let Err(e) = purge_expired(&pool).await {
+            log::warn!("清理审计日志失败: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(RETENTION_CHECK_SECS)).await;
+    }
+}
+
+fn build_query(query: &AuditQuery) -> QueryBuilder<'_, Sqlite> {
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT l.id, l.action, l.user_id, u.username, l.email_id, e.email, l.target_ids, l.target_count, l.detail, l.created_at FROM audit_log l LEFT JOIN users u ON u.id = l.user_id LEFT JOIN emails e ON e.id = l.email_id WHERE 1 = 1",
+    );
+    if let Some(user_id) = query.user_id {
+        qb.push(" AND (l.user_id = ");
+        qb.push_bind(user_id);
+        qb.push(" OR (l.user_id IS NULL AND e.user_id = ");
+        qb.push_bind(user_id);
+        qb.push("))");
+    }
+    if let Some(action) = query
+        .action
+        .as_deref()
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+    {
+        qb.push(" AND (l.action = ");
+        qb.push_bind(action);
+        qb.push(" OR l.action LIKE ");
+        qb.push_bind(format!("{action}.%"));
+        qb.push(")");
+    }
+    if let Some(email_id) = query.email_id {
+        qb.push(" AND l.email_id = ");
+        qb.push_bind(email_id);
+    }
+    if let Some(date_from) = query.date_from.as_deref().filter(|d| !d.is_empty()) {
+        qb.push(" AND substr(l.created_at, 1, 10) >= ");
+        qb.push_bind(date_from);
+    }
+    if let Some(date_to) = query.date_to.as_deref().filter(|d| !d.is_empty()) {
+        qb.push(" AND substr(l.created_at, 1, 10) <= ");
+        qb.push_bind(date_to);
+    }
+    qb.push(" ORDER BY l.id DESC");
+    qb
+}
+
+fn to_csv(entries: &[AuditEntry]) -> String {
+    let mut out = String::from(
+        "id,created_at,action,user_id,username,email_id,email,target_count,target_ids,detail\n",
+    );
+    for entry in entries {
+        let target_ids = entry
+            .target_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let fields = [
+            entry.id.to_string(),
+            entry.created_at.clone().unwrap_or_default(),
+            entry.action.clone(),
+            entry.user_id.map(|id| id.to_string()).unwrap_or_default(),
+            entry.username.clone().unwrap_or_default(),
+            entry.email_id.map(|id| id.to_string()).unwrap_or_default(),
+            entry.email.clone().unwrap_or_default(),
+            entry.target_count.to_string(),
+            target_ids,
+            entry.detail.clone().unwrap_or_default(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&line.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// CSV 字段转义
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_retention_days() {
+        assert!(validate_retention_days(0).is_ok());
+        assert!(validate_retention_days(MIN_RETENTION_DAYS).is_ok());
+        assert!(validate_retention_days(1).is_err());
+        assert!(validate_retention_days(-1).is_err());
+        assert!(validate_retention_days(MAX_RETENTION_DAYS + 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retention_trigger_keeps_recent_entries() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO audit_log (action, created_at) VALUES ('old', datetime('now', '-40 days')), ('recent', datetime('now', '-10 days'))",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // 绕过 set_retention_days 直接写入过短的保留期限，触发器仍按 30 天保护
+        db::set_config(&pool, CONFIG_RETENTION_DAYS, "1", None)
+            .await
+            .unwrap();
+        assert!(sqlx::query("DELETE FROM audit_log WHERE action = 'recent'")
+            .execute(&pool)
+            .await
+            .is_err());
+        assert_eq!(purge_expired(&pool).await.unwrap(), 1);
+
+        set_retention_days(&pool, 1, 0).await.unwrap();
+        assert!(sqlx::query("DELETE FROM audit_log")
+            .execute(&pool)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
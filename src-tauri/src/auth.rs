@@ -0,0 +1,519 @@
+//! 本地用户与会话模块
+//! 用户密码以 PBKDF2-HMAC-SHA256 加盐哈希保存；登录后发放会话令牌（仅保存在内存中，
+//! 重启应用需重新登录），其他命令凭令牌识别当前用户，每个用户只能访问自己的邮箱账号；
+//! 第一个注册的用户为管理员，备份恢复、保险库等影响全部用户的操作仅管理员可用
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::{Pool, QueryBuilder, Sqlite};
+use zeroize::Zeroizing;
+
+use crate::vault;
+
+/// PBKDF2 迭代次数
+#[cfg(not(test))]
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// 测试中使用较少的迭代次数，避免每次哈希耗时过长
+#[cfg(test)]
+const PBKDF2_ROUNDS: u32 = 1_000;
+
+/// 用户名最短长度
+const MIN_USERNAME_LEN: usize = 3;
+
+/// 登录密码最短长度
+const MIN_PASSWORD_LEN: usize = 6;
+
+/// 会话空闲过期时间（小时）
+const SESSION_IDLE_HOURS: u64 = 12;
+
+/// 会话无效时返回的错误（前端据此跳转登录页）
+pub const SESSION_EXPIRED: &str = "会话无效或已过期，请重新登录";
+
+/// 非管理员调用管理员命令时返回的错误
+pub const ADMIN_REQUIRED: &str = "需要管理员权限";
+
+/// 登录会话
+struct Session {
+    user_id: i64,
+    last_used: Instant,
+}
+
+/// 令牌 -> 会话
+static SESSIONS: Mutex<BTreeMap<String, Session>> = Mutex::new(BTreeMap::new());
+
+/// 本地用户
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub is_admin: bool,
+    pub created_at: Option<String>,
+}
+
+/// 登录结果
+#[derive(Debug, Serialize)]
+pub struct LoginSession {
+    pub token: String,
+    pub user: User,
+}
+
+fn sessions() -> std::sync::MutexGuard<'static, BTreeMap<String, Session>> {
+    SESSIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 注册用户；第一个注册的用户成为管理员，并认领升级前已有的邮箱账号
+pub async fn register(pool: &Pool<Sqlite>, username: &str, password: &str) -> Result<User> {
+    let username = username.trim();
+    if username.chars().count() < MIN_USERNAME_LEN {
+        return Err(anyhow!("用户名至少需要 {} 个字符", MIN_USERNAME_LEN));
+    }
+    validate_password(password)?;
+
+    let salt = rand::random::<[u8; 16]>().to_vec();
+    let hash = hash_password(password, salt.clone(), PBKDF2_ROUNDS).await?;
+
+    let mut tx = pool.begin().await?;
+    let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(&mut *tx)
+        .await?;
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (username, password_hash, salt, rounds, is_admin) VALUES (?, ?, ?, ?, ?) ON CONFLICT(username) DO NOTHING RETURNING id, username, is_admin, created_at",
+    )
+    .bind(username)
+    .bind(hex::encode(hash.as_slice()))
+    .bind(hex::encode(&salt))
+    .bind(PBKDF2_ROUNDS as i64)
+    .bind(existing == 0)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| anyhow!("用户名已存在"))?;
+    if existing == 0 {
+        let claimed = sqlx::query("UPDATE emails SET user_id = ? WHERE user_id IS NULL")
+            .bind(user.id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        log::info!("用户 {} 认领已有邮箱账号: {}", user.username, claimed);
+        sqlx::query("UPDATE tags SET user_id = ? WHERE user_id IS NULL")
+            .bind(user.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(user)
+}
+
+/// 校验用户名与密码，发放会话令牌
+pub async fn login(pool: &Pool<Sqlite>, username: &str, password: &str) -> Result<LoginSession> {
+    let user_id = verify_credentials(pool, username.trim(), password)
+        .await?
+        .ok_or_else(|| anyhow!("用户名或密码错误"))?;
+    let user = get_user(pool, user_id).await?;
+
+    let token = hex::encode(rand::random::<[u8; 32]>());
+    sessions().insert(
+        token.clone(),
+        Session {
+            user_id,
+            last_used: Instant::now(),
+        },
+    );
+    log::info!("用户登录: {}", user.username);
+
+    Ok(LoginSession { token, user })
+}
+
+/// 注销会话
+pub fn logout(token: &str) -> bool {
+    sessions().remove(token).is_some()
+}
+
+/// 修改登录密码，并注销该用户的其他会话
+pub async fn change_password(
+    pool: &Pool<Sqlite>,
+    token: &str,
+    old_password: &str,
+    new_password: &str,
+) -> Result<()> {
+    let user_id = require_user(token)?;
+    validate_password(new_password)?;
+    let user = get_user(pool, user_id).await?;
+    if verify_credentials(pool, &user.username, old_password)
+        .await?
+        .is_none()
+    {
+        return Err(anyhow!("原密码错误"));
+    }
+
+    let salt = rand::random::<[u8; 16]>().to_vec();
+    let hash = hash_password(new_password, salt.clone(), PBKDF2_ROUNDS).await?;
+    sqlx::query(
+        "UPDATE users SET password_hash = ?, salt = ?, rounds = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(hex::encode(hash.as_slice()))
+    .bind(hex::encode(&salt))
+    .bind(PBKDF2_ROUNDS as i64)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    sessions().retain(|t, session| session.user_id != user_id || t == token);
+    Ok(())
+}
+
+/// 获取会话对应的用户
+pub async fn current_user(pool: &Pool<Sqlite>, token: &str) -> Result<User> {
+    let user_id = require_user(token)?;
+    get_user(pool, user_id).await
+}
+
+/// 校验会话令牌并刷新空闲时间，返回用户 ID
+pub fn require_user(token: &str) -> Result<i64> {
+    let mut sessions = sessions();
+    let idle = Duration::from_secs(SESSION_IDLE_HOURS * 3600);
+    sessions.retain(|_, session| session.last_used.elapsed() < idle);
+    let session = sessions
+        .get_mut(token)
+        .ok_or_else(|| anyhow!(SESSION_EXPIRED))?;
+    session.last_used = Instant::now();
+    // 用户操作延长保险库的空闲计时
+    vault::touch();
+    Ok(session.user_id)
+}
+
+/// 校验会话，并确认当前用户是管理员
+pub async fn require_admin(pool: &Pool<Sqlite>, token: &str) -> Result<i64> {
+    let user_id = require_user(token)?;
+    if !get_user(pool, user_id).await?.is_admin {
+        return Err(anyhow!(ADMIN_REQUIRED));
+    }
+    Ok(user_id)
+}
+
+/// 当前已登录（会话未过期）的用户 ID
+pub fn active_user_ids() -> Vec<i64> {
+    let idle = Duration::from_secs(SESSION_IDLE_HOURS * 3600);
+    let mut user_ids: Vec<i64> = sessions()
+        .values()
+        .filter(|session| session.last_used.elapsed() < idle)
+        .map(|session| session.user_id)
+        .collect();
+    user_ids.sort_unstable();
+    user_ids.dedup();
+    user_ids
+}
+
+/// 已登录的用户中是否有管理员（托盘等没有会话令牌的入口据此判断是否允许全局操作）
+pub async fn admin_logged_in(pool: &Pool<Sqlite>) -> Result<bool> {
+    for user_id in active_user_ids() {
+        let is_admin = sqlx::query_scalar::<_, bool>("SELECT is_admin FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?
+            .unwrap_or(false);
+        if is_admin {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// 校验会话，并确认邮箱账号属于当前用户
+pub async fn authorize_account(pool: &Pool<Sqlite>, token: &str, email_id: i64) -> Result<i64> {
+    authorize_accounts(pool, token, &[email_id]).await
+}
+
+/// 校验会话，并确认全部邮箱账号属于当前用户
+pub async fn authorize_accounts(
+    pool: &Pool<Sqlite>,
+    token: &str,
+    email_ids: &[i64],
+) -> Result<i64> {
+    let user_id = require_user(token)?;
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT id FROM emails WHERE deleted_at IS NULL AND user_id = ",
+    );
+    qb.push_bind(user_id);
+    let owned: Vec<i64> = qb.build_query_scalar().fetch_all(pool).await?;
+    if email_ids.iter().any(|id| !owned.contains(id)) {
+        return Err(anyhow!("邮箱不存在"));
+    }
+    Ok(user_id)
+}
+
+/// 校验会话，并确认邮件均属于当前用户的账号
+pub async fn authorize_mails(pool: &Pool<Sqlite>, token: &str, mail_ids: &[i64]) -> Result<i64> {
+    let user_id = require_user(token)?;
+    if mail_ids.is_empty() {
+        return Ok(user_id);
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT m.id FROM mail_records m JOIN emails e ON e.id = m.email_id WHERE m.deleted_at IS NULL AND e.deleted_at IS NULL AND e.user_id = ",
+    );
+    qb.push_bind(user_id);
+    qb.push(" AND m.id IN (");
+    let mut separated = qb.separated(", ");
+    for id in mail_ids {
+        separated.push_bind(id);
+    }
+    qb.push(")");
+    let owned: Vec<i64> = qb.build_query_scalar().fetch_all(pool).await?;
+    if mail_ids.iter().any(|id| !owned.contains(id)) {
+        return Err(anyhow!("邮件不存在"));
+    }
+    Ok(user_id)
+}
+
+/// 校验会话，并确认附件属于当前用户的账号
+pub async fn authorize_attachment(
+    pool: &Pool<Sqlite>,
+    token: &str,
+    attachment_id: i64,
+) -> Result<i64> {
+    let user_id = require_user(token)?;
+    let owned = sqlx::query_scalar::<_, i64>(
+        "SELECT a.id FROM attachments a JOIN mail_records m ON m.id = a.mail_id JOIN emails e ON e.id = m.email_id WHERE a.id = ? AND m.deleted_at IS NULL AND e.deleted_at IS NULL AND e.user_id = ?",
+    )
+    .bind(attachment_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    if owned.is_none() {
+        return Err(anyhow!("附件不存在"));
+    }
+    Ok(user_id)
+}
+
+async fn get_user(pool: &Pool<Sqlite>, user_id: i64) -> Result<User> {
+    sqlx::query_as::<_, User>("SELECT id, username, is_admin, created_at FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow!(SESSION_EXPIRED))
+}
+
+/// 校验用户名与密码，成功时返回用户 ID
+async fn verify_credentials(
+    pool: &Pool<Sqlite>,
+    username: &str,
+    password: &str,
+) -> Result<Option<i64>> {
+    let Some((id, password_hash, salt, rounds)) = sqlx::query_as::<_, (i64, String, String, i64)>(
+        "SELECT id, password_hash, salt, rounds FROM users WHERE username = ?",
+    )
+    .bind(username)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let hash = hash_password(password, hex::decode(salt)?, u32::try_from(rounds)?).await?;
+    let expected = hex::decode(password_hash)?;
+    Ok(constant_time_eq(hash.as_slice(), &expected).then_some(id))
+}
+
+fn validate_password(password: &str) -> Result<()> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(anyhow!("密码至少需要 {} 个字符", MIN_PASSWORD_LEN));
+    }
+    Ok(())
+}
+
+/// PBKDF2 计算密码哈希（在阻塞线程中计算）
+async fn hash_password(password: &str, salt: Vec<u8>, rounds: u32) -> Result<Zeroizing<[u8; 32]>> {
+    let password = Zeroizing::new(password.as_bytes().to_vec());
+    let hash = tokio::task::spawn_blocking(move || {
+        let mut hash = Zeroizing::new([0u8; 32]);
+        pbkdf2::pbkdf2_hmac::<Sha256>(&password, &salt, rounds, hash.as_mut());
+        hash
+    })
+    .await?;
+    Ok(hash)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_require_admin() {
+        let pool = crate::db::test_pool().await;
+        let token = session(1);
+
+        let err = require_admin(&pool, &token).await.unwrap_err();
+        assert_eq!(err.to_string(), ADMIN_REQUIRED);
+        sqlx::query("UPDATE users SET is_admin = 1 WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(require_admin(&pool, &token).await.unwrap(), 1);
+        assert!(require_admin(&pool, "unknown").await.is_err());
+        logout(&token);
+    }
+
+    fn session(user_id: i64) -> String {
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        sessions().insert(
+            token.clone(),
+            Session {
+                user_id,
+                last_used: Instant::now(),
+            },
+        );
+        token
+    }
+
+    async fn insert_email(pool: &Pool<Sqlite>, email: &str, user_id: Option<i64>) -> i64 {
+        sqlx::query_scalar(
+            "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES (?, '', 'cid', '', ?) RETURNING id",
+        )
+        .bind(email)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_register_first_user_is_admin_and_claims_existing_data() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("DELETE FROM users")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let email_id = insert_email(&pool, "legacy@x.com", None).await;
+        sqlx::query("INSERT INTO tags (name) VALUES ('legacy')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let first = register(&pool, "alice", "secret1").await.unwrap();
+        assert!(first.is_admin);
+        let owner: Option<i64> = sqlx::query_scalar("SELECT user_id FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(owner, Some(first.id));
+        let tag_owner: Option<i64> =
+            sqlx::query_scalar("SELECT user_id FROM tags WHERE name = 'legacy'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(tag_owner, Some(first.id));
+
+        let second = register(&pool, "bob", "secret2").await.unwrap();
+        assert!(!second.is_admin);
+        let err = register(&pool, "ALICE", "secret3").await.unwrap_err();
+        assert_eq!(err.to_string(), "用户名已存在");
+        assert!(register(&pool, "al", "secret1").await.is_err());
+        assert!(register(&pool, "carol", "short").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_login_rejects_wrong_password_and_unknown_user() {
+        let pool = crate::db::test_pool().await;
+        register(&pool, "alice", "secret1").await.unwrap();
+
+        let err = login(&pool, "alice", "wrong-password").await.unwrap_err();
+        assert_eq!(err.to_string(), "用户名或密码错误");
+        let err = login(&pool, "nobody", "secret1").await.unwrap_err();
+        assert_eq!(err.to_string(), "用户名或密码错误");
+
+        let session = login(&pool, " alice ", "secret1").await.unwrap();
+        assert_eq!(session.user.username, "alice");
+        assert_eq!(require_user(&session.token).unwrap(), session.user.id);
+        assert!(logout(&session.token));
+        assert!(require_user(&session.token).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_change_password_revokes_other_sessions() {
+        let pool = crate::db::test_pool().await;
+        // 会话表是全局的，使用其他测试不会用到的用户 ID，避免注销它们的会话
+        sqlx::query(
+            "INSERT INTO users (id, username, password_hash, salt, rounds) VALUES (900, 'placeholder', '', '', 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let user = register(&pool, "alice", "secret1").await.unwrap();
+        let current = login(&pool, "alice", "secret1").await.unwrap().token;
+        let other = login(&pool, "alice", "secret1").await.unwrap().token;
+
+        let err = change_password(&pool, &current, "wrong-password", "secret2")
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "原密码错误");
+        assert!(require_user(&other).is_ok());
+
+        change_password(&pool, &current, "secret1", "secret2")
+            .await
+            .unwrap();
+        assert_eq!(require_user(&current).unwrap(), user.id);
+        assert!(require_user(&other).is_err());
+        assert!(login(&pool, "alice", "secret1").await.is_err());
+        assert!(login(&pool, "alice", "secret2").await.is_ok());
+        logout(&current);
+    }
+
+    #[tokio::test]
+    async fn test_authorize_rejects_other_users_accounts_and_mails() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO users (id, username, password_hash, salt, rounds) VALUES (2, 'other', '', '', 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let own = insert_email(&pool, "own@x.com", Some(1)).await;
+        let foreign = insert_email(&pool, "foreign@x.com", Some(2)).await;
+        let mut mails = Vec::new();
+        for email_id in [own, foreign] {
+            let mail_id: i64 = sqlx::query_scalar(
+                "INSERT INTO mail_records (email_id, subject) VALUES (?, 'hi') RETURNING id",
+            )
+            .bind(email_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            mails.push(mail_id);
+        }
+        let token = session(1);
+
+        assert_eq!(authorize_accounts(&pool, &token, &[own]).await.unwrap(), 1);
+        assert!(authorize_accounts(&pool, &token, &[foreign]).await.is_err());
+        assert!(authorize_accounts(&pool, &token, &[own, foreign])
+            .await
+            .is_err());
+        assert!(authorize_accounts(&pool, "unknown", &[own]).await.is_err());
+
+        assert_eq!(
+            authorize_mails(&pool, &token, &[mails[0]]).await.unwrap(),
+            1
+        );
+        assert!(authorize_mails(&pool, &token, &[mails[1]]).await.is_err());
+        assert!(authorize_mails(&pool, &token, &mails).await.is_err());
+        logout(&token);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+    }
+}
@@ -0,0 +1,536 @@
+//! 数据库备份模块
+//! 使用 VACUUM INTO 在线备份 firemail.db，恢复前校验备份文件并暂存，
+//! 下次启动打开数据库之前替换；定时自动备份保存在 app_data/backups 并按数量轮换。
+//! 附件内容（app_data/blobs）不在备份内，恢复后与存储目录对账
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, Pool, Sqlite};
+use tokio::sync::Mutex;
+
+use crate::db;
+
+/// 数据库文件名
+pub const DB_FILE_NAME: &str = "firemail.db";
+
+/// 暂存的恢复文件名，启动时替换数据库
+const STAGED_RESTORE_NAME: &str = "firemail.db.restore";
+
+/// 恢复前的数据库副本
+const PRE_RESTORE_NAME: &str = "firemail.db.before-restore";
+
+/// 自动备份文件名前缀
+const AUTO_BACKUP_PREFIX: &str = "firemail-auto-";
+
+/// 检查是否需要自动备份的间隔（秒）
+const SCHEDULE_CHECK_SECS: u64 = 10 * 60;
+
+/// 恢复时必须存在的表
+const REQUIRED_TABLES: &[&str] = &["emails", "mail_records", "attachments"];
+
+const CONFIG_AUTO_ENABLED: &str = "backup.auto_enabled";
+const CONFIG_INTERVAL_HOURS: &str = "backup.interval_hours";
+const CONFIG_KEEP: &str = "backup.keep";
+
+const DEFAULT_INTERVAL_HOURS: i64 = 24;
+const DEFAULT_KEEP: i64 = 7;
+
+/// 应用数据目录（启动时设置）
+static APP_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// 备份互斥，避免手动备份与自动备份同时进行
+static BACKUP_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// 备份文件信息
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub size: u64,
+    pub created_at: String,
+    /// 是否为自动备份
+    pub automatic: bool,
+}
+
+/// 自动备份设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSettings {
+    pub auto_enabled: bool,
+    /// 自动备份间隔（小时）
+    pub interval_hours: i64,
+    /// 保留的自动备份数量
+    pub keep: i64,
+}
+
+/// 备份文件校验结果
+#[derive(Debug, Serialize)]
+pub struct BackupValidation {
+    pub valid: bool,
+    /// 备份的迁移版本
+    pub schema_version: i64,
+    pub account_count: i64,
+    pub mail_count: i64,
+    pub errors: Vec<String>,
+}
+
+/// 设置应用数据目录并创建备份目录
+pub fn init(app_data_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(app_data_dir.join("backups"))?;
+    APP_DIR
+        .set(app_data_dir.to_path_buf())
+        .map_err(|_| anyhow!("备份目录已初始化"))
+}
+
+fn app_dir() -> Result<&'static Path> {
+    APP_DIR
+        .get()
+        .map(PathBuf::as_path)
+        .ok_or_else(|| anyhow!("备份目录未初始化"))
+}
+
+/// 自动备份目录
+pub fn backup_dir() -> Result<PathBuf> {
+    Ok(app_dir()?.join("backups"))
+}
+
+/// 在线备份数据库；target 为目录或未指定时自动生成文件名
+pub async fn backup_database(pool: &Pool<Sqlite>, target: Option<&Path>) -> Result<BackupInfo> {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let path = match target {
+        Some(target) if !target.is_dir() => target.to_path_buf(),
+        Some(dir) => dir.join(format!("firemail-backup-{stamp}.db")),
+        None => backup_dir()?.join(format!("firemail-backup-{stamp}.db")),
+    };
+    write_backup(pool, &path, false).await
+}
+
+async fn write_backup(pool: &Pool<Sqlite>, path: &Path, automatic: bool) -> Result<BackupInfo> {
+    let _guard = BACKUP_LOCK.lock().await;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    // VACUUM INTO 要求目标文件不存在，先写入临时文件再替换
+    let tmp_path = path.with_extension(format!("tmp-{}", rand::random::<u32>()));
+    let result = sqlx::query("VACUUM INTO ?")
+        .bind(tmp_path.to_string_lossy().into_owned())
+        .execute(pool)
+        .await;
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return Err(e.into());
+    }
+    tokio::fs::rename(&tmp_path, path).await?;
+
+    let size = tokio::fs::metadata(path).await?.len();
+    log::info!("数据库备份完成: path={}, size={}", path.display(), size);
+    Ok(BackupInfo {
+        path: path.to_string_lossy().into_owned(),
+        size,
+        created_at: Utc::now().to_rfc3339(),
+        automatic,
+    })
+}
+
+/// 列出备份目录中的备份，最新的在前
+pub async fn list_backups() -> Result<Vec<BackupInfo>> {
+    let dir = backup_dir()?;
+    let mut backups = Vec::new();
+    let mut entries = tokio::fs::read_dir(&dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.ends_with(".db") {
+            continue;
+        }
+        let metadata = entry.metadata().await?;
+        let modified: DateTime<Utc> = metadata.modified()?.into();
+        backups.push(BackupInfo {
+            path: path.to_string_lossy().into_owned(),
+            size: metadata.len(),
+            created_at: modified.to_rfc3339(),
+            automatic: name.starts_with(AUTO_BACKUP_PREFIX),
+        });
+    }
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(backups)
+}
+
+/// 获取备份互斥，期间不会新建或轮换备份
+pub async fn lock() -> tokio::sync::MutexGuard<'static, ()> {
+    BACKUP_LOCK.lock().await
+}
+
+/// app_data 中保存的数据库副本：备份目录中的备份以及恢复前保留的原数据库
+pub async fn local_copies() -> Result<Vec<PathBuf>> {
+    let mut copies: Vec<PathBuf> = list_backups()
+        .await?
+        .into_iter()
+        .map(|b| PathBuf::from(b.path))
+        .collect();
+    let previous = app_dir()?.join(PRE_RESTORE_NAME);
+    if previous.is_file() {
+        copies.push(previous);
+    }
+    Ok(copies)
+}
+
+/// 校验备份文件：完整性检查、必需的表以及迁移版本不高于当前程序
+pub async fn validate_backup(path: &Path) -> Result<BackupValidation> {
+    if !path.is_file() {
+        return Err(anyhow!("备份文件不存在: {}", path.display()));
+    }
+
+    let mut conn = SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(true)
+        .connect()
+        .await
+        .map_err(|e| anyhow!("无法打开备份文件: {}", e))?;
+
+    let mut validation = BackupValidation {
+        valid: false,
+        schema_version: 0,
+        account_count: 0,
+        mail_count: 0,
+        errors: Vec::new(),
+    };
+
+    let messages = sqlx::query_scalar::<_, String>("PRAGMA integrity_check")
+        .fetch_all(&mut conn)
+        .await
+        .map_err(|e| anyhow!("不是有效的数据库文件: {}", e))?;
+    if messages != ["ok"] {
+        validation.errors.extend(messages);
+    }
+
+    let tables =
+        sqlx::query_scalar::<_, String>("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .fetch_all(&mut conn)
+            .await?;
+    for table in REQUIRED_TABLES {
+        if !tables.iter().any(|t| t == table) {
+            validation.errors.push(format!("缺少数据表: {}", table));
+        }
+    }
+
+    if tables.iter().any(|t| t == "schema_migrations") {
+        validation.schema_version =
+            sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(version) FROM schema_migrations")
+                .fetch_one(&mut conn)
+                .await?
+                .unwrap_or(0);
+    }
+    if validation.schema_version > db::latest_migration_version() {
+        validation
+            .errors
+            .push("备份来自更新版本的程序，请升级后再恢复".to_string());
+    }
+
+    if validation.errors.is_empty() {
+        validation.account_count = sqlx::query_scalar("SELECT COUNT(*) FROM emails")
+            .fetch_one(&mut conn)
+            .await?;
+        validation.mail_count = sqlx::query_scalar("SELECT COUNT(*) FROM mail_records")
+            .fetch_one(&mut conn)
+            .await?;
+        validation.valid = true;
+    }
+
+    conn.close().await?;
+    Ok(validation)
+}
+
+/// 校验并暂存备份，下次启动时替换当前数据库
+pub async fn stage_restore(pool: &Pool<Sqlite>, path: &Path) -> Result<BackupValidation> {
+    let validation = validate_backup(path).await?;
+    if !validation.valid {
+        return Err(anyhow!(
+            "备份文件校验失败: {}",
+            validation.errors.join("; ")
+        ));
+    }
+
+    // 将 WAL 写回主文件，保证替换前保留的副本完整
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool)
+        .await?;
+
+    let staged = app_dir()?.join(STAGED_RESTORE_NAME);
+    let tmp_path = staged.with_extension(format!("tmp-{}", rand::random::<u32>()));
+    tokio::fs::copy(path, &tmp_path).await?;
+    tokio::fs::rename(&tmp_path, &staged).await?;
+
+    log::info!("已暂存数据库恢复: source={}", path.display());
+    Ok(validation)
+}
+
+/// 启动时应用暂存的恢复文件，返回是否已恢复；原数据库保留为 firemail.db.before-restore
+pub fn apply_staged_restore(app_data_dir: &Path) -> Result<bool> {
+    let staged = app_data_dir.join(STAGED_RESTORE_NAME);
+    if !staged.exists() {
+        return Ok(false);
+    }
+
+    let db_path = app_data_dir.join(DB_FILE_NAME);
+    let previous = app_data_dir.join(PRE_RESTORE_NAME);
+    // WAL / SHM 文件属于原数据库，必须一并移走，否则会被应用到恢复的数据库
+    for suffix in ["", "-wal", "-shm"] {
+        let current = PathBuf::from(format!("{}{}", db_path.display(), suffix));
+        let kept = PathBuf::from(format!("{}{}", previous.display(), suffix));
+        if kept.exists() {
+            std::fs::remove_file(&kept)?;
+        }
+        if current.exists() {
+            std::fs::rename(&current, &kept)?;
+        }
+    }
+    std::fs::rename(&staged, &db_path)?;
+
+    log::info!("已从暂存文件恢复数据库");
+    Ok(true)
+}
+
+/// 读取自动备份设置
+pub async fn get_settings(pool: &Pool<Sqlite>) -> Result<BackupSettings> {
+    let auto_enabled = db::get_config(pool, CONFIG_AUTO_ENABLED).await?;
+    let interval_hours = db::get_config(pool, CONFIG_INTERVAL_HOURS).await?;
+    let keep = db::get_config(pool, CONFIG_KEEP).await?;
+
+    Ok(BackupSettings {
+        auto_enabled: auto_enabled.as_deref() == Some("true"),
+        interval_hours: interval_hours
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_INTERVAL_HOURS),
+        keep: keep.and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_KEEP),
+    })
+}
+
+/// 保存自动备份设置
+pub async fn set_settings(pool: &Pool<Sqlite>, settings: &BackupSettings) -> Result<()> {
+    if settings.interval_hours <= 0 {
+        return Err(anyhow!("备份间隔必须大于 0"));
+    }
+    if settings.keep <= 0 {
+        return Err(anyhow!("保留数量必须大于 0"));
+    }
+
+    db::set_config(
+        pool,
+        CONFIG_AUTO_ENABLED,
+        if settings.auto_enabled {
+            "true"
+        } else {
+            "false"
+        },
+        Some("是否启用自动备份"),
+    )
+    .await?;
+    db::set_config(
+        pool,
+        CONFIG_INTERVAL_HOURS,
+        &settings.interval_hours.to_string(),
+        Some("自动备份间隔（小时）"),
+    )
+    .await?;
+    db::set_config(
+        pool,
+        CONFIG_KEEP,
+        &settings.keep.to_string(),
+        Some("保留的自动备份数量"),
+    )
+    .await?;
+    Ok(())
+}
+
+/// 后台自动备份：定期检查距上次自动备份的时间，到期后备份并轮换
+pub async fn run_scheduler(pool: Pool<Sqlite>) {
+    loop {
+        if let Err(e) = run_scheduled_backup(&pool).await {
+            log::warn!("自动备份失败: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(SCHEDULE_CHECK_SECS)).await;
+    }
+}
+
+/// 到期时执行一次自动备份，返回新备份
+async fn run_scheduled_backup(pool: &Pool<Sqlite>) -> Result<Option<BackupInfo>> {
+    let settings = get_settings(pool).await?;
+    if !settings.auto_enabled {
+        return Ok(None);
+    }
+
+    let backups = list_backups().await?;
+    let last = backups
+        .iter()
+        .find(|b| b.automatic)
+        .and_then(|b| DateTime::parse_from_rfc3339(&b.created_at).ok());
+    if let Some(last) = last {
+        let elapsed = Utc::now().signed_duration_since(last);
+        if elapsed < chrono::Duration::hours(settings.interval_hours) {
+            return Ok(None);
+        }
+    }
+
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let path = backup_dir()?.join(format!("{AUTO_BACKUP_PREFIX}{stamp}.db"));
+    let info = write_backup(pool, &path, true).await?;
+    rotate_backups(settings.keep as usize).await?;
+    Ok(Some(info))
+}
+
+/// 只保留最新的 keep 个自动备份，手动备份不受影响
+async fn rotate_backups(keep: usize) -> Result<()> {
+    let backups = list_backups().await?;
+    for backup in backups.iter().filter(|b| b.automatic).skip(keep) {
+        match tokio::fs::remove_file(&backup.path).await {
+            Ok(()) => log::info!("已删除过期的自动备份: {}", backup.path),
+            Err(e) => log::warn!("删除自动备份失败: {}: {}", backup.path, e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    /// 测试共用的应用数据目录（APP_DIR 只能设置一次）
+    fn test_app_dir() -> &'static Path {
+        static DIR: OnceLock<PathBuf> = OnceLock::new();
+        DIR.get_or_init(|| {
+            let dir = std::env::temp_dir().join(format!("backup-test-{}", rand::random::<u32>()));
+            init(&dir).unwrap();
+            dir
+        })
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.db", name, rand::random::<u32>()))
+    }
+
+    #[tokio::test]
+    async fn test_validate_backup() {
+        let source = temp_path("backup-source");
+        let pool = db::test_file_pool(&source).await;
+        sqlx::query(
+            "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES ('a@x.com', '', 'cid', '', 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let path = temp_path("backup-valid");
+        let info = backup_database(&pool, Some(&path)).await.unwrap();
+        assert!(!info.automatic);
+        assert!(info.size > 0);
+        let validation = validate_backup(&path).await.unwrap();
+        assert!(validation.valid, "{:?}", validation.errors);
+        assert_eq!(validation.schema_version, db::latest_migration_version());
+        assert_eq!(validation.account_count, 1);
+        assert_eq!(validation.mail_count, 0);
+
+        // 缺少必需的表
+        let partial = temp_path("backup-partial");
+        let mut conn = SqliteConnectOptions::new()
+            .filename(&partial)
+            .create_if_missing(true)
+            .connect()
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE emails (id INTEGER PRIMARY KEY)")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        conn.close().await.unwrap();
+        let validation = validate_backup(&partial).await.unwrap();
+        assert!(!validation.valid);
+        assert_eq!(validation.errors.len(), 2);
+
+        // 不是数据库文件
+        let garbage = temp_path("backup-garbage");
+        std::fs::write(&garbage, b"not a database").unwrap();
+        assert!(validate_backup(&garbage).await.is_err());
+        assert!(validate_backup(&temp_path("backup-missing")).await.is_err());
+
+        for file in [source, path, partial, garbage] {
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stage_and_apply_restore() {
+        let app_dir = test_app_dir();
+        let source = temp_path("backup-source");
+        let pool = db::test_file_pool(&source).await;
+        let backup = temp_path("backup-restore");
+        backup_database(&pool, Some(&backup)).await.unwrap();
+
+        // 校验失败的文件不会被暂存
+        let garbage = temp_path("backup-garbage");
+        std::fs::write(&garbage, b"not a database").unwrap();
+        assert!(stage_restore(&pool, &garbage).await.is_err());
+        assert!(!apply_staged_restore(app_dir).unwrap());
+
+        stage_restore(&pool, &backup).await.unwrap();
+        let db_path = app_dir.join(DB_FILE_NAME);
+        std::fs::write(&db_path, b"current").unwrap();
+        std::fs::write(app_dir.join(format!("{DB_FILE_NAME}-wal")), b"wal").unwrap();
+
+        assert!(apply_staged_restore(app_dir).unwrap());
+        assert_eq!(
+            std::fs::read(&db_path).unwrap(),
+            std::fs::read(&backup).unwrap()
+        );
+        assert!(!app_dir.join(STAGED_RESTORE_NAME).exists());
+        // 原数据库及其 WAL 一并保留，不会应用到恢复的数据库
+        assert_eq!(
+            std::fs::read(app_dir.join(PRE_RESTORE_NAME)).unwrap(),
+            b"current"
+        );
+        assert!(!app_dir.join(format!("{DB_FILE_NAME}-wal")).exists());
+        assert!(app_dir.join(format!("{PRE_RESTORE_NAME}-wal")).exists());
+        assert!(local_copies()
+            .await
+            .unwrap()
+            .contains(&app_dir.join(PRE_RESTORE_NAME)));
+
+        assert!(!apply_staged_restore(app_dir).unwrap());
+        for file in [source, backup, garbage] {
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotate_backups_keeps_latest_automatic() {
+        let dir = test_app_dir().join("backups");
+        let now = SystemTime::now();
+        let write = |name: &str, age_secs: u64| {
+            let path = dir.join(name);
+            let file = std::fs::File::create(&path).unwrap();
+            file.set_modified(now - Duration::from_secs(age_secs))
+                .unwrap();
+            path
+        };
+        let newest = write(&format!("{AUTO_BACKUP_PREFIX}3.db"), 10);
+        let middle = write(&format!("{AUTO_BACKUP_PREFIX}2.db"), 20);
+        let oldest = write(&format!("{AUTO_BACKUP_PREFIX}1.db"), 30);
+        let manual = write("firemail-backup-old.db", 40);
+
+        rotate_backups(2).await.unwrap();
+
+        assert!(newest.exists());
+        assert!(middle.exists());
+        assert!(!oldest.exists());
+        assert!(manual.exists());
+        let backups = list_backups().await.unwrap();
+        assert_eq!(backups.iter().filter(|b| b.automatic).count(), 2);
+        assert_eq!(backups[0].path, newest.to_string_lossy());
+    }
+}
@@ -0,0 +1,620 @@
+//! 批量收件任务模块
+//!
+//! 以有限并发执行批量收件，每完成一个账号即通过 Tauri 事件推送进度，
+//! 支持按任务 ID 取消以及查询运行中任务的阶段性结果；任务只对发起的用户可见
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{watch, Mutex, Semaphore};
+use tokio::task::JoinSet;
+
+use crate::email::{self, CheckResult};
+
+/// 默认并发数
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// 并发数与每个代理并发数的上限
+const MAX_CONCURRENCY: usize = 32;
+
+/// 最多保留的已结束任务数量
+const MAX_FINISHED_JOBS: usize = 20;
+
+/// 批量任务状态
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchJobStatus {
+    /// 任务 ID
+    pub job_id: String,
+    /// 文件夹
+    pub folder: String,
+    /// 开始时间
+    pub started_at: String,
+    /// 账号总数
+    pub total: usize,
+    /// 已完成数量
+    pub completed: usize,
+    /// 成功数量
+    pub success_count: usize,
+    /// 失败数量
+    pub failed_count: usize,
+    /// 是否仍在运行
+    pub running: bool,
+    /// 是否已取消
+    pub cancelled: bool,
+    /// 已完成账号的结果（按完成顺序）
+    pub results: Vec<CheckResult>,
+}
+
+/// 单个账号完成时的进度事件 payload
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgressEvent {
+    /// 任务 ID
+    pub job_id: String,
+    /// 已完成数量
+    pub completed: usize,
+    /// 账号总数
+    pub total: usize,
+    /// 本次完成的账号结果
+    pub result: CheckResult,
+}
+
+/// 批量任务
+struct BatchJob {
+    /// 发起任务的用户
+    user_id: i64,
+    status: BatchJobStatus,
+    /// 取消信号
+    cancel_tx: watch::Sender<bool>,
+}
+
+/// 批量任务管理器
+pub struct BatchJobManager {
+    /// 任务列表 (job_id -> job)
+    jobs: Arc<Mutex<HashMap<String, BatchJob>>>,
+}
+
+impl BatchJobManager {
+    pub fn new() -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 启动批量收件任务，返回任务 ID
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start_job(
+        &self,
+        app_handle: AppHandle,
+        pool: Pool<Sqlite>,
+        user_id: i64,
+        email_ids: Vec<i64>,
+        folder: String,
+        concurrency: Option<usize>,
+        per_proxy_limit: Option<usize>,
+    ) -> Result<String, String> {
+        let job_id = format!("batch-{}", hex::encode(rand::random::<[u8; 8]>()));
+        let (concurrency, per_proxy_limit) = job_limits(concurrency, per_proxy_limit);
+
+        // 按代理分组限流
+        let proxy_keys = load_proxy_keys(&pool, &email_ids)
+            .await
+            .map_err(|e| format!("获取代理配置失败: {}", e))?;
+
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+
+        {
+            let mut jobs = self.jobs.lock().await;
+            prune_finished_jobs(&mut jobs);
+            jobs.insert(
+                job_id.clone(),
+                BatchJob {
+                    user_id,
+                    status: BatchJobStatus {
+                        job_id: job_id.clone(),
+                        folder: folder.clone(),
+                        started_at: Utc::now().to_rfc3339(),
+                        total: email_ids.len(),
+                        completed: 0,
+                        success_count: 0,
+                        failed_count: 0,
+                        running: true,
+                        cancelled: false,
+                        results: Vec::new(),
+                    },
+                    cancel_tx,
+                },
+            );
+        }
+
+        let jobs = self.jobs.clone();
+        let job_id_clone = job_id.clone();
+
+        tokio::spawn(async move {
+            run_job(
+                app_handle,
+                pool,
+                job_id_clone,
+                email_ids,
+                folder,
+                concurrency,
+                per_proxy_limit,
+                proxy_keys,
+                cancel_rx,
+                jobs,
+            )
+            .await;
+        });
+
+        Ok(job_id)
+    }
+
+    /// 取消任务，已完成的账号结果会保留
+    pub async fn cancel_job(&self, user_id: i64, job_id: &str) -> Result<bool, String> {
+        let mut jobs = self.jobs.lock().await;
+        match jobs.get_mut(job_id).filter(|job| job.user_id == user_id) {
+            Some(job) if job.status.running => {
+                job.status.cancelled = true;
+                let _ = job.cancel_tx.send(true);
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(format!("任务不存在: {}", job_id)),
+        }
+    }
+
+    /// 查询任务状态（含已完成账号的结果）
+    pub async fn get_status(&self, user_id: i64, job_id: &str) -> Option<BatchJobStatus> {
+        let jobs = self.jobs.lock().await;
+        jobs.get(job_id)
+            .filter(|job| job.user_id == user_id)
+            .map(|job| job.status.clone())
+    }
+}
+
+/// 确定任务的并发数与每个代理的并发数（均不超过上限；每个代理的并发数为 0 或未指定时不单独限制）
+fn job_limits(
+    concurrency: Option<usize>,
+    per_proxy_limit: Option<usize>,
+) -> (usize, Option<usize>) {
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .clamp(1, MAX_CONCURRENCY);
+    let per_proxy_limit = per_proxy_limit
+        .filter(|limit| *limit > 0)
+        .map(|limit| limit.clamp(1, MAX_CONCURRENCY));
+    (concurrency, per_proxy_limit)
+}
+
+/// 清理多余的已结束任务
+fn prune_finished_jobs(jobs: &mut HashMap<String, BatchJob>) {
+    let mut finished: Vec<(String, String)> = jobs
+        .iter()
+        .filter(|(_, job)| !job.status.running)
+        .map(|(id, job)| (job.status.started_at.clone(), id.clone()))
+        .collect();
+    if finished.len() < MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort();
+    for (_, id) in finished.iter().take(finished.len() + 1 - MAX_FINISHED_JOBS) {
+        jobs.remove(id);
+    }
+}
+
+/// 读取账号对应的代理标识 (email_id -> proxy key)
+async fn load_proxy_keys(
+    pool: &Pool<Sqlite>,
+    email_ids: &[i64],
+) -> anyhow::Result<HashMap<i64, String>> {
+    let mut keys = HashMap::new();
+    for email_id in email_ids {
+        let row = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+            "SELECT proxy_type, proxy_url FROM emails WHERE id = ?",
+        )
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await?;
+        let key = match row {
+            Some((Some(proxy_type), Some(proxy_url))) if proxy_type != "none" => proxy_url,
+            _ => "direct".to_string(),
+        };
+        keys.insert(*email_id, key);
+    }
+    Ok(keys)
+}
+
+/// 运行批量任务
+#[allow(clippy::too_many_arguments)]
+async fn run_job(
+    app_handle: AppHandle,
+    pool: Pool<Sqlite>,
+    job_id: String,
+    email_ids: Vec<i64>,
+    folder: String,
+    concurrency: usize,
+    per_proxy_limit: Option<usize>,
+    proxy_keys: HashMap<i64, String>,
+    cancel_rx: watch::Receiver<bool>,
+    jobs: Arc<Mutex<HashMap<String, BatchJob>>>,
+) {
+    log::info!(
+        "批量收件任务启动: job_id={}, total={}, concurrency={}, per_proxy_limit={:?}",
+        job_id,
+        email_ids.len(),
+        concurrency,
+        per_proxy_limit
+    );
+
+    let check = |email_id: i64| {
+        let pool = pool.clone();
+        let folder = folder.clone();
+        async move { email::check_outlook_email(&pool, email_id, &folder).await }
+    };
+    let final_status = execute_job(
+        &job_id,
+        email_ids,
+        concurrency,
+        per_proxy_limit,
+        &proxy_keys,
+        cancel_rx,
+        &jobs,
+        check,
+        |event| {
+            let _ = app_handle.emit("batch-check-progress", event);
+        },
+    )
+    .await;
+
+    if let Some(status) = final_status {
+        log::info!(
+            "批量收件任务结束: job_id={}, completed={}/{}, cancelled={}",
+            job_id,
+            status.completed,
+            status.total,
+            status.cancelled
+        );
+        let _ = app_handle.emit("batch-check-finished", status);
+    }
+}
+
+/// 按全局并发与代理限流执行收件，每完成一个账号更新任务状态并回调进度，
+/// 返回最终状态（不含结果明细）
+#[allow(clippy::too_many_arguments)]
+async fn execute_job<F, Fut>(
+    job_id: &str,
+    email_ids: Vec<i64>,
+    concurrency: usize,
+    per_proxy_limit: Option<usize>,
+    proxy_keys: &HashMap<i64, String>,
+    cancel_rx: watch::Receiver<bool>,
+    jobs: &Mutex<HashMap<String, BatchJob>>,
+    check: F,
+    mut on_progress: impl FnMut(BatchProgressEvent),
+) -> Option<BatchJobStatus>
+where
+    F: Fn(i64) -> Fut,
+    Fut: Future<Output = anyhow::Result<CheckResult>> + Send + 'static,
+{
+    let global = Arc::new(Semaphore::new(concurrency));
+    let mut proxy_limits: HashMap<String, Arc<Semaphore>> = HashMap::new();
+    let mut tasks = JoinSet::new();
+
+    for email_id in email_ids {
+        let proxy_limit = per_proxy_limit.map(|limit| {
+            let key = proxy_keys
+                .get(&email_id)
+                .cloned()
+                .unwrap_or_else(|| "direct".to_string());
+            proxy_limits
+                .entry(key)
+                .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+                .clone()
+        });
+        let global = global.clone();
+        let account_check = check(email_id);
+        let mut cancel_rx = cancel_rx.clone();
+
+        tasks.spawn(async move {
+            let check = async {
+                // 先占用代理名额再占用全局名额，避免等待代理时占住全局并发
+                let _proxy_permit = match proxy_limit {
+                    Some(sem) => Some(sem.acquire_owned().await),
+                    None => None,
+                };
+                let _permit = global.acquire_owned().await;
+                account_check.await
+            };
+
+            tokio::select! {
+                result = check => Some(match result {
+                    Ok(result) => result,
+                    Err(e) => CheckResult::failure(email_id, format!("收件失败: {e}")),
+                }),
+                _ = cancel_rx.wait_for(|cancelled| *cancelled) => None,
+            }
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        let result = match joined {
+            Ok(Some(result)) => result,
+            // 已取消的账号不计入结果
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("批量收件子任务异常: job_id={}, error={}", job_id, e);
+                continue;
+            }
+        };
+
+        let event = {
+            let mut jobs = jobs.lock().await;
+            let Some(job) = jobs.get_mut(job_id) else {
+                break;
+            };
+            let status = &mut job.status;
+            status.completed += 1;
+            if result.success {
+                status.success_count += 1;
+            } else {
+                status.failed_count += 1;
+            }
+            status.results.push(result.clone());
+
+            BatchProgressEvent {
+                job_id: job_id.to_string(),
+                completed: status.completed,
+                total: status.total,
+                result,
+            }
+        };
+
+        on_progress(event);
+    }
+
+    let mut jobs = jobs.lock().await;
+    jobs.get_mut(job_id).map(|job| {
+        job.status.running = false;
+        let mut status = job.status.clone();
+        status.results.clear();
+        status
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    const JOB_ID: &str = "batch-test";
+
+    fn success(email_id: i64) -> CheckResult {
+        CheckResult {
+            email_id,
+            success: true,
+            fetched: 1,
+            saved: 1,
+            deleted: 0,
+            message: String::new(),
+            new_mail_ids: Vec::new(),
+        }
+    }
+
+    /// 登记一个运行中的任务（用户 1），返回取消信号接收端
+    async fn register_job(manager: &BatchJobManager, total: usize) -> watch::Receiver<bool> {
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+        manager.jobs.lock().await.insert(
+            JOB_ID.to_string(),
+            BatchJob {
+                user_id: 1,
+                status: BatchJobStatus {
+                    job_id: JOB_ID.to_string(),
+                    folder: "INBOX".to_string(),
+                    started_at: Utc::now().to_rfc3339(),
+                    total,
+                    completed: 0,
+                    success_count: 0,
+                    failed_count: 0,
+                    running: true,
+                    cancelled: false,
+                    results: Vec::new(),
+                },
+                cancel_tx,
+            },
+        );
+        cancel_rx
+    }
+
+    /// 记录同时运行的检查数量峰值
+    #[derive(Default)]
+    struct Gauge {
+        active: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl Gauge {
+        async fn hold(&self) {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_job_limits_are_bounded() {
+        assert_eq!(job_limits(None, None), (DEFAULT_CONCURRENCY, None));
+        assert_eq!(job_limits(Some(0), Some(0)), (1, None));
+        assert_eq!(
+            job_limits(Some(1000), Some(1000)),
+            (MAX_CONCURRENCY, Some(MAX_CONCURRENCY))
+        );
+        assert_eq!(job_limits(Some(4), Some(2)), (4, Some(2)));
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_and_progress() {
+        let manager = BatchJobManager::new();
+        let cancel_rx = register_job(&manager, 6).await;
+        let gauge = Arc::new(Gauge::default());
+        let mut events = Vec::new();
+
+        let check = |email_id: i64| {
+            let gauge = gauge.clone();
+            async move {
+                gauge.hold().await;
+                if email_id == 3 {
+                    anyhow::bail!("token expired");
+                }
+                Ok(success(email_id))
+            }
+        };
+        let status = execute_job(
+            JOB_ID,
+            (1..=6).collect(),
+            2,
+            None,
+            &HashMap::new(),
+            cancel_rx,
+            &manager.jobs,
+            check,
+            |event| events.push(event),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(gauge.peak.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            events.iter().map(|e| e.completed).collect::<Vec<_>>(),
+            (1..=6).collect::<Vec<_>>()
+        );
+        assert!(events.iter().all(|e| e.total == 6 && e.job_id == JOB_ID));
+        let failed = events.iter().find(|e| e.result.email_id == 3).unwrap();
+        assert!(!failed.result.success);
+        assert!(failed.result.message.contains("token expired"));
+
+        assert!(!status.running);
+        assert_eq!(
+            (status.completed, status.success_count, status.failed_count),
+            (6, 5, 1)
+        );
+        // 最终状态不带结果明细，查询接口仍保留
+        assert!(status.results.is_empty());
+        let queried = manager.get_status(1, JOB_ID).await.unwrap();
+        assert_eq!(queried.results.len(), 6);
+        assert!(manager.get_status(2, JOB_ID).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_per_proxy_limit() {
+        let pool = db::test_pool().await;
+        let mut email_ids = Vec::new();
+        for (i, proxy) in [
+            Some("socks5://a"),
+            Some("socks5://a"),
+            Some("socks5://a"),
+            None,
+            None,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO emails (email, password, client_id, refresh_token, user_id, proxy_type, proxy_url) VALUES (?, '', 'cid', '', 1, ?, ?) RETURNING id",
+            )
+            .bind(format!("u{i}@x.com"))
+            .bind(if proxy.is_some() { "socks5" } else { "none" })
+            .bind(proxy)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            email_ids.push(id);
+        }
+        let proxy_keys = load_proxy_keys(&pool, &email_ids).await.unwrap();
+        assert_eq!(proxy_keys[&email_ids[0]], "socks5://a");
+        assert_eq!(proxy_keys[&email_ids[4]], "direct");
+
+        let manager = BatchJobManager::new();
+        let cancel_rx = register_job(&manager, email_ids.len()).await;
+        let gauges: HashMap<String, Arc<Gauge>> = ["socks5://a", "direct"]
+            .into_iter()
+            .map(|key| (key.to_string(), Arc::new(Gauge::default())))
+            .collect();
+
+        let check = |email_id: i64| {
+            let gauge = gauges[&proxy_keys[&email_id]].clone();
+            async move {
+                gauge.hold().await;
+                Ok(success(email_id))
+            }
+        };
+        let status = execute_job(
+            JOB_ID,
+            email_ids,
+            8,
+            Some(1),
+            &proxy_keys,
+            cancel_rx,
+            &manager.jobs,
+            check,
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(status.success_count, 5);
+        for gauge in gauges.values() {
+            assert_eq!(gauge.peak.load(Ordering::SeqCst), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_keeps_finished_results() {
+        let manager = BatchJobManager::new();
+        let cancel_rx = register_job(&manager, 3).await;
+        let proxy_keys = HashMap::new();
+
+        // 账号 1 立即完成，其余账号一直挂起直到取消
+        let check = |email_id: i64| async move {
+            if email_id != 1 {
+                std::future::pending::<()>().await;
+            }
+            Ok(success(email_id))
+        };
+        let run = execute_job(
+            JOB_ID,
+            vec![1, 2, 3],
+            3,
+            None,
+            &proxy_keys,
+            cancel_rx,
+            &manager.jobs,
+            check,
+            |_| {},
+        );
+        let cancel = async {
+            while manager.get_status(1, JOB_ID).await.unwrap().completed == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            assert!(manager.cancel_job(2, JOB_ID).await.is_err());
+            assert!(manager.cancel_job(1, JOB_ID).await.unwrap());
+        };
+        let (status, _) = tokio::join!(run, cancel);
+        let status = status.unwrap();
+
+        assert!(status.cancelled);
+        assert!(!status.running);
+        assert_eq!((status.total, status.completed), (3, 1));
+        let queried = manager.get_status(1, JOB_ID).await.unwrap();
+        assert_eq!(queried.results.len(), 1);
+        assert_eq!(queried.results[0].email_id, 1);
+        // 已结束的任务不能再次取消
+        assert!(!manager.cancel_job(1, JOB_ID).await.unwrap());
+    }
+}
@@ -0,0 +1,286 @@
+//! 附件内容存储模块
+//! 附件按 SHA-256 保存在应用数据目录的 blobs 目录下，相同内容只保存一份；
+//! 引用计数由 attachments 表上的触发器维护，计数归零的内容由垃圾回收删除
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Sqlite};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// 存储根目录（启动时设置）
+static BLOB_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// 写入与回收互斥，避免回收时删除刚被重新引用的文件
+static STORE_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// 每批迁移的内嵌附件数量
+const MIGRATE_BATCH_SIZE: i64 = 50;
+
+/// 垃圾回收结果
+#[derive(Debug, Default, Serialize)]
+pub struct GcResult {
+    pub removed: usize,
+    pub freed_bytes: i64,
+}
+
+/// 设置存储根目录（app_data/blobs）
+pub fn init(app_data_dir: &Path) -> Result<()> {
+    let root = app_data_dir.join("blobs");
+    std::fs::create_dir_all(&root)?;
+    BLOB_ROOT
+        .set(root)
+        .map_err(|_| anyhow!("附件存储目录已初始化"))
+}
+
+fn root() -> Result<&'static Path> {
+    BLOB_ROOT
+        .get()
+        .map(PathBuf::as_path)
+        .ok_or_else(|| anyhow!("附件存储目录未初始化"))
+}
+
+/// 计算内容的 SHA-256（小写十六进制）
+pub fn hash_content(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+/// 内容文件路径：按哈希前两位分目录；哈希必须是 64 位小写十六进制，避免越界或拼出存储目录外的路径
+fn blob_path(root: &Path, hash: &str) -> Result<PathBuf> {
+    let valid = hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    if !valid {
+        return Err(anyhow!("无效的附件内容哈希: {}", hash));
+    }
+    Ok(root.join(&hash[..2]).join(hash))
+}
+
+/// 获取写入锁；写入内容并登记 attachments 引用期间需持有
+pub async fn lock() -> MutexGuard<'static, ()> {
+    STORE_LOCK.lock().await
+}
+
+/// 保存内容并登记到 attachment_blobs，返回哈希
+///
+/// 调用方需持有 [`lock`]，并在释放前插入引用该哈希的附件记录
+pub async fn put(pool: &Pool<Sqlite>, content: &[u8]) -> Result<String> {
+    let hash = hash_content(content);
+    let path = blob_path(root()?, &hash)?;
+
+    if !tokio::fs::try_exists(&path).await? {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // 先写临时文件再重命名，避免中断时留下不完整的内容
+        let tmp_path = path.with_extension(format!("tmp-{}", rand::random::<u32>()));
+        tokio::fs::write(&tmp_path, content).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+    }
+
+    sqlx::query(
+        "INSERT INTO attachment_blobs (hash, size) VALUES (?, ?) ON CONFLICT(hash) DO NOTHING",
+    )
+    .bind(&hash)
+    .bind(content.len() as i64)
+    .execute(pool)
+    .await?;
+
+    Ok(hash)
+}
+
+/// 内容文件路径，可直接复制而无需读入内存
+pub fn path_of(hash: &str) -> Result<PathBuf> {
+    let path = blob_path(root()?, hash)?;
+    if !path.exists() {
+        return Err(anyhow!("附件内容文件不存在 ({})", hash));
+    }
+    Ok(path)
+}
+
+/// 删除引用计数归零的内容
+pub async fn collect_garbage(pool: &Pool<Sqlite>) -> Result<GcResult> {
+    let _guard = lock().await;
+    let root = root()?;
+
+    let unreferenced = sqlx::query_as::<_, (String, i64)>(
+        "DELETE FROM attachment_blobs WHERE ref_count <= 0 RETURNING hash, size",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut result = GcResult::default();
+    for (hash, size) in unreferenced {
+        let path = match blob_path(root, &hash) {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("{}", e);
+                continue;
+            }
+        };
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                log::warn!("删除附件内容失败: hash={}, error={}", hash, e);
+                continue;
+            }
+        }
+        result.removed += 1;
+        result.freed_bytes += size;
+    }
+
+    if result.removed > 0 {
+        log::info!(
+            "附件垃圾回收完成: removed={}, freed_bytes={}",
+            result.removed,
+            result.freed_bytes
+        );
+    }
+    Ok(result)
+}
+
+/// 删除邮件后回收附件内容，失败只记录日志
+pub async fn collect_garbage_quietly(pool: &Pool<Sqlite>) {
+    if let Err(e) = collect_garbage(pool).await {
+        log::warn!("附件垃圾回收失败: {}", e);
+    }
+}
+
+/// 数据库与存储目录的对账结果
+#[derive(Debug, Default, Serialize)]
+pub struct ReconcileResult {
+    /// 删除的未登记内容文件数
+    pub orphans_removed: usize,
+    /// 内容文件缺失、改为按需重新下载的附件数
+    pub redownloadable: u64,
+    /// 内容文件缺失且无法重新下载的附件数
+    pub missing: i64,
+}
+
+/// 对账数据库与存储目录（恢复备份后执行）：删除未登记的内容文件，
+/// 内容缺失的附件若有服务器引用则清除 blob_hash 以便重新下载
+pub async fn reconcile(pool: &Pool<Sqlite>) -> Result<ReconcileResult> {
+    let mut result = ReconcileResult::default();
+    {
+        let _guard = lock().await;
+        let root = root()?;
+
+        let known: HashSet<String> =
+            sqlx::query_scalar::<_, String>("SELECT hash FROM attachment_blobs")
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .collect();
+
+        let mut on_disk = HashSet::new();
+        for dir in std::fs::read_dir(root)? {
+            let dir = dir?.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            for file in std::fs::read_dir(&dir)? {
+                let path = file?.path();
+                let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if known.contains(name) {
+                    on_disk.insert(name.to_string());
+                    continue;
+                }
+                match tokio::fs::remove_file(&path).await {
+                    Ok(()) => result.orphans_removed += 1,
+                    Err(e) => log::warn!("删除未登记的附件内容失败: {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        for hash in known.difference(&on_disk) {
+            result.redownloadable += sqlx::query(
+                "UPDATE attachments SET blob_hash = NULL WHERE blob_hash = ? AND remote_ref IS NOT NULL",
+            )
+            .bind(hash)
+            .execute(pool)
+            .await?
+            .rows_affected();
+            result.missing += sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM attachments WHERE blob_hash = ?",
+            )
+            .bind(hash)
+            .fetch_one(pool)
+            .await?;
+        }
+    }
+
+    collect_garbage(pool).await?;
+    log::info!(
+        "附件存储对账完成: orphans_removed={}, redownloadable={}, missing={}",
+        result.orphans_removed,
+        result.redownloadable,
+        result.missing
+    );
+    Ok(result)
+}
+
+/// 将仍保存在数据库中的附件内容迁移到存储目录，返回迁移数量
+pub async fn migrate_inline_blobs(pool: &Pool<Sqlite>) -> Result<usize> {
+    let mut migrated = 0usize;
+
+    loop {
+        let rows = sqlx::query_as::<_, (i64, Vec<u8>)>(
+            "SELECT id, content FROM attachments WHERE blob_hash IS NULL AND content IS NOT NULL LIMIT ?",
+        )
+        .bind(MIGRATE_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+        if rows.is_empty() {
+            break;
+        }
+
+        let _guard = lock().await;
+        for (id, content) in rows {
+            let hash = put(pool, &content).await?;
+            sqlx::query("UPDATE attachments SET blob_hash = ?, content = NULL WHERE id = ?")
+                .bind(&hash)
+                .bind(id)
+                .execute(pool)
+                .await?;
+            migrated += 1;
+        }
+    }
+
+    if migrated > 0 {
+        log::info!("附件内容迁移完成: migrated={}", migrated);
+    }
+
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_path() {
+        let hash = hash_content(b"hello");
+        assert_eq!(
+            hash,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        let path = blob_path(Path::new("/data/blobs"), &hash).unwrap();
+        assert_eq!(path, Path::new("/data/blobs/2c").join(&hash));
+    }
+
+    #[test]
+    fn test_blob_path_rejects_invalid_hashes() {
+        let root = Path::new("/data/blobs");
+        let hash = hash_content(b"hello");
+        assert!(blob_path(root, "").is_err());
+        assert!(blob_path(root, "a").is_err());
+        assert!(blob_path(root, &hash[..63]).is_err());
+        assert!(blob_path(root, &hash.to_uppercase()).is_err());
+        assert!(blob_path(root, &format!("../{}", &hash[3..])).is_err());
+    }
+}
@@ -1,22 +1,104 @@
+use crate::archive::{
+    self, MailboxExportResult, MailboxFormat, MailboxImportResult, MailboxSelection,
+};
+use crate::attachments::{self, AttachmentExportFilter, AttachmentExportResult, ExportFormat};
+use crate::audit::{self, AuditEntry, AuditExportFormat, AuditQuery};
+use crate::auth::{self, LoginSession, User};
+use crate::backup::{self, BackupInfo, BackupSettings, BackupValidation};
+use crate::credentials::{self, AccountCredentials, CredentialField};
 use crate::db::AppState;
 use crate::email::{
     self, AttachmentContent, AttachmentInfo, BatchCheckResult, CheckResult, EmailAccount,
-    ImportResult, MailRecord,
+    EmailFilter, ImportResult, MailRecord, ValidateResult,
+};
+use crate::folders::MailFolder;
+use crate::mail_actions::{self, BatchMailActionResult, MailAction};
+use crate::mail_state::{self, UnreadCount};
+use crate::maintenance::{self, IntegrityReport, StorageReport, VacuumResult};
+use crate::notifications::{self, MuteInput, NotificationMute, NotificationSettings};
+use crate::rules::{self, MailRule, MailRuleInput, RuleHit, RuleTestResult};
+use crate::settings::{self, AppSettings, SettingsChange};
+use crate::tags::{self, Tag, TagAssignResult};
+use crate::trash::{self, PurgeResult, TrashedAccount, TrashedMail};
+use crate::tray::{self, BackgroundSettings};
+use crate::vault::{self, VaultStatus};
+use crate::webhooks::{
+    self, DeliveryQuery, Webhook, WebhookDelivery, WebhookInput, WebhookSecret, WebhookTestResult,
 };
+use std::path::PathBuf;
 use tauri::State;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_fs::FsExt;
+
+#[tauri::command]
+/// 注册本地用户
+pub async fn register(
+    state: State<'_, AppState>,
+    username: String,
+    password: String,
+) -> Result<User, String> {
+    match auth::register(&state.db, &username, &password).await {
+        Ok(user) => Ok(user),
+        Err(e) => Err(format!("注册失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 登录，返回会话令牌
+pub async fn login(
+    state: State<'_, AppState>,
+    username: String,
+    password: String,
+) -> Result<LoginSession, String> {
+    match auth::login(&state.db, &username, &password).await {
+        Ok(session) => Ok(session),
+        Err(e) => Err(format!("登录失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 注销会话
+pub async fn logout(token: String) -> Result<bool, String> {
+    Ok(auth::logout(&token))
+}
+
+#[tauri::command]
+/// 获取当前会话的用户
+pub async fn get_current_user(state: State<'_, AppState>, token: String) -> Result<User, String> {
+    auth::current_user(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+/// 修改登录密码（注销该用户的其他会话）
+pub async fn change_password(
+    state: State<'_, AppState>,
+    token: String,
+    old_password: String,
+    new_password: String,
+) -> Result<(), String> {
+    match auth::change_password(&state.db, &token, &old_password, &new_password).await {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("修改密码失败: {}", e)),
+    }
+}
 
 #[tauri::command]
 /// 添加邮箱账号
 pub async fn add_email(
     state: State<'_, AppState>,
+    token: String,
     email: String,
     password: String,
     client_id: String,
     refresh_token: String,
     mail_type: Option<String>,
 ) -> Result<i64, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
     match email::add_email(
         &state.db,
+        user_id,
         &email,
         &password,
         &client_id,
@@ -34,9 +116,11 @@ pub async fn add_email(
 /// 批量导入邮箱
 pub async fn import_emails(
     state: State<'_, AppState>,
+    token: String,
     input: String,
 ) -> Result<ImportResult, String> {
-    match email::import_emails_batch(&state.db, &input).await {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match email::import_emails_batch(&state.db, user_id, &input).await {
         Ok(result) => Ok(result),
         Err(e) => Err(format!("批量导入邮箱失败: {}", e)),
     }
@@ -44,17 +128,103 @@ pub async fn import_emails(
 
 #[tauri::command]
 /// 获取邮箱列表
-pub async fn get_emails(state: State<'_, AppState>) -> Result<Vec<EmailAccount>, String> {
-    match email::get_emails(&state.db).await {
+pub async fn get_emails(
+    state: State<'_, AppState>,
+    token: String,
+    filter: Option<EmailFilter>,
+) -> Result<Vec<EmailAccount>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let filter = EmailFilter {
+        user_id: Some(user_id),
+        ..filter.unwrap_or_default()
+    };
+    match email::get_emails(&state.db, &filter).await {
         Ok(emails) => Ok(emails),
         Err(e) => Err(format!("获取邮箱列表失败: {}", e)),
     }
 }
 
+#[tauri::command]
+/// 导出邮箱到文件（批量导入格式），返回导出的数量
+pub async fn export_emails(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    email_ids: Option<Vec<i64>>,
+    filter: Option<EmailFilter>,
+    separator: Option<String>,
+    target: String,
+) -> Result<usize, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let target = selected_path(&app_handle, &target)?;
+    let email_ids = email_ids.unwrap_or_default();
+    let filter = EmailFilter {
+        user_id: Some(user_id),
+        ..filter.unwrap_or_default()
+    };
+    match email::export_emails(
+        &state.db,
+        user_id,
+        &email_ids,
+        &filter,
+        separator.as_deref(),
+        &target,
+    )
+    .await
+    {
+        Ok(count) => Ok(count),
+        Err(e) => Err(format!("导出邮箱失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 查看账号凭据（记录审计日志，限制访问频率）
+pub async fn reveal_credentials(
+    state: State<'_, AppState>,
+    token: String,
+    email_id: i64,
+) -> Result<AccountCredentials, String> {
+    let user_id = auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    match credentials::reveal_credentials(&state.db, user_id, email_id).await {
+        Ok(credentials) => Ok(credentials),
+        Err(e) => Err(format!("获取凭据失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 复制账号凭据到剪贴板，凭据不经过前端（记录审计日志，限制访问频率）
+pub async fn copy_credential(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    email_id: i64,
+    field: CredentialField,
+) -> Result<(), String> {
+    let user_id = auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let value = credentials::credential_value(&state.db, user_id, email_id, field)
+        .await
+        .map_err(|e| format!("复制凭据失败: {}", e))?;
+    app_handle
+        .clipboard()
+        .write_text(value)
+        .map_err(|e| format!("复制凭据失败: {}", e))
+}
+
 #[tauri::command]
 /// 删除邮箱
-pub async fn delete_email(state: State<'_, AppState>, email_id: i64) -> Result<bool, String> {
-    match email::delete_email(&state.db, email_id).await {
+pub async fn delete_email(
+    state: State<'_, AppState>,
+    token: String,
+    email_id: i64,
+) -> Result<bool, String> {
+    let user_id = auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    match email::delete_email(&state.db, user_id, email_id).await {
         Ok(success) => Ok(success),
         Err(e) => Err(format!("删除邮箱失败: {}", e)),
     }
@@ -64,9 +234,13 @@ pub async fn delete_email(state: State<'_, AppState>, email_id: i64) -> Result<b
 /// Outlook 单邮箱收件
 pub async fn check_outlook_email(
     state: State<'_, AppState>,
+    token: String,
     email_id: i64,
     folder: Option<String>,
 ) -> Result<CheckResult, String> {
+    auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
     let folder = folder.unwrap_or_else(|| "INBOX".to_string());
     match email::check_outlook_email(&state.db, email_id, &folder).await {
         Ok(result) => Ok(result),
@@ -78,34 +252,122 @@ pub async fn check_outlook_email(
 /// Outlook 批量收件
 pub async fn batch_check_outlook_emails(
     state: State<'_, AppState>,
+    token: String,
     email_ids: Vec<i64>,
     folder: Option<String>,
+    filter: Option<EmailFilter>,
 ) -> Result<BatchCheckResult, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
     let folder = folder.unwrap_or_else(|| "INBOX".to_string());
-    match email::batch_check_outlook_emails(&state.db, email_ids, &folder).await {
+    let filter = EmailFilter {
+        user_id: Some(user_id),
+        remote_only: true,
+        ..filter.unwrap_or_default()
+    };
+    match email::batch_check_outlook_emails(&state.db, email_ids, &folder, &filter).await {
         Ok(result) => Ok(result),
         Err(e) => Err(format!("批量收件失败: {}", e)),
     }
 }
 
+#[tauri::command]
+/// 批量验证账号令牌（不收取邮件）
+pub async fn validate_accounts(
+    state: State<'_, AppState>,
+    token: String,
+    email_ids: Vec<i64>,
+    filter: Option<EmailFilter>,
+) -> Result<Vec<ValidateResult>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let filter = EmailFilter {
+        user_id: Some(user_id),
+        remote_only: true,
+        ..filter.unwrap_or_default()
+    };
+    match email::validate_accounts(&state.db, email_ids, &filter).await {
+        Ok(results) => Ok(results),
+        Err(e) => Err(format!("批量验证账号失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取邮箱文件夹列表（含子文件夹与未读/总数）
+pub async fn list_folders(
+    state: State<'_, AppState>,
+    token: String,
+    email_id: i64,
+    refresh: Option<bool>,
+) -> Result<Vec<MailFolder>, String> {
+    auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    match email::list_folders(&state.db, email_id, refresh.unwrap_or(false)).await {
+        Ok(folders) => Ok(folders),
+        Err(e) => Err(format!("获取文件夹失败: {}", e)),
+    }
+}
+
 #[tauri::command]
 /// 获取邮件记录
 pub async fn get_mail_records(
     state: State<'_, AppState>,
+    token: String,
     email_id: i64,
 ) -> Result<Vec<MailRecord>, String> {
+    auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
     match email::get_mail_records(&state.db, email_id).await {
         Ok(records) => Ok(records),
         Err(e) => Err(format!("获取邮件记录失败: {}", e)),
     }
 }
 
+#[tauri::command]
+/// 在服务器上批量执行邮件操作（标记已读/未读、旗标、移动、删除、归档）
+pub async fn apply_mail_action(
+    state: State<'_, AppState>,
+    token: String,
+    mail_ids: Vec<i64>,
+    action: MailAction,
+) -> Result<BatchMailActionResult, String> {
+    let user_id = auth::authorize_mails(&state.db, &token, &mail_ids)
+        .await
+        .map_err(|e| e.to_string())?;
+    match mail_actions::apply_mail_action(&state.db, Some(user_id), &mail_ids, &action).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("邮件操作失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 按账号、文件夹统计未读邮件数（不传 email_id 时统计全部账号）
+pub async fn get_unread_counts(
+    state: State<'_, AppState>,
+    token: String,
+    email_id: Option<i64>,
+) -> Result<Vec<UnreadCount>, String> {
+    let user_id = match email_id {
+        Some(email_id) => auth::authorize_account(&state.db, &token, email_id).await,
+        None => auth::require_user(&token),
+    }
+    .map_err(|e| e.to_string())?;
+    match mail_state::get_unread_counts(&state.db, user_id, email_id).await {
+        Ok(counts) => Ok(counts),
+        Err(e) => Err(format!("获取未读数失败: {}", e)),
+    }
+}
+
 #[tauri::command]
 /// 获取附件列表
 pub async fn get_attachments(
     state: State<'_, AppState>,
+    token: String,
     mail_id: i64,
 ) -> Result<Vec<AttachmentInfo>, String> {
+    auth::authorize_mails(&state.db, &token, &[mail_id])
+        .await
+        .map_err(|e| e.to_string())?;
     match email::get_attachments(&state.db, mail_id).await {
         Ok(attachments) => Ok(attachments),
         Err(e) => Err(format!("获取附件列表失败: {}", e)),
@@ -116,10 +378,1079 @@ pub async fn get_attachments(
 /// 获取附件内容
 pub async fn get_attachment_content(
     state: State<'_, AppState>,
+    token: String,
     attachment_id: i64,
 ) -> Result<AttachmentContent, String> {
+    auth::authorize_attachment(&state.db, &token, attachment_id)
+        .await
+        .map_err(|e| e.to_string())?;
     match email::get_attachment_content(&state.db, attachment_id).await {
         Ok(content) => Ok(content),
         Err(e) => Err(format!("获取附件内容失败: {}", e)),
     }
 }
+
+#[tauri::command]
+/// 将附件直接保存到指定路径（路径为文件夹时使用附件文件名），返回保存的文件路径
+pub async fn save_attachment(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    attachment_id: i64,
+    path: String,
+) -> Result<String, String> {
+    auth::authorize_attachment(&state.db, &token, attachment_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let path = selected_path(&app_handle, &path)?;
+    match attachments::save_attachment(&state.db, attachment_id, &path).await {
+        Ok(saved) => Ok(saved),
+        Err(e) => Err(format!("保存附件失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 批量导出符合条件的附件到文件夹或 ZIP
+pub async fn export_attachments(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    filter: AttachmentExportFilter,
+    target: String,
+    format: ExportFormat,
+) -> Result<AttachmentExportResult, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let filter = AttachmentExportFilter {
+        user_id: Some(user_id),
+        ..filter
+    };
+    let target = selected_path(&app_handle, &target)?;
+    match attachments::export_attachments(&state.db, &filter, &target, format).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("导出附件失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 导出邮件到 mbox 或 Maildir 归档
+pub async fn export_mailbox(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    selection: MailboxSelection,
+    target: String,
+    format: MailboxFormat,
+) -> Result<MailboxExportResult, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let selection = MailboxSelection {
+        user_id: Some(user_id),
+        ..selection
+    };
+    let target = selected_path(&app_handle, &target)?;
+    match archive::export_mailbox(&state.db, &selection, &target, format).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("导出邮箱失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 导入 mbox / Maildir / .eml 归档到本地账号
+pub async fn import_mailbox(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    path: String,
+    account: String,
+) -> Result<MailboxImportResult, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let path = selected_path(&app_handle, &path)?;
+    match archive::import_mailbox(&state.db, user_id, &path, &account).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("导入邮箱失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 设置账号自动下载附件的大小上限（MB），不传表示不自动下载
+pub async fn set_attachment_auto_download(
+    state: State<'_, AppState>,
+    token: String,
+    email_id: i64,
+    limit_mb: Option<i64>,
+) -> Result<bool, String> {
+    auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    match attachments::set_auto_download_limit(&state.db, email_id, limit_mb).await {
+        Ok(success) => Ok(success),
+        Err(e) => Err(format!("设置附件自动下载失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取标签列表
+pub async fn get_tags(state: State<'_, AppState>, token: String) -> Result<Vec<Tag>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match tags::list_tags(&state.db, user_id).await {
+        Ok(tags) => Ok(tags),
+        Err(e) => Err(format!("获取标签列表失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 创建标签
+pub async fn create_tag(
+    state: State<'_, AppState>,
+    token: String,
+    name: String,
+    color: Option<String>,
+) -> Result<i64, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match tags::create_tag(&state.db, user_id, &name, color.as_deref()).await {
+        Ok(id) => Ok(id),
+        Err(e) => Err(format!("创建标签失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 修改标签
+pub async fn update_tag(
+    state: State<'_, AppState>,
+    token: String,
+    tag_id: i64,
+    name: String,
+    color: Option<String>,
+) -> Result<bool, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match tags::update_tag(&state.db, user_id, tag_id, &name, color.as_deref()).await {
+        Ok(success) => Ok(success),
+        Err(e) => Err(format!("修改标签失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 删除标签
+pub async fn delete_tag(
+    state: State<'_, AppState>,
+    token: String,
+    tag_id: i64,
+) -> Result<bool, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match tags::delete_tag(&state.db, user_id, tag_id).await {
+        Ok(success) => Ok(success),
+        Err(e) => Err(format!("删除标签失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 批量为邮箱添加标签
+pub async fn assign_tags(
+    state: State<'_, AppState>,
+    token: String,
+    email_ids: Vec<i64>,
+    tag_ids: Vec<i64>,
+) -> Result<TagAssignResult, String> {
+    let user_id = auth::authorize_accounts(&state.db, &token, &email_ids)
+        .await
+        .map_err(|e| e.to_string())?;
+    match tags::assign_tags(&state.db, user_id, &email_ids, &tag_ids).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("添加标签失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 批量移除邮箱标签
+pub async fn unassign_tags(
+    state: State<'_, AppState>,
+    token: String,
+    email_ids: Vec<i64>,
+    tag_ids: Vec<i64>,
+) -> Result<TagAssignResult, String> {
+    let user_id = auth::authorize_accounts(&state.db, &token, &email_ids)
+        .await
+        .map_err(|e| e.to_string())?;
+    match tags::unassign_tags(&state.db, user_id, &email_ids, &tag_ids).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("移除标签失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 设置邮箱备注
+pub async fn set_email_notes(
+    state: State<'_, AppState>,
+    token: String,
+    email_id: i64,
+    notes: Option<String>,
+) -> Result<bool, String> {
+    auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    match tags::set_email_notes(&state.db, email_id, notes.as_deref()).await {
+        Ok(success) => Ok(success),
+        Err(e) => Err(format!("设置备注失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 备份数据库到指定文件或文件夹，不指定时保存到应用数据目录
+pub async fn backup_database(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    target: Option<String>,
+) -> Result<BackupInfo, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    let target = target
+        .map(|target| selected_path(&app_handle, &target))
+        .transpose()?;
+    match backup::backup_database(&state.db, target.as_deref()).await {
+        Ok(info) => Ok(info),
+        Err(e) => Err(format!("备份数据库失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取应用数据目录中的备份列表
+pub async fn list_backups(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<Vec<BackupInfo>, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match backup::list_backups().await {
+        Ok(backups) => Ok(backups),
+        Err(e) => Err(format!("获取备份列表失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 校验备份文件
+pub async fn validate_backup(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    path: String,
+) -> Result<BackupValidation, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    let path = backup_source(&app_handle, &path)?;
+    match backup::validate_backup(&path).await {
+        Ok(validation) => Ok(validation),
+        Err(e) => Err(format!("校验备份失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 从备份恢复数据库：校验后暂存，重启应用后生效；restart 为 true 时立即重启
+pub async fn restore_database(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    path: String,
+    restart: Option<bool>,
+) -> Result<BackupValidation, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    let path = backup_source(&app_handle, &path)?;
+    let validation = backup::stage_restore(&state.db, &path)
+        .await
+        .map_err(|e| format!("恢复数据库失败: {}", e))?;
+    if restart.unwrap_or(false) {
+        app_handle.restart();
+    }
+    Ok(validation)
+}
+
+#[tauri::command]
+/// 获取自动备份设置
+pub async fn get_backup_settings(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<BackupSettings, String> {
+    auth::require_user(&token).map_err(|e| e.to_string())?;
+    match backup::get_settings(&state.db).await {
+        Ok(settings) => Ok(settings),
+        Err(e) => Err(format!("获取备份设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 保存自动备份设置
+pub async fn set_backup_settings(
+    state: State<'_, AppState>,
+    token: String,
+    settings: BackupSettings,
+) -> Result<(), String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match backup::set_settings(&state.db, &settings).await {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("保存备份设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 检查数据库完整性
+pub async fn check_database_integrity(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<IntegrityReport, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match maintenance::integrity_check(&state.db).await {
+        Ok(report) => Ok(report),
+        Err(e) => Err(format!("检查数据库失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 整理数据库文件（VACUUM）
+pub async fn vacuum_database(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<VacuumResult, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match maintenance::vacuum(&state.db).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("整理数据库失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 更新数据库统计信息（ANALYZE）
+pub async fn analyze_database(state: State<'_, AppState>, token: String) -> Result<(), String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match maintenance::analyze(&state.db).await {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("分析数据库失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取数据库与附件的占用空间
+pub async fn get_storage_report(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<StorageReport, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match maintenance::storage_report(&state.db, user_id).await {
+        Ok(report) => Ok(report),
+        Err(e) => Err(format!("获取存储占用失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取凭据保险库状态
+pub async fn get_vault_status(token: String) -> Result<VaultStatus, String> {
+    auth::require_user(&token).map_err(|e| e.to_string())?;
+    Ok(vault::status())
+}
+
+#[tauri::command]
+/// 启用凭据保险库并加密已有凭据
+pub async fn enable_vault(
+    state: State<'_, AppState>,
+    token: String,
+    master_password: String,
+) -> Result<VaultStatus, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match vault::enable(&state.db, &master_password).await {
+        Ok(status) => Ok(status),
+        Err(e) => Err(format!("启用保险库失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 使用主密码解锁保险库
+pub async fn unlock_vault(
+    state: State<'_, AppState>,
+    token: String,
+    master_password: String,
+) -> Result<VaultStatus, String> {
+    auth::require_user(&token).map_err(|e| e.to_string())?;
+    match vault::unlock(&state.db, &master_password).await {
+        Ok(status) => Ok(status),
+        Err(e) => Err(format!("解锁保险库失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 锁定保险库
+pub async fn lock_vault(token: String) -> Result<VaultStatus, String> {
+    auth::require_user(&token).map_err(|e| e.to_string())?;
+    Ok(vault::lock())
+}
+
+#[tauri::command]
+/// 修改保险库主密码
+pub async fn change_master_password(
+    state: State<'_, AppState>,
+    token: String,
+    old_password: String,
+    new_password: String,
+) -> Result<VaultStatus, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match vault::change_password(&state.db, &old_password, &new_password).await {
+        Ok(status) => Ok(status),
+        Err(e) => Err(format!("修改主密码失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 关闭保险库并解密凭据
+pub async fn disable_vault(
+    state: State<'_, AppState>,
+    token: String,
+    master_password: String,
+) -> Result<VaultStatus, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match vault::disable(&state.db, &master_password).await {
+        Ok(status) => Ok(status),
+        Err(e) => Err(format!("关闭保险库失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 设置保险库空闲自动锁定时间（分钟），0 表示不自动锁定
+pub async fn set_vault_auto_lock(
+    state: State<'_, AppState>,
+    token: String,
+    minutes: i64,
+) -> Result<VaultStatus, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match vault::set_auto_lock(&state.db, minutes).await {
+        Ok(status) => Ok(status),
+        Err(e) => Err(format!("设置自动锁定失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 查询审计日志（当前用户的操作及其账号上的系统操作）
+pub async fn get_audit_log(
+    state: State<'_, AppState>,
+    token: String,
+    query: Option<AuditQuery>,
+) -> Result<Vec<AuditEntry>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let query = AuditQuery {
+        user_id: Some(user_id),
+        ..query.unwrap_or_default()
+    };
+    match audit::query(&state.db, &query).await {
+        Ok(entries) => Ok(entries),
+        Err(e) => Err(format!("获取审计日志失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 导出审计日志到 CSV 或 JSON 文件，返回导出的条数
+pub async fn export_audit_log(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    query: Option<AuditQuery>,
+    target: String,
+    format: AuditExportFormat,
+) -> Result<usize, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let query = AuditQuery {
+        user_id: Some(user_id),
+        ..query.unwrap_or_default()
+    };
+    let target = selected_path(&app_handle, &target)?;
+    match audit::export(&state.db, &query, &target, format).await {
+        Ok(count) => Ok(count),
+        Err(e) => Err(format!("导出审计日志失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取审计日志保留天数（0 表示永久保留）
+pub async fn get_audit_retention(state: State<'_, AppState>, token: String) -> Result<i64, String> {
+    auth::require_user(&token).map_err(|e| e.to_string())?;
+    match audit::get_retention_days(&state.db).await {
+        Ok(days) => Ok(days),
+        Err(e) => Err(format!("获取审计日志设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 设置审计日志保留天数（仅管理员，0 表示永久保留，否则至少 30 天），并立即清理过期记录
+pub async fn set_audit_retention(
+    state: State<'_, AppState>,
+    token: String,
+    days: i64,
+) -> Result<u64, String> {
+    let user_id = auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    let purged = async {
+        audit::set_retention_days(&state.db, user_id, days).await?;
+        audit::purge_expired(&state.db).await
+    };
+    match purged.await {
+        Ok(count) => Ok(count),
+        Err(e) => Err(format!("设置审计日志保留期限失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 列出回收站中的邮箱账号
+pub async fn list_trashed_accounts(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<Vec<TrashedAccount>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match trash::list_accounts(&state.db, user_id).await {
+        Ok(accounts) => Ok(accounts),
+        Err(e) => Err(format!("获取回收站账号失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 列出回收站中的邮件（可按账号过滤）
+pub async fn list_trashed_mails(
+    state: State<'_, AppState>,
+    token: String,
+    email_id: Option<i64>,
+) -> Result<Vec<TrashedMail>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match trash::list_mails(&state.db, user_id, email_id).await {
+        Ok(mails) => Ok(mails),
+        Err(e) => Err(format!("获取回收站邮件失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 从回收站恢复邮箱账号，返回恢复的账号 ID
+pub async fn restore_accounts(
+    state: State<'_, AppState>,
+    token: String,
+    email_ids: Vec<i64>,
+) -> Result<Vec<i64>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match trash::restore_accounts(&state.db, user_id, &email_ids).await {
+        Ok(restored) => Ok(restored),
+        Err(e) => Err(format!("恢复邮箱失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 从回收站恢复邮件，返回恢复的邮件 ID
+pub async fn restore_mails(
+    state: State<'_, AppState>,
+    token: String,
+    mail_ids: Vec<i64>,
+) -> Result<Vec<i64>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match trash::restore_mails(&state.db, user_id, &mail_ids).await {
+        Ok(restored) => Ok(restored),
+        Err(e) => Err(format!("恢复邮件失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 清空回收站（彻底删除，不可恢复）
+pub async fn empty_trash(state: State<'_, AppState>, token: String) -> Result<PurgeResult, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match trash::empty_trash(&state.db, user_id).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("清空回收站失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取回收站保留天数
+pub async fn get_trash_retention(state: State<'_, AppState>, token: String) -> Result<i64, String> {
+    auth::require_user(&token).map_err(|e| e.to_string())?;
+    match trash::get_retention_days(&state.db).await {
+        Ok(days) => Ok(days),
+        Err(e) => Err(format!("获取回收站设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 设置回收站保留天数（0 表示永久保留），并立即清除过期记录
+pub async fn set_trash_retention(
+    state: State<'_, AppState>,
+    token: String,
+    days: i64,
+) -> Result<PurgeResult, String> {
+    let user_id = auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    let purged = async {
+        trash::set_retention_days(&state.db, user_id, days).await?;
+        trash::purge_expired(&state.db).await
+    };
+    match purged.await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("设置回收站保留期限失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取收件规则列表
+pub async fn list_mail_rules(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<Vec<MailRule>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match rules::list_rules(&state.db, user_id).await {
+        Ok(rules) => Ok(rules),
+        Err(e) => Err(format!("获取收件规则失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 新建收件规则
+pub async fn create_mail_rule(
+    state: State<'_, AppState>,
+    token: String,
+    rule: MailRuleInput,
+) -> Result<MailRule, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match rules::create_rule(&state.db, user_id, &rule).await {
+        Ok(rule) => Ok(rule),
+        Err(e) => Err(format!("新建收件规则失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 修改收件规则
+pub async fn update_mail_rule(
+    state: State<'_, AppState>,
+    token: String,
+    rule_id: i64,
+    rule: MailRuleInput,
+) -> Result<bool, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match rules::update_rule(&state.db, user_id, rule_id, &rule).await {
+        Ok(updated) => Ok(updated),
+        Err(e) => Err(format!("修改收件规则失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 删除收件规则
+pub async fn delete_mail_rule(
+    state: State<'_, AppState>,
+    token: String,
+    rule_id: i64,
+) -> Result<bool, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match rules::delete_rule(&state.db, user_id, rule_id).await {
+        Ok(deleted) => Ok(deleted),
+        Err(e) => Err(format!("删除收件规则失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 用已有邮件试运行收件规则（只匹配，不执行动作）
+pub async fn test_mail_rule(
+    state: State<'_, AppState>,
+    token: String,
+    rule: MailRuleInput,
+    email_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<RuleTestResult, String> {
+    let user_id = match email_id {
+        Some(email_id) => auth::authorize_account(&state.db, &token, email_id).await,
+        None => auth::require_user(&token),
+    }
+    .map_err(|e| e.to_string())?;
+    match rules::test_rule(&state.db, user_id, &rule, email_id, limit).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("试运行收件规则失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取收件规则命中记录
+pub async fn get_mail_rule_hits(
+    state: State<'_, AppState>,
+    token: String,
+    rule_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<RuleHit>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match rules::list_hits(&state.db, user_id, rule_id, limit).await {
+        Ok(hits) => Ok(hits),
+        Err(e) => Err(format!("获取规则命中记录失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取 Webhook 端点列表
+pub async fn list_webhooks(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<Vec<Webhook>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match webhooks::list_webhooks(&state.db, user_id).await {
+        Ok(webhooks) => Ok(webhooks),
+        Err(e) => Err(format!("获取 Webhook 失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 新建 Webhook 端点（签名密钥只在此时返回）
+pub async fn create_webhook(
+    state: State<'_, AppState>,
+    token: String,
+    webhook: WebhookInput,
+) -> Result<WebhookSecret, String> {
+    let user_id = match webhook.email_id {
+        Some(email_id) => auth::authorize_account(&state.db, &token, email_id).await,
+        None => auth::require_user(&token),
+    }
+    .map_err(|e| e.to_string())?;
+    match webhooks::create_webhook(&state.db, user_id, &webhook).await {
+        Ok(secret) => Ok(secret),
+        Err(e) => Err(format!("新建 Webhook 失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 修改 Webhook 端点
+pub async fn update_webhook(
+    state: State<'_, AppState>,
+    token: String,
+    webhook_id: i64,
+    webhook: WebhookInput,
+) -> Result<bool, String> {
+    let user_id = match webhook.email_id {
+        Some(email_id) => auth::authorize_account(&state.db, &token, email_id).await,
+        None => auth::require_user(&token),
+    }
+    .map_err(|e| e.to_string())?;
+    match webhooks::update_webhook(&state.db, user_id, webhook_id, &webhook).await {
+        Ok(updated) => Ok(updated),
+        Err(e) => Err(format!("修改 Webhook 失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 删除 Webhook 端点
+pub async fn delete_webhook(
+    state: State<'_, AppState>,
+    token: String,
+    webhook_id: i64,
+) -> Result<bool, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match webhooks::delete_webhook(&state.db, user_id, webhook_id).await {
+        Ok(deleted) => Ok(deleted),
+        Err(e) => Err(format!("删除 Webhook 失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 重新生成 Webhook 签名密钥
+pub async fn rotate_webhook_secret(
+    state: State<'_, AppState>,
+    token: String,
+    webhook_id: i64,
+) -> Result<WebhookSecret, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match webhooks::rotate_secret(&state.db, user_id, webhook_id).await {
+        Ok(secret) => Ok(secret),
+        Err(e) => Err(format!("重新生成 Webhook 密钥失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 向 Webhook 端点发送测试事件
+pub async fn test_webhook(
+    state: State<'_, AppState>,
+    token: String,
+    webhook_id: i64,
+) -> Result<WebhookTestResult, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match webhooks::send_test(&state.db, user_id, webhook_id).await {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("测试 Webhook 失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取 Webhook 投递记录（status 为 dead 时即死信列表）
+pub async fn list_webhook_deliveries(
+    state: State<'_, AppState>,
+    token: String,
+    query: Option<DeliveryQuery>,
+) -> Result<Vec<WebhookDelivery>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match webhooks::list_deliveries(&state.db, user_id, &query.unwrap_or_default()).await {
+        Ok(deliveries) => Ok(deliveries),
+        Err(e) => Err(format!("获取 Webhook 投递记录失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 重新投递 Webhook 记录
+pub async fn retry_webhook_deliveries(
+    state: State<'_, AppState>,
+    token: String,
+    delivery_ids: Vec<i64>,
+) -> Result<u64, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match webhooks::retry_deliveries(&state.db, user_id, &delivery_ids).await {
+        Ok(count) => Ok(count),
+        Err(e) => Err(format!("重新投递失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 删除 Webhook 投递记录
+pub async fn delete_webhook_deliveries(
+    state: State<'_, AppState>,
+    token: String,
+    delivery_ids: Vec<i64>,
+) -> Result<u64, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match webhooks::delete_deliveries(&state.db, user_id, &delivery_ids).await {
+        Ok(count) => Ok(count),
+        Err(e) => Err(format!("删除投递记录失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取桌面通知设置
+pub async fn get_notification_settings(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<NotificationSettings, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match notifications::get_settings(&state.db, user_id).await {
+        Ok(settings) => Ok(settings),
+        Err(e) => Err(format!("获取通知设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 保存桌面通知设置（开关、免打扰时段、合并阈值）
+pub async fn set_notification_settings(
+    state: State<'_, AppState>,
+    token: String,
+    settings: NotificationSettings,
+) -> Result<NotificationSettings, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match notifications::set_settings(&state.db, user_id, &settings).await {
+        Ok(settings) => Ok(settings),
+        Err(e) => Err(format!("保存通知设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取通知静音列表
+pub async fn list_notification_mutes(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<Vec<NotificationMute>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match notifications::list_mutes(&state.db, user_id).await {
+        Ok(mutes) => Ok(mutes),
+        Err(e) => Err(format!("获取静音列表失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 按账号或发件人静音通知
+pub async fn add_notification_mute(
+    state: State<'_, AppState>,
+    token: String,
+    mute: MuteInput,
+) -> Result<i64, String> {
+    let user_id = match mute.email_id {
+        Some(email_id) => auth::authorize_account(&state.db, &token, email_id).await,
+        None => auth::require_user(&token),
+    }
+    .map_err(|e| e.to_string())?;
+    match notifications::add_mute(&state.db, user_id, &mute).await {
+        Ok(id) => Ok(id),
+        Err(e) => Err(format!("静音失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 取消通知静音
+pub async fn remove_notification_mute(
+    state: State<'_, AppState>,
+    token: String,
+    mute_id: i64,
+) -> Result<bool, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    match notifications::remove_mute(&state.db, user_id, mute_id).await {
+        Ok(removed) => Ok(removed),
+        Err(e) => Err(format!("取消静音失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 获取后台运行设置（关闭窗口后台运行、开机启动、全部监听是否暂停）
+pub async fn get_background_settings(
+    app_handle: tauri::AppHandle,
+    token: String,
+) -> Result<BackgroundSettings, String> {
+    auth::require_user(&token).map_err(|e| e.to_string())?;
+    Ok(tray::get_settings(&app_handle))
+}
+
+#[tauri::command]
+/// 修改后台运行设置，未传的项保持不变；影响整个应用，仅管理员可用
+pub async fn set_background_settings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    run_in_background: Option<bool>,
+    autostart: Option<bool>,
+) -> Result<BackgroundSettings, String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(enabled) = run_in_background {
+        if let Err(e) = tray::set_run_in_background(&state.db, enabled).await {
+            return Err(format!("保存后台运行设置失败: {}", e));
+        }
+    }
+    if let Some(enabled) = autostart {
+        if let Err(e) = tray::set_autostart(&app_handle, enabled) {
+            return Err(format!("设置开机启动失败: {}", e));
+        }
+    }
+    tray::refresh(&app_handle).await;
+    Ok(tray::get_settings(&app_handle))
+}
+
+#[tauri::command]
+/// 获取应用设置（监听间隔、收件窗口、网络超时、IMAP 服务器）
+pub async fn get_settings(
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<AppSettings, String> {
+    auth::require_user(&token).map_err(|e| e.to_string())?;
+    match settings::load(&state.db).await {
+        Ok(settings) => Ok(settings),
+        Err(e) => Err(format!("获取设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 校验并保存应用设置（仅管理员），有变化时发送 settings-changed 事件
+pub async fn update_settings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    settings: AppSettings,
+) -> Result<SettingsChange, String> {
+    let user_id = auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match settings::update(&state.db, user_id, &settings).await {
+        Ok(change) => {
+            settings::emit_changed(&app_handle, &change);
+            Ok(change)
+        }
+        Err(e) => Err(format!("保存设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 恢复默认应用设置（仅管理员）
+pub async fn reset_settings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<SettingsChange, String> {
+    let user_id = auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    match settings::reset(&state.db, user_id).await {
+        Ok(change) => {
+            settings::emit_changed(&app_handle, &change);
+            Ok(change)
+        }
+        Err(e) => Err(format!("恢复默认设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 导出应用设置为 JSON 文件
+pub async fn export_settings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    target: String,
+) -> Result<(), String> {
+    auth::require_user(&token).map_err(|e| e.to_string())?;
+    let target = selected_path(&app_handle, &target)?;
+    match settings::export(&state.db, &target).await {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("导出设置失败: {}", e)),
+    }
+}
+
+#[tauri::command]
+/// 从 JSON 文件导入应用设置（仅管理员），文件中缺少的项恢复为默认值
+pub async fn import_settings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    source: String,
+) -> Result<SettingsChange, String> {
+    let user_id = auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    let source = selected_path(&app_handle, &source)?;
+    match settings::import(&state.db, user_id, &source).await {
+        Ok(change) => {
+            settings::emit_changed(&app_handle, &change);
+            Ok(change)
+        }
+        Err(e) => Err(format!("导入设置失败: {}", e)),
+    }
+}
+
+/// 校验读写路径：只接受文件对话框选择的文件或文件夹（对话框会将其加入文件系统作用域）
+fn selected_path(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    if !app_handle.fs_scope().is_allowed(&path) {
+        return Err("目标路径不在允许范围内，请通过文件对话框选择".to_string());
+    }
+    Ok(path)
+}
+
+/// 校验备份来源：备份目录中的备份（list_backups 返回的路径），或文件对话框选择的文件
+fn backup_source(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let candidate = PathBuf::from(path);
+    let in_backup_dir = backup::backup_dir()
+        .ok()
+        .and_then(|dir| dir.canonicalize().ok())
+        .zip(candidate.canonicalize().ok())
+        .is_some_and(|(dir, file)| file.parent() == Some(dir.as_path()));
+    if in_backup_dir {
+        return Ok(candidate);
+    }
+    selected_path(app_handle, path)
+}
@@ -0,0 +1,154 @@
+//! 账号凭据访问模块
+//! 账号列表只返回脱敏信息，密码与刷新令牌需通过显式的查看/复制命令获取；
+//! 每次访问写入审计日志，并按时间窗口限制每个用户的访问次数
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+
+use crate::audit::{self, AuditEvent};
+use crate::vault;
+
+/// 时间窗口内允许的凭据访问次数
+const MAX_ACCESSES_PER_WINDOW: usize = 10;
+
+/// 限流时间窗口（秒）
+const RATE_LIMIT_WINDOW_SECS: u64 = 60;
+
+/// 用户 ID -> 最近的凭据访问时间（按用户分别限流）
+static RECENT_ACCESSES: Mutex<BTreeMap<i64, VecDeque<Instant>>> = Mutex::new(BTreeMap::new());
+
+/// 可单独复制的凭据字段
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialField {
+    Password,
+    RefreshToken,
+}
+
+impl CredentialField {
+    fn as_str(self) -> &'static str {
+        match self {
+            CredentialField::Password => "password",
+            CredentialField::RefreshToken => "refresh_token",
+        }
+    }
+}
+
+/// 账号凭据
+#[derive(Debug, Serialize)]
+pub struct AccountCredentials {
+    pub email_id: i64,
+    pub email: String,
+    pub password: String,
+    pub client_id: String,
+    pub refresh_token: String,
+}
+
+/// 查看账号的全部凭据（审计、限流）
+pub async fn reveal_credentials(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    email_id: i64,
+) -> Result<AccountCredentials> {
+    check_rate_limit(user_id)?;
+    let credentials = load_credentials(pool, email_id).await?;
+    audit::record(
+        pool,
+        audit::ACTION_CREDENTIAL_REVEAL,
+        AuditEvent {
+            user_id: Some(user_id),
+            email_id: Some(email_id),
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(credentials)
+}
+
+/// 读取单个凭据字段用于复制（审计、限流）
+pub async fn credential_value(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    email_id: i64,
+    field: CredentialField,
+) -> Result<String> {
+    check_rate_limit(user_id)?;
+    let credentials = load_credentials(pool, email_id).await?;
+    audit::record(
+        pool,
+        audit::ACTION_CREDENTIAL_COPY,
+        AuditEvent {
+            user_id: Some(user_id),
+            email_id: Some(email_id),
+            detail: Some(field.as_str().to_string()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(match field {
+        CredentialField::Password => credentials.password,
+        CredentialField::RefreshToken => credentials.refresh_token,
+    })
+}
+
+async fn load_credentials(pool: &Pool<Sqlite>, email_id: i64) -> Result<AccountCredentials> {
+    let (email, password, client_id, refresh_token) =
+        sqlx::query_as::<_, (String, String, String, String)>(
+            "SELECT email, password, client_id, refresh_token FROM emails WHERE id = ?",
+        )
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow!("邮箱不存在"))?;
+
+    Ok(AccountCredentials {
+        email_id,
+        email,
+        password: vault::open(&password)?,
+        client_id,
+        refresh_token: vault::open(&refresh_token)?,
+    })
+}
+
+/// 检查并登记用户的一次凭据访问（查看、复制或导出），超出限制时报错
+pub fn check_rate_limit(user_id: i64) -> Result<()> {
+    let mut recent = RECENT_ACCESSES.lock().unwrap_or_else(|e| e.into_inner());
+    let window = Duration::from_secs(RATE_LIMIT_WINDOW_SECS);
+    recent.retain(|_, accesses| {
+        while accesses.front().is_some_and(|t| t.elapsed() >= window) {
+            accesses.pop_front();
+        }
+        !accesses.is_empty()
+    });
+
+    let accesses = recent.entry(user_id).or_default();
+    if accesses.len() >= MAX_ACCESSES_PER_WINDOW {
+        return Err(anyhow!(
+            "凭据访问过于频繁，请 {} 秒后再试",
+            RATE_LIMIT_WINDOW_SECS
+        ));
+    }
+    accesses.push_back(Instant::now());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_is_per_user() {
+        // 使用其他测试不会用到的用户 ID，避免共享的时间窗口互相影响
+        for _ in 0..MAX_ACCESSES_PER_WINDOW {
+            check_rate_limit(901).unwrap();
+        }
+        assert!(check_rate_limit(901).is_err());
+        check_rate_limit(902).unwrap();
+    }
+}
@@ -3,17 +3,110 @@ use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
 use std::fs;
 use tauri::Manager;
 
+use crate::backup;
+use crate::blob_store;
+use crate::folders;
+use crate::maintenance;
+use crate::vault;
+
 pub struct AppState {
     pub db: Pool<Sqlite>,
 }
 
+/// 数据库迁移脚本（按版本号顺序执行，每个版本只执行一次）
+const MIGRATIONS: &[(i64, &str)] = &[
+    (
+        20240101000000,
+        include_str!("../migrations/20240101000000_init.sql"),
+    ),
+    (
+        20261018000100,
+        include_str!("../migrations/20261018000100_account_tags.sql"),
+    ),
+    (
+        20261018000200,
+        include_str!("../migrations/20261018000200_account_health.sql"),
+    ),
+    (
+        20261018000300,
+        include_str!("../migrations/20261018000300_watch_settings.sql"),
+    ),
+    (
+        20261018000400,
+        include_str!("../migrations/20261018000400_mail_folders.sql"),
+    ),
+    (
+        20261018000500,
+        include_str!("../migrations/20261018000500_mail_remote_ids.sql"),
+    ),
+    (
+        20261018000600,
+        include_str!("../migrations/20261018000600_mail_state.sql"),
+    ),
+    (
+        20261018000700,
+        include_str!("../migrations/20261018000700_attachment_blobs.sql"),
+    ),
+    (
+        20261018000800,
+        include_str!("../migrations/20261018000800_lazy_attachments.sql"),
+    ),
+    (
+        20261018000900,
+        include_str!("../migrations/20261018000900_attachment_kinds.sql"),
+    ),
+    (
+        20261018001000,
+        include_str!("../migrations/20261018001000_audit_log.sql"),
+    ),
+    (
+        20261018001100,
+        include_str!("../migrations/20261018001100_users.sql"),
+    ),
+    (
+        20261018001200,
+        include_str!("../migrations/20261018001200_audit_log_details.sql"),
+    ),
+    (
+        20261018001300,
+        include_str!("../migrations/20261018001300_trash.sql"),
+    ),
+    (
+        20261018001400,
+        include_str!("../migrations/20261018001400_mail_rules.sql"),
+    ),
+    (
+        20261018001500,
+        include_str!("../migrations/20261018001500_webhooks.sql"),
+    ),
+    (
+        20261018001600,
+        include_str!("../migrations/20261018001600_notifications.sql"),
+    ),
+    (
+        20261018001700,
+        include_str!("../migrations/20261018001700_tag_owner.sql"),
+    ),
+    (
+        20261018001800,
+        include_str!("../migrations/20261018001800_user_roles.sql"),
+    ),
+    (
+        20261018001900,
+        include_str!("../migrations/20261018001900_audit_retention_floor.sql"),
+    ),
+];
+
 pub async fn init_db(app_handle: &tauri::AppHandle) -> Result<Pool<Sqlite>> {
     let app_dir = app_handle.path().app_data_dir()?;
     if !app_dir.exists() {
         fs::create_dir_all(&app_dir)?;
     }
 
-    let db_path = app_dir.join("firemail.db");
+    let db_path = app_dir.join(backup::DB_FILE_NAME);
+    // 应用上次暂存的恢复文件（需在打开数据库之前替换）
+    let restored = backup::apply_staged_restore(&app_dir)?;
+
     let db_url = format!("sqlite://{}", db_path.to_string_lossy());
 
     if !db_path.exists() {
@@ -25,9 +118,215 @@ pub async fn init_db(app_handle: &tauri::AppHandle) -> Result<Pool<Sqlite>> {
         .connect(&db_url)
         .await?;
 
-    sqlx::query(include_str!("../migrations/20240101000000_init.sql"))
-        .execute(&pool)
-        .await?;
+    run_migrations(&pool).await?;
+    folders::backfill_folder_keys(&pool).await?;
+
+    // 附件内容存储在 app_data/blobs，并迁移旧版本保存在数据库中的附件
+    blob_store::init(&app_dir)?;
+    let migrated = blob_store::migrate_inline_blobs(&pool).await?;
+    if migrated > 0 {
+        // 迁移后数据库中空出的空间在后台整理回收，不阻塞启动
+        let vacuum_pool = pool.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = maintenance::vacuum(&vacuum_pool).await {
+                log::warn!("迁移附件后整理数据库失败: {}", e);
+            }
+        });
+    }
+    if restored {
+        // 恢复的数据库与当前附件存储可能不一致
+        blob_store::reconcile(&pool).await?;
+    }
+    blob_store::collect_garbage_quietly(&pool).await;
+
+    backup::init(&app_dir)?;
+    // 读取保险库配置，启用时需由用户输入主密码解锁
+    vault::load(&pool).await?;
 
     Ok(pool)
 }
+
+/// 当前程序支持的最新迁移版本
+pub fn latest_migration_version() -> i64 {
+    MIGRATIONS.last().map(|(version, _)| *version).unwrap_or(0)
+}
+
+/// 读取 system_config 配置项
+pub async fn get_config(pool: &Pool<Sqlite>, key: &str) -> Result<Option<String>> {
+    let value =
+        sqlx::query_scalar::<_, Option<String>>("SELECT value FROM system_config WHERE key = ?")
+            .bind(key)
+            .fetch_optional(pool)
+            .await?
+            .flatten();
+    Ok(value)
+}
+
+/// 写入 system_config 配置项
+pub async fn set_config(
+    pool: &Pool<Sqlite>,
+    key: &str,
+    value: &str,
+    description: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"INSERT INTO system_config (key, value, description) VALUES (?, ?, ?)
+ON CONFLICT(key) DO UPDATE
+SET value = excluded.value,
+    description = COALESCE(excluded.description, system_config.description),
+    updated_at = CURRENT_TIMESTAMP"#,
+    )
+    .bind(key)
+    .bind(value)
+    .bind(description)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 执行尚未应用的迁移脚本
+async fn run_migrations(pool: &Pool<Sqlite>) -> Result<()> {
+    apply_migrations(pool, MIGRATIONS).await
+}
+
+/// 按顺序执行给定迁移中尚未应用的部分
+async fn apply_migrations(pool: &Pool<Sqlite>, migrations: &[(i64, &str)]) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (version INTEGER PRIMARY KEY, applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP)",
+    )
+    .execute(pool)
+    .await?;
+
+    for (version, sql) in migrations {
+        let applied =
+            sqlx::query_scalar::<_, i64>("SELECT version FROM schema_migrations WHERE version = ?")
+                .bind(version)
+                .fetch_optional(pool)
+                .await?
+                .is_some();
+        if applied {
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(sql).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_migrations (version) VALUES (?)")
+            .bind(version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        log::info!("数据库迁移完成: version={}", version);
+    }
+
+    Ok(())
+}
+
+/// 测试用内存数据库（已执行全部迁移，并创建 ID 为 1 的用户）
+#[cfg(test)]
+pub async fn test_pool() -> Pool<Sqlite> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    seed_test_pool(pool).await
+}
+
+/// 测试用文件数据库（VACUUM INTO 等内存数据库不支持的场景）
+#[cfg(test)]
+pub async fn test_file_pool(path: &std::path::Path) -> Pool<Sqlite> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&format!("sqlite://{}?mode=rwc", path.to_string_lossy()))
+        .await
+        .unwrap();
+    seed_test_pool(pool).await
+}
+
+#[cfg(test)]
+async fn seed_test_pool(pool: Pool<Sqlite>) -> Pool<Sqlite> {
+    run_migrations(&pool).await.unwrap();
+    sqlx::query(
+        "INSERT INTO users (id, username, password_hash, salt, rounds) VALUES (1, 'owner', '', '', 1)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tag_owner_migration_keeps_tag_scoped_webhooks_and_rules() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let split = MIGRATIONS
+            .iter()
+            .position(|(version, _)| *version == 20261018001700)
+            .unwrap();
+        apply_migrations(&pool, &MIGRATIONS[..split]).await.unwrap();
+
+        // 标签 work 同时被两个用户的账号使用，用户 2 的 Webhook 与规则也引用它
+        sqlx::query(
+            r#"INSERT INTO users (id, username, password_hash, salt, rounds) VALUES (1, 'a', '', '', 1), (2, 'b', '', '', 1);
+INSERT INTO emails (id, email, password, client_id, refresh_token, user_id) VALUES (1, 'a@example.com', 'p', 'c', 'r', 1), (2, 'b@example.com', 'p', 'c', 'r', 2);
+INSERT INTO tags (id, name) VALUES (1, 'work');
+INSERT INTO email_tags (email_id, tag_id) VALUES (1, 1), (2, 1);
+INSERT INTO webhooks (id, user_id, name, url, secret, tag_id) VALUES (1, 1, 'a', 'https://example.com', 's', 1), (2, 2, 'b', 'https://example.com', 's', 1);
+INSERT INTO webhook_deliveries (webhook_id, event, payload) VALUES (2, 'mail.received', '{}');
+INSERT INTO mail_rules (id, user_id, name, conditions) VALUES (1, 2, 'r', '[{"type":"account_tag","tag_id":1},{"type":"folder","folder":"INBOX"}]');"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        run_migrations(&pool).await.unwrap();
+
+        let copy: i64 =
+            sqlx::query_scalar("SELECT id FROM tags WHERE user_id = 2 AND name = 'work'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_ne!(copy, 1);
+
+        let webhooks: Vec<(i64, Option<i64>)> =
+            sqlx::query_as("SELECT id, tag_id FROM webhooks ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(webhooks, vec![(1, Some(1)), (2, Some(copy))]);
+        let deliveries: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhook_deliveries")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(deliveries, 1);
+
+        let conditions: String =
+            sqlx::query_scalar("SELECT conditions FROM mail_rules WHERE id = 1")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let conditions: serde_json::Value = serde_json::from_str(&conditions).unwrap();
+        assert_eq!(
+            conditions,
+            serde_json::json!([
+                {"type": "account_tag", "tag_id": copy},
+                {"type": "folder", "folder": "INBOX"}
+            ])
+        );
+
+        let links: Vec<(i64, i64)> =
+            sqlx::query_as("SELECT email_id, tag_id FROM email_tags ORDER BY email_id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(links, vec![(1, 1), (2, copy)]);
+    }
+}
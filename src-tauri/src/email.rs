@@ -1,18 +1,40 @@
 use anyhow::{anyhow, Result};
-use base64::engine::general_purpose::STANDARD;
-use base64::Engine;
 use chrono::{DateTime, Utc};
 use imap::Authenticator;
+use imap_proto::types::{
+    BodyContentCommon, BodyStructure, ContentEncoding, MessageSection, SectionPath,
+};
+use imap_proto::NameAttribute;
 use mailparse::{DispositionType, MailHeaderMap, ParsedMail};
 use native_tls::TlsConnector;
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
-
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::archive;
+use crate::attachments;
+use crate::audit::{self, AuditEvent};
+use crate::blob_store;
+use crate::credentials;
+use crate::folders::{self, DiscoveredFolder, MailFolder, ResolvedFolder};
 use crate::graph_api;
+use crate::health::{self, HealthStatus, OAuthError};
+use crate::mail_state::{self, MailState};
 use crate::proxy::{create_http_client, ProxyConfig};
+use crate::rules;
+use crate::settings;
+use crate::tags::{self, AccountTag};
 use crate::token_cache;
+use crate::trash;
+use crate::vault;
+use crate::webhooks;
 
 /// API 模式
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -49,16 +71,62 @@ impl From<Option<String>> for ApiMode {
 pub struct EmailAccount {
     pub id: i64,
     pub email: String,
-    /// 邮箱密码（前端显示/复制使用）
-    pub password: String,
+    /// 是否已保存密码（凭据需通过 reveal_credentials / copy_credential 获取）
+    pub has_password: bool,
     pub mail_type: String,
     pub client_id: String,
-    pub refresh_token: String,
+    /// 是否已保存刷新令牌
+    pub has_refresh_token: bool,
     pub last_check_time: Option<String>,
     pub api_mode: Option<String>,
     pub proxy_type: Option<String>,
     pub proxy_url: Option<String>,
     pub default_folder: Option<String>,
+    /// 账号备注
+    pub notes: Option<String>,
+    /// 健康状态
+    pub health_status: Option<String>,
+    /// 最近一次错误信息
+    pub last_error: Option<String>,
+    /// 最近一次错误时间
+    pub last_error_at: Option<String>,
+    /// 账号标签
+    #[sqlx(skip)]
+    pub tags: Vec<AccountTag>,
+}
+
+/// 邮箱列表筛选条件
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EmailFilter {
+    /// 仅包含带有该标签的账号
+    pub tag_id: Option<i64>,
+    /// 仅包含该健康状态的账号
+    pub health: Option<HealthStatus>,
+    /// 仅包含该用户的账号（由后端根据会话设置）
+    #[serde(skip)]
+    pub user_id: Option<i64>,
+    /// 排除本地归档账号（收件、验证等需要连接服务器的操作由后端设置）
+    #[serde(skip)]
+    pub remote_only: bool,
+}
+
+impl EmailFilter {
+    /// 是否未设置任何筛选条件
+    pub fn is_empty(&self) -> bool {
+        self.tag_id.is_none()
+            && self.health.is_none()
+            && self.user_id.is_none()
+            && !self.remote_only
+    }
+}
+
+/// 账号验证结果
+#[derive(Debug, serde::Serialize)]
+pub struct ValidateResult {
+    pub email_id: i64,
+    pub email: String,
+    pub status: HealthStatus,
+    pub message: String,
 }
 
 /// 邮件记录
@@ -72,6 +140,13 @@ pub struct MailRecord {
     pub content: Option<String>,
     pub folder: Option<String>,
     pub has_attachments: i64,
+    pub is_read: bool,
+    pub is_flagged: bool,
+    pub is_answered: bool,
+    /// 重要性：low / normal / high
+    pub importance: Option<String>,
+    #[sqlx(json)]
+    pub categories: Vec<String>,
 }
 
 /// 附件信息
@@ -82,19 +157,27 @@ pub struct AttachmentInfo {
     pub filename: Option<String>,
     pub content_type: Option<String>,
     pub size: Option<i64>,
+    /// 内容是否已下载到本地
+    pub is_downloaded: bool,
+    /// 附件类型：file / item / reference
+    pub kind: String,
+    /// 云附件链接
+    pub source_url: Option<String>,
 }
 
-/// 附件内容
+/// 附件内容：返回附件存储中的内容文件路径，不经 IPC 传输内容本身
 #[derive(Debug, serde::Serialize)]
 pub struct AttachmentContent {
     pub id: i64,
     pub filename: Option<String>,
     pub content_type: Option<String>,
-    pub content_base64: String,
+    pub size: u64,
+    /// 内容文件路径（只读，需要另存时使用 save_attachment）
+    pub path: String,
 }
 
 /// 收件结果
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct CheckResult {
     pub email_id: i64,
     pub success: bool,
@@ -102,6 +185,24 @@ pub struct CheckResult {
     pub saved: usize,
     pub deleted: usize,
     pub message: String,
+    /// 新入库且未被规则通知的邮件 ID（用于桌面通知，不返回前端）
+    #[serde(skip)]
+    pub new_mail_ids: Vec<i64>,
+}
+
+impl CheckResult {
+    /// 构建失败结果
+    pub fn failure(email_id: i64, message: String) -> Self {
+        Self {
+            email_id,
+            success: false,
+            fetched: 0,
+            saved: 0,
+            deleted: 0,
+            message,
+            new_mail_ids: Vec::new(),
+        }
+    }
 }
 
 /// 批量收件结果
@@ -169,13 +270,48 @@ struct OutlookAccount {
     proxy_type: Option<String>,
     proxy_url: Option<String>,
     default_folder: Option<String>,
+    attachment_auto_download_mb: Option<i64>,
 }
 
 /// 附件输入数据
 struct AttachmentInput {
     filename: String,
     content_type: String,
-    content: Vec<u8>,
+    size: i64,
+    /// 服务器来源（graph / imap），内容按需下载时使用
+    remote_source: Option<&'static str>,
+    /// Graph 附件 ID 或 IMAP 段号
+    remote_ref: Option<String>,
+    /// 已获取的内容；为空时只保存元数据
+    content: Option<Vec<u8>>,
+    /// 附件类型：file / item / reference
+    kind: &'static str,
+    /// 云附件链接
+    source_url: Option<String>,
+}
+
+impl From<&graph_api::GraphAttachmentData> for AttachmentInput {
+    fn from(attachment: &graph_api::GraphAttachmentData) -> Self {
+        // 云附件没有可下载的内容，保存为指向原始链接的快捷方式
+        let (filename, content) = match &attachment.source_url {
+            Some(url) if attachment.kind == attachments::KIND_REFERENCE => (
+                format!("{}.url", attachment.filename),
+                Some(attachments::reference_shortcut(url)),
+            ),
+            _ => (attachment.filename.clone(), None),
+        };
+
+        AttachmentInput {
+            filename,
+            content_type: attachment.content_type.clone(),
+            size: attachment.size,
+            remote_source: Some(attachments::SOURCE_GRAPH),
+            remote_ref: Some(attachment.attachment_id.clone()),
+            content,
+            kind: attachment.kind,
+            source_url: attachment.source_url.clone(),
+        }
+    }
 }
 
 /// 抓取到的邮件记录
@@ -186,11 +322,41 @@ struct MailFetchRecord {
     content: String,
     folder: String,
     attachments: Vec<AttachmentInput>,
+    /// Graph API 邮件 ID
+    graph_id: Option<String>,
+    /// IMAP UID
+    imap_uid: Option<u32>,
+    /// Message-ID 头
+    message_id: Option<String>,
+    /// 已读、旗标等状态
+    state: MailState,
+}
+
+impl From<&graph_api::GraphMailRecord> for MailFetchRecord {
+    fn from(record: &graph_api::GraphMailRecord) -> Self {
+        MailFetchRecord {
+            subject: record.subject.clone(),
+            sender: record.sender.clone(),
+            received_time: record.received_time.clone(),
+            content: record.content.clone(),
+            folder: record.folder.clone(),
+            graph_id: Some(record.graph_id.clone()),
+            imap_uid: None,
+            message_id: record.message_id.clone(),
+            state: record.state.clone(),
+            attachments: record
+                .attachments
+                .iter()
+                .map(AttachmentInput::from)
+                .collect(),
+        }
+    }
 }
 
 /// 添加邮箱账号
 pub async fn add_email(
     pool: &Pool<Sqlite>,
+    user_id: i64,
     email: &str,
     password: &str,
     client_id: &str,
@@ -199,23 +365,50 @@ pub async fn add_email(
 ) -> Result<i64> {
     let mail_type = mail_type.unwrap_or("outlook");
 
+    let trashed = sqlx::query_scalar::<_, i64>(
+        "SELECT id FROM emails WHERE email = ? AND user_id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(email)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    if trashed.is_some() {
+        return Err(anyhow!("邮箱 {} 在回收站中，请先恢复", email));
+    }
+
+    let seal_guard = vault::seal_guard().await;
     let id: i64 = sqlx::query_scalar(
-        "INSERT INTO emails (email, password, client_id, refresh_token, mail_type) VALUES (?, ?, ?, ?, ?) RETURNING id",
+        "INSERT INTO emails (email, password, client_id, refresh_token, mail_type, user_id) VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
     )
     .bind(email)
-    .bind(password)
+    .bind(vault::seal(password)?)
     .bind(client_id)
-    .bind(refresh_token)
+    .bind(vault::seal(refresh_token)?)
     .bind(mail_type)
+    .bind(user_id)
     .fetch_one(pool)
     .await?;
+    drop(seal_guard);
+
+    audit::record(
+        pool,
+        audit::ACTION_ACCOUNT_ADD,
+        AuditEvent {
+            user_id: Some(user_id),
+            email_id: Some(id),
+            target_ids: &[id],
+            detail: Some(email.to_string()),
+        },
+    )
+    .await?;
 
     Ok(id)
 }
 
-/// 添加或覆盖邮箱账号
+/// 添加或覆盖邮箱账号；其他用户已添加的同名邮箱不会被覆盖，回收站中的同名账号会被恢复
 pub async fn add_or_update_email(
     pool: &Pool<Sqlite>,
+    user_id: i64,
     email: &str,
     password: &str,
     client_id: &str,
@@ -224,33 +417,117 @@ pub async fn add_or_update_email(
 ) -> Result<i64> {
     let mail_type = mail_type.unwrap_or("outlook");
 
+    let seal_guard = vault::seal_guard().await;
     let id: i64 = sqlx::query_scalar(
-        r#"INSERT INTO emails (email, password, client_id, refresh_token, mail_type)
-VALUES (?, ?, ?, ?, ?)
+        r#"INSERT INTO emails (email, password, client_id, refresh_token, mail_type, user_id)
+VALUES (?, ?, ?, ?, ?, ?)
 ON CONFLICT(email) DO UPDATE
 SET password = excluded.password,
     client_id = excluded.client_id,
     refresh_token = excluded.refresh_token,
     mail_type = excluded.mail_type,
+    user_id = excluded.user_id,
+    access_token = NULL,
+    cached_token = NULL,
+    token_expires_at = NULL,
+    health_status = 'unknown',
+    last_error = NULL,
+    last_error_at = NULL,
+    health_checked_at = NULL,
+    deleted_at = NULL,
+    deleted_reason = NULL,
     updated_at = CURRENT_TIMESTAMP
+WHERE emails.user_id IS NULL OR emails.user_id = excluded.user_id
 RETURNING id"#,
     )
     .bind(email)
-    .bind(password)
+    .bind(vault::seal(password)?)
     .bind(client_id)
-    .bind(refresh_token)
+    .bind(vault::seal(refresh_token)?)
     .bind(mail_type)
-    .fetch_one(pool)
-    .await?;
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("邮箱已被其他用户添加"))?;
+    drop(seal_guard);
+
+    token_cache::clear_token_cache(id);
 
     Ok(id)
 }
 
+/// 更新邮箱凭据（未传入的字段保持不变），并清除已缓存的令牌
+pub async fn update_email_credentials(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    email_id: i64,
+    password: Option<&str>,
+    client_id: Option<&str>,
+    refresh_token: Option<&str>,
+) -> Result<bool> {
+    let seal_guard = vault::seal_guard().await;
+    let result = sqlx::query(
+        r#"UPDATE emails
+SET password = COALESCE(?, password),
+    client_id = COALESCE(?, client_id),
+    refresh_token = COALESCE(?, refresh_token),
+    access_token = NULL,
+    cached_token = NULL,
+    token_expires_at = NULL,
+    health_status = 'unknown',
+    last_error = NULL,
+    last_error_at = NULL,
+    health_checked_at = NULL,
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = ?"#,
+    )
+    .bind(password.map(vault::seal).transpose()?)
+    .bind(client_id)
+    .bind(refresh_token.map(vault::seal).transpose()?)
+    .bind(email_id)
+    .execute(pool)
+    .await?;
+    drop(seal_guard);
+
+    token_cache::clear_token_cache(email_id);
+
+    let updated = result.rows_affected() > 0;
+    if updated {
+        let fields: Vec<&str> = [
+            ("password", password.is_some()),
+            ("client_id", client_id.is_some()),
+            ("refresh_token", refresh_token.is_some()),
+        ]
+        .iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(name, _)| *name)
+        .collect();
+        audit::record(
+            pool,
+            audit::ACTION_ACCOUNT_UPDATE,
+            AuditEvent {
+                user_id: Some(user_id),
+                email_id: Some(email_id),
+                target_ids: &[email_id],
+                detail: Some(format!("fields={}", fields.join(","))),
+            },
+        )
+        .await?;
+    }
+
+    Ok(updated)
+}
+
 /// 批量导入邮箱
-pub async fn import_emails_batch(pool: &Pool<Sqlite>, input: &str) -> Result<ImportResult> {
+pub async fn import_emails_batch(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    input: &str,
+) -> Result<ImportResult> {
     let mut success_count = 0;
     let mut failed_count = 0;
     let mut failed_lines = Vec::new();
+    let mut imported_ids = Vec::new();
 
     for (line_no, line) in input.lines().enumerate() {
         let line = line.trim();
@@ -284,10 +561,21 @@ pub async fn import_emails_batch(pool: &Pool<Sqlite>, input: &str) -> Result<Imp
             continue;
         }
 
-        match add_or_update_email(pool, email, password, client_id, refresh_token, None).await {
-            Ok(_) => {
+        match add_or_update_email(
+            pool,
+            user_id,
+            email,
+            password,
+            client_id,
+            refresh_token,
+            None,
+        )
+        .await
+        {
+            Ok(id) => {
                 log::info!("成功导入或覆盖邮箱: {}", email);
                 success_count += 1;
+                imported_ids.push(id);
             }
             Err(e) => {
                 log::error!("导入邮箱失败: {} - 错误: {}", email, e);
@@ -297,6 +585,18 @@ pub async fn import_emails_batch(pool: &Pool<Sqlite>, input: &str) -> Result<Imp
         }
     }
 
+    audit::record(
+        pool,
+        audit::ACTION_ACCOUNT_IMPORT,
+        AuditEvent {
+            user_id: Some(user_id),
+            target_ids: &imported_ids,
+            detail: Some(format!("success={success_count}, failed={failed_count}")),
+            ..Default::default()
+        },
+    )
+    .await?;
+
     Ok(ImportResult {
         success_count,
         failed_count,
@@ -304,31 +604,160 @@ pub async fn import_emails_batch(pool: &Pool<Sqlite>, input: &str) -> Result<Imp
     })
 }
 
+/// 追加邮箱筛选条件（表别名为 e）
+fn push_email_filter(qb: &mut QueryBuilder<'_, Sqlite>, filter: &EmailFilter) {
+    if let Some(tag_id) = filter.tag_id {
+        qb.push(
+            " AND EXISTS (SELECT 1 FROM email_tags et JOIN tags t ON t.id = et.tag_id AND t.user_id = e.user_id WHERE et.email_id = e.id AND et.tag_id = ",
+        );
+        qb.push_bind(tag_id);
+        qb.push(")");
+    }
+    if let Some(health) = filter.health {
+        qb.push(" AND COALESCE(e.health_status, 'unknown') = ");
+        qb.push_bind(health.as_str());
+    }
+    if let Some(user_id) = filter.user_id {
+        qb.push(" AND e.user_id = ");
+        qb.push_bind(user_id);
+    }
+    if filter.remote_only {
+        qb.push(" AND COALESCE(e.mail_type, 'outlook') != ");
+        qb.push_bind(archive::MAIL_TYPE_LOCAL);
+    }
+}
+
 /// 获取邮箱列表
-pub async fn get_emails(pool: &Pool<Sqlite>) -> Result<Vec<EmailAccount>> {
-    let emails = sqlx::query_as::<_, EmailAccount>(
-        "SELECT id, email, password, mail_type, client_id, refresh_token, last_check_time, api_mode, proxy_type, proxy_url, default_folder FROM emails ORDER BY created_at DESC",
-    )
-    .fetch_all(pool)
-    .await?;
+pub async fn get_emails(pool: &Pool<Sqlite>, filter: &EmailFilter) -> Result<Vec<EmailAccount>> {
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT e.id, e.email, e.password != '' AS has_password, e.mail_type, e.client_id, e.refresh_token != '' AS has_refresh_token, e.last_check_time, e.api_mode, e.proxy_type, e.proxy_url, e.default_folder, e.notes, e.health_status, e.last_error, e.last_error_at FROM emails e WHERE e.deleted_at IS NULL",
+    );
+    push_email_filter(&mut qb, filter);
+    qb.push(" ORDER BY e.created_at DESC");
+
+    let mut emails = qb.build_query_as::<EmailAccount>().fetch_all(pool).await?;
+
+    let ids: Vec<i64> = emails.iter().map(|e| e.id).collect();
+    let mut tag_map = tags::get_tags_for_emails(pool, &ids).await?;
+    for email in &mut emails {
+        email.tags = tag_map.remove(&email.id).unwrap_or_default();
+    }
 
     Ok(emails)
 }
 
-/// 删除邮箱
-pub async fn delete_email(pool: &Pool<Sqlite>, email_id: i64) -> Result<bool> {
-    let result = sqlx::query("DELETE FROM emails WHERE id = ?")
-        .bind(email_id)
-        .execute(pool)
+/// 根据显式 ID 列表与筛选条件确定要处理的邮箱
+///
+/// email_ids 为空时返回筛选条件命中的全部邮箱，否则返回两者的交集（保持传入顺序）
+pub async fn resolve_email_ids(
+    pool: &Pool<Sqlite>,
+    email_ids: &[i64],
+    filter: &EmailFilter,
+) -> Result<Vec<i64>> {
+    if filter.is_empty() {
+        return Ok(email_ids.to_vec());
+    }
+
+    let mut qb =
+        QueryBuilder::<Sqlite>::new("SELECT e.id FROM emails e WHERE e.deleted_at IS NULL");
+    push_email_filter(&mut qb, filter);
+    qb.push(" ORDER BY e.created_at DESC");
+    let matched: Vec<i64> = qb.build_query_scalar().fetch_all(pool).await?;
+
+    if email_ids.is_empty() {
+        return Ok(matched);
+    }
+
+    Ok(email_ids
+        .iter()
+        .copied()
+        .filter(|id| matched.contains(id))
+        .collect())
+}
+
+/// 导出邮箱到文件（与批量导入相同的 `邮箱----密码----client_id----refresh_token` 格式，可指定分隔符），
+/// 凭据只在后端写入文件、不返回给前端，返回导出的数量
+pub async fn export_emails(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    email_ids: &[i64],
+    filter: &EmailFilter,
+    separator: Option<&str>,
+    target: &Path,
+) -> Result<usize> {
+    vault::ensure_unlocked()?;
+    credentials::check_rate_limit(user_id)?;
+    let separator = separator.filter(|s| !s.is_empty()).unwrap_or("----");
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT e.id, e.email, e.password, e.client_id, e.refresh_token FROM emails e WHERE e.deleted_at IS NULL",
+    );
+    push_email_filter(&mut qb, filter);
+    qb.push(" ORDER BY e.created_at DESC");
+    let accounts = qb
+        .build_query_as::<(i64, String, String, String, String)>()
+        .fetch_all(pool)
         .await?;
 
-    Ok(result.rows_affected() > 0)
+    let mut lines = Vec::new();
+    let mut exported_ids = Vec::new();
+    for (id, email, password, client_id, refresh_token) in accounts {
+        if !email_ids.is_empty() && !email_ids.contains(&id) {
+            continue;
+        }
+        exported_ids.push(id);
+        lines.push(
+            [
+                email,
+                vault::open(&password)?,
+                client_id,
+                vault::open(&refresh_token)?,
+            ]
+            .join(separator),
+        );
+    }
+
+    tokio::fs::write(target, lines.join("\n")).await?;
+
+    audit::record(
+        pool,
+        audit::ACTION_ACCOUNT_EXPORT,
+        AuditEvent {
+            user_id: Some(user_id),
+            target_ids: &exported_ids,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(exported_ids.len())
+}
+
+/// 删除邮箱（移入回收站，保留期满后彻底清除）
+pub async fn delete_email(pool: &Pool<Sqlite>, user_id: i64, email_id: i64) -> Result<bool> {
+    let Some(email) = trash::trash_account(pool, email_id, trash::REASON_USER).await? else {
+        return Ok(false);
+    };
+
+    audit::record(
+        pool,
+        audit::ACTION_ACCOUNT_DELETE,
+        AuditEvent {
+            user_id: Some(user_id),
+            email_id: Some(email_id),
+            target_ids: &[email_id],
+            detail: Some(email),
+        },
+    )
+    .await?;
+
+    Ok(true)
 }
 
 /// 获取邮件记录
 pub async fn get_mail_records(pool: &Pool<Sqlite>, email_id: i64) -> Result<Vec<MailRecord>> {
     let records = sqlx::query_as::<_, MailRecord>(
-        "SELECT id, email_id, subject, sender, received_time, content, folder, has_attachments FROM mail_records WHERE email_id = ? ORDER BY received_time DESC",
+        "SELECT id, email_id, subject, sender, received_time, content, folder, has_attachments, is_read, is_flagged, is_answered, importance, categories FROM mail_records WHERE email_id = ? AND deleted_at IS NULL ORDER BY received_time DESC",
     )
     .bind(email_id)
     .fetch_all(pool)
@@ -340,7 +769,7 @@ pub async fn get_mail_records(pool: &Pool<Sqlite>, email_id: i64) -> Result<Vec<
 /// 获取附件列表
 pub async fn get_attachments(pool: &Pool<Sqlite>, mail_id: i64) -> Result<Vec<AttachmentInfo>> {
     let attachments = sqlx::query_as::<_, AttachmentInfo>(
-        "SELECT id, mail_id, filename, content_type, size FROM attachments WHERE mail_id = ? ORDER BY id DESC",
+        "SELECT id, mail_id, filename, content_type, size, (blob_hash IS NOT NULL OR content IS NOT NULL) AS is_downloaded, kind, source_url FROM attachments WHERE mail_id = ? ORDER BY id DESC",
     )
     .bind(mail_id)
     .fetch_all(pool)
@@ -354,27 +783,79 @@ pub async fn get_attachment_content(
     pool: &Pool<Sqlite>,
     attachment_id: i64,
 ) -> Result<AttachmentContent> {
-    let row = sqlx::query_as::<_, (i64, Option<String>, Option<String>, Vec<u8>)>(
-        "SELECT id, filename, content_type, content FROM attachments WHERE id = ?",
+    let content_type = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT content_type FROM attachments WHERE id = ?",
     )
     .bind(attachment_id)
     .fetch_one(pool)
     .await?;
 
+    // 未下载的附件先从服务器下载并缓存到附件存储
+    let (filename, path) = attachments::ensure_local(pool, attachment_id).await?;
+    let size = tokio::fs::metadata(&path).await?.len();
+
     Ok(AttachmentContent {
-        id: row.0,
-        filename: row.1,
-        content_type: row.2,
-        content_base64: STANDARD.encode(row.3),
+        id: attachment_id,
+        filename,
+        content_type,
+        size,
+        path: path.to_string_lossy().into_owned(),
     })
 }
 
-/// Outlook 单邮箱收件（增强版：支持 Token 缓存、代理、Graph API）
+/// Outlook 单邮箱收件，并记录账号健康状态
 pub async fn check_outlook_email(
     pool: &Pool<Sqlite>,
     email_id: i64,
     folder: &str,
 ) -> Result<CheckResult> {
+    let results = check_outlook_folders(pool, email_id, &[folder.to_string()]).await?;
+    results
+        .into_iter()
+        .next()
+        .map(|(_, result)| result)
+        .unwrap_or_else(|| Err(anyhow!("未指定文件夹")))
+}
+
+/// Outlook 多文件夹收件（共用一次令牌获取），并记录账号健康状态
+///
+/// 外层错误表示账号级失败（如刷新令牌失败），各文件夹的失败在结果中单独返回
+pub async fn check_outlook_folders(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    folders: &[String],
+) -> Result<Vec<(String, Result<CheckResult>)>> {
+    match check_outlook_folders_inner(pool, email_id, folders).await {
+        Ok(results) => {
+            match results.iter().find_map(|(_, result)| result.as_ref().err()) {
+                Some(err) => {
+                    if let Err(e) = health::record_failure(pool, email_id, err).await {
+                        log::error!("记录账号健康状态失败: email_id={}, error={}", email_id, e);
+                    }
+                }
+                None => {
+                    if let Err(e) = health::record_success(pool, email_id).await {
+                        log::error!("记录账号健康状态失败: email_id={}, error={}", email_id, e);
+                    }
+                }
+            }
+            Ok(results)
+        }
+        Err(err) => {
+            if let Err(e) = health::record_failure(pool, email_id, &err).await {
+                log::error!("记录账号健康状态失败: email_id={}, error={}", email_id, e);
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Outlook 多文件夹收件（增强版：支持 Token 缓存、代理、Graph API）
+async fn check_outlook_folders_inner(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    folders: &[String],
+) -> Result<Vec<(String, Result<CheckResult>)>> {
     let account = get_outlook_account(pool, email_id).await?;
     let mail_type = account
         .mail_type
@@ -387,29 +868,73 @@ pub async fn check_outlook_email(
     // 构建代理配置
     let proxy_config = ProxyConfig::from_db(account.proxy_type.clone(), account.proxy_url.clone());
 
-    // 获取配置的 API 模式
-    let configured_mode = ApiMode::from(account.api_mode.clone());
+    // 所有文件夹共用一次令牌获取
+    let (access_token, mut api_mode) =
+        acquire_outlook_access_token(pool, &account, &proxy_config).await?;
+
+    let mut results = Vec::with_capacity(folders.len());
+    for folder in folders {
+        let result = check_outlook_folder(
+            pool,
+            &account,
+            &proxy_config,
+            &access_token,
+            api_mode,
+            folder,
+        )
+        .await;
+        // 后续文件夹沿用本次实际生效的模式
+        let result = result.map(|(result, used_mode)| {
+            api_mode = used_mode;
+            result
+        });
+        results.push((folder.clone(), result));
+    }
 
-    // 使用传入的 folder 参数
-    let folder = folder.to_string();
+    // 所有文件夹都以本轮开始前的时间为增量起点，结束后统一更新
+    if results.iter().any(|(_, result)| result.is_ok()) {
+        update_last_check_time(pool, email_id).await?;
+    }
 
-    // 尝试从缓存获取 Token，如果没有则刷新并检测权限
-    let (access_token, api_mode) = match token_cache::get_valid_token(pool, email_id).await? {
-        Some(token) => {
-            // 缓存命中，使用配置的模式
-            (token, configured_mode)
+    // 按账号策略自动下载较小的附件
+    if let Some(limit_mb) = account.attachment_auto_download_mb {
+        let session = OutlookSession {
+            email: account.email.clone(),
+            access_token,
+            api_mode,
+            proxy_config,
+        };
+        if let Err(e) = attachments::auto_download(pool, &session, email_id, limit_mb).await {
+            log::warn!("自动下载附件失败: email_id={}, error={}", email_id, e);
         }
+    }
+
+    Ok(results)
+}
+
+/// 获取可用的访问令牌：优先使用缓存，否则刷新并检测 Graph API 权限
+async fn acquire_outlook_access_token(
+    pool: &Pool<Sqlite>,
+    account: &OutlookAccount,
+    proxy_config: &ProxyConfig,
+) -> Result<(String, ApiMode)> {
+    // 获取配置的 API 模式
+    let configured_mode = ApiMode::from(account.api_mode.clone());
+
+    match token_cache::get_valid_token(pool, account.id).await? {
+        // 缓存命中，使用配置的模式
+        Some(token) => Ok((token, configured_mode)),
         None => {
             // 刷新 Token 并检测 Graph API 权限
             let result = refresh_outlook_access_token_with_proxy(
                 &account.client_id,
                 &account.refresh_token,
-                &proxy_config,
+                proxy_config,
             )
             .await?;
 
             // 缓存 Token
-            token_cache::cache_token(pool, email_id, &result.access_token, result.expires_in)
+            token_cache::cache_token(pool, account.id, &result.access_token, result.expires_in)
                 .await?;
             update_email_token(pool, account.id, &result.access_token).await?;
 
@@ -423,96 +948,59 @@ pub async fn check_outlook_email(
             };
             update_email_api_mode(pool, account.id, actual_mode).await?;
 
-            (result.access_token, actual_mode)
+            Ok((result.access_token, actual_mode))
         }
-    };
+    }
+}
 
-    let mut fetched = 0usize;
-    let mut saved = 0usize;
-    let mut server_mail_ids: Vec<MailIdentifier> = Vec::new();
+/// 收取单个文件夹，返回结果与实际使用的 API 模式
+async fn check_outlook_folder(
+    pool: &Pool<Sqlite>,
+    account: &OutlookAccount,
+    proxy_config: &ProxyConfig,
+    access_token: &str,
+    api_mode: ApiMode,
+    folder: &str,
+) -> Result<(CheckResult, ApiMode)> {
+    let email_id = account.id;
+    let folder = folders::resolve_folder(pool, email_id, folder).await?;
+    let fetch_window = settings::current().sync.fetch_window;
+    let initial_sync = !folder_has_mails(pool, email_id, &folder.key).await?;
+
+    let mut fetch = FolderFetch::default();
 
     // 根据 API 模式选择收件方式
     let used_mode = match api_mode {
         ApiMode::Graph => {
             // 使用 Graph API 收件，失败时回退到 IMAP
-            match graph_api::fetch_via_graph(&access_token, &folder, 100, &proxy_config).await {
+            match graph_api::fetch_via_graph(
+                access_token,
+                &folder.graph_ref,
+                &folder.key,
+                fetch_window,
+                proxy_config,
+            )
+            .await
+            {
                 Ok(records) => {
-                    for record in &records {
-                        fetched += 1;
-
-                        // 收集服务器邮件标识
-                        server_mail_ids.push(MailIdentifier {
-                            subject: record.subject.clone(),
-                            sender: record.sender.clone(),
-                            received_time: record.received_time.clone(),
-                        });
-
-                        // 构建兼容的记录用于去重检查
-                        let fetch_record = MailFetchRecord {
-                            subject: record.subject.clone(),
-                            sender: record.sender.clone(),
-                            received_time: record.received_time.clone(),
-                            content: record.content.clone(),
-                            folder: record.folder.clone(),
-                            attachments: record
-                                .attachments
-                                .iter()
-                                .map(|a| AttachmentInput {
-                                    filename: a.filename.clone(),
-                                    content_type: a.content_type.clone(),
-                                    content: a.content.clone(),
-                                })
-                                .collect(),
-                        };
-
-                        if mail_record_exists(pool, email_id, &fetch_record).await? {
-                            continue;
-                        }
-
-                        let mail_id = insert_mail_record(pool, email_id, &fetch_record).await?;
-                        saved += 1;
-
-                        if !fetch_record.attachments.is_empty() {
-                            insert_attachments(pool, mail_id, &fetch_record.attachments).await?;
-                        }
-                    }
-
+                    store_graph_mails(pool, email_id, &records, &mut fetch).await?;
                     ApiMode::Graph
                 }
                 Err(graph_err) => {
                     // Graph API 失败，回退到 IMAP
                     log::warn!("Graph API 失败，回退到 IMAP: {}", graph_err);
-                    let last_check_time = account.last_check_time.clone();
-                    let email_address = account.email.clone();
-                    let folder_clone = folder.clone();
-                    let access_token_clone = access_token.clone();
-                    let fetch_result = tokio::task::spawn_blocking(move || {
-                        fetch_outlook_emails(
-                            &email_address,
-                            &access_token_clone,
-                            &folder_clone,
-                            last_check_time,
-                        )
-                    })
+                    let (records, server_mails) =
+                        fetch_via_imap(account, access_token, &folder).await?;
+                    store_imap_mails(
+                        pool,
+                        email_id,
+                        &folder.key,
+                        &records,
+                        &server_mails,
+                        &mut fetch,
+                    )
                     .await?;
 
-                    let (records, server_ids) = fetch_result?;
-                    server_mail_ids = server_ids;
-                    for record in &records {
-                        fetched += 1;
-
-                        if mail_record_exists(pool, email_id, record).await? {
-                            continue;
-                        }
-
-                        let mail_id = insert_mail_record(pool, email_id, record).await?;
-                        saved += 1;
-
-                        if !record.attachments.is_empty() {
-                            insert_attachments(pool, mail_id, &record.attachments).await?;
-                        }
-                    }
-
                     // 更新为 IMAP 模式
                     update_email_api_mode(pool, email_id, ApiMode::Imap).await?;
                     ApiMode::Imap
@@ -521,38 +1009,17 @@ pub async fn check_outlook_email(
         }
         ApiMode::Imap => {
             // 使用 IMAP 收件
-            let last_check_time = account.last_check_time.clone();
-            let email_address = account.email.clone();
-            let folder_clone = folder.clone();
-            let access_token_clone = access_token.clone();
-            let fetch_result = tokio::task::spawn_blocking(move || {
-                fetch_outlook_emails(
-                    &email_address,
-                    &access_token_clone,
-                    &folder_clone,
-                    last_check_time,
-                )
-            })
-            .await?;
-
-            match fetch_result {
-                Ok((records, server_ids)) => {
-                    server_mail_ids = server_ids;
-                    for record in &records {
-                        fetched += 1;
-
-                        if mail_record_exists(pool, email_id, record).await? {
-                            continue;
-                        }
-
-                        let mail_id = insert_mail_record(pool, email_id, record).await?;
-                        saved += 1;
-
-                        if !record.attachments.is_empty() {
-                            insert_attachments(pool, mail_id, &record.attachments).await?;
-                        }
-                    }
-
+            match fetch_via_imap(account, access_token, &folder).await {
+                Ok((records, server_mails)) => {
+                    store_imap_mails(
+                        pool,
+                        email_id,
+                        &folder.key,
+                        &records,
+                        &server_mails,
+                        &mut fetch,
+                    )
+                    .await?;
                     ApiMode::Imap
                 }
                 Err(err) => {
@@ -564,48 +1031,15 @@ pub async fn check_outlook_email(
 
                     // IMAP 认证失败时，回退到 Graph API 收件
                     log::warn!("IMAP 认证失败，回退到 Graph API: {}", err_msg);
-                    let records =
-                        graph_api::fetch_via_graph(&access_token, &folder, 100, &proxy_config)
-                            .await?;
-
-                    for record in &records {
-                        fetched += 1;
-
-                        // 收集服务器邮件标识
-                        server_mail_ids.push(MailIdentifier {
-                            subject: record.subject.clone(),
-                            sender: record.sender.clone(),
-                            received_time: record.received_time.clone(),
-                        });
-
-                        let fetch_record = MailFetchRecord {
-                            subject: record.subject.clone(),
-                            sender: record.sender.clone(),
-                            received_time: record.received_time.clone(),
-                            content: record.content.clone(),
-                            folder: record.folder.clone(),
-                            attachments: record
-                                .attachments
-                                .iter()
-                                .map(|a| AttachmentInput {
-                                    filename: a.filename.clone(),
-                                    content_type: a.content_type.clone(),
-                                    content: a.content.clone(),
-                                })
-                                .collect(),
-                        };
-
-                        if mail_record_exists(pool, email_id, &fetch_record).await? {
-                            continue;
-                        }
-
-                        let mail_id = insert_mail_record(pool, email_id, &fetch_record).await?;
-                        saved += 1;
-
-                        if !fetch_record.attachments.is_empty() {
-                            insert_attachments(pool, mail_id, &fetch_record.attachments).await?;
-                        }
-                    }
+                    let records = graph_api::fetch_via_graph(
+                        access_token,
+                        &folder.graph_ref,
+                        &folder.key,
+                        fetch_window,
+                        proxy_config,
+                    )
+                    .await?;
+                    store_graph_mails(pool, email_id, &records, &mut fetch).await?;
 
                     update_email_api_mode(pool, email_id, ApiMode::Graph).await?;
                     ApiMode::Graph
@@ -616,46 +1050,17 @@ pub async fn check_outlook_email(
             // 缓存命中时 Auto 模式，优先尝试 Graph API
             log::info!("缓存命中但模式为 Auto，优先尝试 Graph API");
 
-            match graph_api::fetch_via_graph(&access_token, &folder, 100, &proxy_config).await {
+            match graph_api::fetch_via_graph(
+                access_token,
+                &folder.graph_ref,
+                &folder.key,
+                fetch_window,
+                proxy_config,
+            )
+            .await
+            {
                 Ok(records) => {
-                    for record in &records {
-                        fetched += 1;
-
-                        // 收集服务器邮件标识
-                        server_mail_ids.push(MailIdentifier {
-                            subject: record.subject.clone(),
-                            sender: record.sender.clone(),
-                            received_time: record.received_time.clone(),
-                        });
-
-                        let fetch_record = MailFetchRecord {
-                            subject: record.subject.clone(),
-                            sender: record.sender.clone(),
-                            received_time: record.received_time.clone(),
-                            content: record.content.clone(),
-                            folder: record.folder.clone(),
-                            attachments: record
-                                .attachments
-                                .iter()
-                                .map(|a| AttachmentInput {
-                                    filename: a.filename.clone(),
-                                    content_type: a.content_type.clone(),
-                                    content: a.content.clone(),
-                                })
-                                .collect(),
-                        };
-
-                        if mail_record_exists(pool, email_id, &fetch_record).await? {
-                            continue;
-                        }
-
-                        let mail_id = insert_mail_record(pool, email_id, &fetch_record).await?;
-                        saved += 1;
-
-                        if !fetch_record.attachments.is_empty() {
-                            insert_attachments(pool, mail_id, &fetch_record.attachments).await?;
-                        }
-                    }
+                    store_graph_mails(pool, email_id, &records, &mut fetch).await?;
 
                     // Graph API 成功，更新模式
                     update_email_api_mode(pool, email_id, ApiMode::Graph).await?;
@@ -664,37 +1069,18 @@ pub async fn check_outlook_email(
                 Err(graph_err) => {
                     // Graph API 失败，回退到 IMAP
                     log::warn!("Graph API 失败，回退到 IMAP: {}", graph_err);
-                    let last_check_time = account.last_check_time.clone();
-                    let email_address = account.email.clone();
-                    let folder_clone = folder.clone();
-                    let access_token_clone = access_token.clone();
-                    let fetch_result = tokio::task::spawn_blocking(move || {
-                        fetch_outlook_emails(
-                            &email_address,
-                            &access_token_clone,
-                            &folder_clone,
-                            last_check_time,
-                        )
-                    })
+                    let (records, server_mails) =
+                        fetch_via_imap(account, access_token, &folder).await?;
+                    store_imap_mails(
+                        pool,
+                        email_id,
+                        &folder.key,
+                        &records,
+                        &server_mails,
+                        &mut fetch,
+                    )
                     .await?;
 
-                    let (records, server_ids) = fetch_result?;
-                    server_mail_ids = server_ids;
-                    for record in &records {
-                        fetched += 1;
-
-                        if mail_record_exists(pool, email_id, record).await? {
-                            continue;
-                        }
-
-                        let mail_id = insert_mail_record(pool, email_id, record).await?;
-                        saved += 1;
-
-                        if !record.attachments.is_empty() {
-                            insert_attachments(pool, mail_id, &record.attachments).await?;
-                        }
-                    }
-
                     // IMAP 成功，更新模式
                     update_email_api_mode(pool, email_id, ApiMode::Imap).await?;
                     ApiMode::Imap
@@ -702,13 +1088,63 @@ pub async fn check_outlook_email(
             }
         }
     };
+    let FolderFetch {
+        fetched,
+        saved,
+        mut new_mail_ids,
+        server_mail_ids,
+    } = fetch;
+
+    // 同步恢复重新出现在服务器上的邮件，再同步删除服务器上已删除的邮件
+    let restored =
+        sync_restore_reappeared_mails(pool, email_id, &folder.key, &server_mail_ids).await?;
+    if !restored.is_empty() {
+        log::info!(
+            "同步恢复完成: email_id={}, restored={}",
+            email_id,
+            restored.len()
+        );
+    }
+    let deleted_ids =
+        sync_delete_removed_mails(pool, email_id, &folder.key, &server_mail_ids).await?;
+    let deleted = deleted_ids.len();
+    if deleted > 0 {
+        audit::record(
+            pool,
+            audit::ACTION_MAIL_SYNC_DELETE,
+            AuditEvent {
+                email_id: Some(email_id),
+                target_ids: &deleted_ids,
+                detail: Some(format!("folder={}", folder.key)),
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+
+    // 首次同步（或本地邮件已清空）的文件夹收到的是历史邮件，不推送事件、不执行规则、不发通知
+    if initial_sync && !new_mail_ids.is_empty() {
+        log::info!(
+            "首次同步文件夹，跳过新邮件事件与规则: email_id={}, folder={}, count={}",
+            email_id,
+            folder.key,
+            new_mail_ids.len()
+        );
+        new_mail_ids.clear();
+    }
 
-    // 同步删除服务器上已删除的邮件
-    let deleted = sync_delete_removed_mails(pool, email_id, &folder, &server_mail_ids).await?;
+    // 推送新邮件事件，入队失败不影响收件结果
+    if let Err(e) = webhooks::dispatch_new_mails(pool, email_id, &new_mail_ids).await {
+        log::warn!("推送新邮件事件失败: email_id={}, error={}", email_id, e);
+    }
 
-    update_last_check_time(pool, email_id).await?;
+    // 对新邮件执行收件规则，规则失败不影响收件结果；已由规则通知的邮件不再发送新邮件通知
+    match rules::apply_to_new_mails(pool, email_id, &new_mail_ids).await {
+        Ok(summary) => new_mail_ids.retain(|id| !summary.notified_mail_ids.contains(id)),
+        Err(e) => log::warn!("执行收件规则失败: email_id={}, error={}", email_id, e),
+    }
 
-    Ok(CheckResult {
+    let result = CheckResult {
         email_id,
         success: true,
         fetched,
@@ -718,7 +1154,108 @@ pub async fn check_outlook_email(
             "成功获取 {fetched} 封邮件，新增 {saved} 封，删除 {deleted} 封 (模式: {:?})",
             used_mode
         ),
+        new_mail_ids,
+    };
+    Ok((result, used_mode))
+}
+
+/// 单个文件夹本轮收取的结果
+#[derive(Default)]
+struct FolderFetch {
+    /// 服务器返回的邮件数
+    fetched: usize,
+    /// 新入库的邮件数
+    saved: usize,
+    new_mail_ids: Vec<i64>,
+    /// 服务器上的邮件标识，用于同步删除与恢复
+    server_mail_ids: Vec<MailIdentifier>,
+}
+
+/// 保存一封收取到的邮件：已有记录与服务器对齐，新邮件入库并保存附件
+async fn store_fetched_mail(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    record: &MailFetchRecord,
+    fetch: &mut FolderFetch,
+) -> Result<()> {
+    fetch.fetched += 1;
+
+    if reconcile_existing_mail(pool, email_id, record).await? {
+        return Ok(());
+    }
+
+    let mail_id = insert_mail_record(pool, email_id, record).await?;
+    fetch.saved += 1;
+    fetch.new_mail_ids.push(mail_id);
+
+    if !record.attachments.is_empty() {
+        insert_attachments(pool, mail_id, &record.attachments).await?;
+    }
+    Ok(())
+}
+
+/// 保存 Graph API 收取到的邮件
+async fn store_graph_mails(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    records: &[graph_api::GraphMailRecord],
+    fetch: &mut FolderFetch,
+) -> Result<()> {
+    for record in records {
+        // 收集服务器邮件标识
+        fetch.server_mail_ids.push(MailIdentifier {
+            subject: record.subject.clone(),
+            sender: record.sender.clone(),
+            received_time: record.received_time.clone(),
+        });
+        store_fetched_mail(pool, email_id, &MailFetchRecord::from(record), fetch).await?;
+    }
+    Ok(())
+}
+
+/// 通过 IMAP 收取文件夹（在阻塞线程中执行）
+async fn fetch_via_imap(
+    account: &OutlookAccount,
+    access_token: &str,
+    folder: &ResolvedFolder,
+) -> Result<(Vec<MailFetchRecord>, Vec<ServerMail>)> {
+    let last_check_time = account.last_check_time.clone();
+    let email_address = account.email.clone();
+    let folder = folder.clone();
+    let access_token = access_token.to_string();
+    tokio::task::spawn_blocking(move || {
+        fetch_outlook_emails(&email_address, &access_token, &folder, last_check_time)
     })
+    .await?
+}
+
+/// 保存 IMAP 收取到的邮件，并将服务器上的状态同步到本地已有邮件
+async fn store_imap_mails(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    folder_key: &str,
+    records: &[MailFetchRecord],
+    server_mails: &[ServerMail],
+    fetch: &mut FolderFetch,
+) -> Result<()> {
+    fetch.server_mail_ids = server_mails.iter().map(|m| m.identifier.clone()).collect();
+    sync_mail_states(pool, email_id, folder_key, server_mails).await?;
+    for record in records {
+        store_fetched_mail(pool, email_id, record, fetch).await?;
+    }
+    Ok(())
+}
+
+/// 文件夹在本地是否已有邮件（含回收站中的邮件）
+async fn folder_has_mails(pool: &Pool<Sqlite>, email_id: i64, folder_key: &str) -> Result<bool> {
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM mail_records WHERE email_id = ? AND COALESCE(folder, 'inbox') = ?)",
+    )
+    .bind(email_id)
+    .bind(folder_key)
+    .fetch_one(pool)
+    .await?;
+    Ok(exists)
 }
 
 /// Outlook 批量收件
@@ -726,11 +1263,13 @@ pub async fn batch_check_outlook_emails(
     pool: &Pool<Sqlite>,
     email_ids: Vec<i64>,
     folder: &str,
+    filter: &EmailFilter,
 ) -> Result<BatchCheckResult> {
     let mut results = Vec::new();
     let mut success_count = 0usize;
     let mut failed_count = 0usize;
 
+    let email_ids = resolve_email_ids(pool, &email_ids, filter).await?;
     for email_id in email_ids {
         match check_outlook_email(pool, email_id, folder).await {
             Ok(result) => {
@@ -739,14 +1278,7 @@ pub async fn batch_check_outlook_emails(
             }
             Err(e) => {
                 failed_count += 1;
-                results.push(CheckResult {
-                    email_id,
-                    success: false,
-                    fetched: 0,
-                    saved: 0,
-                    deleted: 0,
-                    message: format!("收件失败: {e}"),
-                });
+                results.push(CheckResult::failure(email_id, format!("收件失败: {e}")));
             }
         }
     }
@@ -758,31 +1290,176 @@ pub async fn batch_check_outlook_emails(
     })
 }
 
-/// 获取 Outlook 邮箱信息
-async fn get_outlook_account(pool: &Pool<Sqlite>, email_id: i64) -> Result<OutlookAccount> {
-    let account = sqlx::query_as::<_, OutlookAccount>(
-        "SELECT id, email, mail_type, client_id, refresh_token, last_check_time, api_mode, proxy_type, proxy_url, default_folder FROM emails WHERE id = ?",
-    )
-    .bind(email_id)
-    .fetch_one(pool)
-    .await?;
+/// 批量验证时同时刷新令牌的账号数
+const VALIDATE_CONCURRENCY: usize = 4;
 
-    Ok(account)
+/// 批量验证账号（仅刷新令牌，不收取邮件，有限并发），并更新健康状态；结果保持账号顺序
+pub async fn validate_accounts(
+    pool: &Pool<Sqlite>,
+    email_ids: Vec<i64>,
+    filter: &EmailFilter,
+) -> Result<Vec<ValidateResult>> {
+    let email_ids = resolve_email_ids(pool, &email_ids, filter).await?;
+    let permits = Arc::new(Semaphore::new(VALIDATE_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+
+    for (index, email_id) in email_ids.into_iter().enumerate() {
+        let pool = pool.clone();
+        let permits = permits.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            (index, validate_account(&pool, email_id).await)
+        });
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    while let Some(joined) = tasks.join_next().await {
+        results.push(joined?);
+    }
+    results.sort_by_key(|(index, _)| *index);
+
+    Ok(results.into_iter().map(|(_, result)| result).collect())
 }
 
-/// 刷新 Outlook 访问令牌（支持代理，自动检测 Graph API 权限）
-///
-/// 返回 TokenRefreshResult，其中 supports_graph 表示是否支持 Graph API（通过检测 scope 中是否包含 Mail.Read）
-async fn refresh_outlook_access_token_with_proxy(
-    client_id: &str,
-    refresh_token: &str,
-    proxy_config: &ProxyConfig,
-) -> Result<TokenRefreshResult> {
-    let client = create_http_client(proxy_config, 30)?;
+/// 验证单个账号
+async fn validate_account(pool: &Pool<Sqlite>, email_id: i64) -> ValidateResult {
+    let account = match get_outlook_account(pool, email_id).await {
+        Ok(account) => account,
+        Err(e) => {
+            return ValidateResult {
+                email_id,
+                email: String::new(),
+                status: HealthStatus::Unknown,
+                message: format!("获取邮箱信息失败: {e}"),
+            }
+        }
+    };
 
-    // 请求 Graph API scope，用于检测权限
-    let response: TokenResponse = client
-        .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/token")
+    let proxy_config = ProxyConfig::from_db(account.proxy_type.clone(), account.proxy_url.clone());
+    let refreshed = refresh_outlook_access_token_with_proxy(
+        &account.client_id,
+        &account.refresh_token,
+        &proxy_config,
+    )
+    .await;
+
+    let outcome = match refreshed {
+        Ok(result) => {
+            let cached =
+                token_cache::cache_token(pool, email_id, &result.access_token, result.expires_in)
+                    .await;
+            match cached {
+                Ok(()) => health::record_success(pool, email_id)
+                    .await
+                    .map(|_| (HealthStatus::Ok, "令牌有效".to_string())),
+                Err(e) => Err(e),
+            }
+        }
+        Err(err) => health::record_failure(pool, email_id, &err)
+            .await
+            .map(|status| (status.unwrap_or(HealthStatus::Unknown), err.to_string())),
+    };
+
+    let (status, message) = outcome.unwrap_or_else(|e| (HealthStatus::Unknown, e.to_string()));
+    ValidateResult {
+        email_id,
+        email: account.email,
+        status,
+        message,
+    }
+}
+
+/// 获取账号的文件夹列表，refresh 为 true 或尚未发现过时从服务器重新获取
+pub async fn list_folders(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    refresh: bool,
+) -> Result<Vec<MailFolder>> {
+    let stored = folders::list_folders(pool, email_id).await?;
+    if !refresh && !stored.is_empty() {
+        return Ok(stored);
+    }
+
+    let session = open_outlook_session(pool, email_id).await?;
+
+    // 与收件一致：优先 Graph API，失败时回退到 IMAP
+    let graph_folders = match session.api_mode {
+        ApiMode::Graph | ApiMode::Auto => {
+            match graph_api::list_mail_folders(&session.access_token, &session.proxy_config).await {
+                Ok(folders) => Some(folders),
+                Err(e) => {
+                    log::warn!("Graph API 获取文件夹失败，回退到 IMAP: {}", e);
+                    None
+                }
+            }
+        }
+        ApiMode::Imap => None,
+    };
+
+    let discovered = match graph_folders {
+        Some(folders) => folders,
+        None => {
+            tokio::task::spawn_blocking(move || {
+                list_imap_folders(&session.email, &session.access_token)
+            })
+            .await??
+        }
+    };
+
+    folders::save_folders(pool, email_id, &discovered).await?;
+    folders::list_folders(pool, email_id).await
+}
+
+/// 已获取令牌的账号访问信息，用于对服务器执行多次操作
+pub struct OutlookSession {
+    pub email: String,
+    pub access_token: String,
+    pub api_mode: ApiMode,
+    pub proxy_config: ProxyConfig,
+}
+
+/// 获取账号的访问令牌并返回访问信息
+pub async fn open_outlook_session(pool: &Pool<Sqlite>, email_id: i64) -> Result<OutlookSession> {
+    let account = get_outlook_account(pool, email_id).await?;
+    let proxy_config = ProxyConfig::from_db(account.proxy_type.clone(), account.proxy_url.clone());
+    let (access_token, api_mode) =
+        acquire_outlook_access_token(pool, &account, &proxy_config).await?;
+
+    Ok(OutlookSession {
+        email: account.email,
+        access_token,
+        api_mode,
+        proxy_config,
+    })
+}
+
+/// 获取 Outlook 邮箱信息
+async fn get_outlook_account(pool: &Pool<Sqlite>, email_id: i64) -> Result<OutlookAccount> {
+    let mut account = sqlx::query_as::<_, OutlookAccount>(
+        "SELECT id, email, mail_type, client_id, refresh_token, last_check_time, api_mode, proxy_type, proxy_url, default_folder, attachment_auto_download_mb FROM emails WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("邮箱不存在"))?;
+    account.refresh_token = vault::open(&account.refresh_token)?;
+
+    Ok(account)
+}
+
+/// 刷新 Outlook 访问令牌（支持代理，自动检测 Graph API 权限）
+///
+/// 返回 TokenRefreshResult，其中 supports_graph 表示是否支持 Graph API（通过检测 scope 中是否包含 Mail.Read）
+async fn refresh_outlook_access_token_with_proxy(
+    client_id: &str,
+    refresh_token: &str,
+    proxy_config: &ProxyConfig,
+) -> Result<TokenRefreshResult> {
+    let client = create_http_client(proxy_config, settings::request_timeout_secs())?;
+
+    // 请求 Graph API scope，用于检测权限
+    let response: TokenResponse = client
+        .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/token")
         .form(&[
             ("client_id", client_id),
             ("grant_type", "refresh_token"),
@@ -815,13 +1492,14 @@ async fn refresh_outlook_access_token_with_proxy(
     let description = response
         .error_description
         .unwrap_or_else(|| "未知错误描述".to_string());
-    Err(anyhow!("刷新令牌失败: {} - {}", error, description))
+    Err(OAuthError { error, description }.into())
 }
 
 /// 更新邮箱访问令牌
 async fn update_email_token(pool: &Pool<Sqlite>, email_id: i64, access_token: &str) -> Result<()> {
+    let _seal_guard = vault::seal_guard().await;
     sqlx::query("UPDATE emails SET access_token = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
-        .bind(access_token)
+        .bind(vault::seal(access_token)?)
         .bind(email_id)
         .execute(pool)
         .await?;
@@ -860,52 +1538,40 @@ async fn update_last_check_time(pool: &Pool<Sqlite>, email_id: i64) -> Result<()
 fn fetch_outlook_emails(
     email_address: &str,
     access_token: &str,
-    folder: &str,
+    folder: &ResolvedFolder,
     last_check_time: Option<String>,
-) -> Result<(Vec<MailFetchRecord>, Vec<MailIdentifier>)> {
-    // 使用更稳定的企业级 IMAP 服务器
-    let tls = TlsConnector::builder().build()?;
-    let addr = "outlook.office365.com:993"
-        .to_socket_addrs()?
-        .next()
-        .ok_or_else(|| anyhow!("无法解析 IMAP 服务器地址"))?;
-    let tcp = TcpStream::connect_timeout(&addr, Duration::from_secs(30))?;
-    let stream = tls.connect("outlook.office365.com", tcp)?;
-    let client = imap::Client::new(stream);
-
-    let authenticator = OutlookAuthenticator {
-        user: email_address.to_string(),
-        access_token: access_token.to_string(),
-    };
-    let mut session = client
-        .authenticate("XOAUTH2", &authenticator)
-        .map_err(|(err, _)| anyhow!(err))?;
+) -> Result<(Vec<MailFetchRecord>, Vec<ServerMail>)> {
+    let mut session = connect_outlook_imap(email_address, access_token)?;
+    let fetch_window = settings::current().sync.fetch_window;
 
     // 支持多文件夹
-    session.select(folder)?;
+    session.select(&folder.imap_name)?;
 
-    // 拉取最近邮件标识用于同步删除，避免仅靠增量无法感知删除
-    let mut server_identifiers = Vec::new();
+    // 拉取最近邮件标识与状态，用于同步删除与状态，避免仅靠增量无法感知删除和状态变化
+    let mut server_mails = Vec::new();
     let mut all_ids: Vec<_> = session.search("ALL")?.into_iter().collect();
     all_ids.sort_unstable();
-    if all_ids.len() > 100 {
-        all_ids = all_ids[all_ids.len() - 100..].to_vec();
+    if all_ids.len() > fetch_window {
+        all_ids = all_ids[all_ids.len() - fetch_window..].to_vec();
     }
     if !all_ids.is_empty() {
-        let id_set = all_ids
-            .iter()
-            .map(|id| id.to_string())
-            .collect::<Vec<_>>()
-            .join(",");
-        let fetches =
-            session.fetch(id_set, "BODY.PEEK[HEADER.FIELDS (SUBJECT FROM DATE)]")?;
+        let fetches = session.fetch(
+            imap_sequence_set(&all_ids),
+            "(UID FLAGS BODY.PEEK[HEADER.FIELDS (SUBJECT FROM DATE IMPORTANCE X-PRIORITY)])",
+        )?;
         for fetch in fetches.iter() {
-            let raw = match fetch.body() {
-                Some(body) => body,
+            let raw = match fetch.header() {
+                Some(header) => header,
                 None => continue,
             };
-            if let Some(identifier) = build_mail_identifier_from_headers(raw) {
-                server_identifiers.push(identifier);
+            if let Some((identifier, importance)) = build_mail_identifier_from_headers(raw) {
+                let mut state = MailState::from_imap_flags(fetch.flags());
+                state.importance = importance;
+                server_mails.push(ServerMail {
+                    identifier,
+                    imap_uid: fetch.uid,
+                    state,
+                });
             }
         }
     }
@@ -917,16 +1583,23 @@ fn fetch_outlook_emails(
 
     let mut ids: Vec<_> = session.search(criteria)?.into_iter().collect();
     ids.sort_unstable();
-    if ids.len() > 100 {
-        ids = ids[ids.len() - 100..].to_vec();
+    if ids.len() > fetch_window {
+        ids = ids[ids.len() - fetch_window..].to_vec();
     }
 
+    // 只下载头部与正文段，附件根据 BODYSTRUCTURE 记录元数据，内容按需下载；
+    // 头部与结构对整个 ID 集合一次获取，正文段按段号分组批量获取
     let mut records = Vec::new();
-    for id in ids {
-        let fetches = session.fetch(id.to_string(), "RFC822")?;
+    // 正文段号 -> [(序号, 记录下标)]
+    let mut text_sections: HashMap<String, Vec<(u32, usize)>> = HashMap::new();
+    if !ids.is_empty() {
+        let fetches = session.fetch(
+            imap_sequence_set(&ids),
+            "(UID FLAGS BODYSTRUCTURE BODY.PEEK[HEADER])",
+        )?;
         for fetch in fetches.iter() {
-            let raw = match fetch.body() {
-                Some(body) => body,
+            let raw = match fetch.header() {
+                Some(header) => header,
                 None => continue,
             };
             let parsed = match mailparse::parse_mail(raw) {
@@ -934,16 +1607,194 @@ fn fetch_outlook_emails(
                 Err(_) => continue,
             };
 
-            match build_mail_record(parsed, folder) {
-                Ok(record) => records.push(record),
+            let mut record = match build_mail_record(parsed, &folder.key) {
+                Ok(record) => record,
                 Err(_) => continue,
+            };
+            record.imap_uid = fetch.uid;
+            let importance = record.state.importance.take();
+            record.state = MailState {
+                importance,
+                ..MailState::from_imap_flags(fetch.flags())
+            };
+
+            if let Some(structure) = fetch.bodystructure() {
+                let mut parts = ImapParts::default();
+                collect_imap_parts(structure, &[], &mut parts);
+                if let Some(section) = parts.plain.or(parts.html) {
+                    text_sections
+                        .entry(section)
+                        .or_default()
+                        .push((fetch.message, records.len()));
+                }
+                record.attachments = parts.attachments;
             }
+            records.push(record);
+        }
+    }
+
+    for (section, targets) in text_sections {
+        let seqs: Vec<u32> = targets.iter().map(|(seq, _)| *seq).collect();
+        let text_fetches = session.fetch(imap_sequence_set(&seqs), imap_part_query(&section))?;
+        let mut contents: HashMap<u32, String> = text_fetches
+            .iter()
+            .filter_map(|f| {
+                let raw = imap_part_content(f, &section)?;
+                let body = mailparse::parse_mail(&raw).ok()?.get_body().ok()?;
+                Some((f.message, body))
+            })
+            .collect();
+        for (seq, index) in targets {
+            records[index].content = contents.remove(&seq).unwrap_or_default();
         }
     }
 
     session.logout()?;
 
-    Ok((records, server_identifiers))
+    Ok((records, server_mails))
+}
+
+/// 将升序的序号列表格式化为 IMAP 序号集合，连续的序号合并为区间（如 1:3,7）
+fn imap_sequence_set(ids: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &id in ids {
+        match ranges.last_mut() {
+            Some((_, end)) if id == *end + 1 => *end = id,
+            _ => ranges.push((id, id)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}:{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 导入一封原始邮件（RFC 822）到指定账号，已存在时跳过，返回是否新增
+///
+/// state 为归档中记录的已读/旗标/已答复状态，重要性从邮件头部解析
+pub async fn import_raw_mail(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    raw: &[u8],
+    folder: &str,
+    state: MailState,
+) -> Result<bool> {
+    let parsed = mailparse::parse_mail(raw)?;
+    let mut record = build_mail_record(parsed, &folders::canonical_folder_key(folder))?;
+    let importance = record.state.importance.take();
+    record.state = MailState {
+        importance,
+        ..state
+    };
+
+    if reconcile_existing_mail(pool, email_id, &record).await? {
+        return Ok(false);
+    }
+
+    let mail_id = insert_mail_record(pool, email_id, &record).await?;
+    if !record.attachments.is_empty() {
+        insert_attachments(pool, mail_id, &record.attachments).await?;
+    }
+
+    Ok(true)
+}
+
+/// 连接并登录 Outlook IMAP（同步）
+pub fn connect_outlook_imap(
+    email_address: &str,
+    access_token: &str,
+) -> Result<imap::Session<native_tls::TlsStream<TcpStream>>> {
+    // 默认使用更稳定的企业级 IMAP 服务器，可在设置中修改
+    let settings = settings::current();
+    let tls = TlsConnector::builder().build()?;
+    let addr = (settings.imap.host.as_str(), settings.imap.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("无法解析 IMAP 服务器地址"))?;
+    let tcp = TcpStream::connect_timeout(
+        &addr,
+        Duration::from_secs(settings.network.connect_timeout_secs),
+    )?;
+    let stream = tls.connect(&settings.imap.host, tcp)?;
+    let client = imap::Client::new(stream);
+
+    let authenticator = OutlookAuthenticator {
+        user: email_address.to_string(),
+        access_token: access_token.to_string(),
+    };
+    let session = client
+        .authenticate("XOAUTH2", &authenticator)
+        .map_err(|(err, _)| anyhow!(err))?;
+
+    Ok(session)
+}
+
+/// 列出 IMAP 文件夹（同步），通过 special-use 标记识别常用文件夹
+fn list_imap_folders(email_address: &str, access_token: &str) -> Result<Vec<DiscoveredFolder>> {
+    let mut session = connect_outlook_imap(email_address, access_token)?;
+    let names = session.list(None, Some("*"))?;
+
+    let mut folders = Vec::new();
+    for name in names.iter() {
+        if name.attributes().contains(&NameAttribute::NoSelect) {
+            continue;
+        }
+
+        let delimiter = name.delimiter().unwrap_or("/");
+        let path = name.name().split(delimiter).collect::<Vec<_>>().join("/");
+        let (parent_path, display_name) = match path.rsplit_once('/') {
+            Some((parent, leaf)) => (Some(parent.to_string()), leaf.to_string()),
+            None => (None, path.clone()),
+        };
+
+        let special_use = if name.name().eq_ignore_ascii_case("INBOX") {
+            Some("inbox")
+        } else {
+            name.attributes().iter().find_map(|attr| match attr {
+                NameAttribute::Junk => Some("junk"),
+                NameAttribute::Sent => Some("sent"),
+                NameAttribute::Drafts => Some("drafts"),
+                NameAttribute::Trash => Some("deleted"),
+                NameAttribute::Archive => Some("archive"),
+                _ => None,
+            })
+        };
+        let folder_key = special_use
+            .map(str::to_string)
+            .unwrap_or_else(|| folders::canonical_folder_key(&path));
+
+        // STATUS 失败时不影响文件夹列表
+        let (total_count, unread_count) = match session.status(name.name(), "(MESSAGES UNSEEN)") {
+            Ok(mailbox) => (Some(mailbox.exists as i64), mailbox.unseen.map(i64::from)),
+            Err(e) => {
+                log::warn!("获取文件夹状态失败: folder={}, error={}", name.name(), e);
+                (None, None)
+            }
+        };
+
+        folders.push(DiscoveredFolder {
+            folder_key,
+            display_name,
+            parent_key: parent_path.as_deref().map(folders::canonical_folder_key),
+            path,
+            graph_id: None,
+            imap_name: Some(name.name().to_string()),
+            special_use: special_use.map(str::to_string),
+            unread_count,
+            total_count,
+        });
+    }
+
+    session.logout()?;
+
+    Ok(folders)
 }
 
 /// 构建邮件记录
@@ -951,6 +1802,11 @@ fn build_mail_record(parsed: ParsedMail, folder: &str) -> Result<MailFetchRecord
     let subject = decode_header_value(parsed.headers.get_first_value("Subject"));
     let sender = decode_header_value(parsed.headers.get_first_value("From"));
     let received_time = parse_received_time(parsed.headers.get_first_value("Date"));
+    let message_id = parsed.headers.get_first_value("Message-ID");
+    let importance = mail_state::importance_from_headers(
+        parsed.headers.get_first_value("Importance").as_deref(),
+        parsed.headers.get_first_value("X-Priority").as_deref(),
+    );
 
     let (plain, html, attachments) = extract_content_and_attachments(&parsed)?;
     let content = plain.or(html).unwrap_or_default();
@@ -962,20 +1818,207 @@ fn build_mail_record(parsed: ParsedMail, folder: &str) -> Result<MailFetchRecord
         content,
         folder: folder.to_string(),
         attachments,
+        graph_id: None,
+        imap_uid: None,
+        message_id,
+        state: MailState {
+            importance,
+            ..MailState::default()
+        },
     })
 }
 
-/// 从邮件头部构建用于同步删除的标识
-fn build_mail_identifier_from_headers(raw: &[u8]) -> Option<MailIdentifier> {
+/// 从邮件头部构建用于同步删除的标识，同时解析重要性
+fn build_mail_identifier_from_headers(raw: &[u8]) -> Option<(MailIdentifier, Option<String>)> {
     let parsed = mailparse::parse_mail(raw).ok()?;
     let subject = decode_header_value(parsed.headers.get_first_value("Subject"));
     let sender = decode_header_value(parsed.headers.get_first_value("From"));
     let received_time = parse_received_time(parsed.headers.get_first_value("Date"));
-    Some(MailIdentifier {
-        subject,
-        sender,
-        received_time,
-    })
+    let importance = mail_state::importance_from_headers(
+        parsed.headers.get_first_value("Importance").as_deref(),
+        parsed.headers.get_first_value("X-Priority").as_deref(),
+    );
+    Some((
+        MailIdentifier {
+            subject,
+            sender,
+            received_time,
+        },
+        importance,
+    ))
+}
+
+/// BODYSTRUCTURE 中的正文段与附件
+#[derive(Default)]
+struct ImapParts {
+    plain: Option<String>,
+    html: Option<String>,
+    attachments: Vec<AttachmentInput>,
+}
+
+/// 递归遍历 BODYSTRUCTURE，规则与 walk_parts 一致
+fn collect_imap_parts(structure: &BodyStructure<'_>, path: &[u32], parts: &mut ImapParts) {
+    let (common, other) = match structure {
+        BodyStructure::Multipart { bodies, .. } => {
+            for (index, body) in bodies.iter().enumerate() {
+                let mut child = path.to_vec();
+                child.push(index as u32 + 1);
+                collect_imap_parts(body, &child, parts);
+            }
+            return;
+        }
+        BodyStructure::Basic { common, other, .. }
+        | BodyStructure::Text { common, other, .. }
+        | BodyStructure::Message { common, other, .. } => (common, other),
+    };
+
+    let section = imap_section_ref(path);
+    let content_type = format!("{}/{}", common.ty.ty, common.ty.subtype).to_lowercase();
+    let filename = imap_part_filename(common);
+    // 嵌入的邮件始终作为附件保存为 .eml
+    let is_message = matches!(structure, BodyStructure::Message { .. });
+    let is_attachment = is_message
+        || common
+            .disposition
+            .as_ref()
+            .is_some_and(|d| d.ty.eq_ignore_ascii_case("attachment"))
+        || filename.is_some();
+
+    if is_attachment {
+        // octets 为编码后的大小，base64 按 3/4 估算原始大小
+        let octets = i64::from(other.octets);
+        let size = match other.transfer_encoding {
+            ContentEncoding::Base64 => octets * 3 / 4,
+            _ => octets,
+        };
+        let default_name = if is_message {
+            "message.eml"
+        } else {
+            "attachment"
+        };
+        parts.attachments.push(AttachmentInput {
+            filename: filename.unwrap_or_else(|| default_name.to_string()),
+            content_type,
+            size,
+            remote_source: Some(attachments::SOURCE_IMAP),
+            remote_ref: Some(section),
+            content: None,
+            kind: if is_message {
+                attachments::KIND_ITEM
+            } else {
+                attachments::KIND_FILE
+            },
+            source_url: None,
+        });
+        return;
+    }
+
+    if content_type == "text/plain" && parts.plain.is_none() {
+        parts.plain = Some(section);
+    } else if content_type == "text/html" && parts.html.is_none() {
+        parts.html = Some(section);
+    }
+}
+
+/// IMAP 段号：单段邮件的正文为 TEXT，其余为 1.2 形式
+fn imap_section_ref(path: &[u32]) -> String {
+    if path.is_empty() {
+        return IMAP_TEXT_SECTION.to_string();
+    }
+    path.iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// 单段邮件正文的段号
+const IMAP_TEXT_SECTION: &str = "TEXT";
+
+/// 从 BODYSTRUCTURE 参数中提取附件文件名
+fn imap_part_filename(common: &BodyContentCommon<'_>) -> Option<String> {
+    let disposition_params = common.disposition.as_ref().and_then(|d| d.params.as_ref());
+    [
+        (disposition_params, "filename"),
+        (common.ty.params.as_ref(), "name"),
+    ]
+    .into_iter()
+    .find_map(|(params, key)| find_mime_param(params?, key))
+}
+
+/// 查找 MIME 参数，支持 RFC 2231（key*=charset''value）与 RFC 2047 编码
+fn find_mime_param(params: &[(Cow<'_, str>, Cow<'_, str>)], key: &str) -> Option<String> {
+    let extended = format!("{key}*");
+    for (name, value) in params {
+        if name.eq_ignore_ascii_case(&extended) {
+            return Some(decode_rfc2231_value(value));
+        }
+    }
+    params
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| decode_mime_words(value))
+}
+
+/// 解码 RFC 2231 参数值（仅支持 UTF-8 与 ASCII 字符集）
+fn decode_rfc2231_value(value: &str) -> String {
+    let encoded = value.splitn(3, '\'').nth(2).unwrap_or(value);
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut chars = encoded.bytes();
+    while let Some(b) = chars.next() {
+        if b == b'%' {
+            let hex: Vec<u8> = chars.by_ref().take(2).collect();
+            match std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                Some(decoded) => bytes.push(decoded),
+                None => {
+                    bytes.push(b'%');
+                    bytes.extend(hex);
+                }
+            }
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// 解码 RFC 2047 编码字（=?charset?B?...?=）
+fn decode_mime_words(value: &str) -> String {
+    mailparse::parse_header(format!("X: {value}").as_bytes())
+        .map(|(header, _)| header.get_value())
+        .unwrap_or_else(|_| value.to_string())
+}
+
+/// 下载指定段所需的 FETCH 参数
+pub fn imap_part_query(section: &str) -> String {
+    if section == IMAP_TEXT_SECTION {
+        "(BODY.PEEK[HEADER] BODY.PEEK[TEXT])".to_string()
+    } else {
+        format!("(BODY.PEEK[{section}.MIME] BODY.PEEK[{section}])")
+    }
+}
+
+/// 拼接段的 MIME 头部与内容，可直接交给 mailparse 解码
+pub fn imap_part_content(fetch: &imap::types::Fetch<'_>, section: &str) -> Option<Vec<u8>> {
+    let (header, body) = if section == IMAP_TEXT_SECTION {
+        (fetch.header()?, fetch.text()?)
+    } else {
+        let path: Vec<u32> = section
+            .split('.')
+            .map(|n| n.parse().ok())
+            .collect::<Option<_>>()?;
+        (
+            fetch.section(&SectionPath::Part(path.clone(), Some(MessageSection::Mime)))?,
+            fetch.section(&SectionPath::Part(path, None))?,
+        )
+    };
+
+    let mut raw = Vec::with_capacity(header.len() + body.len());
+    raw.extend_from_slice(header);
+    raw.extend_from_slice(body);
+    Some(raw)
 }
 
 /// 解析邮件头部
@@ -1031,7 +2074,12 @@ fn walk_parts(
             attachments.push(AttachmentInput {
                 filename: name,
                 content_type: part.ctype.mimetype.clone(),
-                content,
+                size: content.len() as i64,
+                remote_source: None,
+                remote_ref: None,
+                content: Some(content),
+                kind: attachments::KIND_FILE,
+                source_url: None,
             });
             return Ok(());
         }
@@ -1070,14 +2118,14 @@ fn extract_filename(part: &ParsedMail) -> Option<String> {
     None
 }
 
-/// 检查邮件记录是否已存在
-async fn mail_record_exists(
+/// 与本地已有记录对账：记录存在时以服务器为准更新文件夹、服务器标识与状态，返回记录是否已存在
+async fn reconcile_existing_mail(
     pool: &Pool<Sqlite>,
     email_id: i64,
     record: &MailFetchRecord,
 ) -> Result<bool> {
-    if let Some(received_time) = &record.received_time {
-        let exists = sqlx::query_scalar::<_, i64>(
+    let existing = if let Some(received_time) = &record.received_time {
+        sqlx::query_scalar::<_, i64>(
             "SELECT id FROM mail_records WHERE email_id = ? AND subject IS ? AND sender IS ? AND received_time = ? LIMIT 1",
         )
         .bind(email_id)
@@ -1086,21 +2134,52 @@ async fn mail_record_exists(
         .bind(received_time)
         .fetch_optional(pool)
         .await?
-        .is_some();
-        return Ok(exists);
-    }
+    } else {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT id FROM mail_records WHERE email_id = ? AND subject IS ? AND sender IS ? AND received_time IS NULL LIMIT 1",
+        )
+        .bind(email_id)
+        .bind(&record.subject)
+        .bind(&record.sender)
+        .fetch_optional(pool)
+        .await?
+    };
 
-    let exists = sqlx::query_scalar::<_, i64>(
-        "SELECT id FROM mail_records WHERE email_id = ? AND subject IS ? AND sender IS ? AND received_time IS NULL LIMIT 1",
+    let Some(mail_id) = existing else {
+        return Ok(false);
+    };
+
+    // 邮件在服务器上换了文件夹时，原文件夹下的 Graph ID / UID 均已失效；状态以服务器为准
+    // 回收站状态不在这里修改，同步移入回收站的邮件由 sync_restore_reappeared_mails 恢复
+    sqlx::query(
+        r#"UPDATE mail_records
+SET graph_id = COALESCE(?, CASE WHEN folder IS ? THEN graph_id END),
+    imap_uid = COALESCE(?, CASE WHEN folder IS ? THEN imap_uid END),
+    message_id = COALESCE(message_id, ?),
+    folder = ?,
+    is_read = ?,
+    is_flagged = ?,
+    is_answered = ?,
+    importance = COALESCE(?, importance),
+    categories = ?
+WHERE id = ?"#,
     )
-    .bind(email_id)
-    .bind(&record.subject)
-    .bind(&record.sender)
-    .fetch_optional(pool)
-    .await?
-    .is_some();
+    .bind(&record.graph_id)
+    .bind(&record.folder)
+    .bind(record.imap_uid.map(i64::from))
+    .bind(&record.folder)
+    .bind(&record.message_id)
+    .bind(&record.folder)
+    .bind(record.state.is_read)
+    .bind(record.state.is_flagged)
+    .bind(record.state.is_answered)
+    .bind(&record.state.importance)
+    .bind(record.state.categories_json())
+    .bind(mail_id)
+    .execute(pool)
+    .await?;
 
-    Ok(exists)
+    Ok(true)
 }
 
 /// 新增邮件记录
@@ -1111,7 +2190,7 @@ async fn insert_mail_record(
 ) -> Result<i64> {
     let has_attachments = if record.attachments.is_empty() { 0 } else { 1 };
     let mail_id: i64 = sqlx::query_scalar(
-        "INSERT INTO mail_records (email_id, subject, sender, received_time, content, folder, has_attachments) VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING id",
+        "INSERT INTO mail_records (email_id, subject, sender, received_time, content, folder, has_attachments, graph_id, imap_uid, message_id, is_read, is_flagged, is_answered, importance, categories) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
     )
     .bind(email_id)
     .bind(&record.subject)
@@ -1120,28 +2199,44 @@ async fn insert_mail_record(
     .bind(&record.content)
     .bind(&record.folder)
     .bind(has_attachments)
+    .bind(&record.graph_id)
+    .bind(record.imap_uid.map(i64::from))
+    .bind(&record.message_id)
+    .bind(record.state.is_read)
+    .bind(record.state.is_flagged)
+    .bind(record.state.is_answered)
+    .bind(&record.state.importance)
+    .bind(record.state.categories_json())
     .fetch_one(pool)
     .await?;
 
     Ok(mail_id)
 }
 
-/// 新增附件记录
+/// 新增附件记录：已获取的内容写入附件存储，否则只保存元数据与服务器引用
 async fn insert_attachments(
     pool: &Pool<Sqlite>,
     mail_id: i64,
     attachments: &[AttachmentInput],
 ) -> Result<()> {
+    let _guard = blob_store::lock().await;
     for attachment in attachments {
-        let size = attachment.content.len() as i64;
+        let blob_hash = match &attachment.content {
+            Some(content) => Some(blob_store::put(pool, content).await?),
+            None => None,
+        };
         sqlx::query(
-            "INSERT INTO attachments (mail_id, filename, content_type, size, content) VALUES (?, ?, ?, ?, ?)",
+            "INSERT INTO attachments (mail_id, filename, content_type, size, blob_hash, remote_source, remote_ref, kind, source_url) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(mail_id)
         .bind(&attachment.filename)
         .bind(&attachment.content_type)
-        .bind(size)
-        .bind(&attachment.content)
+        .bind(attachment.size)
+        .bind(&blob_hash)
+        .bind(attachment.remote_source)
+        .bind(&attachment.remote_ref)
+        .bind(attachment.kind)
+        .bind(&attachment.source_url)
         .execute(pool)
         .await?;
     }
@@ -1157,64 +2252,145 @@ struct MailIdentifier {
     received_time: Option<String>,
 }
 
-/// 同步删除服务器上已删除的邮件
-/// 返回删除的邮件数量
+/// IMAP 服务器上最近邮件的标识与状态
+struct ServerMail {
+    identifier: MailIdentifier,
+    imap_uid: Option<u32>,
+    state: MailState,
+}
+
+/// 将 IMAP 服务器上的状态同步到本地已有邮件（新邮件的状态在入库时写入）
+async fn sync_mail_states(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    folder: &str,
+    server_mails: &[ServerMail],
+) -> Result<()> {
+    for mail in server_mails {
+        let identifier = &mail.identifier;
+        sqlx::query(
+            r#"UPDATE mail_records
+SET is_read = ?,
+    is_flagged = ?,
+    is_answered = ?,
+    importance = COALESCE(?, importance),
+    categories = ?,
+    imap_uid = COALESCE(?, imap_uid)
+WHERE email_id = ? AND folder = ? AND subject IS ? AND sender IS ? AND received_time IS ?"#,
+        )
+        .bind(mail.state.is_read)
+        .bind(mail.state.is_flagged)
+        .bind(mail.state.is_answered)
+        .bind(&mail.state.importance)
+        .bind(mail.state.categories_json())
+        .bind(mail.imap_uid.map(i64::from))
+        .bind(email_id)
+        .bind(folder)
+        .bind(&identifier.subject)
+        .bind(&identifier.sender)
+        .bind(&identifier.received_time)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// 同步恢复：因同步移入回收站、但又出现在服务器该文件夹中的邮件（用户删除的保持不变）
+/// 返回恢复的邮件 ID
+async fn sync_restore_reappeared_mails(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    folder: &str,
+    server_mails: &[MailIdentifier],
+) -> Result<Vec<i64>> {
+    use std::collections::HashSet;
+
+    let server_set: HashSet<_> = server_mails.iter().map(normalize_identifier).collect();
+    if server_set.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let normalized_folder = folders::canonical_folder_key(folder);
+    let trashed = sqlx::query_as::<_, (i64, Option<String>, Option<String>, Option<String>)>(
+        "SELECT id, subject, sender, received_time FROM mail_records WHERE email_id = ? AND deleted_reason = ? AND COALESCE(folder, 'inbox') = ?",
+    )
+    .bind(email_id)
+    .bind(trash::REASON_SYNC)
+    .bind(&normalized_folder)
+    .fetch_all(pool)
+    .await?;
+
+    let reappeared: Vec<i64> = trashed
+        .into_iter()
+        .filter(|(_, subject, sender, received_time)| {
+            server_set.contains(&normalize_identifier(&MailIdentifier {
+                subject: subject.clone(),
+                sender: sender.clone(),
+                received_time: received_time.clone(),
+            }))
+        })
+        .map(|(mail_id, ..)| mail_id)
+        .collect();
+    trash::restore_synced_mails(pool, &reappeared).await
+}
+
+/// 标准化邮件标识（发件人与时间格式），用于本地与服务器邮件比对
+fn normalize_identifier(mail: &MailIdentifier) -> MailIdentifier {
+    MailIdentifier {
+        subject: mail.subject.clone(),
+        sender: mail.sender.as_ref().map(|s| normalize_sender(s)),
+        received_time: mail.received_time.as_ref().map(|t| normalize_time(t)),
+    }
+}
+
+/// 同步删除服务器上已删除的邮件（移入回收站）
+/// 返回移入回收站的邮件 ID
 async fn sync_delete_removed_mails(
     pool: &Pool<Sqlite>,
     email_id: i64,
     folder: &str,
     server_mails: &[MailIdentifier],
-) -> Result<usize> {
+) -> Result<Vec<i64>> {
     use std::collections::HashSet;
 
     if server_mails.is_empty() {
         log::info!("同步删除: 服务器邮件为空，清理本地文件夹邮件");
-        let normalized_folder = normalize_folder_for_sync(folder);
+        let normalized_folder = folders::canonical_folder_key(folder);
         let local_records = sqlx::query_as::<_, (i64, Option<String>)>(
-            "SELECT id, folder FROM mail_records WHERE email_id = ?",
+            "SELECT id, folder FROM mail_records WHERE email_id = ? AND deleted_at IS NULL",
         )
         .bind(email_id)
         .fetch_all(pool)
         .await?;
 
-        let mut deleted = 0usize;
+        let mut removed = Vec::new();
         for (mail_id, local_folder) in local_records {
             let local_normalized = local_folder
                 .as_ref()
-                .map(|f| normalize_folder_for_sync(f))
+                .map(|f| folders::canonical_folder_key(f))
                 .unwrap_or_else(|| "inbox".to_string());
             if local_normalized != normalized_folder {
                 continue;
             }
 
-            sqlx::query("DELETE FROM attachments WHERE mail_id = ?")
-                .bind(mail_id)
-                .execute(pool)
-                .await?;
-            sqlx::query("DELETE FROM mail_records WHERE id = ?")
-                .bind(mail_id)
-                .execute(pool)
-                .await?;
-
-            deleted += 1;
+            removed.push(mail_id);
         }
+        let deleted = trash::trash_mails(pool, &removed, trash::REASON_SYNC).await?;
 
-        log::info!("同步删除完成: email_id={}, deleted={}", email_id, deleted);
+        log::info!(
+            "同步删除完成: email_id={}, deleted={}",
+            email_id,
+            deleted.len()
+        );
         return Ok(deleted);
     }
 
     // 构建服务器邮件标识集合（标准化时间格式）
-    let server_set: HashSet<_> = server_mails
-        .iter()
-        .map(|m| MailIdentifier {
-            subject: m.subject.clone(),
-            sender: m.sender.as_ref().map(|s| normalize_sender(s)),
-            received_time: m.received_time.as_ref().map(|t| normalize_time(t)),
-        })
-        .collect();
+    let server_set: HashSet<_> = server_mails.iter().map(normalize_identifier).collect();
 
     // 标准化文件夹名称用于匹配
-    let normalized_folder = normalize_folder_for_sync(folder);
+    let normalized_folder = folders::canonical_folder_key(folder);
 
     log::info!("同步删除检查: email_id={}, folder={}, 服务器邮件数={}",
         email_id, normalized_folder, server_set.len());
@@ -1228,13 +2404,13 @@ async fn sync_delete_removed_mails(
         .min();
     if min_server_time.is_none() {
         log::warn!("同步删除跳过: 无法解析服务器邮件时间");
-        return Ok(0);
+        return Ok(Vec::new());
     }
     let min_server_time = min_server_time.unwrap();
 
     // 查询本地该邮箱该文件夹的所有邮件
     let local_records = sqlx::query_as::<_, (i64, Option<String>, Option<String>, Option<String>, Option<String>)>(
-        "SELECT id, subject, sender, received_time, folder FROM mail_records WHERE email_id = ?",
+        "SELECT id, subject, sender, received_time, folder FROM mail_records WHERE email_id = ? AND deleted_at IS NULL",
     )
     .bind(email_id)
     .fetch_all(pool)
@@ -1242,13 +2418,13 @@ async fn sync_delete_removed_mails(
 
     log::info!("本地邮件数: {}", local_records.len());
 
-    let mut deleted = 0usize;
+    let mut removed = Vec::new();
 
     for (mail_id, subject, sender, received_time, local_folder) in local_records {
         // 检查是否属于当前文件夹
         let local_normalized = local_folder
             .as_ref()
-            .map(|f| normalize_folder_for_sync(f))
+            .map(|f| folders::canonical_folder_key(f))
             .unwrap_or_else(|| "inbox".to_string());
 
         if local_normalized != normalized_folder {
@@ -1274,27 +2450,19 @@ async fn sync_delete_removed_mails(
             received_time: received_time.as_ref().map(|t| normalize_time(t)),
         };
 
-        // 如果服务器上不存在该邮件，则删除
+        // 如果服务器上不存在该邮件，则移入回收站
         if !server_set.contains(&local_identifier) {
             log::info!("同步删除邮件: mail_id={}, subject={:?}", mail_id, subject);
-
-            // 先删除附件
-            sqlx::query("DELETE FROM attachments WHERE mail_id = ?")
-                .bind(mail_id)
-                .execute(pool)
-                .await?;
-
-            // 再删除邮件记录
-            sqlx::query("DELETE FROM mail_records WHERE id = ?")
-                .bind(mail_id)
-                .execute(pool)
-                .await?;
-
-            deleted += 1;
+            removed.push(mail_id);
         }
     }
+    let deleted = trash::trash_mails(pool, &removed, trash::REASON_SYNC).await?;
 
-    log::info!("同步删除完成: email_id={}, deleted={}", email_id, deleted);
+    log::info!(
+        "同步删除完成: email_id={}, deleted={}",
+        email_id,
+        deleted.len()
+    );
     Ok(deleted)
 }
 
@@ -1326,12 +2494,228 @@ fn normalize_sender(sender: &str) -> String {
     sender.trim().to_lowercase()
 }
 
-/// 标准化文件夹名称用于同步比对
-fn normalize_folder_for_sync(folder: &str) -> String {
-    let normalized = folder.trim().to_lowercase();
-    if normalized.contains("junk") || normalized.contains("spam") || normalized.contains("垃圾") {
-        "junk".to_string()
-    } else {
-        "inbox".to_string()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_mime_param() {
+        let params = vec![
+            (
+                Cow::Borrowed("NAME"),
+                Cow::Borrowed("=?UTF-8?B?5oql5ZGKLnBkZg==?="),
+            ),
+            (
+                Cow::Borrowed("filename*"),
+                Cow::Borrowed("utf-8''%E6%8A%A5%E5%91%8A.pdf"),
+            ),
+        ];
+        assert_eq!(
+            find_mime_param(&params, "name").as_deref(),
+            Some("报告.pdf")
+        );
+        assert_eq!(
+            find_mime_param(&params, "filename").as_deref(),
+            Some("报告.pdf")
+        );
+        assert_eq!(find_mime_param(&params, "size"), None);
+        assert_eq!(imap_section_ref(&[]), "TEXT");
+        assert_eq!(imap_section_ref(&[1, 2]), "1.2");
+    }
+
+    #[test]
+    fn test_imap_sequence_set() {
+        assert_eq!(imap_sequence_set(&[7]), "7");
+        assert_eq!(imap_sequence_set(&[1, 2, 3, 7, 9, 10]), "1:3,7,9:10");
+    }
+
+    fn fetch_record(subject: &str) -> MailFetchRecord {
+        MailFetchRecord {
+            subject: Some(subject.to_string()),
+            sender: Some("sender@example.com".to_string()),
+            received_time: Some("2026-10-01T08:00:00Z".to_string()),
+            content: String::new(),
+            folder: "inbox".to_string(),
+            attachments: Vec::new(),
+            graph_id: None,
+            imap_uid: None,
+            message_id: None,
+            state: MailState::default(),
+        }
+    }
+
+    fn server_identifier(record: &MailFetchRecord) -> MailIdentifier {
+        MailIdentifier {
+            subject: record.subject.clone(),
+            sender: record.sender.clone(),
+            // 服务器返回的时间格式与本地不同，比对前统一标准化
+            received_time: Some("2026-10-01T08:00:00.000Z".to_string()),
+        }
+    }
+
+    async fn server_refs(
+        pool: &Pool<Sqlite>,
+        mail_id: i64,
+    ) -> (String, Option<String>, Option<i64>, bool) {
+        sqlx::query_as("SELECT folder, graph_id, imap_uid, is_read FROM mail_records WHERE id = ?")
+            .bind(mail_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_existing_mail() {
+        let pool = crate::db::test_pool().await;
+        let email_id = add_email(&pool, 1, "a@x.com", "", "cid", "", None)
+            .await
+            .unwrap();
+        let mut record = fetch_record("hello");
+        record.graph_id = Some("g1".to_string());
+        record.imap_uid = Some(5);
+        let mail_id = insert_mail_record(&pool, email_id, &record).await.unwrap();
+
+        // 不存在的邮件
+        assert!(
+            !reconcile_existing_mail(&pool, email_id, &fetch_record("other"))
+                .await
+                .unwrap()
+        );
+
+        // 同一文件夹：缺少的服务器标识保留原值，状态以服务器为准
+        let mut same_folder = fetch_record("hello");
+        same_folder.state.is_read = true;
+        assert!(reconcile_existing_mail(&pool, email_id, &same_folder)
+            .await
+            .unwrap());
+        assert_eq!(
+            server_refs(&pool, mail_id).await,
+            ("inbox".to_string(), Some("g1".to_string()), Some(5), true)
+        );
+
+        // 换了文件夹：原文件夹下的标识失效
+        let mut moved = fetch_record("hello");
+        moved.folder = "archive".to_string();
+        assert!(reconcile_existing_mail(&pool, email_id, &moved)
+            .await
+            .unwrap());
+        assert_eq!(
+            server_refs(&pool, mail_id).await,
+            ("archive".to_string(), None, None, false)
+        );
+
+        moved.graph_id = Some("g2".to_string());
+        assert!(reconcile_existing_mail(&pool, email_id, &moved)
+            .await
+            .unwrap());
+        assert_eq!(server_refs(&pool, mail_id).await.1.as_deref(), Some("g2"));
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM mail_records")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_add_or_update_email_claims_legacy_rows_and_resets_state() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO users (id, username, password_hash, salt, rounds) VALUES (2, 'other', '', '', 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO emails (email, password, client_id, refresh_token, cached_token, access_token, token_expires_at, health_status, last_error, last_error_at) VALUES ('legacy@x.com', 'old', 'cid', 'old', 'cached', 'access', '2026-10-01T08:00:00Z', 'invalid_grant', 'AADSTS70000', '2026-10-01T08:00:00Z')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let id = add_or_update_email(&pool, 1, "legacy@x.com", "new", "cid2", "new", None)
+            .await
+            .unwrap();
+        let row: (Option<i64>, String, String, bool) = sqlx::query_as(
+            "SELECT user_id, client_id, health_status, cached_token IS NULL AND access_token IS NULL AND last_error IS NULL FROM emails WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            row,
+            (Some(1), "cid2".to_string(), "unknown".to_string(), true)
+        );
+
+        let err = add_or_update_email(&pool, 2, "legacy@x.com", "p", "cid", "r", None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "邮箱已被其他用户添加");
+    }
+
+    #[tokio::test]
+    async fn test_update_email_credentials_resets_health() {
+        let pool = crate::db::test_pool().await;
+        let email_id = add_email(&pool, 1, "a@x.com", "", "cid", "", None)
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE emails SET health_status = 'invalid_grant', last_error = 'AADSTS70000', last_error_at = '2026-10-01T08:00:00Z', health_checked_at = '2026-10-01T08:00:00Z' WHERE id = ?",
+        )
+        .bind(email_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(
+            update_email_credentials(&pool, 1, email_id, None, None, Some("new-token"))
+                .await
+                .unwrap()
+        );
+        let row: (String, bool) = sqlx::query_as(
+            "SELECT health_status, last_error IS NULL AND last_error_at IS NULL AND health_checked_at IS NULL FROM emails WHERE id = ?",
+        )
+        .bind(email_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(row, ("unknown".to_string(), true));
+    }
+
+    #[tokio::test]
+    async fn test_sync_restore_reappeared_mails() {
+        let pool = crate::db::test_pool().await;
+        let email_id = add_email(&pool, 1, "a@x.com", "", "cid", "", None)
+            .await
+            .unwrap();
+        let synced = fetch_record("synced");
+        let deleted = fetch_record("deleted by user");
+        let synced_id = insert_mail_record(&pool, email_id, &synced).await.unwrap();
+        let deleted_id = insert_mail_record(&pool, email_id, &deleted).await.unwrap();
+        trash::trash_mails(&pool, &[synced_id], trash::REASON_SYNC)
+            .await
+            .unwrap();
+        trash::trash_mails(&pool, &[deleted_id], trash::REASON_USER)
+            .await
+            .unwrap();
+
+        // 已有记录的更新不会改变回收站状态
+        assert!(reconcile_existing_mail(&pool, email_id, &synced)
+            .await
+            .unwrap());
+        assert_eq!(trash::list_mails(&pool, 1, None).await.unwrap().len(), 2);
+
+        let server_mails = vec![server_identifier(&synced), server_identifier(&deleted)];
+        let restored = sync_restore_reappeared_mails(&pool, email_id, "Archive", &server_mails)
+            .await
+            .unwrap();
+        assert!(restored.is_empty());
+        let restored = sync_restore_reappeared_mails(&pool, email_id, "INBOX", &server_mails)
+            .await
+            .unwrap();
+        assert_eq!(restored, vec![synced_id]);
+        let trashed = trash::list_mails(&pool, 1, None).await.unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].id, deleted_id);
     }
 }
@@ -0,0 +1,299 @@
+//! 邮件文件夹模块
+//! 保存从服务器发现的文件夹（Graph 文件夹 ID / IMAP 名称），并提供统一的文件夹标识，
+//! 使任意文件夹都能被正确收取与展示
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+
+/// 常用文件夹：(folder_key, Graph well-known 名称, IMAP 默认名称)
+pub const WELL_KNOWN_FOLDERS: &[(&str, &str, &str)] = &[
+    ("inbox", "inbox", "INBOX"),
+    ("junk", "junkemail", "Junk"),
+    ("sent", "sentitems", "Sent"),
+    ("drafts", "drafts", "Drafts"),
+    ("deleted", "deleteditems", "Deleted"),
+    ("archive", "archive", "Archive"),
+];
+
+/// 已保存的文件夹
+#[derive(Debug, Clone, sqlx::FromRow, Serialize, Deserialize)]
+pub struct MailFolder {
+    pub id: i64,
+    pub email_id: i64,
+    /// 统一标识，邮件记录的 folder 字段使用该值
+    pub folder_key: String,
+    pub display_name: String,
+    /// 完整路径（以 / 分隔）
+    pub path: String,
+    pub parent_key: Option<String>,
+    pub graph_id: Option<String>,
+    pub imap_name: Option<String>,
+    /// 特殊用途（inbox/junk/sent/drafts/deleted/archive）
+    pub special_use: Option<String>,
+    pub unread_count: Option<i64>,
+    pub total_count: Option<i64>,
+}
+
+/// 从服务器发现的文件夹
+#[derive(Debug, Clone)]
+pub struct DiscoveredFolder {
+    pub folder_key: String,
+    pub display_name: String,
+    pub path: String,
+    pub parent_key: Option<String>,
+    pub graph_id: Option<String>,
+    pub imap_name: Option<String>,
+    pub special_use: Option<String>,
+    pub unread_count: Option<i64>,
+    pub total_count: Option<i64>,
+}
+
+/// 收件时使用的文件夹标识
+#[derive(Debug, Clone)]
+pub struct ResolvedFolder {
+    /// 统一标识
+    pub key: String,
+    /// Graph API 文件夹引用（ID 或 well-known 名称）
+    pub graph_ref: String,
+    /// IMAP 文件夹名称
+    pub imap_name: String,
+}
+
+/// 常用文件夹的别名（小写）：(folder_key, 别名)
+const FOLDER_ALIASES: &[(&str, &[&str])] = &[
+    ("inbox", &["inbox", "收件箱"]),
+    (
+        "junk",
+        &[
+            "junk",
+            "spam",
+            "junkemail",
+            "junk email",
+            "junk e-mail",
+            "垃圾邮件",
+        ],
+    ),
+    (
+        "sent",
+        &[
+            "sent",
+            "sentitems",
+            "sent items",
+            "sent mail",
+            "已发送",
+            "已发送邮件",
+        ],
+    ),
+    ("drafts", &["drafts", "draft", "草稿", "草稿箱"]),
+    (
+        "deleted",
+        &[
+            "deleted",
+            "deleteditems",
+            "deleted items",
+            "trash",
+            "已删除",
+            "已删除邮件",
+        ],
+    ),
+    ("archive", &["archive", "归档", "存档"]),
+];
+
+/// 计算文件夹的统一标识：常用文件夹别名归一，其余为小写路径
+pub fn canonical_folder_key(folder: &str) -> String {
+    let lower = folder.trim().trim_matches('/').to_lowercase();
+    FOLDER_ALIASES
+        .iter()
+        .find(|(_, aliases)| aliases.contains(&lower.as_str()))
+        .map(|(key, _)| key.to_string())
+        .unwrap_or(lower)
+}
+
+/// 将邮件记录中的文件夹名称统一为 folder_key（启动时执行，已统一的记录不变），返回更新的记录数
+pub async fn backfill_folder_keys(pool: &Pool<Sqlite>) -> Result<u64> {
+    let folders = sqlx::query_scalar::<_, String>(
+        "SELECT DISTINCT folder FROM mail_records WHERE folder IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut updated = 0u64;
+    for folder in folders {
+        let key = canonical_folder_key(&folder);
+        if key == folder {
+            continue;
+        }
+        updated += sqlx::query("UPDATE mail_records SET folder = ? WHERE folder = ?")
+            .bind(&key)
+            .bind(&folder)
+            .execute(pool)
+            .await?
+            .rows_affected();
+    }
+
+    if updated > 0 {
+        log::info!("邮件文件夹标识统一完成: updated={}", updated);
+    }
+    Ok(updated)
+}
+
+/// 获取账号已保存的文件夹列表
+pub async fn list_folders(pool: &Pool<Sqlite>, email_id: i64) -> Result<Vec<MailFolder>> {
+    let folders = sqlx::query_as::<_, MailFolder>(
+        r#"SELECT id, email_id, folder_key, display_name, path, parent_key, graph_id, imap_name, special_use, unread_count, total_count
+FROM mail_folders
+WHERE email_id = ?
+ORDER BY special_use IS NULL, path"#,
+    )
+    .bind(email_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(folders)
+}
+
+/// 保存发现的文件夹，并移除服务器上已不存在的文件夹
+pub async fn save_folders(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    folders: &[DiscoveredFolder],
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    for folder in folders {
+        sqlx::query(
+            r#"INSERT INTO mail_folders (email_id, folder_key, display_name, path, parent_key, graph_id, imap_name, special_use, unread_count, total_count)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT(email_id, folder_key) DO UPDATE SET
+    display_name = excluded.display_name,
+    path = excluded.path,
+    parent_key = excluded.parent_key,
+    graph_id = COALESCE(excluded.graph_id, mail_folders.graph_id),
+    imap_name = COALESCE(excluded.imap_name, mail_folders.imap_name),
+    special_use = COALESCE(excluded.special_use, mail_folders.special_use),
+    unread_count = COALESCE(excluded.unread_count, mail_folders.unread_count),
+    total_count = COALESCE(excluded.total_count, mail_folders.total_count),
+    updated_at = CURRENT_TIMESTAMP"#,
+        )
+        .bind(email_id)
+        .bind(&folder.folder_key)
+        .bind(&folder.display_name)
+        .bind(&folder.path)
+        .bind(&folder.parent_key)
+        .bind(&folder.graph_id)
+        .bind(&folder.imap_name)
+        .bind(&folder.special_use)
+        .bind(folder.unread_count)
+        .bind(folder.total_count)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    if !folders.is_empty() {
+        let mut qb = QueryBuilder::<Sqlite>::new("DELETE FROM mail_folders WHERE email_id = ");
+        qb.push_bind(email_id);
+        qb.push(" AND folder_key NOT IN (");
+        let mut separated = qb.separated(", ");
+        for folder in folders {
+            separated.push_bind(&folder.folder_key);
+        }
+        qb.push(")");
+        qb.build().execute(&mut *tx).await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 解析收件使用的文件夹标识：优先使用已发现的文件夹，其次为常用文件夹的默认名称，
+/// 未知文件夹按原样作为 Graph 引用与 IMAP 名称
+pub async fn resolve_folder(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    folder: &str,
+) -> Result<ResolvedFolder> {
+    let key = canonical_folder_key(folder);
+    let stored = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        "SELECT graph_id, imap_name FROM mail_folders WHERE email_id = ? AND folder_key = ?",
+    )
+    .bind(email_id)
+    .bind(&key)
+    .fetch_optional(pool)
+    .await?;
+    let (graph_id, imap_name) = stored.unwrap_or((None, None));
+
+    let well_known = WELL_KNOWN_FOLDERS.iter().find(|(k, _, _)| *k == key);
+    let graph_ref = graph_id
+        .or_else(|| well_known.map(|(_, graph, _)| graph.to_string()))
+        .unwrap_or_else(|| folder.trim().to_string());
+    let imap_name = imap_name
+        .or_else(|| well_known.map(|(_, _, imap)| imap.to_string()))
+        .unwrap_or_else(|| folder.trim().to_string());
+
+    Ok(ResolvedFolder {
+        key,
+        graph_ref,
+        imap_name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_folder_key() {
+        assert_eq!(canonical_folder_key("INBOX"), "inbox");
+        assert_eq!(canonical_folder_key("Junk Email"), "junk");
+        assert_eq!(canonical_folder_key("Deleted Items"), "deleted");
+        assert_eq!(canonical_folder_key(" Inbox/Clients "), "inbox/clients");
+        assert_eq!(canonical_folder_key("Archive"), "archive");
+        // 统一标识再次计算保持不变
+        assert_eq!(canonical_folder_key("projects/x"), "projects/x");
+        for (key, aliases) in FOLDER_ALIASES {
+            assert_eq!(canonical_folder_key(key), *key);
+            for alias in aliases.iter() {
+                assert_eq!(canonical_folder_key(&alias.to_uppercase()), *key);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backfill_folder_keys() {
+        let pool = crate::db::test_pool().await;
+        let email_id: i64 = sqlx::query_scalar(
+            "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES ('a@x.com', '', 'cid', '', 1) RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        for folder in [
+            "Sent Mail",
+            "已发送",
+            "sent",
+            "INBOX",
+            "Projects/X",
+            "inbox",
+        ] {
+            sqlx::query("INSERT INTO mail_records (email_id, folder) VALUES (?, ?)")
+                .bind(email_id)
+                .bind(folder)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(backfill_folder_keys(&pool).await.unwrap(), 4);
+        let folders: Vec<String> =
+            sqlx::query_scalar("SELECT folder FROM mail_records ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            folders,
+            ["sent", "sent", "sent", "inbox", "projects/x", "inbox"]
+        );
+        assert_eq!(backfill_folder_keys(&pool).await.unwrap(), 0);
+    }
+}
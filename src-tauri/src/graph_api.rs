@@ -1,13 +1,58 @@
 //! Microsoft Graph API 模块
-//! 通过 Graph API 获取邮件（相比 IMAP 更稳定）
+//! 通过 Graph API 获取邮件与文件夹、执行邮件操作（相比 IMAP 更稳定）
 
 #![allow(dead_code)]
 
 use anyhow::{anyhow, Result};
-use reqwest::Client;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Response, StatusCode};
 use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
 
+use crate::attachments::{KIND_FILE, KIND_ITEM, KIND_REFERENCE};
+use crate::folders::{canonical_folder_key, DiscoveredFolder, WELL_KNOWN_FOLDERS};
+use crate::mail_state::{self, MailState};
 use crate::proxy::{create_http_client, ProxyConfig};
+use crate::settings;
+
+/// Graph API 请求错误
+#[derive(Debug, thiserror::Error)]
+#[error("Graph API 请求失败: {status} - {body}")]
+pub struct GraphApiError {
+    pub status: StatusCode,
+    /// 服务端要求的重试等待秒数（429/503 响应的 Retry-After）
+    pub retry_after: Option<u64>,
+    pub body: String,
+}
+
+impl GraphApiError {
+    /// 从失败的响应构建错误
+    async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = response.text().await.unwrap_or_default();
+        Self {
+            status,
+            retry_after,
+            body,
+        }
+    }
+}
+
+/// 解析 Retry-After 头（秒数或 HTTP 日期）
+fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (date.with_timezone(&Utc) - Utc::now()).num_seconds();
+    Some(secs.max(0) as u64)
+}
 
 /// Graph API Token 响应
 #[derive(Debug, Deserialize)]
@@ -38,6 +83,56 @@ struct GraphMail {
     created_date_time: Option<String>,
     received_date_time: Option<String>,
     has_attachments: Option<bool>,
+    internet_message_id: Option<String>,
+    is_read: Option<bool>,
+    flag: Option<MailFlag>,
+    importance: Option<String>,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    single_value_extended_properties: Vec<ExtendedProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MailFlag {
+    flag_status: Option<String>,
+}
+
+/// MAPI 扩展属性
+#[derive(Debug, Deserialize)]
+struct ExtendedProperty {
+    id: String,
+    value: Option<String>,
+}
+
+/// PidTagLastVerbExecuted：最后一次执行的操作（102 答复、103 全部答复、104 转发）
+const LAST_VERB_EXECUTED: &str = "Integer 0x1081";
+
+impl GraphMail {
+    /// 提取邮件状态
+    fn state(&self) -> MailState {
+        let is_answered = self
+            .single_value_extended_properties
+            .iter()
+            .filter(|p| p.id.eq_ignore_ascii_case(LAST_VERB_EXECUTED))
+            .filter_map(|p| p.value.as_deref())
+            .any(|v| matches!(v.trim(), "102" | "103"));
+        MailState {
+            is_read: self.is_read.unwrap_or(false),
+            is_flagged: self
+                .flag
+                .as_ref()
+                .and_then(|f| f.flag_status.as_deref())
+                .is_some_and(|status| status.eq_ignore_ascii_case("flagged")),
+            is_answered,
+            importance: self
+                .importance
+                .as_deref()
+                .and_then(mail_state::normalize_importance),
+            categories: self.categories.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -70,12 +165,25 @@ struct AttachmentListResponse {
 #[serde(rename_all = "camelCase")]
 struct GraphAttachment {
     id: String,
+    #[serde(rename = "@odata.type")]
+    odata_type: Option<String>,
     name: Option<String>,
     content_type: Option<String>,
     size: Option<i64>,
-    content_bytes: Option<String>,
 }
 
+/// 云附件（beta 接口才返回链接）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphReferenceAttachment {
+    source_url: Option<String>,
+}
+
+/// 附件类型：文件、嵌入项目（邮件/日程等）、云附件链接
+const FILE_ATTACHMENT_TYPE: &str = "#microsoft.graph.fileAttachment";
+const ITEM_ATTACHMENT_TYPE: &str = "#microsoft.graph.itemAttachment";
+const REFERENCE_ATTACHMENT_TYPE: &str = "#microsoft.graph.referenceAttachment";
+
 /// 抓取到的邮件记录（与 IMAP 模块共用）
 #[derive(Debug)]
 pub struct GraphMailRecord {
@@ -85,14 +193,26 @@ pub struct GraphMailRecord {
     pub content: String,
     pub folder: String,
     pub attachments: Vec<GraphAttachmentData>,
+    /// Graph API 邮件 ID
+    pub graph_id: String,
+    /// Message-ID 头
+    pub message_id: Option<String>,
+    /// 已读、旗标等状态
+    pub state: MailState,
 }
 
-/// 附件数据
+/// 附件元数据（内容按需下载）
 #[derive(Debug)]
 pub struct GraphAttachmentData {
     pub filename: String,
     pub content_type: String,
-    pub content: Vec<u8>,
+    pub size: i64,
+    /// Graph API 附件 ID
+    pub attachment_id: String,
+    /// 附件类型：file / item / reference
+    pub kind: &'static str,
+    /// 云附件链接
+    pub source_url: Option<String>,
 }
 
 /// Graph API Token 结果
@@ -107,7 +227,7 @@ pub async fn refresh_graph_token(
     refresh_token: &str,
     proxy_config: &ProxyConfig,
 ) -> Result<GraphTokenResult> {
-    let client = create_http_client(proxy_config, 30)?;
+    let client = create_http_client(proxy_config, settings::request_timeout_secs())?;
 
     let response: TokenResponse = client
         .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/token")
@@ -141,32 +261,21 @@ pub async fn refresh_graph_token(
     ))
 }
 
-/// 转换文件夹名称为 Graph API 格式
-fn normalize_folder_name(folder: &str) -> &str {
-    match folder.to_lowercase().as_str() {
-        "inbox" => "inbox",
-        "junk" | "spam" | "junkemail" => "junkemail",
-        "sent" | "sentitems" => "sentitems",
-        "drafts" => "drafts",
-        "deleted" | "deleteditems" | "trash" => "deleteditems",
-        "archive" => "archive",
-        _ => "inbox",
-    }
-}
-
 /// 通过 Graph API 获取邮件
+///
+/// folder_ref 为文件夹 ID 或 well-known 名称，folder_key 写入邮件记录的 folder 字段
 pub async fn fetch_via_graph(
     access_token: &str,
-    folder: &str,
+    folder_ref: &str,
+    folder_key: &str,
     top: usize,
     proxy_config: &ProxyConfig,
 ) -> Result<Vec<GraphMailRecord>> {
     let client = create_http_client(proxy_config, 60)?;
-    let folder_name = normalize_folder_name(folder);
 
     let url = format!(
-        "https://graph.microsoft.com/v1.0/me/mailFolders/{}/messages?$top={}&$orderby=receivedDateTime desc",
-        folder_name, top
+        "https://graph.microsoft.com/v1.0/me/mailFolders/{}/messages?$top={}&$orderby=receivedDateTime desc&$expand=singleValueExtendedProperties($filter=id eq '{}')",
+        folder_ref, top, LAST_VERB_EXECUTED
     );
 
     let response = client
@@ -177,15 +286,17 @@ pub async fn fetch_via_graph(
         .await?;
 
     if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(anyhow!("Graph API 请求失败: {} - {}", status, error_text));
+        return Err(GraphApiError::from_response(response).await.into());
     }
 
     let mail_list: MailListResponse = response.json().await?;
 
+    // beta 接口不可用时，本次收件的其余云附件不再请求链接
+    let mut beta_available = true;
     let mut records = Vec::new();
     for mail in mail_list.value {
+        let state = mail.state();
+
         // 提取发件人
         let sender = mail
             .from
@@ -206,10 +317,12 @@ pub async fn fetch_via_graph(
         // 解析接收时间
         let received_time = mail.received_date_time.or(mail.created_date_time);
 
-        // 获取附件（如果有）
+        // 获取附件元数据（如果有）
         let mut attachments = Vec::new();
         if mail.has_attachments.unwrap_or(false) {
-            if let Ok(att_list) = fetch_attachments(&client, access_token, &mail.id).await {
+            if let Ok(att_list) =
+                fetch_attachments(&client, access_token, &mail.id, &mut beta_available).await
+            {
                 attachments = att_list;
             }
         }
@@ -219,22 +332,26 @@ pub async fn fetch_via_graph(
             sender,
             received_time,
             content,
-            folder: folder.to_string(),
+            folder: folder_key.to_string(),
             attachments,
+            graph_id: mail.id,
+            message_id: mail.internet_message_id,
+            state,
         });
     }
 
     Ok(records)
 }
 
-/// 获取邮件附件
+/// 获取邮件附件元数据（不含内容），云附件链接通过 beta 接口获取，失败时只保存元数据
 async fn fetch_attachments(
     client: &Client,
     access_token: &str,
     message_id: &str,
+    beta_available: &mut bool,
 ) -> Result<Vec<GraphAttachmentData>> {
     let url = format!(
-        "https://graph.microsoft.com/v1.0/me/messages/{}/attachments",
+        "https://graph.microsoft.com/v1.0/me/messages/{}/attachments?$select=id,name,contentType,size",
         message_id
     );
 
@@ -249,20 +366,20 @@ async fn fetch_attachments(
     }
 
     let att_list: AttachmentListResponse = response.json().await?;
+    let mut attachments = parse_attachments(att_list);
 
-    let mut attachments = Vec::new();
-    for att in att_list.value {
-        if let Some(content_b64) = att.content_bytes {
-            if let Ok(content) =
-                base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &content_b64)
-            {
-                attachments.push(GraphAttachmentData {
-                    filename: att.name.unwrap_or_else(|| "attachment".to_string()),
-                    content_type: att
-                        .content_type
-                        .unwrap_or_else(|| "application/octet-stream".to_string()),
-                    content,
-                });
+    for attachment in attachments.iter_mut().filter(|a| a.kind == KIND_REFERENCE) {
+        if !*beta_available {
+            break;
+        }
+        match fetch_reference_url(client, access_token, message_id, &attachment.attachment_id).await
+        {
+            Ok(url) => attachment.source_url = url,
+            Err(e) => {
+                if beta_unsupported(&e) {
+                    *beta_available = false;
+                }
+                log::warn!("获取云附件链接失败: {}", e);
             }
         }
     }
@@ -270,41 +387,447 @@ async fn fetch_attachments(
     Ok(attachments)
 }
 
-/// 获取文件夹列表
-pub async fn get_mail_folders(
+/// 解析附件列表（云附件链接另行获取）
+fn parse_attachments(att_list: AttachmentListResponse) -> Vec<GraphAttachmentData> {
+    att_list
+        .value
+        .into_iter()
+        .map(|att| {
+            let kind = match att.odata_type.as_deref() {
+                Some(FILE_ATTACHMENT_TYPE) => KIND_FILE,
+                Some(ITEM_ATTACHMENT_TYPE) => KIND_ITEM,
+                Some(REFERENCE_ATTACHMENT_TYPE) => KIND_REFERENCE,
+                other => {
+                    // 未知类型按文件处理，下载失败时保留元数据
+                    log::warn!("未知的附件类型: {:?}", other);
+                    KIND_FILE
+                }
+            };
+
+            let mut filename = att.name.unwrap_or_else(|| "attachment".to_string());
+            let mut content_type = att
+                .content_type
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            // 嵌入的邮件/日程以 MIME 格式下载，保存为 .eml
+            if kind == KIND_ITEM {
+                if !filename.to_lowercase().ends_with(".eml") {
+                    filename.push_str(".eml");
+                }
+                content_type = "message/rfc822".to_string();
+            }
+
+            GraphAttachmentData {
+                filename,
+                content_type,
+                size: att.size.unwrap_or(0),
+                attachment_id: att.id,
+                kind,
+                source_url: None,
+            }
+        })
+        .collect()
+}
+
+/// 获取云附件链接（v1.0 接口不返回 sourceUrl，需使用 beta 接口）
+async fn fetch_reference_url(
+    client: &Client,
+    access_token: &str,
+    message_id: &str,
+    attachment_id: &str,
+) -> Result<Option<String>> {
+    let url = format!(
+        "https://graph.microsoft.com/beta/me/messages/{}/attachments/{}",
+        message_id, attachment_id
+    );
+
+    let response = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(GraphApiError::from_response(response).await.into());
+    }
+
+    let attachment: GraphReferenceAttachment = response.json().await?;
+    Ok(attachment.source_url)
+}
+
+/// beta 接口是否对该账号不可用（客户端错误，限流除外），网络错误与服务端错误只影响当前附件
+fn beta_unsupported(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<GraphApiError>()
+        .is_some_and(|e| e.status.is_client_error() && e.status != StatusCode::TOO_MANY_REQUESTS)
+}
+
+/// 下载附件原始内容（文件附件为原始字节，嵌入项目为 MIME），不受 contentBytes 大小限制
+pub async fn download_attachment(
+    access_token: &str,
+    message_id: &str,
+    attachment_id: &str,
+    proxy_config: &ProxyConfig,
+) -> Result<Vec<u8>> {
+    let client = create_http_client(proxy_config, 300)?;
+    let url = format!(
+        "https://graph.microsoft.com/v1.0/me/messages/{}/attachments/{}/$value",
+        message_id, attachment_id
+    );
+
+    let response = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(GraphApiError::from_response(response).await.into());
+    }
+
+    let content = response.bytes().await?;
+    Ok(content.to_vec())
+}
+
+/// 更新邮件属性（如 isRead、flag）
+pub async fn update_message(
+    access_token: &str,
+    message_id: &str,
+    patch: &serde_json::Value,
+    proxy_config: &ProxyConfig,
+) -> Result<()> {
+    let client = create_http_client(proxy_config, settings::request_timeout_secs())?;
+    let url = format!(
+        "https://graph.microsoft.com/v1.0/me/messages/{}",
+        message_id
+    );
+
+    let response = client
+        .patch(&url)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(patch)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(GraphApiError::from_response(response).await.into());
+    }
+
+    Ok(())
+}
+
+/// 移动邮件到指定文件夹，返回移动后的新邮件 ID
+pub async fn move_message(
     access_token: &str,
+    message_id: &str,
+    destination: &str,
     proxy_config: &ProxyConfig,
-) -> Result<Vec<String>> {
-    let client = create_http_client(proxy_config, 30)?;
+) -> Result<String> {
+    let client = create_http_client(proxy_config, settings::request_timeout_secs())?;
+    let url = format!(
+        "https://graph.microsoft.com/v1.0/me/messages/{}/move",
+        message_id
+    );
 
-    let url = "https://graph.microsoft.com/v1.0/me/mailFolders";
+    let response = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&serde_json::json!({ "destinationId": destination }))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(GraphApiError::from_response(response).await.into());
+    }
+
+    let moved: GraphObjectId = response.json().await?;
+    Ok(moved.id)
+}
+
+/// 转发邮件（需要 Mail.Send 权限）
+pub async fn forward_message(
+    access_token: &str,
+    message_id: &str,
+    to: &[String],
+    comment: &str,
+    proxy_config: &ProxyConfig,
+) -> Result<()> {
+    let client = create_http_client(proxy_config, settings::request_timeout_secs())?;
+    let url = format!(
+        "https://graph.microsoft.com/v1.0/me/messages/{}/forward",
+        message_id
+    );
+    let recipients: Vec<serde_json::Value> = to
+        .iter()
+        .map(|address| serde_json::json!({ "emailAddress": { "address": address } }))
+        .collect();
 
     let response = client
-        .get(url)
+        .post(&url)
         .header("Authorization", format!("Bearer {}", access_token))
+        .json(&serde_json::json!({ "comment": comment, "toRecipients": recipients }))
         .send()
         .await?;
 
     if !response.status().is_success() {
-        return Ok(vec!["INBOX".to_string()]);
+        return Err(GraphApiError::from_response(response).await.into());
     }
 
-    #[derive(Deserialize)]
-    struct FolderResponse {
-        value: Vec<Folder>,
+    Ok(())
+}
+
+/// 永久删除邮件（不进入已删除邮件）
+pub async fn permanent_delete_message(
+    access_token: &str,
+    message_id: &str,
+    proxy_config: &ProxyConfig,
+) -> Result<()> {
+    let client = create_http_client(proxy_config, settings::request_timeout_secs())?;
+    let url = format!(
+        "https://graph.microsoft.com/v1.0/me/messages/{}/permanentDelete",
+        message_id
+    );
+
+    let response = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(GraphApiError::from_response(response).await.into());
+    }
+
+    Ok(())
+}
+
+/// Graph API 文件夹列表响应
+#[derive(Debug, Deserialize)]
+struct FolderListResponse {
+    value: Vec<GraphFolder>,
+    #[serde(rename = "@odata.nextLink")]
+    next_link: Option<String>,
+}
+
+/// Graph API 文件夹对象
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphFolder {
+    id: String,
+    display_name: Option<String>,
+    child_folder_count: Option<i64>,
+    unread_item_count: Option<i64>,
+    total_item_count: Option<i64>,
+}
+
+/// Graph API 对象 ID（文件夹或邮件）
+#[derive(Debug, Deserialize)]
+struct GraphObjectId {
+    id: String,
+}
+
+/// 获取文件夹列表（递归包含子文件夹，附带未读数与总数）
+pub async fn list_mail_folders(
+    access_token: &str,
+    proxy_config: &ProxyConfig,
+) -> Result<Vec<DiscoveredFolder>> {
+    let client = create_http_client(proxy_config, settings::request_timeout_secs())?;
+
+    // 查询常用文件夹的 ID，用于标记特殊用途
+    let mut well_known_ids = HashMap::new();
+    for (key, graph_name, _) in WELL_KNOWN_FOLDERS {
+        let url = format!(
+            "https://graph.microsoft.com/v1.0/me/mailFolders/{}?$select=id",
+            graph_name
+        );
+        let response = client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .send()
+            .await?;
+        // 部分账号没有归档等文件夹，忽略
+        if !response.status().is_success() {
+            continue;
+        }
+        let folder: GraphObjectId = response.json().await?;
+        well_known_ids.insert(folder.id, *key);
     }
 
-    #[derive(Deserialize)]
-    #[serde(rename_all = "camelCase")]
-    struct Folder {
-        display_name: Option<String>,
+    let mut folders = Vec::new();
+    // 待查询的文件夹层级：(请求地址, 父文件夹路径, 父文件夹标识)
+    let mut pending: VecDeque<(String, Option<String>, Option<String>)> = VecDeque::new();
+    pending.push_back((
+        "https://graph.microsoft.com/v1.0/me/mailFolders?$top=100".to_string(),
+        None,
+        None,
+    ));
+
+    while let Some((url, parent_path, parent_key)) = pending.pop_front() {
+        let response = client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(GraphApiError::from_response(response).await.into());
+        }
+
+        let list: FolderListResponse = response.json().await?;
+        if let Some(next_link) = list.next_link {
+            pending.push_back((next_link, parent_path.clone(), parent_key.clone()));
+        }
+
+        for folder in list.value {
+            let display_name = folder.display_name.unwrap_or_else(|| folder.id.clone());
+            let path = match &parent_path {
+                Some(parent) => format!("{}/{}", parent, display_name),
+                None => display_name.clone(),
+            };
+            let special_use = well_known_ids.get(&folder.id).map(|k| k.to_string());
+            let folder_key = special_use
+                .clone()
+                .unwrap_or_else(|| canonical_folder_key(&path));
+
+            if folder.child_folder_count.unwrap_or(0) > 0 {
+                pending.push_back((
+                    format!(
+                        "https://graph.microsoft.com/v1.0/me/mailFolders/{}/childFolders?$top=100",
+                        folder.id
+                    ),
+                    Some(path.clone()),
+                    Some(folder_key.clone()),
+                ));
+            }
+
+            folders.push(DiscoveredFolder {
+                folder_key,
+                display_name,
+                path,
+                parent_key: parent_key.clone(),
+                graph_id: Some(folder.id),
+                imap_name: None,
+                special_use,
+                unread_count: folder.unread_item_count,
+                total_count: folder.total_item_count,
+            });
+        }
     }
 
-    let folders: FolderResponse = response.json().await?;
+    Ok(folders)
+}
 
-    Ok(folders
-        .value
-        .into_iter()
-        .filter_map(|f| f.display_name)
-        .collect())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(120));
+        assert_eq!(parse_retry_after(" 5 "), Some(5));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(0));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_parse_attachments() {
+        let list: AttachmentListResponse = serde_json::from_value(serde_json::json!({
+            "value": [
+                {
+                    "@odata.type": "#microsoft.graph.fileAttachment",
+                    "id": "f1",
+                    "name": "report.pdf",
+                    "contentType": "application/pdf",
+                    "size": 1024
+                },
+                {
+                    "@odata.type": "#microsoft.graph.itemAttachment",
+                    "id": "i1",
+                    "name": "Meeting notes",
+                    "contentType": null,
+                    "size": 2048
+                },
+                {
+                    "@odata.type": "#microsoft.graph.itemAttachment",
+                    "id": "i2",
+                    "name": "Forwarded.EML"
+                },
+                {
+                    "@odata.type": "#microsoft.graph.referenceAttachment",
+                    "id": "r1",
+                    "name": "Budget.xlsx",
+                    "contentType": null,
+                    "size": 120
+                },
+                { "@odata.type": "#microsoft.graph.futureAttachment", "id": "u1" }
+            ]
+        }))
+        .unwrap();
+
+        let attachments = parse_attachments(list);
+        let summary: Vec<(&str, &str, &str, i64)> = attachments
+            .iter()
+            .map(|a| {
+                (
+                    a.attachment_id.as_str(),
+                    a.kind,
+                    a.filename.as_str(),
+                    a.size,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("f1", KIND_FILE, "report.pdf", 1024),
+                ("i1", KIND_ITEM, "Meeting notes.eml", 2048),
+                ("i2", KIND_ITEM, "Forwarded.EML", 0),
+                ("r1", KIND_REFERENCE, "Budget.xlsx", 120),
+                ("u1", KIND_FILE, "attachment", 0),
+            ]
+        );
+        assert_eq!(attachments[0].content_type, "application/pdf");
+        assert_eq!(attachments[1].content_type, "message/rfc822");
+        assert_eq!(attachments[3].content_type, "application/octet-stream");
+        assert!(attachments.iter().all(|a| a.source_url.is_none()));
+    }
+
+    #[test]
+    fn test_parse_reference_attachment() {
+        let attachment: GraphReferenceAttachment = serde_json::from_value(serde_json::json!({
+            "@odata.type": "#microsoft.graph.referenceAttachment",
+            "id": "r1",
+            "name": "Budget.xlsx",
+            "sourceUrl": "https://contoso.sharepoint.com/Budget.xlsx",
+            "providerType": "oneDriveBusiness",
+            "permission": "edit"
+        }))
+        .unwrap();
+        assert_eq!(
+            attachment.source_url.as_deref(),
+            Some("https://contoso.sharepoint.com/Budget.xlsx")
+        );
+
+        // v1.0 形态的响应不含链接
+        let attachment: GraphReferenceAttachment =
+            serde_json::from_value(serde_json::json!({ "id": "r1", "name": "Budget.xlsx" }))
+                .unwrap();
+        assert!(attachment.source_url.is_none());
+    }
+
+    #[test]
+    fn test_beta_unsupported() {
+        let error = |status| -> anyhow::Error {
+            GraphApiError {
+                status,
+                retry_after: None,
+                body: String::new(),
+            }
+            .into()
+        };
+        assert!(beta_unsupported(&error(StatusCode::BAD_REQUEST)));
+        assert!(beta_unsupported(&error(StatusCode::FORBIDDEN)));
+        assert!(beta_unsupported(&error(StatusCode::NOT_FOUND)));
+        assert!(!beta_unsupported(&error(StatusCode::TOO_MANY_REQUESTS)));
+        assert!(!beta_unsupported(&error(StatusCode::SERVICE_UNAVAILABLE)));
+        assert!(!beta_unsupported(&anyhow!("connection reset")));
+    }
 }
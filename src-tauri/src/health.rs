@@ -0,0 +1,277 @@
+//! 账号健康状态模块
+//! 根据收件/刷新令牌的错误对账号状态进行分类，并持久化到 emails 表
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+
+use crate::proxy::ProxyConfig;
+
+/// 账号健康状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// 尚未检查
+    Unknown,
+    /// 正常
+    Ok,
+    /// 刷新令牌已过期（长期未使用或超过有效期）
+    TokenExpired,
+    /// 刷新令牌无效（已撤销、密码修改等）
+    InvalidGrant,
+    /// 账号被锁定或封禁
+    Locked,
+    /// 需要重新授权同意
+    NeedsConsent,
+    /// 代理连接失败
+    ProxyError,
+}
+
+impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Unknown => "unknown",
+            HealthStatus::Ok => "ok",
+            HealthStatus::TokenExpired => "token_expired",
+            HealthStatus::InvalidGrant => "invalid_grant",
+            HealthStatus::Locked => "locked",
+            HealthStatus::NeedsConsent => "needs_consent",
+            HealthStatus::ProxyError => "proxy_error",
+        }
+    }
+}
+
+impl From<Option<String>> for HealthStatus {
+    fn from(s: Option<String>) -> Self {
+        match s.as_deref() {
+            Some("ok") => HealthStatus::Ok,
+            Some("token_expired") => HealthStatus::TokenExpired,
+            Some("invalid_grant") => HealthStatus::InvalidGrant,
+            Some("locked") => HealthStatus::Locked,
+            Some("needs_consent") => HealthStatus::NeedsConsent,
+            Some("proxy_error") => HealthStatus::ProxyError,
+            _ => HealthStatus::Unknown,
+        }
+    }
+}
+
+/// OAuth 令牌接口返回的错误
+#[derive(Debug, thiserror::Error)]
+#[error("刷新令牌失败: {error} - {description}")]
+pub struct OAuthError {
+    /// 错误码，如 invalid_grant
+    pub error: String,
+    /// 错误描述，通常包含 AADSTS 错误码
+    pub description: String,
+}
+
+/// 根据 OAuth 错误码与描述分类
+fn classify_oauth_error(error: &OAuthError) -> Option<HealthStatus> {
+    let description = error.description.to_lowercase();
+
+    // AADSTS50053: 登录尝试过多被锁定; AADSTS50057: 账号被禁用
+    if [
+        "aadsts50053",
+        "aadsts50057",
+        "locked",
+        "suspended",
+        "blocked",
+    ]
+    .iter()
+    .any(|k| description.contains(k))
+    {
+        return Some(HealthStatus::Locked);
+    }
+
+    // AADSTS65001: 用户或管理员未同意使用该应用
+    if error.error == "interaction_required"
+        || error.error == "consent_required"
+        || description.contains("aadsts65001")
+        || description.contains("consent")
+    {
+        return Some(HealthStatus::NeedsConsent);
+    }
+
+    // AADSTS70008 / AADSTS700082 / AADSTS50173: 刷新令牌过期或因长期未使用失效
+    if ["aadsts70008", "aadsts700082", "aadsts50173", "expired"]
+        .iter()
+        .any(|k| description.contains(k))
+    {
+        return Some(HealthStatus::TokenExpired);
+    }
+
+    if error.error == "invalid_grant" {
+        return Some(HealthStatus::InvalidGrant);
+    }
+
+    None
+}
+
+/// 对错误进行分类，无法识别时返回 None（保持原状态）
+pub fn classify_error(err: &anyhow::Error, proxy_enabled: bool) -> Option<HealthStatus> {
+    for cause in err.chain() {
+        if let Some(oauth) = cause.downcast_ref::<OAuthError>() {
+            return classify_oauth_error(oauth);
+        }
+        if let Some(req_err) = cause.downcast_ref::<reqwest::Error>() {
+            if proxy_enabled && (req_err.is_connect() || req_err.is_timeout()) {
+                return Some(HealthStatus::ProxyError);
+            }
+        }
+    }
+
+    None
+}
+
+/// 记录检查成功
+pub async fn record_success(pool: &Pool<Sqlite>, email_id: i64) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    sqlx::query(
+        "UPDATE emails SET health_status = ?, last_error = NULL, last_error_at = NULL, health_checked_at = ? WHERE id = ?",
+    )
+    .bind(HealthStatus::Ok.as_str())
+    .bind(now)
+    .bind(email_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 记录检查失败，返回分类后的状态
+pub async fn record_failure(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    err: &anyhow::Error,
+) -> Result<Option<HealthStatus>> {
+    let proxy = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        "SELECT proxy_type, proxy_url FROM emails WHERE id = ?",
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await?;
+    let proxy_enabled = proxy
+        .map(|(proxy_type, proxy_url)| ProxyConfig::from_db(proxy_type, proxy_url).is_enabled())
+        .unwrap_or(false);
+
+    let status = classify_error(err, proxy_enabled);
+    let now = Utc::now().to_rfc3339();
+    sqlx::query(
+        "UPDATE emails SET health_status = COALESCE(?, health_status), last_error = ?, last_error_at = ?, health_checked_at = ? WHERE id = ?",
+    )
+    .bind(status.map(|s| s.as_str()))
+    .bind(err.to_string())
+    .bind(&now)
+    .bind(&now)
+    .bind(email_id)
+    .execute(pool)
+    .await?;
+
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oauth(error: &str, description: &str) -> anyhow::Error {
+        OAuthError {
+            error: error.to_string(),
+            description: description.to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_classify_oauth_errors() {
+        let err = oauth(
+            "invalid_grant",
+            "AADSTS700082: The refresh token has expired due to inactivity.",
+        );
+        assert_eq!(
+            classify_error(&err, false),
+            Some(HealthStatus::TokenExpired)
+        );
+
+        let err = oauth("invalid_grant", "AADSTS50053: The account is locked.");
+        assert_eq!(classify_error(&err, false), Some(HealthStatus::Locked));
+
+        let err = oauth(
+            "invalid_grant",
+            "AADSTS65001: The user or administrator has not consented to use the application.",
+        );
+        assert_eq!(
+            classify_error(&err, false),
+            Some(HealthStatus::NeedsConsent)
+        );
+
+        let err = oauth(
+            "invalid_grant",
+            "AADSTS54005: OAuth2 Authorization code was already redeemed.",
+        );
+        assert_eq!(
+            classify_error(&err, false),
+            Some(HealthStatus::InvalidGrant)
+        );
+    }
+
+    #[test]
+    fn test_classify_unknown_error() {
+        let err = anyhow::anyhow!("Graph API 请求失败: 500");
+        assert_eq!(classify_error(&err, true), None);
+    }
+
+    async fn health_row(
+        pool: &Pool<Sqlite>,
+        email_id: i64,
+    ) -> (Option<String>, Option<String>, Option<String>) {
+        sqlx::query_as("SELECT health_status, last_error, last_error_at FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_record_success_clears_last_error() {
+        let pool = crate::db::test_pool().await;
+        let email_id: i64 = sqlx::query_scalar(
+            "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES ('a@x.com', '', 'cid', '', 1) RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let err = oauth("invalid_grant", "AADSTS50053: The account is locked.");
+        let status = record_failure(&pool, email_id, &err).await.unwrap();
+        assert_eq!(status, Some(HealthStatus::Locked));
+        let (status, last_error, last_error_at) = health_row(&pool, email_id).await;
+        assert_eq!(status.as_deref(), Some("locked"));
+        assert_eq!(last_error, Some(err.to_string()));
+        assert!(last_error_at.is_some());
+
+        record_success(&pool, email_id).await.unwrap();
+        assert_eq!(
+            health_row(&pool, email_id).await,
+            (Some("ok".to_string()), None, None)
+        );
+    }
+
+    #[test]
+    fn test_health_status_round_trip() {
+        for status in [
+            HealthStatus::Ok,
+            HealthStatus::TokenExpired,
+            HealthStatus::InvalidGrant,
+            HealthStatus::Locked,
+            HealthStatus::NeedsConsent,
+            HealthStatus::ProxyError,
+        ] {
+            assert_eq!(
+                HealthStatus::from(Some(status.as_str().to_string())),
+                status
+            );
+        }
+        assert_eq!(HealthStatus::from(None), HealthStatus::Unknown);
+    }
+}
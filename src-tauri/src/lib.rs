@@ -1,10 +1,31 @@
+mod archive;
+mod attachments;
+mod audit;
+mod auth;
+mod backup;
+mod batch_job;
+mod blob_store;
 mod commands;
+mod credentials;
 mod db;
 mod email;
+mod folders;
 mod graph_api;
+mod health;
+mod mail_actions;
+mod mail_state;
 mod mail_watcher;
+mod maintenance;
+mod notifications;
 mod proxy;
+mod rules;
+mod settings;
+mod tags;
 mod token_cache;
+mod trash;
+mod tray;
+mod vault;
+mod webhooks;
 
 use std::sync::Arc;
 use tauri::Manager;
@@ -16,75 +37,439 @@ fn greet(name: &str) -> String {
 
 /// 启动邮件监听器
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn start_mail_watcher(
     app_handle: tauri::AppHandle,
     state: tauri::State<'_, db::AppState>,
     watcher_state: tauri::State<'_, Arc<mail_watcher::MailWatcherManager>>,
+    token: String,
     email_id: i64,
-    folder: String,
+    folder: Option<String>,
+    folders: Option<Vec<String>>,
     interval_secs: Option<u64>,
+    persist: Option<bool>,
 ) -> Result<(), String> {
-    let interval = interval_secs.unwrap_or(60); // 默认 60 秒
+    auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let interval =
+        interval_secs.unwrap_or_else(|| settings::current().watcher.default_interval_secs);
+
+    // folders 指定完整的监听列表；单个 folder 则在已保存的监听列表上临时追加，不写入设置
+    let (folders, persist) = match (folders, folder) {
+        (Some(folders), _) => (folders, persist.unwrap_or(true)),
+        (None, Some(folder)) => {
+            let mut current = mail_watcher::saved_folders(&state.db, email_id)
+                .await
+                .map_err(|e| format!("读取监听设置失败: {}", e))?;
+            current.push(folder);
+            (current, false)
+        }
+        (None, None) => (vec!["INBOX".to_string()], persist.unwrap_or(true)),
+    };
     watcher_state
-        .start_watcher(app_handle, state.db.clone(), email_id, folder, interval)
+        .start_watcher(
+            app_handle,
+            state.db.clone(),
+            email_id,
+            folders,
+            interval,
+            persist,
+        )
         .await
 }
 
 /// 停止邮件监听器
 #[tauri::command]
 async fn stop_mail_watcher(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, db::AppState>,
     watcher_state: tauri::State<'_, Arc<mail_watcher::MailWatcherManager>>,
+    token: String,
     email_id: i64,
+    persist: Option<bool>,
 ) -> Result<(), String> {
-    watcher_state.stop_watcher(email_id).await
+    auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    watcher_state
+        .stop_watcher(
+            app_handle,
+            state.db.clone(),
+            email_id,
+            persist.unwrap_or(true),
+        )
+        .await
 }
 
 /// 检查邮件监听器是否正在运行
 #[tauri::command]
 async fn is_mail_watcher_running(
+    state: tauri::State<'_, db::AppState>,
     watcher_state: tauri::State<'_, Arc<mail_watcher::MailWatcherManager>>,
+    token: String,
     email_id: i64,
 ) -> Result<bool, String> {
+    auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
     Ok(watcher_state.is_running(email_id).await)
 }
 
+/// 获取当前用户所有监听器的状态
+#[tauri::command]
+async fn list_watchers(
+    state: tauri::State<'_, db::AppState>,
+    watcher_state: tauri::State<'_, Arc<mail_watcher::MailWatcherManager>>,
+    token: String,
+) -> Result<Vec<mail_watcher::WatcherInfo>, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let filter = email::EmailFilter {
+        user_id: Some(user_id),
+        ..Default::default()
+    };
+    let email_ids = email::resolve_email_ids(&state.db, &[], &filter)
+        .await
+        .map_err(|e| format!("获取邮箱列表失败: {}", e))?;
+    let mut watchers = watcher_state.list_watchers().await;
+    watchers.retain(|w| email_ids.contains(&w.email_id));
+    Ok(watchers)
+}
+
+/// 强制恢复已暂停的邮件监听器
+#[tauri::command]
+async fn resume_mail_watcher(
+    state: tauri::State<'_, db::AppState>,
+    watcher_state: tauri::State<'_, Arc<mail_watcher::MailWatcherManager>>,
+    token: String,
+    email_id: i64,
+) -> Result<(), String> {
+    auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    watcher_state.resume_watcher(email_id).await;
+    Ok(())
+}
+
+/// 更新邮箱凭据，并恢复因凭据失效而暂停的监听器
+#[tauri::command]
+async fn update_email_credentials(
+    state: tauri::State<'_, db::AppState>,
+    watcher_state: tauri::State<'_, Arc<mail_watcher::MailWatcherManager>>,
+    token: String,
+    email_id: i64,
+    password: Option<String>,
+    client_id: Option<String>,
+    refresh_token: Option<String>,
+) -> Result<bool, String> {
+    let user_id = auth::authorize_account(&state.db, &token, email_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let updated = email::update_email_credentials(
+        &state.db,
+        user_id,
+        email_id,
+        password.as_deref(),
+        client_id.as_deref(),
+        refresh_token.as_deref(),
+    )
+    .await
+    .map_err(|e| format!("更新凭据失败: {}", e))?;
+    if updated {
+        watcher_state.resume_watcher(email_id).await;
+    }
+    Ok(updated)
+}
+
+/// 暂停全部用户的监听（不改变持久化设置，重启后恢复），仅管理员可用
+#[tauri::command]
+async fn suspend_all_watchers(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, db::AppState>,
+    token: String,
+) -> Result<(), String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    tray::set_watchers_suspended(&app_handle, true);
+    tray::refresh(&app_handle).await;
+    Ok(())
+}
+
+/// 恢复全部用户的监听，仅管理员可用
+#[tauri::command]
+async fn resume_all_watchers(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, db::AppState>,
+    token: String,
+) -> Result<(), String> {
+    auth::require_admin(&state.db, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    tray::set_watchers_suspended(&app_handle, false);
+    tray::refresh(&app_handle).await;
+    Ok(())
+}
+
+/// 让当前用户的所有监听器立即检查一次，返回触发的数量
+#[tauri::command]
+async fn check_all_watchers_now(
+    state: tauri::State<'_, db::AppState>,
+    watcher_state: tauri::State<'_, Arc<mail_watcher::MailWatcherManager>>,
+    token: String,
+) -> Result<usize, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let filter = email::EmailFilter {
+        user_id: Some(user_id),
+        remote_only: true,
+        ..Default::default()
+    };
+    let email_ids = email::resolve_email_ids(&state.db, &[], &filter)
+        .await
+        .map_err(|e| format!("获取邮箱列表失败: {}", e))?;
+    Ok(watcher_state.check_now(&email_ids).await)
+}
+
+/// 启动批量收件任务（有限并发，逐个推送进度事件）
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn start_batch_check(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, db::AppState>,
+    job_state: tauri::State<'_, Arc<batch_job::BatchJobManager>>,
+    token: String,
+    email_ids: Vec<i64>,
+    folder: Option<String>,
+    filter: Option<email::EmailFilter>,
+    concurrency: Option<usize>,
+    per_proxy_limit: Option<usize>,
+) -> Result<String, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    let folder = folder.unwrap_or_else(|| "INBOX".to_string());
+    let filter = email::EmailFilter {
+        user_id: Some(user_id),
+        remote_only: true,
+        ..filter.unwrap_or_default()
+    };
+    let email_ids = email::resolve_email_ids(&state.db, &email_ids, &filter)
+        .await
+        .map_err(|e| format!("获取邮箱列表失败: {}", e))?;
+    job_state
+        .start_job(
+            app_handle,
+            state.db.clone(),
+            user_id,
+            email_ids,
+            folder,
+            concurrency,
+            per_proxy_limit,
+        )
+        .await
+}
+
+/// 取消批量收件任务
+#[tauri::command]
+async fn cancel_batch_check(
+    job_state: tauri::State<'_, Arc<batch_job::BatchJobManager>>,
+    token: String,
+    job_id: String,
+) -> Result<bool, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    job_state.cancel_job(user_id, &job_id).await
+}
+
+/// 查询批量收件任务状态（运行中也可查询已完成部分）
+#[tauri::command]
+async fn get_batch_check_status(
+    job_state: tauri::State<'_, Arc<batch_job::BatchJobManager>>,
+    token: String,
+    job_id: String,
+) -> Result<batch_job::BatchJobStatus, String> {
+    let user_id = auth::require_user(&token).map_err(|e| e.to_string())?;
+    job_state
+        .get_status(user_id, &job_id)
+        .await
+        .ok_or_else(|| format!("任务不存在: {}", job_id))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            Some(vec![tray::BACKGROUND_ARG]),
+        ))
         .plugin(tauri_plugin_updater::Builder::new().build())
         .setup(|app| {
             let handle = app.handle().clone();
-            tauri::async_runtime::block_on(async move {
+            let pool = tauri::async_runtime::block_on(async {
                 let pool = db::init_db(&handle)
                     .await
                     .expect("Failed to initialize database");
-                handle.manage(db::AppState { db: pool });
+                handle.manage(db::AppState { db: pool.clone() });
+                pool
             });
 
-            // 初始化邮件监听器管理器
+            // 应用设置（超时、收件窗口、IMAP 服务器等）
+            if let Err(e) = tauri::async_runtime::block_on(settings::load(&pool)) {
+                log::warn!("读取应用设置失败，使用默认值: {}", e);
+            }
+
+            // 桌面通知（新邮件与收件规则）
+            notifications::init(handle.clone());
+
+            // 保险库空闲自动锁定
+            tauri::async_runtime::spawn(vault::run_auto_lock(handle.clone()));
+
+            // 定时自动备份
+            tauri::async_runtime::spawn(backup::run_scheduler(pool.clone()));
+
+            // 定期清理过期审计日志
+            tauri::async_runtime::spawn(audit::run_retention(pool.clone()));
+            tauri::async_runtime::spawn(trash::run_purge(pool.clone()));
+
+            // Webhook 投递队列
+            tauri::async_runtime::spawn(webhooks::run_delivery(pool.clone()));
+
+            // 初始化邮件监听器管理器，并恢复已持久化的监听
             let watcher_manager = Arc::new(mail_watcher::MailWatcherManager::new());
-            app.manage(watcher_manager);
+            app.manage(watcher_manager.clone());
+
+            // 系统托盘；以开机启动参数运行时只在托盘中运行
+            if let Err(e) = tauri::async_runtime::block_on(tray::load_settings(&pool)) {
+                log::warn!("读取后台运行设置失败: {}", e);
+            }
+            // 托盘创建失败（如桌面环境不支持）时照常启动，只是不能隐藏到托盘
+            match tray::init(&handle) {
+                Ok(()) => {
+                    if std::env::args().any(|arg| arg == tray::BACKGROUND_ARG) {
+                        tray::hide_main_window(&handle);
+                    }
+                    tauri::async_runtime::spawn(tray::run_refresh(handle.clone()));
+                }
+                Err(e) => log::error!("创建系统托盘失败，将不使用托盘: {}", e),
+            }
+
+            tauri::async_runtime::spawn(async move {
+                watcher_manager.restore_watchers(handle, pool).await;
+            });
+
+            // 初始化批量收件任务管理器
+            let batch_job_manager = Arc::new(batch_job::BatchJobManager::new());
+            app.manage(batch_job_manager);
 
             Ok(())
         })
+        // 开启后台运行时关闭主窗口只隐藏到托盘
+        .on_window_event(tray::on_window_event)
         // 注册后端命令
         .invoke_handler(tauri::generate_handler![
             greet,
+            commands::register,
+            commands::login,
+            commands::logout,
+            commands::get_current_user,
+            commands::change_password,
             commands::add_email,
             commands::get_emails,
+            commands::export_emails,
+            commands::reveal_credentials,
+            commands::copy_credential,
             commands::delete_email,
             commands::import_emails,
             commands::check_outlook_email,
+            commands::list_folders,
             commands::batch_check_outlook_emails,
+            commands::validate_accounts,
             commands::get_mail_records,
+            commands::apply_mail_action,
+            commands::get_unread_counts,
             commands::get_attachments,
             commands::get_attachment_content,
+            commands::save_attachment,
+            commands::export_attachments,
+            commands::set_attachment_auto_download,
+            commands::export_mailbox,
+            commands::import_mailbox,
+            commands::get_tags,
+            commands::create_tag,
+            commands::update_tag,
+            commands::delete_tag,
+            commands::assign_tags,
+            commands::unassign_tags,
+            commands::set_email_notes,
+            commands::backup_database,
+            commands::list_backups,
+            commands::validate_backup,
+            commands::restore_database,
+            commands::get_backup_settings,
+            commands::set_backup_settings,
+            commands::check_database_integrity,
+            commands::vacuum_database,
+            commands::analyze_database,
+            commands::get_storage_report,
+            commands::get_vault_status,
+            commands::enable_vault,
+            commands::unlock_vault,
+            commands::lock_vault,
+            commands::change_master_password,
+            commands::disable_vault,
+            commands::set_vault_auto_lock,
+            commands::get_audit_log,
+            commands::export_audit_log,
+            commands::get_audit_retention,
+            commands::set_audit_retention,
+            commands::list_trashed_accounts,
+            commands::list_trashed_mails,
+            commands::restore_accounts,
+            commands::restore_mails,
+            commands::empty_trash,
+            commands::get_trash_retention,
+            commands::set_trash_retention,
+            commands::list_mail_rules,
+            commands::create_mail_rule,
+            commands::update_mail_rule,
+            commands::delete_mail_rule,
+            commands::test_mail_rule,
+            commands::get_mail_rule_hits,
+            commands::list_webhooks,
+            commands::create_webhook,
+            commands::update_webhook,
+            commands::delete_webhook,
+            commands::rotate_webhook_secret,
+            commands::test_webhook,
+            commands::list_webhook_deliveries,
+            commands::retry_webhook_deliveries,
+            commands::delete_webhook_deliveries,
+            commands::get_notification_settings,
+            commands::set_notification_settings,
+            commands::list_notification_mutes,
+            commands::add_notification_mute,
+            commands::remove_notification_mute,
+            commands::get_background_settings,
+            commands::set_background_settings,
+            commands::get_settings,
+            commands::update_settings,
+            commands::reset_settings,
+            commands::export_settings,
+            commands::import_settings,
             start_mail_watcher,
             stop_mail_watcher,
-            is_mail_watcher_running
+            is_mail_watcher_running,
+            list_watchers,
+            resume_mail_watcher,
+            suspend_all_watchers,
+            resume_all_watchers,
+            check_all_watchers_now,
+            update_email_credentials,
+            start_batch_check,
+            cancel_batch_check,
+            get_batch_check_status
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
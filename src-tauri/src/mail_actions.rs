@@ -0,0 +1,596 @@
+//! 邮件操作模块
+//! 在服务器上对邮件执行标记已读/未读、旗标、添加类别、移动、删除与归档，
+//! 支持 Graph API 与 IMAP，成功后同步更新本地 mail_records
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+
+use crate::audit::{self, AuditEvent};
+use crate::email::{self, ApiMode, OutlookSession};
+use crate::folders::{self, ResolvedFolder};
+use crate::graph_api;
+use crate::trash;
+
+/// 邮件操作
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MailAction {
+    /// 标记为已读
+    MarkRead,
+    /// 标记为未读
+    MarkUnread,
+    /// 添加旗标
+    Flag,
+    /// 取消旗标
+    Unflag,
+    /// 添加类别（Graph categories / IMAP 关键字）
+    Categorize { category: String },
+    /// 移动到指定文件夹
+    Move { folder: String },
+    /// 删除（移动到已删除邮件）
+    Delete,
+    /// 永久删除
+    HardDelete,
+    /// 归档
+    Archive,
+}
+
+impl MailAction {
+    /// 操作名称（用于审计日志）
+    fn describe(&self) -> String {
+        match self {
+            MailAction::MarkRead => "mark_read".to_string(),
+            MailAction::MarkUnread => "mark_unread".to_string(),
+            MailAction::Flag => "flag".to_string(),
+            MailAction::Unflag => "unflag".to_string(),
+            MailAction::Categorize { category } => format!("categorize:{category}"),
+            MailAction::Move { folder } => format!("move:{folder}"),
+            MailAction::Delete => "delete".to_string(),
+            MailAction::HardDelete => "hard_delete".to_string(),
+            MailAction::Archive => "archive".to_string(),
+        }
+    }
+}
+
+/// 单封邮件的操作结果
+#[derive(Debug, Clone, Serialize)]
+pub struct MailActionResult {
+    pub mail_id: i64,
+    pub success: bool,
+    pub message: String,
+}
+
+/// 批量操作结果
+#[derive(Debug, Serialize)]
+pub struct BatchMailActionResult {
+    pub success_count: usize,
+    pub failed_count: usize,
+    pub results: Vec<MailActionResult>,
+}
+
+/// 解析目标文件夹后的操作
+enum Operation {
+    SetRead(bool),
+    SetFlagged(bool),
+    AddCategory(String),
+    Move(ResolvedFolder),
+    HardDelete,
+}
+
+/// 邮件的服务器标识
+#[derive(Debug, sqlx::FromRow)]
+struct MailRef {
+    id: i64,
+    email_id: i64,
+    folder: Option<String>,
+    graph_id: Option<String>,
+    imap_uid: Option<i64>,
+}
+
+/// 批量执行邮件操作（按账号分组，每个账号只获取一次令牌），user_id 为审计日志中记录的操作用户
+pub async fn apply_mail_action(
+    pool: &Pool<Sqlite>,
+    user_id: Option<i64>,
+    mail_ids: &[i64],
+    action: &MailAction,
+) -> Result<BatchMailActionResult> {
+    let mut results = Vec::new();
+
+    let refs = load_mail_refs(pool, mail_ids).await?;
+    for mail_id in mail_ids {
+        if !refs.iter().any(|r| r.id == *mail_id) {
+            results.push(failure(*mail_id, "邮件不存在"));
+        }
+    }
+
+    let mut by_account: BTreeMap<i64, Vec<MailRef>> = BTreeMap::new();
+    for mail in refs {
+        by_account.entry(mail.email_id).or_default().push(mail);
+    }
+
+    for (email_id, mails) in by_account {
+        match apply_for_account(pool, email_id, mails.as_slice(), action).await {
+            Ok(account_results) => results.extend(account_results),
+            Err(e) => {
+                let message = format!("操作失败: {e}");
+                results.extend(mails.iter().map(|m| failure(m.id, &message)));
+            }
+        }
+    }
+
+    let succeeded: Vec<i64> = results
+        .iter()
+        .filter(|r| r.success)
+        .map(|r| r.mail_id)
+        .collect();
+    let success_count = succeeded.len();
+    audit::record(
+        pool,
+        audit::ACTION_MAIL_ACTION,
+        AuditEvent {
+            user_id,
+            target_ids: &succeeded,
+            detail: Some(format!(
+                "action={}, failed={}",
+                action.describe(),
+                results.len() - success_count
+            )),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(BatchMailActionResult {
+        success_count,
+        failed_count: results.len() - success_count,
+        results,
+    })
+}
+
+/// 对同一账号的邮件执行操作
+async fn apply_for_account(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    mails: &[MailRef],
+    action: &MailAction,
+) -> Result<Vec<MailActionResult>> {
+    let session = email::open_outlook_session(pool, email_id).await?;
+
+    let operation = match action {
+        MailAction::MarkRead => Operation::SetRead(true),
+        MailAction::MarkUnread => Operation::SetRead(false),
+        MailAction::Flag => Operation::SetFlagged(true),
+        MailAction::Unflag => Operation::SetFlagged(false),
+        MailAction::Categorize { category } => {
+            let category = category.trim();
+            if category.is_empty() {
+                return Err(anyhow!("类别不能为空"));
+            }
+            Operation::AddCategory(category.to_string())
+        }
+        MailAction::Move { folder } => {
+            Operation::Move(folders::resolve_folder(pool, email_id, folder).await?)
+        }
+        MailAction::Delete => {
+            Operation::Move(folders::resolve_folder(pool, email_id, "deleted").await?)
+        }
+        MailAction::Archive => {
+            Operation::Move(folders::resolve_folder(pool, email_id, "archive").await?)
+        }
+        MailAction::HardDelete => Operation::HardDelete,
+    };
+
+    let mut results = Vec::new();
+    // IMAP 操作按源文件夹分组，每组一条 UID 命令
+    let mut imap_groups: HashMap<String, Vec<(i64, u32)>> = HashMap::new();
+
+    for mail in mails {
+        let prefer_graph = session.api_mode != ApiMode::Imap || mail.imap_uid.is_none();
+        match (&mail.graph_id, mail.imap_uid) {
+            (Some(graph_id), _) if prefer_graph => {
+                let result = apply_via_graph(pool, &session, mail.id, graph_id, &operation).await;
+                results.push(match result {
+                    Ok(()) => success(mail.id),
+                    Err(e) => failure(mail.id, &format!("操作失败: {e}")),
+                });
+            }
+            (_, Some(uid)) => {
+                let folder = mail.folder.clone().unwrap_or_else(|| "inbox".to_string());
+                imap_groups
+                    .entry(folder)
+                    .or_default()
+                    .push((mail.id, uid as u32));
+            }
+            _ => results.push(failure(mail.id, "缺少服务器标识，请重新收件后再试")),
+        }
+    }
+
+    for (folder, group) in imap_groups {
+        let source = folders::resolve_folder(pool, email_id, &folder).await?;
+        let uids: Vec<u32> = group.iter().map(|(_, uid)| *uid).collect();
+        let result = apply_via_imap(&session, &source, &uids, &operation).await;
+        match result {
+            Ok(()) => {
+                for (mail_id, _) in &group {
+                    update_local(pool, *mail_id, &operation, None).await?;
+                    results.push(success(*mail_id));
+                }
+            }
+            Err(e) => {
+                let message = format!("操作失败: {e}");
+                results.extend(group.iter().map(|(mail_id, _)| failure(*mail_id, &message)));
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+/// 通过 Graph API 执行操作，并更新本地记录
+async fn apply_via_graph(
+    pool: &Pool<Sqlite>,
+    session: &OutlookSession,
+    mail_id: i64,
+    graph_id: &str,
+    operation: &Operation,
+) -> Result<()> {
+    let token = &session.access_token;
+    let proxy_config = &session.proxy_config;
+
+    let new_graph_id = match operation {
+        Operation::SetRead(read) => {
+            let patch = serde_json::json!({ "isRead": read });
+            graph_api::update_message(token, graph_id, &patch, proxy_config).await?;
+            None
+        }
+        Operation::SetFlagged(flagged) => {
+            let status = if *flagged { "flagged" } else { "notFlagged" };
+            let patch = serde_json::json!({ "flag": { "flagStatus": status } });
+            graph_api::update_message(token, graph_id, &patch, proxy_config).await?;
+            None
+        }
+        Operation::AddCategory(category) => {
+            // Graph 需提交完整的类别列表
+            let mut categories = local_categories(pool, mail_id).await?;
+            if !categories.contains(category) {
+                categories.push(category.clone());
+            }
+            let patch = serde_json::json!({ "categories": categories });
+            graph_api::update_message(token, graph_id, &patch, proxy_config).await?;
+            None
+        }
+        Operation::Move(target) => {
+            Some(graph_api::move_message(token, graph_id, &target.graph_ref, proxy_config).await?)
+        }
+        Operation::HardDelete => {
+            graph_api::permanent_delete_message(token, graph_id, proxy_config).await?;
+            None
+        }
+    };
+
+    update_local(pool, mail_id, operation, new_graph_id).await
+}
+
+/// 通过 IMAP UID 命令执行操作
+async fn apply_via_imap(
+    session: &OutlookSession,
+    source: &ResolvedFolder,
+    uids: &[u32],
+    operation: &Operation,
+) -> Result<()> {
+    let email_address = session.email.clone();
+    let access_token = session.access_token.clone();
+    let source_name = source.imap_name.clone();
+    let uid_set = uids
+        .iter()
+        .map(|uid| uid.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let command = match operation {
+        Operation::SetRead(true) => ImapCommand::Store("+FLAGS (\\Seen)".to_string()),
+        Operation::SetRead(false) => ImapCommand::Store("-FLAGS (\\Seen)".to_string()),
+        Operation::SetFlagged(true) => ImapCommand::Store("+FLAGS (\\Flagged)".to_string()),
+        Operation::SetFlagged(false) => ImapCommand::Store("-FLAGS (\\Flagged)".to_string()),
+        Operation::AddCategory(category) => {
+            ImapCommand::Store(format!("+FLAGS ({})", imap_keyword(category)))
+        }
+        Operation::Move(target) => ImapCommand::Move(target.imap_name.clone()),
+        Operation::HardDelete => ImapCommand::Expunge,
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let mut imap = email::connect_outlook_imap(&email_address, &access_token)?;
+        imap.select(&source_name)?;
+        match command {
+            ImapCommand::Store(query) => {
+                imap.uid_store(&uid_set, &query)?;
+            }
+            ImapCommand::Move(target) => imap.uid_mv(&uid_set, &target)?,
+            ImapCommand::Expunge => {
+                imap.uid_store(&uid_set, "+FLAGS (\\Deleted)")?;
+                imap.uid_expunge(&uid_set)?;
+            }
+        }
+        imap.logout()?;
+        Ok::<_, anyhow::Error>(())
+    })
+    .await?
+}
+
+/// IMAP 命令
+enum ImapCommand {
+    Store(String),
+    Move(String),
+    Expunge,
+}
+
+/// 操作成功后更新本地邮件记录
+async fn update_local(
+    pool: &Pool<Sqlite>,
+    mail_id: i64,
+    operation: &Operation,
+    new_graph_id: Option<String>,
+) -> Result<()> {
+    match operation {
+        Operation::SetRead(read) => {
+            sqlx::query("UPDATE mail_records SET is_read = ? WHERE id = ?")
+                .bind(read)
+                .bind(mail_id)
+                .execute(pool)
+                .await?;
+        }
+        Operation::SetFlagged(flagged) => {
+            sqlx::query("UPDATE mail_records SET is_flagged = ? WHERE id = ?")
+                .bind(flagged)
+                .bind(mail_id)
+                .execute(pool)
+                .await?;
+        }
+        Operation::AddCategory(category) => {
+            let mut categories = local_categories(pool, mail_id).await?;
+            if !categories.contains(category) {
+                categories.push(category.clone());
+            }
+            sqlx::query("UPDATE mail_records SET categories = ? WHERE id = ?")
+                .bind(serde_json::to_string(&categories)?)
+                .bind(mail_id)
+                .execute(pool)
+                .await?;
+        }
+        Operation::Move(target) => {
+            // 移动后原 UID 失效，下次收取目标文件夹时补全
+            sqlx::query(
+                "UPDATE mail_records SET folder = ?, graph_id = ?, imap_uid = NULL WHERE id = ?",
+            )
+            .bind(&target.key)
+            .bind(new_graph_id)
+            .bind(mail_id)
+            .execute(pool)
+            .await?;
+        }
+        Operation::HardDelete => {
+            // 服务器上已彻底删除，本地记录移入回收站，保留期满后再清除
+            trash::trash_mails(pool, &[mail_id], trash::REASON_USER).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 读取邮件的本地类别
+async fn local_categories(pool: &Pool<Sqlite>, mail_id: i64) -> Result<Vec<String>> {
+    let categories: Option<String> =
+        sqlx::query_scalar("SELECT categories FROM mail_records WHERE id = ?")
+            .bind(mail_id)
+            .fetch_one(pool)
+            .await?;
+    Ok(categories
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default())
+}
+
+/// 将类别名转换为 IMAP 关键字（只允许 atom 字符）
+fn imap_keyword(category: &str) -> String {
+    category
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && !"(){%*\"\\]".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// 读取邮件的服务器标识
+async fn load_mail_refs(pool: &Pool<Sqlite>, mail_ids: &[i64]) -> Result<Vec<MailRef>> {
+    if mail_ids.is_empty() {
+        return Err(anyhow!("未选择邮件"));
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT id, email_id, folder, graph_id, imap_uid FROM mail_records WHERE id IN (",
+    );
+    let mut separated = qb.separated(", ");
+    for id in mail_ids {
+        separated.push_bind(id);
+    }
+    qb.push(")");
+
+    let refs = qb.build_query_as::<MailRef>().fetch_all(pool).await?;
+    Ok(refs)
+}
+
+fn success(mail_id: i64) -> MailActionResult {
+    MailActionResult {
+        mail_id,
+        success: true,
+        message: "操作成功".to_string(),
+    }
+}
+
+fn failure(mail_id: i64, message: &str) -> MailActionResult {
+    MailActionResult {
+        mail_id,
+        success: false,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db, token_cache};
+
+    /// 创建账号与一封邮件，返回 (账号 ID, 邮件 ID)
+    async fn setup(pool: &Pool<Sqlite>) -> (i64, i64) {
+        let email_id = email::add_email(pool, 1, "a@x.com", "", "cid", "", None)
+            .await
+            .unwrap();
+        let mail_id: i64 = sqlx::query_scalar(
+            "INSERT INTO mail_records (email_id, subject, folder, imap_uid) VALUES (?, 'hi', 'inbox', 7) RETURNING id",
+        )
+        .bind(email_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        (email_id, mail_id)
+    }
+
+    #[test]
+    fn test_imap_keyword() {
+        assert_eq!(imap_keyword("Work"), "Work");
+        assert_eq!(imap_keyword("Red (urgent)"), "Red__urgent_");
+        assert_eq!(imap_keyword("客户A"), "__A");
+        assert_eq!(imap_keyword("a\\b\"c%d*e]"), "a_b_c_d_e_");
+    }
+
+    #[tokio::test]
+    async fn test_update_local() {
+        let pool = db::test_pool().await;
+        let (_, mail_id) = setup(&pool).await;
+
+        update_local(&pool, mail_id, &Operation::SetRead(true), None)
+            .await
+            .unwrap();
+        update_local(&pool, mail_id, &Operation::SetFlagged(true), None)
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let operation = Operation::AddCategory("Work".to_string());
+            update_local(&pool, mail_id, &operation, None)
+                .await
+                .unwrap();
+        }
+        let (is_read, is_flagged): (bool, bool) =
+            sqlx::query_as("SELECT is_read, is_flagged FROM mail_records WHERE id = ?")
+                .bind(mail_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(is_read && is_flagged);
+        assert_eq!(local_categories(&pool, mail_id).await.unwrap(), ["Work"]);
+
+        // 移动后记录新的 Graph ID，原 UID 失效
+        let target = ResolvedFolder {
+            key: "archive".to_string(),
+            graph_ref: "archive".to_string(),
+            imap_name: "Archive".to_string(),
+        };
+        update_local(
+            &pool,
+            mail_id,
+            &Operation::Move(target),
+            Some("g2".to_string()),
+        )
+        .await
+        .unwrap();
+        let refs: (String, Option<String>, Option<i64>) =
+            sqlx::query_as("SELECT folder, graph_id, imap_uid FROM mail_records WHERE id = ?")
+                .bind(mail_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(refs, ("archive".to_string(), Some("g2".to_string()), None));
+
+        // 彻底删除后本地记录移入回收站
+        update_local(&pool, mail_id, &Operation::HardDelete, None)
+            .await
+            .unwrap();
+        let trashed = trash::list_mails(&pool, 1, None).await.unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(
+            trashed[0].deleted_reason.as_deref(),
+            Some(trash::REASON_USER)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_for_account_without_server_refs() {
+        let pool = db::test_pool().await;
+        let (email_id, mail_id) = setup(&pool).await;
+        // 使用缓存的令牌，不连接服务器
+        token_cache::cache_token(&pool, email_id, "token", 3600)
+            .await
+            .unwrap();
+        let mails = load_mail_refs(&pool, &[mail_id]).await.unwrap();
+
+        let action = MailAction::Categorize {
+            category: " ".to_string(),
+        };
+        let err = apply_for_account(&pool, email_id, &mails, &action)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("类别不能为空"));
+
+        let orphan: i64 = sqlx::query_scalar(
+            "INSERT INTO mail_records (email_id, subject, folder) VALUES (?, 'orphan', 'inbox') RETURNING id",
+        )
+        .bind(email_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let mails = load_mail_refs(&pool, &[orphan]).await.unwrap();
+        let results = apply_for_account(&pool, email_id, &mails, &MailAction::MarkRead)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].success);
+        assert!(results[0].message.contains("缺少服务器标识"));
+    }
+
+    #[tokio::test]
+    async fn test_apply_mail_action_reports_failures() {
+        let pool = db::test_pool().await;
+        let (email_id, mail_id) = setup(&pool).await;
+        token_cache::cache_token(&pool, email_id, "token", 3600)
+            .await
+            .unwrap();
+
+        let action = MailAction::Categorize {
+            category: String::new(),
+        };
+        let result = apply_mail_action(&pool, Some(1), &[mail_id, mail_id + 100], &action)
+            .await
+            .unwrap();
+        assert_eq!((result.success_count, result.failed_count), (0, 2));
+        let missing = result.results.iter().find(|r| r.mail_id == mail_id + 100);
+        assert_eq!(missing.unwrap().message, "邮件不存在");
+        let failed = result.results.iter().find(|r| r.mail_id == mail_id);
+        assert!(failed.unwrap().message.contains("类别不能为空"));
+        assert!(apply_mail_action(&pool, Some(1), &[], &action)
+            .await
+            .is_err());
+
+        let detail: String = sqlx::query_scalar("SELECT detail FROM audit_log WHERE action = ?")
+            .bind(audit::ACTION_MAIL_ACTION)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(detail, "action=categorize:, failed=2");
+    }
+}
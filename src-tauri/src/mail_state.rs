@@ -0,0 +1,147 @@
+//! 邮件状态模块
+//! 统一 Graph API 字段与 IMAP FLAGS 表示的邮件状态（已读、旗标、已答复、重要性、类别），
+//! 并提供按账号、文件夹统计的未读数
+
+use anyhow::Result;
+use imap::types::Flag;
+use serde::Serialize;
+use sqlx::{Pool, QueryBuilder, Sqlite};
+
+/// 邮件状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MailState {
+    pub is_read: bool,
+    pub is_flagged: bool,
+    pub is_answered: bool,
+    /// 重要性：low / normal / high
+    pub importance: Option<String>,
+    /// 类别（Graph categories / IMAP 关键字）
+    pub categories: Vec<String>,
+}
+
+impl MailState {
+    /// 从 IMAP FLAGS 构建状态，重要性需从邮件头部另行解析
+    pub fn from_imap_flags(flags: &[Flag<'_>]) -> Self {
+        let mut state = MailState::default();
+        for flag in flags {
+            match flag {
+                Flag::Seen => state.is_read = true,
+                Flag::Flagged => state.is_flagged = true,
+                Flag::Answered => state.is_answered = true,
+                // 以 $ 开头的为系统关键字（如 $Forwarded、$Junk），不作为类别
+                Flag::Custom(keyword)
+                    if !keyword.starts_with('$') && !keyword.starts_with('\\') =>
+                {
+                    state.categories.push(keyword.to_string());
+                }
+                _ => {}
+            }
+        }
+        state
+    }
+
+    /// 类别的 JSON 表示（mail_records.categories 字段）
+    pub fn categories_json(&self) -> String {
+        serde_json::to_string(&self.categories).unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+/// 规范化重要性取值
+pub fn normalize_importance(value: &str) -> Option<String> {
+    let importance = match value.trim().to_lowercase().as_str() {
+        "low" | "non-urgent" => "low",
+        "normal" => "normal",
+        "high" | "urgent" => "high",
+        _ => return None,
+    };
+    Some(importance.to_string())
+}
+
+/// 从 Importance / X-Priority 邮件头解析重要性
+pub fn importance_from_headers(
+    importance: Option<&str>,
+    x_priority: Option<&str>,
+) -> Option<String> {
+    if let Some(importance) = importance.and_then(normalize_importance) {
+        return Some(importance);
+    }
+
+    // X-Priority: 1 (Highest) ~ 5 (Lowest)
+    let priority = x_priority?.trim().chars().next()?;
+    let importance = match priority {
+        '1' | '2' => "high",
+        '3' => "normal",
+        '4' | '5' => "low",
+        _ => return None,
+    };
+    Some(importance.to_string())
+}
+
+/// 未读数统计
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct UnreadCount {
+    pub email_id: i64,
+    pub folder: String,
+    pub unread_count: i64,
+    pub total_count: i64,
+}
+
+/// 按账号、文件夹统计本地邮件的未读数，email_id 为空时统计该用户的全部账号
+pub async fn get_unread_counts(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    email_id: Option<i64>,
+) -> Result<Vec<UnreadCount>> {
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT email_id, COALESCE(folder, 'inbox') AS folder, SUM(CASE WHEN is_read = 0 THEN 1 ELSE 0 END) AS unread_count, COUNT(*) AS total_count FROM mail_records WHERE deleted_at IS NULL AND email_id IN (SELECT id FROM emails WHERE deleted_at IS NULL AND user_id = ",
+    );
+    qb.push_bind(user_id);
+    qb.push(")");
+    if let Some(email_id) = email_id {
+        qb.push(" AND email_id = ");
+        qb.push_bind(email_id);
+    }
+    qb.push(" GROUP BY email_id, COALESCE(folder, 'inbox') ORDER BY email_id, folder");
+
+    let counts = qb.build_query_as::<UnreadCount>().fetch_all(pool).await?;
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_from_imap_flags() {
+        let flags = [
+            Flag::Seen,
+            Flag::Answered,
+            Flag::Custom(Cow::Borrowed("$Forwarded")),
+            Flag::Custom(Cow::Borrowed("Red category")),
+        ];
+        let state = MailState::from_imap_flags(&flags);
+        assert!(state.is_read);
+        assert!(!state.is_flagged);
+        assert!(state.is_answered);
+        assert_eq!(state.categories, vec!["Red category".to_string()]);
+        assert_eq!(state.categories_json(), r#"["Red category"]"#);
+    }
+
+    #[test]
+    fn test_importance_from_headers() {
+        assert_eq!(
+            importance_from_headers(Some("High"), None).as_deref(),
+            Some("high")
+        );
+        assert_eq!(
+            importance_from_headers(None, Some("1 (Highest)")).as_deref(),
+            Some("high")
+        );
+        assert_eq!(
+            importance_from_headers(Some("bogus"), Some("5")).as_deref(),
+            Some("low")
+        );
+        assert_eq!(importance_from_headers(None, None), None);
+    }
+}
@@ -1,15 +1,44 @@
 //! 邮件监听器模块
 //!
-//! 提供后台轮询邮件的功能，通过 Tauri 事件机制通知前端
+//! 提供后台轮询邮件的功能，通过 Tauri 事件机制通知前端，收到新邮件时弹出桌面通知。
+//! 每个邮箱一个监听器，覆盖多个文件夹，每轮共用一次令牌获取并逐个文件夹推送更新。
+//! 监听设置持久化在 emails 表中，应用启动时自动恢复；
+//! 所有监听器共享一个全局并发上限，并在轮询间隔上叠加随机抖动，避免同时请求。
+//! 连续失败时按指数退避（遵循 Graph 返回的 Retry-After），遇到无法重试的凭据错误
+//! 时自动暂停，凭据更新后自动恢复
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex, Notify, Semaphore};
+use chrono::{DateTime, Utc};
+use rand::Rng;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 use sqlx::{Pool, Sqlite};
 use tauri::{AppHandle, Emitter};
+use zeroize::Zeroizing;
 
-use crate::email::{self, MailRecord};
+use crate::archive;
+use crate::email::{self, CheckResult, MailRecord};
+use crate::folders::canonical_folder_key;
+use crate::graph_api::GraphApiError;
+use crate::health::{self, HealthStatus};
+use crate::notifications;
+use crate::settings;
+use crate::vault;
+
+/// 全局同时进行的收件数量上限
+const MAX_CONCURRENT_CHECKS: usize = 4;
+
+/// 轮询间隔的抖动比例（±10%）
+const INTERVAL_JITTER_RATIO: f64 = 0.1;
+
+/// 退避等待的上限（秒）
+const MAX_BACKOFF_SECS: u64 = 30 * 60;
+
+/// 暂停期间检查凭据是否更新的间隔（秒）
+const PAUSED_RECHECK_SECS: u64 = 60;
 
 /// 邮件更新事件的 payload
 #[derive(Debug, Clone, Serialize)]
@@ -39,208 +68,980 @@ pub struct MailProgressEvent {
     pub message: String,
 }
 
+/// 监听器运行状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatcherStatus {
+    /// 等待下一次检查
+    Waiting,
+    /// 正在检查
+    Checking,
+    /// 连续失败，退避等待中
+    BackingOff,
+    /// 凭据不可用，已暂停，等待凭据更新
+    Paused,
+    /// 已暂停全部监听，等待恢复
+    Suspended,
+}
+
+/// 监听器信息（供前端展示）
+#[derive(Debug, Clone, Serialize)]
+pub struct WatcherInfo {
+    /// 邮箱 ID
+    pub email_id: i64,
+    /// 监听的文件夹
+    pub folders: Vec<String>,
+    /// 轮询间隔（秒）
+    pub interval_secs: u64,
+    /// 运行状态
+    pub status: WatcherStatus,
+    /// 上次检查时间
+    pub last_run: Option<String>,
+    /// 下次检查时间
+    pub next_run: Option<String>,
+    /// 上次检查的错误信息
+    pub last_error: Option<String>,
+    /// 连续失败次数
+    pub consecutive_failures: u32,
+    /// 暂停原因
+    pub paused_reason: Option<String>,
+}
+
 /// 监听器状态
 struct WatcherState {
-    /// 是否正在运行
-    running: bool,
+    /// 启动代次，用于区分同一邮箱先后启动的监听任务
+    generation: u64,
+    /// 展示信息
+    info: WatcherInfo,
     /// 取消信号
     cancel_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    /// 恢复信号（暂停时使用）
+    resume: Arc<Notify>,
+    /// 立即检查信号
+    wake: Arc<Notify>,
 }
 
+type WatcherMap = Arc<Mutex<HashMap<i64, WatcherState>>>;
+
 /// 全局监听器管理器
 pub struct MailWatcherManager {
     /// 每个邮箱的监听器状态 (email_id -> state)
-    watchers: Arc<Mutex<HashMap<i64, WatcherState>>>,
+    watchers: WatcherMap,
+    /// 全局收件并发控制
+    check_permits: Arc<Semaphore>,
+    /// 下一个启动代次
+    next_generation: Mutex<u64>,
+    /// 是否已暂停全部监听（仅本次运行有效，不改变持久化设置）
+    suspended: watch::Sender<bool>,
 }
 
 impl MailWatcherManager {
     pub fn new() -> Self {
         Self {
             watchers: Arc::new(Mutex::new(HashMap::new())),
+            check_permits: Arc::new(Semaphore::new(MAX_CONCURRENT_CHECKS)),
+            next_generation: Mutex::new(0),
+            suspended: watch::Sender::new(false),
+        }
+    }
+
+    /// 恢复启动前已开启的监听器
+    pub async fn restore_watchers(&self, app_handle: AppHandle, pool: Pool<Sqlite>) {
+        let settings = match load_watch_settings(&pool).await {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::error!("读取监听设置失败: {}", e);
+                return;
+            }
+        };
+
+        log::info!("恢复邮件监听器: count={}", settings.len());
+        for (email_id, folders, interval_secs) in settings {
+            // 首次检查随机延后，避免启动时所有账号同时收件
+            let initial_delay = random_initial_delay(interval_secs);
+            self.spawn_watcher(
+                app_handle.clone(),
+                pool.clone(),
+                email_id,
+                folders,
+                interval_secs,
+                initial_delay,
+            )
+            .await;
         }
     }
 
     /// 启动邮件监听器
+    ///
+    /// persist 为 true 时保存设置，应用重启后自动恢复；为 false 时仅临时运行（如邮件查看窗口的自动刷新）
     pub async fn start_watcher(
         &self,
         app_handle: AppHandle,
         pool: Pool<Sqlite>,
         email_id: i64,
-        folder: String,
+        folders: Vec<String>,
         interval_secs: u64,
+        persist: bool,
     ) -> Result<(), String> {
+        // 去除重复的文件夹（按统一标识）
+        let mut seen = HashSet::new();
+        let folders: Vec<String> = folders
+            .into_iter()
+            .map(|f| f.trim().to_string())
+            .filter(|f| !f.is_empty() && seen.insert(canonical_folder_key(f)))
+            .collect();
+        if folders.is_empty() {
+            return Err("监听文件夹不能为空".to_string());
+        }
+        let is_local = archive::is_local_account(&pool, email_id)
+            .await
+            .map_err(|e| format!("获取邮箱信息失败: {}", e))?;
+        if is_local {
+            return Err("本地账号不支持监听".to_string());
+        }
+        let interval_secs = interval_secs.max(1);
+
+        if persist {
+            save_watch_settings(&pool, email_id, &folders, interval_secs)
+                .await
+                .map_err(|e| format!("保存监听设置失败: {}", e))?;
+        }
+
+        self.spawn_watcher(
+            app_handle,
+            pool,
+            email_id,
+            folders,
+            interval_secs,
+            Duration::ZERO,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// 启动后台监听任务（同一邮箱已有任务时先停止旧任务）
+    async fn spawn_watcher(
+        &self,
+        app_handle: AppHandle,
+        pool: Pool<Sqlite>,
+        email_id: i64,
+        folders: Vec<String>,
+        interval_secs: u64,
+        initial_delay: Duration,
+    ) {
+        let generation = {
+            let mut next = self.next_generation.lock().await;
+            *next += 1;
+            *next
+        };
+
         let mut watchers = self.watchers.lock().await;
 
         // 如果已经在运行，先停止
         if let Some(state) = watchers.get_mut(&email_id) {
-            if state.running {
-                if let Some(tx) = state.cancel_tx.take() {
-                    let _ = tx.send(());
-                }
+            if let Some(tx) = state.cancel_tx.take() {
+                let _ = tx.send(());
             }
         }
 
-        // 创建取消信号
+        // 创建取消与恢复信号
         let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+        let resume = Arc::new(Notify::new());
+        let wake = Arc::new(Notify::new());
 
         // 更新状态
         watchers.insert(email_id, WatcherState {
-            running: true,
+            generation,
+            info: WatcherInfo {
+                email_id,
+                folders: folders.clone(),
+                interval_secs,
+                status: WatcherStatus::Waiting,
+                last_run: None,
+                next_run: None,
+                last_error: None,
+                consecutive_failures: 0,
+                paused_reason: None,
+            },
             cancel_tx: Some(cancel_tx),
+            resume: resume.clone(),
+            wake: wake.clone(),
         });
 
-        let watchers_clone = self.watchers.clone();
+        let watcher = WatcherTask {
+            app_handle,
+            pool,
+            email_id,
+            generation,
+            folders,
+            interval_secs,
+            watchers: self.watchers.clone(),
+            check_permits: self.check_permits.clone(),
+            resume,
+            wake,
+            suspended: self.suspended.subscribe(),
+        };
 
         // 启动后台任务
         tokio::spawn(async move {
-            run_watcher(
-                app_handle,
-                pool,
-                email_id,
-                folder,
-                interval_secs,
-                cancel_rx,
-                watchers_clone,
-            ).await;
+            run_watcher(watcher, initial_delay, cancel_rx).await;
         });
+    }
 
-        Ok(())
+    /// 恢复已暂停的监听器（凭据更新后调用）
+    pub async fn resume_watcher(&self, email_id: i64) {
+        let watchers = self.watchers.lock().await;
+        if let Some(state) = watchers.get(&email_id) {
+            if state.info.status == WatcherStatus::Paused {
+                state.resume.notify_one();
+            }
+        }
     }
 
-    /// 停止邮件监听器
-    pub async fn stop_watcher(&self, email_id: i64) -> Result<(), String> {
-        let mut watchers = self.watchers.lock().await;
+    /// 暂停全部监听（正在进行的检查会完成），恢复前不再收件
+    pub fn suspend_all(&self) {
+        self.suspended.send_replace(true);
+    }
 
-        if let Some(state) = watchers.get_mut(&email_id) {
-            if state.running {
+    /// 恢复全部监听，已到期的监听立即检查
+    pub fn resume_all(&self) {
+        self.suspended.send_replace(false);
+    }
+
+    /// 是否已暂停全部监听
+    pub fn is_suspended(&self) -> bool {
+        *self.suspended.borrow()
+    }
+
+    /// 让指定邮箱的监听器立即检查一次（因凭据失效暂停的除外），返回触发的数量
+    pub async fn check_now(&self, email_ids: &[i64]) -> usize {
+        self.wake_watchers(|email_id| email_ids.contains(&email_id)).await
+    }
+
+    async fn wake_watchers(&self, include: impl Fn(i64) -> bool) -> usize {
+        let watchers = self.watchers.lock().await;
+        let mut count = 0;
+        for (email_id, state) in watchers.iter() {
+            if include(*email_id) && state.info.status != WatcherStatus::Paused {
+                state.wake.notify_one();
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// 停止邮件监听器
+    ///
+    /// persist 为 true 时同时关闭持久化的监听设置；为 false 时仅停止临时监听，
+    /// 若该邮箱开启了持久化监听则恢复为持久化设置
+    pub async fn stop_watcher(
+        &self,
+        app_handle: AppHandle,
+        pool: Pool<Sqlite>,
+        email_id: i64,
+        persist: bool,
+    ) -> Result<(), String> {
+        {
+            let mut watchers = self.watchers.lock().await;
+            if let Some(mut state) = watchers.remove(&email_id) {
                 if let Some(tx) = state.cancel_tx.take() {
                     let _ = tx.send(());
                 }
-                state.running = false;
             }
         }
 
-        watchers.remove(&email_id);
+        if persist {
+            sqlx::query(
+                "UPDATE emails SET enable_realtime_check = 0, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            )
+            .bind(email_id)
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("保存监听设置失败: {}", e))?;
+            return Ok(());
+        }
+
+        let settings = load_watch_settings(&pool)
+            .await
+            .map_err(|e| format!("读取监听设置失败: {}", e))?;
+        if let Some((_, folders, interval_secs)) =
+            settings.into_iter().find(|(id, _, _)| *id == email_id)
+        {
+            let initial_delay = jittered_interval(interval_secs);
+            self.spawn_watcher(
+                app_handle,
+                pool,
+                email_id,
+                folders,
+                interval_secs,
+                initial_delay,
+            )
+            .await;
+        }
+
         Ok(())
     }
 
     /// 检查监听器是否正在运行
     pub async fn is_running(&self, email_id: i64) -> bool {
         let watchers = self.watchers.lock().await;
-        watchers.get(&email_id).map(|s| s.running).unwrap_or(false)
+        watchers.contains_key(&email_id)
+    }
+
+    /// 获取所有监听器信息
+    pub async fn list_watchers(&self) -> Vec<WatcherInfo> {
+        let watchers = self.watchers.lock().await;
+        let mut list: Vec<WatcherInfo> = watchers.values().map(|s| s.info.clone()).collect();
+        list.sort_by_key(|info| info.email_id);
+        list
     }
 }
 
-/// 运行监听器的后台任务
-async fn run_watcher(
+/// 读取需要恢复的监听设置 (email_id, folders, interval_secs)
+async fn load_watch_settings(pool: &Pool<Sqlite>) -> anyhow::Result<Vec<(i64, Vec<String>, u64)>> {
+    let rows = sqlx::query_as::<_, (i64, Option<String>, Option<i64>, Option<String>)>(
+        "SELECT id, watch_folders, watch_interval_secs, default_folder FROM emails WHERE enable_realtime_check = 1 AND deleted_at IS NULL AND COALESCE(mail_type, 'outlook') != ?",
+    )
+    .bind(archive::MAIL_TYPE_LOCAL)
+    .fetch_all(pool)
+    .await?;
+
+    let default_interval = settings::current().watcher.default_interval_secs;
+    Ok(rows
+        .into_iter()
+        .map(|(email_id, folders, interval_secs, default_folder)| {
+            let folders = folders
+                .and_then(|f| serde_json::from_str::<Vec<String>>(&f).ok())
+                .filter(|f| !f.is_empty())
+                .unwrap_or_else(|| vec![default_folder.unwrap_or_else(|| "INBOX".to_string())]);
+            let interval_secs = interval_secs
+                .filter(|i| *i > 0)
+                .map(|i| i as u64)
+                .unwrap_or(default_interval);
+            (email_id, folders, interval_secs)
+        })
+        .collect())
+}
+
+/// 读取邮箱已保存的持久化监听文件夹（未开启持久化监听时为空）
+pub async fn saved_folders(pool: &Pool<Sqlite>, email_id: i64) -> anyhow::Result<Vec<String>> {
+    let folders = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT watch_folders FROM emails WHERE id = ? AND enable_realtime_check = 1",
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await?
+    .flatten()
+    .and_then(|f| serde_json::from_str::<Vec<String>>(&f).ok())
+    .unwrap_or_default();
+    Ok(folders)
+}
+
+/// 保存并开启持久化监听设置
+async fn save_watch_settings(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    folders: &[String],
+    interval_secs: u64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "UPDATE emails SET enable_realtime_check = 1, watch_folders = ?, watch_interval_secs = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(serde_json::to_string(folders)?)
+    .bind(interval_secs as i64)
+    .bind(email_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 计算带抖动的轮询间隔
+fn jittered_interval(interval_secs: u64) -> Duration {
+    let base = interval_secs as f64;
+    let jitter = rand::thread_rng().gen_range(-INTERVAL_JITTER_RATIO..=INTERVAL_JITTER_RATIO);
+    Duration::from_secs_f64((base * (1.0 + jitter)).max(1.0))
+}
+
+/// 计算第 failures 次连续失败后的退避基准秒数（不含抖动）
+fn backoff_secs(interval_secs: u64, failures: u32) -> u64 {
+    let cap = MAX_BACKOFF_SECS.max(interval_secs);
+    let factor = 1u64 << failures.min(16);
+    interval_secs.max(1).saturating_mul(factor).min(cap)
+}
+
+/// 计算带抖动的退避等待时间，并遵循服务端 Retry-After
+fn backoff_delay(interval_secs: u64, failures: u32, retry_after: Option<u64>) -> Duration {
+    let base = backoff_secs(interval_secs, failures) as f64;
+    // 等量抖动：在 [base/2, base] 之间随机
+    let secs = base / 2.0 + rand::thread_rng().gen_range(0.0..=base / 2.0);
+    let secs = secs.max(retry_after.unwrap_or(0) as f64);
+    Duration::from_secs_f64(secs.max(1.0))
+}
+
+/// 从错误中提取 Graph 返回的 Retry-After
+fn retry_after_of(err: &anyhow::Error) -> Option<u64> {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<GraphApiError>())
+        .find_map(|e| e.retry_after)
+}
+
+/// 判断错误是否无法通过重试恢复，返回暂停原因
+fn non_retryable_reason(err: &anyhow::Error) -> Option<HealthStatus> {
+    match health::classify_error(err, false) {
+        Some(
+            status @ (HealthStatus::InvalidGrant
+            | HealthStatus::TokenExpired
+            | HealthStatus::Locked
+            | HealthStatus::NeedsConsent),
+        ) => Some(status),
+        _ => None,
+    }
+}
+
+/// 读取账号凭据解密后的摘要，用于判断暂停期间凭据是否已更新
+/// （启用保险库或修改主密码会重新加密凭据，密文变化但摘要不变）
+async fn load_credentials_digest(pool: &Pool<Sqlite>, email_id: i64) -> Option<Vec<u8>> {
+    let (client_id, refresh_token) = sqlx::query_as::<_, (String, String)>(
+        "SELECT client_id, refresh_token FROM emails WHERE id = ?",
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await
+    .ok()
+    .flatten()?;
+    let client_id = Zeroizing::new(vault::open(&client_id).ok()?);
+    let refresh_token = Zeroizing::new(vault::open(&refresh_token).ok()?);
+
+    let mut hasher = Sha256::new();
+    hasher.update(client_id.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(refresh_token.as_bytes());
+    Some(hasher.finalize().to_vec())
+}
+
+/// 计算恢复时的随机首次延迟（不超过一个轮询间隔）
+fn random_initial_delay(interval_secs: u64) -> Duration {
+    Duration::from_secs_f64(rand::thread_rng().gen_range(0.0..interval_secs.max(1) as f64))
+}
+
+/// 更新监听器展示信息（仅当代次匹配时）
+async fn update_info(
+    watchers: &WatcherMap,
+    email_id: i64,
+    generation: u64,
+    update: impl FnOnce(&mut WatcherInfo),
+) {
+    let mut watchers = watchers.lock().await;
+    if let Some(state) = watchers.get_mut(&email_id) {
+        if state.generation == generation {
+            update(&mut state.info);
+        }
+    }
+}
+
+/// 单个监听任务的运行参数
+struct WatcherTask {
     app_handle: AppHandle,
     pool: Pool<Sqlite>,
     email_id: i64,
-    folder: String,
+    generation: u64,
+    folders: Vec<String>,
     interval_secs: u64,
+    watchers: WatcherMap,
+    check_permits: Arc<Semaphore>,
+    resume: Arc<Notify>,
+    wake: Arc<Notify>,
+    suspended: watch::Receiver<bool>,
+}
+
+impl WatcherTask {
+    /// 更新展示信息
+    async fn update_info(&self, update: impl FnOnce(&mut WatcherInfo)) {
+        update_info(&self.watchers, self.email_id, self.generation, update).await;
+    }
+
+    /// 已暂停全部监听时等待恢复，收到停止信号时返回 false
+    async fn wait_while_suspended(
+        &self,
+        cancel_rx: &mut tokio::sync::oneshot::Receiver<()>,
+    ) -> bool {
+        let mut suspended = self.suspended.clone();
+        if !*suspended.borrow_and_update() {
+            return true;
+        }
+
+        self.update_info(|info| {
+            info.status = WatcherStatus::Suspended;
+            info.next_run = None;
+        })
+        .await;
+        wait_until_unsuspended(&mut suspended, cancel_rx).await
+    }
+
+    /// 暂停直到凭据更新或收到恢复信号，收到停止信号时返回 false
+    async fn wait_for_resume(&self, cancel_rx: &mut tokio::sync::oneshot::Receiver<()>) -> bool {
+        let paused_credentials = load_credentials_digest(&self.pool, self.email_id).await;
+
+        loop {
+            tokio::select! {
+                _ = self.resume.notified() => return true,
+                _ = tokio::time::sleep(Duration::from_secs(PAUSED_RECHECK_SECS)) => {
+                    let current = load_credentials_digest(&self.pool, self.email_id).await;
+                    if current.is_some() && current != paused_credentials {
+                        log::info!("检测到凭据已更新，恢复监听: email_id={}", self.email_id);
+                        return true;
+                    }
+                }
+                _ = &mut *cancel_rx => return false,
+            }
+        }
+    }
+}
+
+/// 等待全部监听恢复，收到停止信号时返回 false
+async fn wait_until_unsuspended(
+    suspended: &mut watch::Receiver<bool>,
+    cancel_rx: &mut tokio::sync::oneshot::Receiver<()>,
+) -> bool {
+    loop {
+        tokio::select! {
+            changed = suspended.changed() => {
+                if changed.is_err() {
+                    return false;
+                }
+                if !*suspended.borrow_and_update() {
+                    return true;
+                }
+            }
+            _ = &mut *cancel_rx => return false,
+        }
+    }
+}
+
+/// 运行监听器的后台任务
+async fn run_watcher(
+    task: WatcherTask,
+    initial_delay: Duration,
     mut cancel_rx: tokio::sync::oneshot::Receiver<()>,
-    watchers: Arc<Mutex<HashMap<i64, WatcherState>>>,
 ) {
-    log::info!("邮件监听器启动: email_id={}, folder={}, interval={}s", email_id, folder, interval_secs);
+    let email_id = task.email_id;
+    let app_handle = &task.app_handle;
+
+    log::info!("邮件监听器启动: email_id={}, folders={:?}, interval={}s", email_id, task.folders, task.interval_secs);
 
     // 发送启动事件
     let _ = app_handle.emit("mail-watcher-started", serde_json::json!({
         "email_id": email_id,
-        "folder": folder,
+        "folders": task.folders,
     }));
 
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(interval_secs));
+    let mut delay = initial_delay;
+    let mut failures = 0u32;
 
     loop {
-        tokio::select! {
-            _ = interval.tick() => {
-                // 发送进度事件
-                let _ = app_handle.emit("mail-progress", MailProgressEvent {
-                    email_id,
-                    progress: 10,
-                    message: "正在检查新邮件...".to_string(),
-                });
-
-                // 执行收件
-                match email::check_outlook_email(&pool, email_id, &folder).await {
-                    Ok(result) => {
-                        log::info!("邮件检查完成: email_id={}, fetched={}, saved={}, deleted={}",
-                            email_id, result.fetched, result.saved, result.deleted);
-
-                        // 获取最新的邮件列表
-                        let records = match email::get_mail_records(&pool, email_id).await {
-                            Ok(r) => r,
-                            Err(e) => {
-                                log::error!("获取邮件记录失败: {}", e);
-                                vec![]
-                            }
-                        };
-
-                        // 过滤当前文件夹的邮件
-                        let folder_records: Vec<MailRecord> = records
-                            .into_iter()
-                            .filter(|r| {
-                                r.folder.as_ref()
-                                    .map(|f| normalize_folder(f) == normalize_folder(&folder))
-                                    .unwrap_or(false)
-                            })
-                            .collect();
-
-                        // 发送更新事件
-                        let _ = app_handle.emit("mail-updated", MailUpdateEvent {
-                            email_id,
-                            folder: folder.clone(),
-                            new_count: result.saved,
-                            deleted_count: result.deleted,
-                            records: folder_records,
-                            message: result.message,
-                        });
-                    }
-                    Err(e) => {
-                        log::error!("邮件检查失败: email_id={}, error={}", email_id, e);
-
-                        // 发送错误事件
-                        let _ = app_handle.emit("mail-error", serde_json::json!({
-                            "email_id": email_id,
-                            "folder": folder,
-                            "error": e.to_string(),
-                        }));
-                    }
-                }
+        let next_run: DateTime<Utc> =
+            Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+        task.update_info(|info| {
+            if info.status != WatcherStatus::BackingOff {
+                info.status = WatcherStatus::Waiting;
+            }
+            info.next_run = Some(next_run.to_rfc3339());
+        })
+        .await;
 
-                // 发送进度完成事件
-                let _ = app_handle.emit("mail-progress", MailProgressEvent {
-                    email_id,
-                    progress: 100,
-                    message: "检查完成".to_string(),
-                });
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = task.wake.notified() => {}
+            _ = &mut cancel_rx => {
+                log::info!("邮件监听器收到停止信号: email_id={}", email_id);
+                break;
             }
+        }
+
+        if !task.wait_while_suspended(&mut cancel_rx).await {
+            log::info!("邮件监听器收到停止信号: email_id={}", email_id);
+            break;
+        }
+
+        // 等待全局收件名额
+        let permit = tokio::select! {
+            permit = task.check_permits.clone().acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => break,
+            },
             _ = &mut cancel_rx => {
                 log::info!("邮件监听器收到停止信号: email_id={}", email_id);
                 break;
             }
+        };
+
+        task.update_info(|info| {
+            info.status = WatcherStatus::Checking;
+            info.next_run = None;
+        })
+        .await;
+
+        let last_error = check_folders(app_handle, &task.pool, email_id, &task.folders)
+            .await
+            .err();
+        drop(permit);
+
+        let last_run = Utc::now().to_rfc3339();
+        let error_message = last_error.as_ref().map(|e| e.to_string());
+        task.update_info(|info| {
+            info.last_run = Some(last_run);
+            info.last_error = error_message;
+        })
+        .await;
+
+        let Some(err) = last_error else {
+            failures = 0;
+            task.update_info(|info| {
+                info.status = WatcherStatus::Waiting;
+                info.consecutive_failures = 0;
+            })
+            .await;
+            delay = jittered_interval(task.interval_secs);
+            continue;
+        };
+
+        // 凭据类错误重试无意义，暂停监听直到凭据更新
+        if let Some(status) = non_retryable_reason(&err) {
+            let reason = format!("{}: {}", status.as_str(), err);
+            log::warn!("邮件监听器已暂停: email_id={}, reason={}", email_id, reason);
+            task.update_info(|info| {
+                info.status = WatcherStatus::Paused;
+                info.paused_reason = Some(reason.clone());
+            })
+            .await;
+            let _ = app_handle.emit("mail-watcher-paused", serde_json::json!({
+                "email_id": email_id,
+                "reason": reason,
+            }));
+
+            if !task.wait_for_resume(&mut cancel_rx).await {
+                log::info!("邮件监听器收到停止信号: email_id={}", email_id);
+                break;
+            }
+
+            failures = 0;
+            task.update_info(|info| {
+                info.status = WatcherStatus::Waiting;
+                info.paused_reason = None;
+                info.consecutive_failures = 0;
+            })
+            .await;
+            let _ = app_handle.emit("mail-watcher-resumed", serde_json::json!({
+                "email_id": email_id,
+            }));
+            delay = Duration::ZERO;
+            continue;
         }
+
+        failures += 1;
+        let retry_after = retry_after_of(&err);
+        delay = backoff_delay(task.interval_secs, failures, retry_after);
+        log::warn!(
+            "邮件监听器退避: email_id={}, failures={}, retry_after={:?}, delay={:?}",
+            email_id,
+            failures,
+            retry_after,
+            delay
+        );
+        task.update_info(|info| {
+            info.status = WatcherStatus::BackingOff;
+            info.consecutive_failures = failures;
+        })
+        .await;
     }
 
-    // 清理状态
-    let mut watchers_guard = watchers.lock().await;
-    watchers_guard.remove(&email_id);
+    // 清理状态（新任务已替换时不清理）
+    {
+        let mut watchers_guard = task.watchers.lock().await;
+        if watchers_guard
+            .get(&email_id)
+            .map(|s| s.generation == task.generation)
+            .unwrap_or(false)
+        {
+            watchers_guard.remove(&email_id);
+        }
+    }
 
     // 发送停止事件
     let _ = app_handle.emit("mail-watcher-stopped", serde_json::json!({
         "email_id": email_id,
-        "folder": folder,
+        "folders": task.folders,
     }));
 
     log::info!("邮件监听器已停止: email_id={}", email_id);
 }
 
-/// 标准化文件夹名称
-fn normalize_folder(folder: &str) -> String {
-    let normalized = folder.trim().to_lowercase();
-    if normalized.contains("junk") || normalized.contains("spam") || normalized.contains("垃圾") {
-        "junk".to_string()
-    } else {
-        "inbox".to_string()
+/// 检查所有监听的文件夹（共用一次令牌获取），逐个文件夹发送事件，失败时返回首个错误
+async fn check_folders(
+    app_handle: &AppHandle,
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    folders: &[String],
+) -> anyhow::Result<()> {
+    // 发送进度事件
+    let _ = app_handle.emit("mail-progress", MailProgressEvent {
+        email_id,
+        progress: 10,
+        message: "正在检查新邮件...".to_string(),
+    });
+
+    // 执行收件
+    let outcome = match email::check_outlook_folders(pool, email_id, folders).await {
+        Ok(results) => {
+            // 获取最新的邮件列表
+            let records = match email::get_mail_records(pool, email_id).await {
+                Ok(r) => r,
+                Err(e) => {
+                    log::error!("获取邮件记录失败: {}", e);
+                    vec![]
+                }
+            };
+
+            let mut first_error = None;
+            for (folder, result) in results {
+                match result {
+                    Ok(result) => {
+                        log::info!("邮件检查完成: email_id={}, folder={}, fetched={}, saved={}, deleted={}",
+                            email_id, folder, result.fetched, result.saved, result.deleted);
+                        let new_mail_ids = result.new_mail_ids.clone();
+                        emit_folder_updated(app_handle, email_id, &folder, &records, result);
+                        if let Err(e) =
+                            notifications::notify_new_mails(pool, email_id, &new_mail_ids).await
+                        {
+                            log::warn!("发送新邮件通知失败: email_id={}, error={}", email_id, e);
+                        }
+                    }
+                    Err(e) => {
+                        emit_folder_error(app_handle, email_id, &folder, &e);
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+            first_error.map_or(Ok(()), Err)
+        }
+        Err(e) => {
+            // 账号级失败（如刷新令牌失败），所有文件夹均视为失败
+            for folder in folders {
+                emit_folder_error(app_handle, email_id, folder, &e);
+            }
+            Err(e)
+        }
+    };
+
+    // 发送进度完成事件
+    let _ = app_handle.emit("mail-progress", MailProgressEvent {
+        email_id,
+        progress: 100,
+        message: "检查完成".to_string(),
+    });
+
+    outcome
+}
+
+/// 发送单个文件夹的更新事件
+fn emit_folder_updated(
+    app_handle: &AppHandle,
+    email_id: i64,
+    folder: &str,
+    records: &[MailRecord],
+    result: CheckResult,
+) {
+    // 过滤当前文件夹的邮件
+    let folder_records: Vec<MailRecord> = records
+        .iter()
+        .filter(|r| {
+            r.folder.as_ref()
+                .map(|f| canonical_folder_key(f) == canonical_folder_key(folder))
+                .unwrap_or(false)
+        })
+        .cloned()
+        .collect();
+
+    let _ = app_handle.emit("mail-updated", MailUpdateEvent {
+        email_id,
+        folder: folder.to_string(),
+        new_count: result.saved,
+        deleted_count: result.deleted,
+        records: folder_records,
+        message: result.message,
+    });
+}
+
+/// 发送单个文件夹的错误事件
+fn emit_folder_error(app_handle: &AppHandle, email_id: i64, folder: &str, err: &anyhow::Error) {
+    log::error!("邮件检查失败: email_id={}, folder={}, error={}", email_id, folder, err);
+
+    // 发送错误事件
+    let _ = app_handle.emit("mail-error", serde_json::json!({
+        "email_id": email_id,
+        "folder": folder,
+        "error": err.to_string(),
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_secs() {
+        assert_eq!(backoff_secs(60, 1), 120);
+        assert_eq!(backoff_secs(60, 3), 480);
+        assert_eq!(backoff_secs(60, 10), MAX_BACKOFF_SECS);
+        // 间隔本身超过上限时不缩短
+        assert_eq!(backoff_secs(3600, 2), 3600);
+    }
+
+    #[test]
+    fn test_backoff_delay_honours_retry_after() {
+        let delay = backoff_delay(5, 1, Some(300));
+        assert!(delay >= Duration::from_secs(300));
+        let delay = backoff_delay(60, 1, None);
+        assert!(delay >= Duration::from_secs(60) && delay <= Duration::from_secs(120));
+    }
+
+    /// 插入一个不运行后台任务的监听器状态，返回其立即检查信号
+    async fn insert_watcher(
+        manager: &MailWatcherManager,
+        email_id: i64,
+        status: WatcherStatus,
+    ) -> Arc<Notify> {
+        let wake = Arc::new(Notify::new());
+        manager.watchers.lock().await.insert(email_id, WatcherState {
+            generation: email_id as u64,
+            info: WatcherInfo {
+                email_id,
+                folders: vec!["INBOX".to_string()],
+                interval_secs: 60,
+                status,
+                last_run: None,
+                next_run: None,
+                last_error: None,
+                consecutive_failures: 0,
+                paused_reason: None,
+            },
+            cancel_tx: None,
+            resume: Arc::new(Notify::new()),
+            wake: wake.clone(),
+        });
+        wake
+    }
+
+    async fn is_woken(wake: &Notify) -> bool {
+        tokio::time::timeout(Duration::from_millis(50), wake.notified())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_check_now_wakes_only_selected_watchers() {
+        let manager = MailWatcherManager::new();
+        let mine = insert_watcher(&manager, 1, WatcherStatus::Waiting).await;
+        let paused = insert_watcher(&manager, 2, WatcherStatus::Paused).await;
+        let other = insert_watcher(&manager, 3, WatcherStatus::BackingOff).await;
+
+        // 因凭据失效暂停的监听器不唤醒，其他邮箱的监听器不受影响
+        assert_eq!(manager.check_now(&[1, 2]).await, 1);
+        assert!(is_woken(&mine).await);
+        assert!(!is_woken(&paused).await);
+        assert!(!is_woken(&other).await);
+
+        assert_eq!(manager.check_now(&[1, 3]).await, 2);
+        assert!(is_woken(&mine).await);
+        assert!(is_woken(&other).await);
+    }
+
+    #[tokio::test]
+    async fn test_suspend_and_resume_all() {
+        let manager = MailWatcherManager::new();
+        assert!(!manager.is_suspended());
+        manager.suspend_all();
+        assert!(manager.is_suspended());
+
+        let mut suspended = manager.suspended.subscribe();
+        assert!(*suspended.borrow_and_update());
+        let (_cancel_tx, mut cancel_rx) = tokio::sync::oneshot::channel();
+        let waiting = tokio::spawn(async move {
+            wait_until_unsuspended(&mut suspended, &mut cancel_rx).await
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        manager.resume_all();
+        assert!(!manager.is_suspended());
+        assert!(waiting.await.unwrap());
+
+        // 暂停期间收到停止信号
+        manager.suspend_all();
+        let mut suspended = manager.suspended.subscribe();
+        suspended.borrow_and_update();
+        let (cancel_tx, mut cancel_rx) = tokio::sync::oneshot::channel();
+        cancel_tx.send(()).unwrap();
+        assert!(!wait_until_unsuspended(&mut suspended, &mut cancel_rx).await);
+    }
+
+    #[tokio::test]
+    async fn test_saved_folders() {
+        let pool = crate::db::test_pool().await;
+        let email_id: i64 = sqlx::query_scalar(
+            "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES ('a@x.com', '', 'cid', '', 1) RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert!(saved_folders(&pool, email_id).await.unwrap().is_empty());
+
+        let folders = vec!["INBOX".to_string(), "Junk".to_string()];
+        save_watch_settings(&pool, email_id, &folders, 60).await.unwrap();
+        assert_eq!(saved_folders(&pool, email_id).await.unwrap(), folders);
+
+        // 关闭持久化监听后不再返回已保存的列表
+        sqlx::query("UPDATE emails SET enable_realtime_check = 0 WHERE id = ?")
+            .bind(email_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(saved_folders(&pool, email_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_local_accounts_are_not_watched() {
+        let pool = crate::db::test_pool().await;
+        let remote = email::add_email(&pool, 1, "a@x.com", "", "cid", "", None)
+            .await
+            .unwrap();
+        let local_type = Some(archive::MAIL_TYPE_LOCAL);
+        let local = email::add_email(&pool, 1, "archive", "", "", "", local_type)
+            .await
+            .unwrap();
+        let folders = vec!["INBOX".to_string()];
+        save_watch_settings(&pool, remote, &folders, 60).await.unwrap();
+        save_watch_settings(&pool, local, &folders, 60).await.unwrap();
+
+        let restored: Vec<i64> = load_watch_settings(&pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(email_id, _, _)| email_id)
+            .collect();
+        assert_eq!(restored, vec![remote]);
+
+        let filter = email::EmailFilter {
+            user_id: Some(1),
+            remote_only: true,
+            ..Default::default()
+        };
+        let email_ids = email::resolve_email_ids(&pool, &[local, remote], &filter)
+            .await
+            .unwrap();
+        assert_eq!(email_ids, vec![remote]);
+        assert!(archive::is_local_account(&pool, local).await.unwrap());
+        assert!(!archive::is_local_account(&pool, remote).await.unwrap());
     }
 }
@@ -0,0 +1,227 @@
+//! 数据库维护模块
+//! 完整性检查、VACUUM、ANALYZE，以及数据库与附件占用空间的统计（整体与按账号）
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+
+/// 完整性检查结果
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    /// integrity_check 的输出（正常时为 ["ok"]）
+    pub messages: Vec<String>,
+    /// 违反外键约束的记录数
+    pub foreign_key_violations: usize,
+}
+
+/// VACUUM 结果
+#[derive(Debug, Serialize)]
+pub struct VacuumResult {
+    pub size_before: i64,
+    pub size_after: i64,
+}
+
+/// 账号占用空间
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AccountStorage {
+    pub email_id: i64,
+    pub email: String,
+    pub mail_count: i64,
+    /// 邮件主题、发件人与正文的字节数
+    pub content_bytes: i64,
+    pub attachment_count: i64,
+    /// 已下载到本地的附件字节数（相同内容在多个账号间共享时分别计入）
+    pub attachment_bytes: i64,
+    /// 尚未下载的附件字节数
+    pub pending_attachment_bytes: i64,
+}
+
+/// 存储占用报告
+#[derive(Debug, Serialize)]
+pub struct StorageReport {
+    /// 数据库文件大小（页数 × 页大小）
+    pub database_bytes: i64,
+    /// 数据库中可回收的空闲页大小
+    pub free_bytes: i64,
+    /// 附件存储目录中的内容总大小（去重后）
+    pub blob_bytes: i64,
+    pub blob_count: i64,
+    pub accounts: Vec<AccountStorage>,
+}
+
+/// 执行 PRAGMA integrity_check 与 foreign_key_check
+pub async fn integrity_check(pool: &Pool<Sqlite>) -> Result<IntegrityReport> {
+    let messages = sqlx::query_scalar::<_, String>("PRAGMA integrity_check")
+        .fetch_all(pool)
+        .await?;
+    let foreign_key_violations = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(pool)
+        .await?
+        .len();
+
+    Ok(IntegrityReport {
+        ok: messages == ["ok"] && foreign_key_violations == 0,
+        messages,
+        foreign_key_violations,
+    })
+}
+
+/// 整理数据库文件，回收空闲页
+pub async fn vacuum(pool: &Pool<Sqlite>) -> Result<VacuumResult> {
+    let size_before = database_size(pool).await?;
+    sqlx::query("VACUUM").execute(pool).await?;
+    let size_after = database_size(pool).await?;
+
+    log::info!(
+        "数据库整理完成: size_before={}, size_after={}",
+        size_before,
+        size_after
+    );
+    Ok(VacuumResult {
+        size_before,
+        size_after,
+    })
+}
+
+/// 更新查询优化器的统计信息
+pub async fn analyze(pool: &Pool<Sqlite>) -> Result<()> {
+    sqlx::query("ANALYZE").execute(pool).await?;
+    sqlx::query("PRAGMA optimize").execute(pool).await?;
+    Ok(())
+}
+
+/// 统计数据库与附件的占用空间（按账号统计时只包含该用户的账号）
+pub async fn storage_report(pool: &Pool<Sqlite>, user_id: i64) -> Result<StorageReport> {
+    let page_size = pragma_i64(pool, "PRAGMA page_size").await?;
+    let free_pages = pragma_i64(pool, "PRAGMA freelist_count").await?;
+    let (blob_count, blob_bytes) = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM attachment_blobs",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let accounts = sqlx::query_as::<_, AccountStorage>(
+        r#"SELECT e.id AS email_id, e.email,
+    COALESCE(m.mail_count, 0) AS mail_count,
+    COALESCE(m.content_bytes, 0) AS content_bytes,
+    COALESCE(a.attachment_count, 0) AS attachment_count,
+    COALESCE(a.attachment_bytes, 0) AS attachment_bytes,
+    COALESCE(a.pending_attachment_bytes, 0) AS pending_attachment_bytes
+FROM emails e
+LEFT JOIN (
+    SELECT email_id, COUNT(*) AS mail_count,
+        SUM(COALESCE(LENGTH(CAST(subject AS BLOB)), 0) + COALESCE(LENGTH(CAST(sender AS BLOB)), 0) + COALESCE(LENGTH(CAST(content AS BLOB)), 0)) AS content_bytes
+    FROM mail_records GROUP BY email_id
+) m ON m.email_id = e.id
+LEFT JOIN (
+    SELECT r.email_id, COUNT(*) AS attachment_count,
+        SUM(CASE WHEN a.blob_hash IS NOT NULL THEN COALESCE(a.size, 0) ELSE 0 END) AS attachment_bytes,
+        SUM(CASE WHEN a.blob_hash IS NULL THEN COALESCE(a.size, 0) ELSE 0 END) AS pending_attachment_bytes
+    FROM attachments a JOIN mail_records r ON r.id = a.mail_id GROUP BY r.email_id
+) a ON a.email_id = e.id
+WHERE e.user_id = ?
+ORDER BY e.id"#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(StorageReport {
+        database_bytes: database_size(pool).await?,
+        free_bytes: free_pages * page_size,
+        blob_bytes,
+        blob_count,
+        accounts,
+    })
+}
+
+async fn database_size(pool: &Pool<Sqlite>) -> Result<i64> {
+    let page_count = pragma_i64(pool, "PRAGMA page_count").await?;
+    let page_size = pragma_i64(pool, "PRAGMA page_size").await?;
+    Ok(page_count * page_size)
+}
+
+async fn pragma_i64(pool: &Pool<Sqlite>, pragma: &str) -> Result<i64> {
+    Ok(sqlx::query_scalar::<_, i64>(pragma).fetch_one(pool).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+
+    #[tokio::test]
+    async fn test_storage_report_and_vacuum() {
+        let path = std::env::temp_dir().join(format!("maintenance-{}.db", rand::random::<u32>()));
+        let pool = db::test_file_pool(&path).await;
+        sqlx::query(
+            "INSERT INTO users (id, username, password_hash, salt, rounds) VALUES (2, 'other', '', '', 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let mut email_ids = Vec::new();
+        for (email, user_id) in [("a@x.com", 1), ("b@x.com", 2)] {
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES (?, '', 'cid', '', ?) RETURNING id",
+            )
+            .bind(email)
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            email_ids.push(id);
+        }
+        let mail_id: i64 = sqlx::query_scalar(
+            "INSERT INTO mail_records (email_id, subject, sender, content, folder) VALUES (?, 'hi', 'x@y.com', ?, 'inbox') RETURNING id",
+        )
+        .bind(email_ids[0])
+        .bind("x".repeat(100_000))
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let hash = "ab".repeat(32);
+        sqlx::query("INSERT INTO attachment_blobs (hash, size) VALUES (?, 10)")
+            .bind(&hash)
+            .execute(&pool)
+            .await
+            .unwrap();
+        for blob_hash in [Some(hash.as_str()), None] {
+            sqlx::query(
+                "INSERT INTO attachments (mail_id, filename, size, blob_hash) VALUES (?, 'a.txt', 10, ?)",
+            )
+            .bind(mail_id)
+            .bind(blob_hash)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let report = storage_report(&pool, 1).await.unwrap();
+        assert_eq!((report.blob_count, report.blob_bytes), (1, 10));
+        assert_eq!(report.accounts.len(), 1);
+        let account = &report.accounts[0];
+        assert_eq!(account.email_id, email_ids[0]);
+        assert_eq!(account.mail_count, 1);
+        assert_eq!(account.content_bytes, 2 + 7 + 100_000);
+        assert_eq!(account.attachment_count, 2);
+        assert_eq!(account.attachment_bytes, 10);
+        assert_eq!(account.pending_attachment_bytes, 10);
+
+        assert!(integrity_check(&pool).await.unwrap().ok);
+
+        // 删除正文后空闲页由 VACUUM 回收
+        sqlx::query("UPDATE mail_records SET content = NULL")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(storage_report(&pool, 1).await.unwrap().free_bytes > 0);
+        let result = vacuum(&pool).await.unwrap();
+        assert!(result.size_after < result.size_before);
+        assert_eq!(storage_report(&pool, 1).await.unwrap().free_bytes, 0);
+
+        pool.close().await;
+        let _ = std::fs::remove_file(path);
+    }
+}
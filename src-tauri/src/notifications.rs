@@ -0,0 +1,556 @@
+//! 桌面通知模块
+//! 监听器收到新邮件（mail-updated 且 new_count > 0）时弹出系统通知，显示发件人、主题与提取到的验证码。
+//! 支持按账号、按发件人静音（可设置到期时间）和免打扰时段；同一时间窗口内的通知先缓冲，
+//! 数量超过阈值时合并为一条汇总通知，避免批量收件时刷屏。收件规则的通知动作也经由此模块发送
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+use crate::rules;
+
+/// 用于发送系统通知与前端事件
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// 等待合并的通知
+static PENDING: Mutex<Vec<PendingNotice>> = Mutex::new(Vec::new());
+
+/// 合并通知的时间窗口（毫秒）
+const GROUP_WINDOW_MS: u64 = 1500;
+
+/// 默认合并阈值：同一窗口内超过该数量的通知合并为一条
+const DEFAULT_GROUP_THRESHOLD: i64 = 3;
+
+/// 合并阈值上限
+const MAX_GROUP_THRESHOLD: i64 = 50;
+
+/// 汇总通知中列出的邮件数
+const GROUP_PREVIEW_LINES: usize = 5;
+
+/// 通知正文中主题的最大长度（字符）
+const MAX_SUBJECT_CHARS: usize = 80;
+
+/// 通知设置
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// 免打扰开始时间（HH:MM，本地时间）
+    pub quiet_start: Option<String>,
+    /// 免打扰结束时间（HH:MM，本地时间），早于开始时间表示跨零点
+    pub quiet_end: Option<String>,
+    pub group_threshold: i64,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            quiet_start: None,
+            quiet_end: None,
+            group_threshold: DEFAULT_GROUP_THRESHOLD,
+        }
+    }
+}
+
+/// 静音项
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct NotificationMute {
+    pub id: i64,
+    pub email_id: Option<i64>,
+    /// 账号邮箱地址（按账号静音时）
+    pub account: Option<String>,
+    pub sender: Option<String>,
+    pub muted_until: Option<String>,
+    pub created_at: Option<String>,
+}
+
+/// 新增静音项的参数，email_id 与 sender 二选一
+#[derive(Debug, Deserialize)]
+pub struct MuteInput {
+    pub email_id: Option<i64>,
+    /// 完整发件地址或 @域名
+    pub sender: Option<String>,
+    /// 静音时长（分钟），为空表示一直静音
+    pub minutes: Option<i64>,
+}
+
+/// 待发送的新邮件通知
+#[derive(Debug, Clone)]
+struct PendingNotice {
+    user_id: i64,
+    group_threshold: i64,
+    account: String,
+    sender: Option<String>,
+    subject: Option<String>,
+    code: Option<String>,
+}
+
+/// 通知的标题与正文
+#[derive(Debug, PartialEq)]
+struct Notice {
+    title: String,
+    body: String,
+}
+
+/// 新邮件通知所需的信息
+#[derive(sqlx::FromRow)]
+struct NewMail {
+    account: String,
+    sender: Option<String>,
+    subject: Option<String>,
+    content: Option<String>,
+    code: Option<String>,
+}
+
+/// 保存应用句柄
+pub fn init(app_handle: AppHandle) {
+    let _ = APP_HANDLE.set(app_handle);
+}
+
+/// 应用句柄（尚未初始化时为 None）
+pub fn app_handle() -> Option<&'static AppHandle> {
+    APP_HANDLE.get()
+}
+
+/// 获取用户的通知设置
+pub async fn get_settings(pool: &Pool<Sqlite>, user_id: i64) -> Result<NotificationSettings> {
+    let settings = sqlx::query_as::<_, NotificationSettings>(
+        "SELECT enabled, quiet_start, quiet_end, group_threshold FROM notification_settings WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(settings.unwrap_or_default())
+}
+
+/// 保存用户的通知设置
+pub async fn set_settings(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    settings: &NotificationSettings,
+) -> Result<NotificationSettings> {
+    let quiet_start = normalize_time(settings.quiet_start.as_deref())?;
+    let quiet_end = normalize_time(settings.quiet_end.as_deref())?;
+    if quiet_start.is_some() != quiet_end.is_some() {
+        return Err(anyhow!("免打扰时段需要同时设置开始与结束时间"));
+    }
+    if !(1..=MAX_GROUP_THRESHOLD).contains(&settings.group_threshold) {
+        return Err(anyhow!("合并阈值需在 1-{} 之间", MAX_GROUP_THRESHOLD));
+    }
+
+    sqlx::query(
+        r#"INSERT INTO notification_settings (user_id, enabled, quiet_start, quiet_end, group_threshold) VALUES (?, ?, ?, ?, ?)
+ON CONFLICT(user_id) DO UPDATE SET enabled = excluded.enabled, quiet_start = excluded.quiet_start,
+    quiet_end = excluded.quiet_end, group_threshold = excluded.group_threshold, updated_at = CURRENT_TIMESTAMP"#,
+    )
+    .bind(user_id)
+    .bind(settings.enabled)
+    .bind(&quiet_start)
+    .bind(&quiet_end)
+    .bind(settings.group_threshold)
+    .execute(pool)
+    .await?;
+
+    get_settings(pool, user_id).await
+}
+
+/// 获取用户的静音项（不含已过期的）
+pub async fn list_mutes(pool: &Pool<Sqlite>, user_id: i64) -> Result<Vec<NotificationMute>> {
+    let mutes = sqlx::query_as::<_, NotificationMute>(
+        r#"SELECT m.id, m.email_id, e.email AS account, m.sender, m.muted_until, m.created_at
+FROM notification_mutes m LEFT JOIN emails e ON e.id = m.email_id
+WHERE m.user_id = ? AND (m.muted_until IS NULL OR m.muted_until > CURRENT_TIMESTAMP)
+ORDER BY m.id"#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(mutes)
+}
+
+/// 新增静音项（账号归属由命令层校验），返回静音项 ID
+pub async fn add_mute(pool: &Pool<Sqlite>, user_id: i64, input: &MuteInput) -> Result<i64> {
+    let sender = input
+        .sender
+        .as_deref()
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty());
+    match (input.email_id, &sender) {
+        (Some(_), Some(_)) | (None, None) => {
+            return Err(anyhow!("请指定账号或发件人其中之一"));
+        }
+        (None, Some(sender)) if !sender.contains('@') => {
+            return Err(anyhow!("发件人需为完整地址或 @域名"));
+        }
+        _ => {}
+    }
+    let muted_until = match input.minutes {
+        Some(minutes) if minutes <= 0 => return Err(anyhow!("静音时长必须大于 0")),
+        Some(minutes) => Some(format!("+{minutes} minutes")),
+        None => None,
+    };
+
+    // 同一对象只保留一条静音项
+    sqlx::query(
+        "DELETE FROM notification_mutes WHERE user_id = ? AND email_id IS ? AND sender IS ?",
+    )
+    .bind(user_id)
+    .bind(input.email_id)
+    .bind(&sender)
+    .execute(pool)
+    .await?;
+
+    let id = sqlx::query_scalar(
+        "INSERT INTO notification_mutes (user_id, email_id, sender, muted_until) VALUES (?, ?, ?, CASE WHEN ? IS NULL THEN NULL ELSE datetime('now', ?) END) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(input.email_id)
+    .bind(&sender)
+    .bind(&muted_until)
+    .bind(&muted_until)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// 删除静音项
+pub async fn remove_mute(pool: &Pool<Sqlite>, user_id: i64, mute_id: i64) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM notification_mutes WHERE id = ? AND user_id = ?")
+        .bind(mute_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 为新邮件发送桌面通知，返回加入通知队列的邮件数
+///
+/// 跳过已静音的账号与发件人；通知关闭或处于免打扰时段时不发送
+pub async fn notify_new_mails(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    mail_ids: &[i64],
+) -> Result<usize> {
+    if mail_ids.is_empty() {
+        return Ok(0);
+    }
+    let owner: Option<i64> = sqlx::query_scalar("SELECT user_id FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+    let Some(user_id) = owner else {
+        return Ok(0);
+    };
+
+    let settings = get_settings(pool, user_id).await?;
+    if !should_notify(&settings) {
+        return Ok(0);
+    }
+
+    let mutes: Vec<(Option<i64>, Option<String>)> = sqlx::query_as(
+        "SELECT email_id, sender FROM notification_mutes WHERE user_id = ? AND (muted_until IS NULL OR muted_until > CURRENT_TIMESTAMP)",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    if mutes.iter().any(|(id, _)| *id == Some(email_id)) {
+        return Ok(0);
+    }
+
+    // 规则已提取的验证码优先，否则用内置规则提取
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        r#"SELECT e.email AS account, m.sender, m.subject, m.content,
+    (SELECT h.extracted_code FROM mail_rule_hits h WHERE h.mail_id = m.id AND h.extracted_code IS NOT NULL ORDER BY h.id LIMIT 1) AS code
+FROM mail_records m JOIN emails e ON e.id = m.email_id
+WHERE m.deleted_at IS NULL AND m.id IN ("#,
+    );
+    let mut separated = qb.separated(", ");
+    for id in mail_ids {
+        separated.push_bind(id);
+    }
+    qb.push(") ORDER BY m.id");
+    let mails = qb.build_query_as::<NewMail>().fetch_all(pool).await?;
+
+    let muted_senders: Vec<&str> = mutes.iter().filter_map(|(_, s)| s.as_deref()).collect();
+    let notices: Vec<PendingNotice> = mails
+        .into_iter()
+        .filter(|mail| {
+            let sender = mail.sender.as_deref().unwrap_or_default();
+            !muted_senders.iter().any(|m| sender_matches(sender, m))
+        })
+        .map(|mail| {
+            let code = mail.code.or_else(|| {
+                [mail.subject.as_deref(), mail.content.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .find_map(rules::extract_default_code)
+            });
+            PendingNotice {
+                user_id,
+                group_threshold: settings.group_threshold,
+                account: mail.account,
+                sender: mail.sender,
+                subject: mail.subject,
+                code,
+            }
+        })
+        .collect();
+
+    let count = notices.len();
+    enqueue(notices);
+    Ok(count)
+}
+
+/// 发送收件规则的通知（规则是用户显式配置的，不受静音影响，但遵守通知开关与免打扰时段）
+pub async fn notify_rule(pool: &Pool<Sqlite>, user_id: i64, title: &str, body: &str) -> Result<()> {
+    let settings = get_settings(pool, user_id).await?;
+    if should_notify(&settings) {
+        show(&Notice {
+            title: title.to_string(),
+            body: body.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// 通知开启且不在免打扰时段
+fn should_notify(settings: &NotificationSettings) -> bool {
+    if !settings.enabled {
+        return false;
+    }
+    let start = settings.quiet_start.as_deref().and_then(parse_time);
+    let end = settings.quiet_end.as_deref().and_then(parse_time);
+    match (start, end) {
+        (Some(start), Some(end)) => !in_quiet_hours(chrono::Local::now().time(), start, end),
+        _ => true,
+    }
+}
+
+/// 加入通知队列，时间窗口结束后统一发送
+fn enqueue(notices: Vec<PendingNotice>) {
+    if notices.is_empty() {
+        return;
+    }
+    let schedule = {
+        let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+        let was_empty = pending.is_empty();
+        pending.extend(notices);
+        was_empty
+    };
+    if schedule {
+        tauri::async_runtime::spawn(async {
+            tokio::time::sleep(Duration::from_millis(GROUP_WINDOW_MS)).await;
+            let notices = std::mem::take(&mut *PENDING.lock().unwrap_or_else(|e| e.into_inner()));
+            for notice in build_notices(notices) {
+                show(&notice);
+            }
+        });
+    }
+}
+
+/// 按用户分组构建通知：超过合并阈值时合并为一条汇总通知
+fn build_notices(pending: Vec<PendingNotice>) -> Vec<Notice> {
+    let mut by_user: BTreeMap<i64, Vec<PendingNotice>> = BTreeMap::new();
+    for notice in pending {
+        by_user.entry(notice.user_id).or_default().push(notice);
+    }
+
+    let mut notices = Vec::new();
+    for items in by_user.into_values() {
+        let threshold = items.iter().map(|n| n.group_threshold).min().unwrap_or(1);
+        if items.len() as i64 <= threshold {
+            notices.extend(items.iter().map(single_notice));
+            continue;
+        }
+
+        let mut accounts: Vec<&str> = items.iter().map(|n| n.account.as_str()).collect();
+        accounts.sort_unstable();
+        accounts.dedup();
+        let title = if accounts.len() == 1 {
+            format!("{} 收到 {} 封新邮件", accounts[0], items.len())
+        } else {
+            format!("{} 个账号收到 {} 封新邮件", accounts.len(), items.len())
+        };
+
+        // 带验证码的邮件优先列出
+        let mut ordered: Vec<&PendingNotice> = items.iter().collect();
+        ordered.sort_by_key(|n| n.code.is_none());
+        let mut lines: Vec<String> = ordered
+            .iter()
+            .take(GROUP_PREVIEW_LINES)
+            .map(|n| {
+                let mut line = format!("{}: {}", sender_label(n), subject_label(n));
+                if let Some(code) = &n.code {
+                    line.push_str(&format!(" [{code}]"));
+                }
+                line
+            })
+            .collect();
+        if items.len() > GROUP_PREVIEW_LINES {
+            lines.push(format!(
+                "…以及其他 {} 封",
+                items.len() - GROUP_PREVIEW_LINES
+            ));
+        }
+        notices.push(Notice {
+            title,
+            body: lines.join("\n"),
+        });
+    }
+    notices
+}
+
+/// 单封邮件的通知
+fn single_notice(notice: &PendingNotice) -> Notice {
+    let mut body = format!("{}\n{}", sender_label(notice), subject_label(notice));
+    if let Some(code) = &notice.code {
+        body.push_str(&format!("\n验证码: {code}"));
+    }
+    Notice {
+        title: format!("新邮件 - {}", notice.account),
+        body,
+    }
+}
+
+fn sender_label(notice: &PendingNotice) -> &str {
+    notice
+        .sender
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or("(未知发件人)")
+}
+
+fn subject_label(notice: &PendingNotice) -> String {
+    let subject = notice
+        .subject
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or("(无主题)");
+    if subject.chars().count() > MAX_SUBJECT_CHARS {
+        let truncated: String = subject.chars().take(MAX_SUBJECT_CHARS).collect();
+        format!("{truncated}…")
+    } else {
+        subject.to_string()
+    }
+}
+
+/// 弹出系统通知
+fn show(notice: &Notice) {
+    let Some(app_handle) = APP_HANDLE.get() else {
+        return;
+    };
+    if let Err(e) = app_handle
+        .notification()
+        .builder()
+        .title(&notice.title)
+        .body(&notice.body)
+        .show()
+    {
+        log::warn!("发送桌面通知失败: {}", e);
+    }
+}
+
+/// 发件人是否匹配静音项：完整地址精确匹配，@域名匹配该域名
+fn sender_matches(sender: &str, muted: &str) -> bool {
+    let sender = sender.to_lowercase();
+    // "名称 <地址>" 形式取尖括号中的地址
+    let address = match (sender.rfind('<'), sender.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &sender[start + 1..end],
+        _ => sender.as_str(),
+    }
+    .trim();
+    if muted.starts_with('@') {
+        address.ends_with(muted)
+    } else {
+        address == muted
+    }
+}
+
+/// 当前时间是否处于免打扰时段 [start, end)，end 早于 start 表示跨零点
+fn in_quiet_hours(now: NaiveTime, start: NaiveTime, end: NaiveTime) -> bool {
+    if start <= end {
+        start <= now && now < end
+    } else {
+        now >= start || now < end
+    }
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+/// 校验并规范化 HH:MM 时间，空值返回 None
+fn normalize_time(value: Option<&str>) -> Result<Option<String>> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(value) => parse_time(value)
+            .map(|t| Some(t.format("%H:%M").to_string()))
+            .ok_or_else(|| anyhow!("时间格式无效: {}（应为 HH:MM）", value)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> NaiveTime {
+        parse_time(value).unwrap()
+    }
+
+    fn pending(user_id: i64, account: &str, code: Option<&str>) -> PendingNotice {
+        PendingNotice {
+            user_id,
+            group_threshold: 2,
+            account: account.to_string(),
+            sender: Some("a@b.com".to_string()),
+            subject: Some("hi".to_string()),
+            code: code.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_in_quiet_hours() {
+        assert!(in_quiet_hours(time("23:30"), time("22:00"), time("07:00")));
+        assert!(in_quiet_hours(time("06:59"), time("22:00"), time("07:00")));
+        assert!(!in_quiet_hours(time("07:00"), time("22:00"), time("07:00")));
+        assert!(in_quiet_hours(time("13:00"), time("12:00"), time("14:00")));
+        assert!(!in_quiet_hours(time("14:00"), time("12:00"), time("14:00")));
+    }
+
+    #[test]
+    fn test_sender_matches() {
+        assert!(sender_matches("Shop <News@Shop.com>", "news@shop.com"));
+        assert!(sender_matches("news@shop.com", "@shop.com"));
+        assert!(!sender_matches("news@myshop.com", "news@shop.com"));
+        assert!(!sender_matches("Shop <news@shop.com.cn>", "@shop.com"));
+    }
+
+    #[test]
+    fn test_build_notices_groups() {
+        let notices = build_notices(vec![
+            pending(1, "x@a.com", None),
+            pending(2, "y@a.com", None),
+        ]);
+        assert_eq!(notices.len(), 2);
+        assert!(notices[0].body.contains("a@b.com\nhi"));
+
+        let notices = build_notices(vec![
+            pending(1, "x@a.com", None),
+            pending(1, "x@a.com", Some("123456")),
+            pending(1, "y@a.com", None),
+        ]);
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].title, "2 个账号收到 3 封新邮件");
+        assert!(notices[0].body.starts_with("a@b.com: hi [123456]"));
+    }
+}
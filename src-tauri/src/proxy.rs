@@ -8,6 +8,8 @@ use reqwest::{Client, Proxy};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+use crate::settings;
+
 /// 代理类型
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -72,9 +74,10 @@ impl ProxyConfig {
 
 /// 创建带代理的 HTTP 客户端
 pub fn create_http_client(config: &ProxyConfig, timeout_secs: u64) -> Result<Client> {
+    let connect_timeout = settings::current().network.connect_timeout_secs;
     let mut builder = Client::builder()
         .timeout(Duration::from_secs(timeout_secs))
-        .connect_timeout(Duration::from_secs(30));
+        .connect_timeout(Duration::from_secs(connect_timeout));
 
     if config.is_enabled() {
         let proxy_url = config
@@ -104,9 +107,10 @@ pub fn create_http_client(config: &ProxyConfig, timeout_secs: u64) -> Result<Cli
 
 /// 创建不带代理的默认 HTTP 客户端
 pub fn create_default_client(timeout_secs: u64) -> Result<Client> {
+    let connect_timeout = settings::current().network.connect_timeout_secs;
     let client = Client::builder()
         .timeout(Duration::from_secs(timeout_secs))
-        .connect_timeout(Duration::from_secs(30))
+        .connect_timeout(Duration::from_secs(connect_timeout))
         .build()?;
 
     Ok(client)
@@ -0,0 +1,1324 @@
+//! 收件规则模块
+//! 新邮件入库后按优先级依次匹配当前用户的规则（发件人/主题/正文正则、文件夹、账号标签、附件类型），
+//! 命中后执行添加类别、标记已读、移动、删除、转发、提取验证码、Webhook 与桌面通知等动作。
+//! 手动收件与实时监听共用同一收件流程，因此都会执行规则；规则只处理创建之后收到的邮件，
+//! 文件夹首次同步拉取的历史邮件也不执行规则。规则可以对已有邮件试运行（不执行动作）
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+use tauri::Emitter;
+
+use crate::email::{self, ApiMode};
+use crate::folders;
+use crate::graph_api;
+use crate::mail_actions::{self, MailAction};
+use crate::notifications;
+use crate::tags;
+use crate::webhooks;
+
+/// 内置验证码规则编译结果
+static DEFAULT_CODE_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// 默认的验证码提取规则：关键字后的 4-8 位数字
+const DEFAULT_CODE_PATTERN: &str =
+    r"(?:code|验证码|校验码|动态码|确认码|otp|pin)\D{0,20}?(\d{4,8})";
+
+/// 正则编译后的大小上限（字节）
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// 试运行默认扫描的邮件数
+const DEFAULT_TEST_LIMIT: i64 = 200;
+
+/// 试运行最多扫描的邮件数
+const MAX_TEST_LIMIT: i64 = 2000;
+
+/// 命中记录默认返回的条数
+const DEFAULT_HIT_LIMIT: i64 = 200;
+
+/// 规则条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleCondition {
+    /// 发件人匹配正则（不区分大小写）
+    Sender { pattern: String },
+    /// 主题匹配正则
+    Subject { pattern: String },
+    /// 正文匹配正则
+    Body { pattern: String },
+    /// 位于指定文件夹
+    Folder { folder: String },
+    /// 账号带有指定标签
+    AccountTag { tag_id: i64 },
+    /// 含指定类型的附件：扩展名（pdf）、MIME 类型（application/pdf）或主类型（image）
+    AttachmentType { kind: String },
+}
+
+/// 规则动作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// 添加类别
+    Tag { category: String },
+    /// 标记为已读
+    MarkRead,
+    /// 移动到指定文件夹
+    Move { folder: String },
+    /// 删除（移动到已删除邮件）
+    Delete,
+    /// 转发（仅 Graph API 模式）
+    Forward {
+        to: Vec<String>,
+        comment: Option<String>,
+    },
+    /// 提取验证码，pattern 为空时使用内置规则；有捕获组时取第一个捕获组
+    ExtractCode { pattern: Option<String> },
+    /// 通过指定的 Webhook 端点推送 rule.matched 事件
+    Webhook { webhook_id: i64 },
+    /// 桌面通知
+    Notify { title: Option<String> },
+}
+
+impl RuleAction {
+    /// 动作名称（用于命中记录）
+    fn describe(&self) -> &'static str {
+        match self {
+            RuleAction::Tag { .. } => "tag",
+            RuleAction::MarkRead => "mark_read",
+            RuleAction::Move { .. } => "move",
+            RuleAction::Delete => "delete",
+            RuleAction::Forward { .. } => "forward",
+            RuleAction::ExtractCode { .. } => "extract_code",
+            RuleAction::Webhook { .. } => "webhook",
+            RuleAction::Notify { .. } => "notify",
+        }
+    }
+
+    /// 是否操作服务器上的邮件；邮件移动或删除后原标识失效，此类动作不再执行
+    fn targets_server_mail(&self) -> bool {
+        matches!(
+            self,
+            RuleAction::Tag { .. }
+                | RuleAction::MarkRead
+                | RuleAction::Move { .. }
+                | RuleAction::Delete
+                | RuleAction::Forward { .. }
+        )
+    }
+}
+
+/// 收件规则
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct MailRule {
+    pub id: i64,
+    pub name: String,
+    pub enabled: bool,
+    /// 数值越小越先执行
+    pub priority: i64,
+    /// true 表示条件全部满足，false 表示任一满足
+    pub match_all: bool,
+    /// 命中后不再执行后续规则
+    pub stop_processing: bool,
+    #[sqlx(json)]
+    pub conditions: Vec<RuleCondition>,
+    #[sqlx(json)]
+    pub actions: Vec<RuleAction>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// 新建或修改规则的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct MailRuleInput {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub priority: i64,
+    #[serde(default = "default_true")]
+    pub match_all: bool,
+    #[serde(default)]
+    pub stop_processing: bool,
+    pub conditions: Vec<RuleCondition>,
+    pub actions: Vec<RuleAction>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 单个动作的执行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionOutcome {
+    pub action: String,
+    pub success: bool,
+    pub message: String,
+}
+
+/// 规则命中记录
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct RuleHit {
+    pub id: i64,
+    pub rule_id: i64,
+    pub rule_name: String,
+    pub mail_id: i64,
+    pub email_id: i64,
+    pub subject: Option<String>,
+    pub extracted_code: Option<String>,
+    #[sqlx(json)]
+    pub results: Vec<ActionOutcome>,
+    pub created_at: Option<String>,
+}
+
+/// 试运行命中的邮件
+#[derive(Debug, Serialize)]
+pub struct RuleTestMatch {
+    pub mail_id: i64,
+    pub email_id: i64,
+    pub account: String,
+    pub subject: Option<String>,
+    pub sender: Option<String>,
+    pub received_time: Option<String>,
+    pub folder: Option<String>,
+    /// 规则含提取验证码动作时的提取结果
+    pub extracted_code: Option<String>,
+}
+
+/// 试运行结果
+#[derive(Debug, Serialize)]
+pub struct RuleTestResult {
+    /// 扫描的邮件数
+    pub scanned: usize,
+    pub matches: Vec<RuleTestMatch>,
+}
+
+/// 对新邮件执行规则的结果
+#[derive(Debug, Default)]
+pub struct RuleRunSummary {
+    /// 命中次数
+    pub hits: usize,
+    /// 已由规则发送通知的邮件 ID（不再重复发送新邮件通知）
+    pub notified_mail_ids: Vec<i64>,
+}
+
+/// 单条规则对单封邮件的执行结果
+struct Execution {
+    /// 邮件是否已被移动或删除
+    relocated: bool,
+    /// 是否已发送规则通知
+    notified: bool,
+}
+
+/// 规则通知事件
+#[derive(Debug, Clone, Serialize)]
+struct RuleNotification {
+    rule_id: i64,
+    mail_id: i64,
+    email_id: i64,
+    title: String,
+    body: String,
+    code: Option<String>,
+}
+
+/// 参与匹配的邮件信息
+#[derive(Debug, sqlx::FromRow)]
+struct RuleMail {
+    id: i64,
+    email_id: i64,
+    account: String,
+    subject: Option<String>,
+    sender: Option<String>,
+    received_time: Option<String>,
+    content: Option<String>,
+    folder: Option<String>,
+}
+
+/// 附件的 (文件名, MIME 类型)
+type AttachmentMeta = (Option<String>, Option<String>);
+
+/// 邮件及其账号标签与附件
+struct MailContext {
+    mail: RuleMail,
+    tag_ids: Vec<i64>,
+    attachments: Vec<AttachmentMeta>,
+}
+
+/// 编译后的条件
+enum Matcher {
+    Sender(Regex),
+    Subject(Regex),
+    Body(Regex),
+    Folder(String),
+    AccountTag(i64),
+    AttachmentType(String),
+}
+
+/// 编译后的规则
+struct CompiledRule {
+    rule: MailRule,
+    matchers: Vec<Matcher>,
+    code_pattern: Option<Regex>,
+    /// 规则创建时间，更早收到的邮件不执行动作
+    created_at: Option<DateTime<Utc>>,
+}
+
+/// 获取用户的规则（按优先级排序）
+pub async fn list_rules(pool: &Pool<Sqlite>, user_id: i64) -> Result<Vec<MailRule>> {
+    let rules = sqlx::query_as::<_, MailRule>(
+        "SELECT id, name, enabled, priority, match_all, stop_processing, conditions, actions, created_at, updated_at FROM mail_rules WHERE user_id = ? ORDER BY priority, id",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rules)
+}
+
+/// 新建规则
+pub async fn create_rule(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    input: &MailRuleInput,
+) -> Result<MailRule> {
+    validate(pool, user_id, input).await?;
+    let rule = sqlx::query_as::<_, MailRule>(
+        "INSERT INTO mail_rules (user_id, name, enabled, priority, match_all, stop_processing, conditions, actions) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id, name, enabled, priority, match_all, stop_processing, conditions, actions, created_at, updated_at",
+    )
+    .bind(user_id)
+    .bind(input.name.trim())
+    .bind(input.enabled)
+    .bind(input.priority)
+    .bind(input.match_all)
+    .bind(input.stop_processing)
+    .bind(serde_json::to_string(&input.conditions)?)
+    .bind(serde_json::to_string(&input.actions)?)
+    .fetch_one(pool)
+    .await?;
+    Ok(rule)
+}
+
+/// 修改规则，规则不存在时返回 false
+pub async fn update_rule(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    rule_id: i64,
+    input: &MailRuleInput,
+) -> Result<bool> {
+    validate(pool, user_id, input).await?;
+    let result = sqlx::query(
+        r#"UPDATE mail_rules
+SET name = ?, enabled = ?, priority = ?, match_all = ?, stop_processing = ?, conditions = ?, actions = ?,
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = ? AND user_id = ?"#,
+    )
+    .bind(input.name.trim())
+    .bind(input.enabled)
+    .bind(input.priority)
+    .bind(input.match_all)
+    .bind(input.stop_processing)
+    .bind(serde_json::to_string(&input.conditions)?)
+    .bind(serde_json::to_string(&input.actions)?)
+    .bind(rule_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 删除规则（命中记录一并删除）
+pub async fn delete_rule(pool: &Pool<Sqlite>, user_id: i64, rule_id: i64) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM mail_rules WHERE id = ? AND user_id = ?")
+        .bind(rule_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 获取规则命中记录（最新在前）
+pub async fn list_hits(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    rule_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<RuleHit>> {
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT h.id, h.rule_id, r.name AS rule_name, h.mail_id, h.email_id, m.subject, h.extracted_code, h.results, h.created_at FROM mail_rule_hits h JOIN mail_rules r ON r.id = h.rule_id JOIN mail_records m ON m.id = h.mail_id WHERE r.user_id = ",
+    );
+    qb.push_bind(user_id);
+    if let Some(rule_id) = rule_id {
+        qb.push(" AND h.rule_id = ");
+        qb.push_bind(rule_id);
+    }
+    qb.push(" ORDER BY h.id DESC LIMIT ");
+    qb.push_bind(limit.unwrap_or(DEFAULT_HIT_LIMIT).max(1));
+
+    let hits = qb.build_query_as::<RuleHit>().fetch_all(pool).await?;
+    Ok(hits)
+}
+
+/// 用已有邮件试运行规则：只匹配条件与提取验证码，不执行任何动作
+pub async fn test_rule(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    input: &MailRuleInput,
+    email_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<RuleTestResult> {
+    validate(pool, user_id, input).await?;
+    let compiled = compile(MailRule {
+        id: 0,
+        name: input.name.trim().to_string(),
+        enabled: true,
+        priority: input.priority,
+        match_all: input.match_all,
+        stop_processing: input.stop_processing,
+        conditions: input.conditions.clone(),
+        actions: input.actions.clone(),
+        created_at: None,
+        updated_at: None,
+    })?;
+
+    let limit = limit.unwrap_or(DEFAULT_TEST_LIMIT).clamp(1, MAX_TEST_LIMIT);
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT m.id FROM mail_records m JOIN emails e ON e.id = m.email_id WHERE m.deleted_at IS NULL AND e.deleted_at IS NULL AND e.user_id = ",
+    );
+    qb.push_bind(user_id);
+    if let Some(email_id) = email_id {
+        qb.push(" AND m.email_id = ");
+        qb.push_bind(email_id);
+    }
+    qb.push(" ORDER BY m.received_time DESC, m.id DESC LIMIT ");
+    qb.push_bind(limit);
+    let mail_ids: Vec<i64> = qb.build_query_scalar().fetch_all(pool).await?;
+
+    let contexts = load_contexts(pool, &mail_ids).await?;
+    let scanned = contexts.len();
+    let matches = contexts
+        .into_iter()
+        .filter(|ctx| compiled.matches(ctx))
+        .map(|ctx| RuleTestMatch {
+            extracted_code: compiled.extract_code(&ctx.mail),
+            mail_id: ctx.mail.id,
+            email_id: ctx.mail.email_id,
+            account: ctx.mail.account,
+            subject: ctx.mail.subject,
+            sender: ctx.mail.sender,
+            received_time: ctx.mail.received_time,
+            folder: ctx.mail.folder,
+        })
+        .collect();
+
+    Ok(RuleTestResult { scanned, matches })
+}
+
+/// 对账号新收到的邮件执行其所有者的启用规则
+///
+/// 单个动作或规则失败只记录日志与命中记录，不影响其他规则和邮件；
+/// 邮件被移动或删除后不再执行后续规则
+pub async fn apply_to_new_mails(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    mail_ids: &[i64],
+) -> Result<RuleRunSummary> {
+    let mut summary = RuleRunSummary::default();
+    if mail_ids.is_empty() {
+        return Ok(summary);
+    }
+
+    let owner: Option<i64> = sqlx::query_scalar("SELECT user_id FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+    let Some(user_id) = owner else {
+        return Ok(summary);
+    };
+
+    let mut rules = Vec::new();
+    for rule in list_rules(pool, user_id).await? {
+        if !rule.enabled {
+            continue;
+        }
+        // 单条规则无效（如旧数据中的正则）不影响其他规则
+        let name = rule.name.clone();
+        match compile(rule) {
+            Ok(compiled) => rules.push(compiled),
+            Err(e) => log::warn!("收件规则无效，已跳过: rule={}, error={}", name, e),
+        }
+    }
+    if rules.is_empty() {
+        return Ok(summary);
+    }
+
+    for ctx in load_contexts(pool, mail_ids).await? {
+        for rule in &rules {
+            if !rule.received_after_creation(&ctx.mail) || !rule.matches(&ctx) {
+                continue;
+            }
+            summary.hits += 1;
+            log::info!(
+                "收件规则命中: rule={}, mail_id={}",
+                rule.rule.name,
+                ctx.mail.id
+            );
+            match execute(pool, user_id, rule, &ctx.mail).await {
+                Ok(execution) => {
+                    if execution.notified && !summary.notified_mail_ids.contains(&ctx.mail.id) {
+                        summary.notified_mail_ids.push(ctx.mail.id);
+                    }
+                    if execution.relocated {
+                        break;
+                    }
+                }
+                Err(e) => log::warn!(
+                    "执行收件规则失败: rule={}, mail_id={}, error={}",
+                    rule.rule.name,
+                    ctx.mail.id,
+                    e
+                ),
+            }
+            if rule.rule.stop_processing {
+                break;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// 执行规则的全部动作，并保存命中记录
+async fn execute(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    rule: &CompiledRule,
+    mail: &RuleMail,
+) -> Result<Execution> {
+    // 先提取验证码，Webhook 与通知中可以带上
+    let code = rule.extract_code(mail);
+
+    let mut relocated = false;
+    let mut notified = false;
+    let mut outcomes = Vec::with_capacity(rule.rule.actions.len());
+    for action in &rule.rule.actions {
+        if relocated && action.targets_server_mail() {
+            outcomes.push(ActionOutcome {
+                action: action.describe().to_string(),
+                success: false,
+                message: "邮件已被移动或删除，已跳过".to_string(),
+            });
+            continue;
+        }
+        let result = match action {
+            RuleAction::Tag { category } => {
+                let action = MailAction::Categorize {
+                    category: category.clone(),
+                };
+                apply_mail_action(pool, user_id, mail.id, &action).await
+            }
+            RuleAction::MarkRead => {
+                apply_mail_action(pool, user_id, mail.id, &MailAction::MarkRead).await
+            }
+            RuleAction::Move { folder } => {
+                let action = MailAction::Move {
+                    folder: folder.clone(),
+                };
+                apply_mail_action(pool, user_id, mail.id, &action).await
+            }
+            RuleAction::Delete => {
+                apply_mail_action(pool, user_id, mail.id, &MailAction::Delete).await
+            }
+            RuleAction::Forward { to, comment } => {
+                forward(pool, mail, to, comment.as_deref().unwrap_or_default()).await
+            }
+            RuleAction::ExtractCode { .. } => match &code {
+                Some(code) => Ok(format!("验证码: {code}")),
+                None => Err(anyhow!("未找到验证码")),
+            },
+            RuleAction::Webhook { webhook_id } => {
+                enqueue_webhook(
+                    pool,
+                    user_id,
+                    *webhook_id,
+                    &rule.rule,
+                    mail,
+                    code.as_deref(),
+                )
+                .await
+            }
+            RuleAction::Notify { title } => notify(
+                pool,
+                user_id,
+                &rule.rule,
+                mail,
+                title.as_deref(),
+                code.as_deref(),
+            )
+            .await
+            .map(|_| "已发送".to_string()),
+        };
+        if result.is_ok() {
+            match action {
+                RuleAction::Move { .. } | RuleAction::Delete => relocated = true,
+                RuleAction::Notify { .. } => notified = true,
+                _ => {}
+            }
+        }
+        outcomes.push(match result {
+            Ok(message) => ActionOutcome {
+                action: action.describe().to_string(),
+                success: true,
+                message,
+            },
+            Err(e) => {
+                log::warn!(
+                    "收件规则动作失败: rule={}, action={}, mail_id={}, error={}",
+                    rule.rule.name,
+                    action.describe(),
+                    mail.id,
+                    e
+                );
+                ActionOutcome {
+                    action: action.describe().to_string(),
+                    success: false,
+                    message: e.to_string(),
+                }
+            }
+        });
+    }
+
+    sqlx::query(
+        "INSERT INTO mail_rule_hits (rule_id, mail_id, email_id, extracted_code, results) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(rule.rule.id)
+    .bind(mail.id)
+    .bind(mail.email_id)
+    .bind(&code)
+    .bind(serde_json::to_string(&outcomes)?)
+    .execute(pool)
+    .await?;
+
+    if let Some(code) = &code {
+        let source = webhooks::EventSource {
+            user_id,
+            email_id: mail.email_id,
+            rule_id: Some(rule.rule.id),
+        };
+        let data = serde_json::json!({
+            "rule": { "id": rule.rule.id, "name": rule.rule.name },
+            "mail": mail_summary(mail),
+            "code": code,
+        });
+        if let Err(e) = webhooks::dispatch(pool, webhooks::EVENT_CODE_EXTRACTED, source, data).await
+        {
+            log::warn!("推送验证码事件失败: mail_id={}, error={}", mail.id, e);
+        }
+    }
+
+    Ok(Execution {
+        relocated,
+        notified,
+    })
+}
+
+/// 在服务器上对单封邮件执行操作
+async fn apply_mail_action(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    mail_id: i64,
+    action: &MailAction,
+) -> Result<String> {
+    let result = mail_actions::apply_mail_action(pool, Some(user_id), &[mail_id], action).await?;
+    match result.results.into_iter().next() {
+        Some(r) if r.success => Ok(r.message),
+        Some(r) => Err(anyhow!(r.message)),
+        None => Err(anyhow!("邮件不存在")),
+    }
+}
+
+/// 通过 Graph API 转发邮件
+async fn forward(
+    pool: &Pool<Sqlite>,
+    mail: &RuleMail,
+    to: &[String],
+    comment: &str,
+) -> Result<String> {
+    let graph_id: Option<String> =
+        sqlx::query_scalar("SELECT graph_id FROM mail_records WHERE id = ?")
+            .bind(mail.id)
+            .fetch_one(pool)
+            .await?;
+    let graph_id = graph_id.ok_or_else(|| anyhow!("缺少 Graph 邮件标识，无法转发"))?;
+
+    let session = email::open_outlook_session(pool, mail.email_id).await?;
+    if session.api_mode == ApiMode::Imap {
+        return Err(anyhow!("仅 Graph API 模式支持转发"));
+    }
+    graph_api::forward_message(
+        &session.access_token,
+        &graph_id,
+        to,
+        comment,
+        &session.proxy_config,
+    )
+    .await?;
+    Ok(format!("已转发给 {}", to.join(", ")))
+}
+
+/// 把 rule.matched 事件加入 Webhook 投递队列
+async fn enqueue_webhook(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    webhook_id: i64,
+    rule: &MailRule,
+    mail: &RuleMail,
+    code: Option<&str>,
+) -> Result<String> {
+    let data = serde_json::json!({
+        "rule": { "id": rule.id, "name": rule.name },
+        "mail": mail_summary(mail),
+        "code": code,
+    });
+    let delivery_id = webhooks::enqueue(
+        pool,
+        user_id,
+        webhook_id,
+        webhooks::EVENT_RULE_MATCHED,
+        data,
+    )
+    .await?;
+    Ok(format!("已加入投递队列 #{delivery_id}"))
+}
+
+/// 推送事件中的邮件摘要
+fn mail_summary(mail: &RuleMail) -> webhooks::MailSummary {
+    webhooks::MailSummary {
+        id: mail.id,
+        email_id: mail.email_id,
+        account: mail.account.clone(),
+        subject: mail.subject.clone(),
+        sender: mail.sender.clone(),
+        received_time: mail.received_time.clone(),
+        folder: mail.folder.clone(),
+    }
+}
+
+/// 发送规则通知：前端事件与桌面通知
+async fn notify(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    rule: &MailRule,
+    mail: &RuleMail,
+    title: Option<&str>,
+    code: Option<&str>,
+) -> Result<()> {
+    let title = title
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or(&rule.name)
+        .to_string();
+    let mut body = format!(
+        "{}: {}",
+        mail.sender.as_deref().unwrap_or("(未知发件人)"),
+        mail.subject.as_deref().unwrap_or("(无主题)")
+    );
+    if let Some(code) = code {
+        body.push_str(&format!("\n验证码: {code}"));
+    }
+
+    notifications::notify_rule(pool, user_id, &title, &body).await?;
+    if let Some(app_handle) = notifications::app_handle() {
+        let _ = app_handle.emit(
+            "rule-notification",
+            RuleNotification {
+                rule_id: rule.id,
+                mail_id: mail.id,
+                email_id: mail.email_id,
+                title,
+                body,
+                code: code.map(str::to_string),
+            },
+        );
+    }
+    Ok(())
+}
+
+/// 读取邮件、账号标签与附件
+async fn load_contexts(pool: &Pool<Sqlite>, mail_ids: &[i64]) -> Result<Vec<MailContext>> {
+    if mail_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT m.id, m.email_id, e.email AS account, m.subject, m.sender, m.received_time, m.content, m.folder FROM mail_records m JOIN emails e ON e.id = m.email_id WHERE m.id IN (",
+    );
+    let mut separated = qb.separated(", ");
+    for id in mail_ids {
+        separated.push_bind(id);
+    }
+    qb.push(")");
+    let mut mails: HashMap<i64, RuleMail> = qb
+        .build_query_as::<RuleMail>()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|m| (m.id, m))
+        .collect();
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT mail_id, filename, content_type FROM attachments WHERE mail_id IN (",
+    );
+    let mut separated = qb.separated(", ");
+    for id in mail_ids {
+        separated.push_bind(id);
+    }
+    qb.push(")");
+    let mut attachments: HashMap<i64, Vec<AttachmentMeta>> = HashMap::new();
+    for (mail_id, filename, content_type) in qb
+        .build_query_as::<(i64, Option<String>, Option<String>)>()
+        .fetch_all(pool)
+        .await?
+    {
+        attachments
+            .entry(mail_id)
+            .or_default()
+            .push((filename, content_type));
+    }
+
+    let email_ids: Vec<i64> = mails.values().map(|m| m.email_id).collect();
+    let mut tag_ids: HashMap<i64, Vec<i64>> = HashMap::new();
+    if !email_ids.is_empty() {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT et.email_id, et.tag_id FROM email_tags et JOIN emails e ON e.id = et.email_id JOIN tags t ON t.id = et.tag_id AND t.user_id = e.user_id WHERE et.email_id IN (",
+        );
+        let mut separated = qb.separated(", ");
+        for id in &email_ids {
+            separated.push_bind(id);
+        }
+        qb.push(")");
+        for (email_id, tag_id) in qb.build_query_as::<(i64, i64)>().fetch_all(pool).await? {
+            tag_ids.entry(email_id).or_default().push(tag_id);
+        }
+    }
+
+    // 保持传入顺序
+    Ok(mail_ids
+        .iter()
+        .filter_map(|id| mails.remove(id))
+        .map(|mail| MailContext {
+            tag_ids: tag_ids.get(&mail.email_id).cloned().unwrap_or_default(),
+            attachments: attachments.remove(&mail.id).unwrap_or_default(),
+            mail,
+        })
+        .collect())
+}
+
+/// 校验规则参数
+async fn validate(pool: &Pool<Sqlite>, user_id: i64, input: &MailRuleInput) -> Result<()> {
+    if input.name.trim().is_empty() {
+        return Err(anyhow!("规则名称不能为空"));
+    }
+    if input.conditions.is_empty() {
+        return Err(anyhow!("至少需要一个条件"));
+    }
+    if input.actions.is_empty() {
+        return Err(anyhow!("至少需要一个动作"));
+    }
+
+    for condition in &input.conditions {
+        match condition {
+            RuleCondition::Sender { pattern }
+            | RuleCondition::Subject { pattern }
+            | RuleCondition::Body { pattern } => {
+                build_regex(pattern)?;
+            }
+            RuleCondition::Folder { folder } if folder.trim().is_empty() => {
+                return Err(anyhow!("文件夹不能为空"));
+            }
+            RuleCondition::AttachmentType { kind } if normalize_kind(kind).is_empty() => {
+                return Err(anyhow!("附件类型不能为空"));
+            }
+            RuleCondition::AccountTag { tag_id } => {
+                tags::ensure_owned(pool, user_id, &[*tag_id]).await?;
+            }
+            _ => {}
+        }
+    }
+
+    for action in &input.actions {
+        match action {
+            RuleAction::Tag { category } if category.trim().is_empty() => {
+                return Err(anyhow!("类别不能为空"));
+            }
+            RuleAction::Move { folder } if folder.trim().is_empty() => {
+                return Err(anyhow!("目标文件夹不能为空"));
+            }
+            RuleAction::Forward { to, .. }
+                if to.is_empty() || to.iter().any(|a| !a.contains('@')) =>
+            {
+                return Err(anyhow!("转发地址无效"));
+            }
+            RuleAction::ExtractCode {
+                pattern: Some(pattern),
+            } => {
+                build_regex(pattern)?;
+            }
+            RuleAction::Webhook { webhook_id } => {
+                webhooks::ensure_owned(pool, user_id, *webhook_id).await?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 编译规则中的正则
+fn compile(rule: MailRule) -> Result<CompiledRule> {
+    let mut matchers = Vec::with_capacity(rule.conditions.len());
+    for condition in &rule.conditions {
+        matchers.push(match condition {
+            RuleCondition::Sender { pattern } => Matcher::Sender(build_regex(pattern)?),
+            RuleCondition::Subject { pattern } => Matcher::Subject(build_regex(pattern)?),
+            RuleCondition::Body { pattern } => Matcher::Body(build_regex(pattern)?),
+            RuleCondition::Folder { folder } => {
+                Matcher::Folder(folders::canonical_folder_key(folder))
+            }
+            RuleCondition::AccountTag { tag_id } => Matcher::AccountTag(*tag_id),
+            RuleCondition::AttachmentType { kind } => Matcher::AttachmentType(normalize_kind(kind)),
+        });
+    }
+
+    let code_pattern = rule
+        .actions
+        .iter()
+        .find_map(|action| match action {
+            RuleAction::ExtractCode { pattern } => Some(
+                pattern
+                    .as_deref()
+                    .filter(|p| !p.trim().is_empty())
+                    .unwrap_or(DEFAULT_CODE_PATTERN),
+            ),
+            _ => None,
+        })
+        .map(build_regex)
+        .transpose()?;
+
+    // created_at 为 SQLite CURRENT_TIMESTAMP（UTC）
+    let created_at = rule
+        .created_at
+        .as_deref()
+        .and_then(|t| NaiveDateTime::parse_from_str(t, "%Y-%m-%d %H:%M:%S").ok())
+        .map(|t| t.and_utc());
+
+    Ok(CompiledRule {
+        rule,
+        matchers,
+        code_pattern,
+        created_at,
+    })
+}
+
+impl CompiledRule {
+    fn matches(&self, ctx: &MailContext) -> bool {
+        let mut results = self.matchers.iter().map(|m| m.matches(ctx));
+        if self.rule.match_all {
+            results.all(|matched| matched)
+        } else {
+            results.any(|matched| matched)
+        }
+    }
+
+    /// 邮件是否在规则创建之后收到；时间无法解析时视为新邮件
+    fn received_after_creation(&self, mail: &RuleMail) -> bool {
+        let received = mail
+            .received_time
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+        match (self.created_at, received) {
+            (Some(created_at), Some(received)) => received >= created_at,
+            _ => true,
+        }
+    }
+
+    /// 依次在主题与正文中提取验证码
+    fn extract_code(&self, mail: &RuleMail) -> Option<String> {
+        let pattern = self.code_pattern.as_ref()?;
+        [mail.subject.as_deref(), mail.content.as_deref()]
+            .into_iter()
+            .flatten()
+            .find_map(|text| extract_code(pattern, text))
+    }
+}
+
+impl Matcher {
+    fn matches(&self, ctx: &MailContext) -> bool {
+        let mail = &ctx.mail;
+        match self {
+            Matcher::Sender(re) => mail.sender.as_deref().is_some_and(|s| re.is_match(s)),
+            Matcher::Subject(re) => mail.subject.as_deref().is_some_and(|s| re.is_match(s)),
+            Matcher::Body(re) => mail.content.as_deref().is_some_and(|s| re.is_match(s)),
+            Matcher::Folder(folder) => {
+                let key = mail
+                    .folder
+                    .as_deref()
+                    .map(folders::canonical_folder_key)
+                    .unwrap_or_else(|| "inbox".to_string());
+                &key == folder
+            }
+            Matcher::AccountTag(tag_id) => ctx.tag_ids.contains(tag_id),
+            Matcher::AttachmentType(kind) => {
+                ctx.attachments.iter().any(|(filename, content_type)| {
+                    attachment_matches(kind, filename.as_deref(), content_type.as_deref())
+                })
+            }
+        }
+    }
+}
+
+fn build_regex(pattern: &str) -> Result<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| anyhow!("正则表达式无效: {}", e))
+}
+
+/// 规范化附件类型：小写并去掉扩展名前的点
+fn normalize_kind(kind: &str) -> String {
+    kind.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// 附件是否属于指定类型（kind 已规范化）
+fn attachment_matches(kind: &str, filename: Option<&str>, content_type: Option<&str>) -> bool {
+    let content_type = content_type.unwrap_or_default().to_lowercase();
+    if kind.contains('/') {
+        return content_type == kind || content_type.starts_with(&format!("{kind};"));
+    }
+    let extension_matches = filename
+        .and_then(|name| name.rsplit_once('.'))
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case(kind));
+    extension_matches || content_type.starts_with(&format!("{kind}/"))
+}
+
+/// 用内置规则从文本中提取验证码
+pub fn extract_default_code(text: &str) -> Option<String> {
+    let pattern = DEFAULT_CODE_REGEX
+        .get_or_init(|| build_regex(DEFAULT_CODE_PATTERN).expect("内置验证码规则有效"));
+    extract_code(pattern, text)
+}
+
+/// 提取验证码：有捕获组时取第一个捕获组，否则取整个匹配
+fn extract_code(pattern: &Regex, text: &str) -> Option<String> {
+    let captures = pattern.captures(text)?;
+    captures
+        .get(1)
+        .or_else(|| captures.get(0))
+        .map(|m| m.as_str().trim().to_string())
+        .filter(|code| !code.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+
+    #[test]
+    fn test_extract_code() {
+        let pattern = build_regex(DEFAULT_CODE_PATTERN).unwrap();
+        assert_eq!(
+            extract_code(&pattern, "Your verification code is 482913.").as_deref(),
+            Some("482913")
+        );
+        assert_eq!(
+            extract_code(&pattern, "您的验证码：0731，5 分钟内有效").as_deref(),
+            Some("0731")
+        );
+        assert_eq!(extract_code(&pattern, "Invoice 2026-10"), None);
+
+        let custom = build_regex(r"[A-Z]{3}-\d{3}").unwrap();
+        assert_eq!(
+            extract_code(&custom, "token abc-123").as_deref(),
+            Some("abc-123")
+        );
+    }
+
+    #[test]
+    fn test_attachment_matches() {
+        assert!(attachment_matches("pdf", Some("Invoice.PDF"), None));
+        assert!(attachment_matches(
+            "image",
+            Some("a.bin"),
+            Some("image/png")
+        ));
+        assert!(attachment_matches(
+            "application/pdf",
+            None,
+            Some("application/pdf; name=a.pdf")
+        ));
+        assert!(!attachment_matches("pdf", Some("pdf"), Some("text/plain")));
+    }
+
+    fn rule_input(value: serde_json::Value) -> MailRuleInput {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn compile_input(input: &MailRuleInput, created_at: Option<&str>) -> CompiledRule {
+        compile(MailRule {
+            id: 1,
+            name: input.name.clone(),
+            enabled: true,
+            priority: input.priority,
+            match_all: input.match_all,
+            stop_processing: input.stop_processing,
+            conditions: input.conditions.clone(),
+            actions: input.actions.clone(),
+            created_at: created_at.map(str::to_string),
+            updated_at: None,
+        })
+        .unwrap()
+    }
+
+    fn context(sender: &str, folder: Option<&str>, tag_ids: Vec<i64>) -> MailContext {
+        MailContext {
+            mail: RuleMail {
+                id: 1,
+                email_id: 1,
+                account: "a@x.com".to_string(),
+                subject: Some("Your code".to_string()),
+                sender: Some(sender.to_string()),
+                received_time: Some("2026-10-18T08:00:00+00:00".to_string()),
+                content: Some("code: 482913".to_string()),
+                folder: folder.map(str::to_string),
+            },
+            tag_ids,
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_compiled_rule_matches() {
+        let conditions = serde_json::json!([
+            {"type": "sender", "pattern": "@svc\\.com$"},
+            {"type": "folder", "folder": "Junk Email"},
+            {"type": "account_tag", "tag_id": 7}
+        ]);
+        let all = compile_input(
+            &rule_input(serde_json::json!({
+                "name": "all", "conditions": conditions, "actions": [{"type": "mark_read"}]
+            })),
+            None,
+        );
+        let any = compile_input(
+            &rule_input(serde_json::json!({
+                "name": "any", "match_all": false, "conditions": conditions,
+                "actions": [{"type": "mark_read"}]
+            })),
+            None,
+        );
+
+        // 文件夹按统一标识比较
+        assert!(all.matches(&context("No-Reply@SVC.com", Some("junk"), vec![7])));
+        assert!(!all.matches(&context("no-reply@svc.com", Some("junk"), vec![8])));
+        assert!(!all.matches(&context("no-reply@svc.com", Some("inbox"), vec![7])));
+        assert!(any.matches(&context("x@other.com", Some("JunkEmail"), vec![])));
+        assert!(any.matches(&context("x@other.com", None, vec![7])));
+        assert!(!any.matches(&context("x@other.com", None, vec![])));
+
+        // 没有文件夹的邮件视为收件箱
+        let inbox = compile_input(
+            &rule_input(serde_json::json!({
+                "name": "inbox", "conditions": [{"type": "folder", "folder": "INBOX"}],
+                "actions": [{"type": "mark_read"}]
+            })),
+            None,
+        );
+        assert!(inbox.matches(&context("x@other.com", None, vec![])));
+    }
+
+    #[test]
+    fn test_received_after_creation() {
+        let input = rule_input(serde_json::json!({
+            "name": "new", "conditions": [{"type": "subject", "pattern": "code"}],
+            "actions": [{"type": "mark_read"}]
+        }));
+        let mail = context("a@x.com", None, vec![]).mail;
+        assert!(compile_input(&input, Some("2026-10-18 07:59:59")).received_after_creation(&mail));
+        assert!(!compile_input(&input, Some("2026-10-18 08:00:01")).received_after_creation(&mail));
+        assert!(compile_input(&input, None).received_after_creation(&mail));
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let pool = db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO users (id, username, password_hash, salt, rounds) VALUES (2, 'other', '', '', 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let mine = tags::create_tag(&pool, 1, "otp", None).await.unwrap();
+        let theirs = tags::create_tag(&pool, 2, "otp", None).await.unwrap();
+
+        let valid = |conditions: serde_json::Value, actions: serde_json::Value| {
+            rule_input(serde_json::json!({
+                "name": "r", "conditions": conditions, "actions": actions
+            }))
+        };
+        let tag = |id: i64| serde_json::json!([{"type": "account_tag", "tag_id": id}]);
+        let read = serde_json::json!([{"type": "mark_read"}]);
+
+        assert!(validate(&pool, 1, &valid(tag(mine), read.clone()))
+            .await
+            .is_ok());
+        assert!(validate(&pool, 1, &valid(tag(theirs), read.clone()))
+            .await
+            .is_err());
+        assert!(
+            validate(&pool, 1, &valid(serde_json::json!([]), read.clone()))
+                .await
+                .is_err()
+        );
+        assert!(validate(
+            &pool,
+            1,
+            &valid(
+                serde_json::json!([{"type": "subject", "pattern": "("}]),
+                read.clone()
+            )
+        )
+        .await
+        .is_err());
+        assert!(validate(
+            &pool,
+            1,
+            &valid(serde_json::json!([{"type": "folder", "folder": " "}]), read)
+        )
+        .await
+        .is_err());
+        assert!(validate(
+            &pool,
+            1,
+            &valid(
+                tag(mine),
+                serde_json::json!([{"type": "forward", "to": ["not-an-address"]}])
+            )
+        )
+        .await
+        .is_err());
+        assert!(validate(
+            &pool,
+            1,
+            &valid(
+                tag(mine),
+                serde_json::json!([{"type": "webhook", "webhook_id": 99}])
+            )
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stop_processing_and_test_rule() {
+        let pool = db::test_pool().await;
+        let email_id: i64 = sqlx::query_scalar(
+            "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES ('a@x.com', '', 'cid', '', 1) RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let mut mail_ids = Vec::new();
+        for (subject, received_time) in [
+            ("Your code 482913", "2099-01-01T00:00:00+00:00"),
+            ("Invoice", "2099-01-02T00:00:00+00:00"),
+            ("Old code 111111", "2000-01-01T00:00:00+00:00"),
+        ] {
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO mail_records (email_id, subject, sender, folder, received_time) VALUES (?, ?, 'no-reply@svc.com', 'inbox', ?) RETURNING id",
+            )
+            .bind(email_id)
+            .bind(subject)
+            .bind(received_time)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            mail_ids.push(id);
+        }
+
+        let first = rule_input(serde_json::json!({
+            "name": "first", "priority": 1, "stop_processing": true,
+            "conditions": [{"type": "subject", "pattern": "code"}],
+            "actions": [{"type": "extract_code"}]
+        }));
+        let second = rule_input(serde_json::json!({
+            "name": "second", "priority": 2,
+            "conditions": [{"type": "sender", "pattern": "svc"}],
+            "actions": [{"type": "extract_code"}]
+        }));
+
+        // 试运行不执行动作，也不受创建时间限制
+        let result = test_rule(&pool, 1, &first, None, None).await.unwrap();
+        assert_eq!(result.scanned, 3);
+        assert_eq!(result.matches.len(), 2);
+        assert_eq!(result.matches[0].extracted_code.as_deref(), Some("482913"));
+        assert!(list_hits(&pool, 1, None, None).await.unwrap().is_empty());
+
+        let first = create_rule(&pool, 1, &first).await.unwrap();
+        let second = create_rule(&pool, 1, &second).await.unwrap();
+
+        // 第一封命中 first 后停止；第二封只命中 second；第三封早于规则创建，不执行
+        let summary = apply_to_new_mails(&pool, email_id, &mail_ids)
+            .await
+            .unwrap();
+        assert_eq!(summary.hits, 2);
+        assert!(summary.notified_mail_ids.is_empty());
+        let hits = list_hits(&pool, 1, None, None).await.unwrap();
+        let pairs: Vec<(i64, i64)> = hits.iter().map(|h| (h.rule_id, h.mail_id)).collect();
+        assert_eq!(
+            pairs,
+            vec![(second.id, mail_ids[1]), (first.id, mail_ids[0])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notified_mails_reported() {
+        let pool = db::test_pool().await;
+        let email_id: i64 = sqlx::query_scalar(
+            "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES ('a@x.com', '', 'cid', '', 1) RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let mut mail_ids = Vec::new();
+        for subject in ["Your code 482913", "Invoice"] {
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO mail_records (email_id, subject, sender, folder, received_time) VALUES (?, ?, 'no-reply@svc.com', 'inbox', '2099-01-01T00:00:00+00:00') RETURNING id",
+            )
+            .bind(email_id)
+            .bind(subject)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            mail_ids.push(id);
+        }
+
+        // 两条规则都通知同一封邮件时只报告一次
+        for name in ["notify", "notify again"] {
+            let input = rule_input(serde_json::json!({
+                "name": name,
+                "conditions": [{"type": "subject", "pattern": "code"}],
+                "actions": [{"type": "notify"}]
+            }));
+            create_rule(&pool, 1, &input).await.unwrap();
+        }
+
+        let summary = apply_to_new_mails(&pool, email_id, &mail_ids)
+            .await
+            .unwrap();
+        assert_eq!(summary.hits, 2);
+        assert_eq!(summary.notified_mail_ids, vec![mail_ids[0]]);
+    }
+}
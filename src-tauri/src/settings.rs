@@ -0,0 +1,439 @@
+//! 应用设置模块
+//! 监听间隔、收件窗口、网络超时、IMAP 服务器等可调参数以 "分组.字段" 为键保存在 system_config 表中，
+//! 缺省时使用内置默认值。修改前统一校验，保存后更新内存缓存（同步代码通过 current() 读取），
+//! 并返回变更的键供命令层发送 settings-changed 事件；设置可导出为 JSON 文件并在其他设备导入
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+use tauri::{AppHandle, Emitter};
+
+use crate::audit::{self, AuditEvent};
+
+/// 设置变更事件
+pub const EVENT_SETTINGS_CHANGED: &str = "settings-changed";
+
+/// 导出文件的格式标识
+const FILE_FORMAT: &str = "flaremail-settings";
+
+/// 导出文件的格式版本
+const FILE_VERSION: u32 = 1;
+
+/// 可选的 IMAP 服务器（收件时会向其发送账号的访问令牌，只允许微软的服务器）
+pub const ALLOWED_IMAP_HOSTS: [&str; 2] = ["outlook.office365.com", "imap-mail.outlook.com"];
+
+/// 各设置项的说明（同时是 system_config 中的全部键）
+const SETTING_DESCRIPTIONS: [(&str, &str); 6] = [
+    ("watcher.default_interval_secs", "监听默认轮询间隔（秒）"),
+    ("sync.fetch_window", "每次收件检查的最近邮件数"),
+    ("network.request_timeout_secs", "网络请求超时（秒）"),
+    ("network.connect_timeout_secs", "网络连接超时（秒）"),
+    ("imap.host", "IMAP 服务器地址"),
+    ("imap.port", "IMAP 服务器端口"),
+];
+
+/// 当前生效的设置
+static CURRENT: OnceLock<RwLock<AppSettings>> = OnceLock::new();
+
+/// 应用设置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub watcher: WatcherSettings,
+    pub sync: SyncSettings,
+    pub network: NetworkSettings,
+    pub imap: ImapSettings,
+}
+
+/// 邮件监听设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatcherSettings {
+    /// 未指定间隔时的轮询间隔（秒）
+    pub default_interval_secs: u64,
+}
+
+impl Default for WatcherSettings {
+    fn default() -> Self {
+        Self {
+            default_interval_secs: 60,
+        }
+    }
+}
+
+/// 收件设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    /// 每次检查拉取的最近邮件数（同时用于同步删除与状态）
+    pub fetch_window: usize,
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        Self { fetch_window: 100 }
+    }
+}
+
+/// 网络设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// 普通请求超时（秒），收件与附件下载使用各自更长的超时
+    pub request_timeout_secs: u64,
+    /// 建立连接超时（秒），同时用于 IMAP
+    pub connect_timeout_secs: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: 30,
+            connect_timeout_secs: 30,
+        }
+    }
+}
+
+/// IMAP 设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImapSettings {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for ImapSettings {
+    fn default() -> Self {
+        Self {
+            host: "outlook.office365.com".to_string(),
+            port: 993,
+        }
+    }
+}
+
+/// 保存结果（settings-changed 事件的 payload）
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsChange {
+    pub settings: AppSettings,
+    /// 发生变化的键，如 imap.host
+    pub changed: Vec<String>,
+}
+
+/// 导出文件
+#[derive(Debug, Serialize, Deserialize)]
+struct SettingsFile {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    settings: AppSettings,
+}
+
+fn cache() -> &'static RwLock<AppSettings> {
+    CURRENT.get_or_init(|| RwLock::new(AppSettings::default()))
+}
+
+/// 当前生效的设置（启动时由 load 读取，未读取前为默认值）
+pub fn current() -> AppSettings {
+    cache().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 普通网络请求的超时（秒）
+pub fn request_timeout_secs() -> u64 {
+    cache()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .network
+        .request_timeout_secs
+}
+
+/// 有设置项变化时通知前端
+pub fn emit_changed(app: &AppHandle, change: &SettingsChange) {
+    if change.changed.is_empty() {
+        return;
+    }
+    if let Err(e) = app.emit(EVENT_SETTINGS_CHANGED, change) {
+        log::warn!("发送设置变更事件失败: {}", e);
+    }
+}
+
+/// 从数据库读取设置并更新内存缓存
+pub async fn load(pool: &Pool<Sqlite>) -> Result<AppSettings> {
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT key, value FROM system_config WHERE key IN (");
+    let mut separated = qb.separated(", ");
+    for (key, _) in SETTING_DESCRIPTIONS {
+        separated.push_bind(key);
+    }
+    qb.push(")");
+    let rows: HashMap<String, String> = qb
+        .build_query_as::<(String, String)>()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let settings = from_entries(&rows);
+    *cache().write().unwrap_or_else(|e| e.into_inner()) = settings.clone();
+    Ok(settings)
+}
+
+/// 校验并保存设置，返回变更的键
+pub async fn update(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    settings: &AppSettings,
+) -> Result<SettingsChange> {
+    let change = save(pool, settings).await?;
+    if !change.changed.is_empty() {
+        audit::record(
+            pool,
+            audit::ACTION_SETTINGS_UPDATE,
+            AuditEvent {
+                user_id: Some(user_id),
+                detail: Some(change.changed.join(",")),
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+    Ok(change)
+}
+
+/// 恢复默认设置
+pub async fn reset(pool: &Pool<Sqlite>, user_id: i64) -> Result<SettingsChange> {
+    update(pool, user_id, &AppSettings::default()).await
+}
+
+/// 导出设置为 JSON 文件
+pub async fn export(pool: &Pool<Sqlite>, target: &Path) -> Result<()> {
+    let file = SettingsFile {
+        format: FILE_FORMAT.to_string(),
+        version: FILE_VERSION,
+        settings: load(pool).await?,
+    };
+    tokio::fs::write(target, serde_json::to_vec_pretty(&file)?).await?;
+    Ok(())
+}
+
+/// 从 JSON 文件导入设置（文件中缺少的项使用默认值）
+pub async fn import(pool: &Pool<Sqlite>, user_id: i64, source: &Path) -> Result<SettingsChange> {
+    let data = tokio::fs::read(source).await?;
+    let file: SettingsFile =
+        serde_json::from_slice(&data).map_err(|e| anyhow!("设置文件无效: {}", e))?;
+    if file.format != FILE_FORMAT {
+        return Err(anyhow!("不是 FlareMail 设置文件"));
+    }
+    if file.version > FILE_VERSION {
+        return Err(anyhow!(
+            "设置文件版本 {} 高于当前支持的版本 {}",
+            file.version,
+            FILE_VERSION
+        ));
+    }
+
+    let change = save(pool, &file.settings).await?;
+    audit::record(
+        pool,
+        audit::ACTION_SETTINGS_IMPORT,
+        AuditEvent {
+            user_id: Some(user_id),
+            detail: Some(change.changed.join(",")),
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(change)
+}
+
+/// 在同一事务中写入有变化的项，并更新内存缓存
+async fn save(pool: &Pool<Sqlite>, settings: &AppSettings) -> Result<SettingsChange> {
+    validate(settings)?;
+    let previous = load(pool).await?;
+    let old_entries: HashMap<String, String> = to_entries(&previous)?.into_iter().collect();
+
+    let mut changed = Vec::new();
+    let mut tx = pool.begin().await?;
+    for (key, value) in to_entries(settings)? {
+        if old_entries.get(&key) == Some(&value) {
+            continue;
+        }
+        let description = SETTING_DESCRIPTIONS
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, d)| *d);
+        sqlx::query(
+            r#"INSERT INTO system_config (key, value, description) VALUES (?, ?, ?)
+ON CONFLICT(key) DO UPDATE
+SET value = excluded.value,
+    description = COALESCE(excluded.description, system_config.description),
+    updated_at = CURRENT_TIMESTAMP"#,
+        )
+        .bind(&key)
+        .bind(&value)
+        .bind(description)
+        .execute(&mut *tx)
+        .await?;
+        changed.push(key);
+    }
+    tx.commit().await?;
+
+    *cache().write().unwrap_or_else(|e| e.into_inner()) = settings.clone();
+    Ok(SettingsChange {
+        settings: settings.clone(),
+        changed,
+    })
+}
+
+/// 校验设置取值
+fn validate(settings: &AppSettings) -> Result<()> {
+    check_range(
+        "watcher.default_interval_secs",
+        settings.watcher.default_interval_secs,
+        10,
+        86400,
+    )?;
+    check_range(
+        "sync.fetch_window",
+        settings.sync.fetch_window as u64,
+        10,
+        1000,
+    )?;
+    check_range(
+        "network.request_timeout_secs",
+        settings.network.request_timeout_secs,
+        5,
+        600,
+    )?;
+    check_range(
+        "network.connect_timeout_secs",
+        settings.network.connect_timeout_secs,
+        3,
+        120,
+    )?;
+
+    if !ALLOWED_IMAP_HOSTS.contains(&settings.imap.host.as_str()) {
+        return Err(anyhow!(
+            "imap.host 只能是以下服务器之一: {}",
+            ALLOWED_IMAP_HOSTS.join(", ")
+        ));
+    }
+    if settings.imap.port == 0 {
+        return Err(anyhow!("imap.port 无效"));
+    }
+    Ok(())
+}
+
+fn check_range(key: &str, value: u64, min: u64, max: u64) -> Result<()> {
+    if !(min..=max).contains(&value) {
+        return Err(anyhow!("{} 需在 {} 到 {} 之间", key, min, max));
+    }
+    Ok(())
+}
+
+/// 展开为 (分组.字段, 值) 列表，字符串保存原文，其他类型保存 JSON
+fn to_entries(settings: &AppSettings) -> Result<Vec<(String, String)>> {
+    let value = serde_json::to_value(settings)?;
+    let mut entries = Vec::new();
+    for (section, fields) in value.as_object().into_iter().flatten() {
+        for (field, value) in fields.as_object().into_iter().flatten() {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            entries.push((format!("{section}.{field}"), value));
+        }
+    }
+    Ok(entries)
+}
+
+/// 从 system_config 的键值还原设置，缺失或无法解析的项使用默认值
+fn from_entries(rows: &HashMap<String, String>) -> AppSettings {
+    let defaults = AppSettings::default();
+    let Ok(mut value) = serde_json::to_value(&defaults) else {
+        return defaults;
+    };
+
+    for (section, fields) in value.as_object_mut().into_iter().flatten() {
+        for (field, current) in fields.as_object_mut().into_iter().flatten() {
+            let key = format!("{section}.{field}");
+            let Some(raw) = rows.get(&key) else {
+                continue;
+            };
+            let parsed = match current {
+                serde_json::Value::String(_) => Some(serde_json::Value::String(raw.clone())),
+                _ => serde_json::from_str(raw).ok(),
+            };
+            match parsed {
+                Some(parsed) => *current = parsed,
+                None => log::warn!("忽略无效的设置项: {}={}", key, raw),
+            }
+        }
+    }
+
+    match serde_json::from_value::<AppSettings>(value) {
+        Ok(settings) if validate(&settings).is_ok() => settings,
+        Ok(settings) => {
+            log::warn!(
+                "设置项超出范围，使用默认值: {}",
+                validate(&settings).unwrap_err()
+            );
+            defaults
+        }
+        Err(e) => {
+            log::warn!("读取设置失败，使用默认值: {}", e);
+            defaults
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_roundtrip() {
+        let mut settings = AppSettings::default();
+        settings.imap.host = "imap-mail.outlook.com".to_string();
+        settings.sync.fetch_window = 250;
+
+        let entries = to_entries(&settings).unwrap();
+        assert_eq!(entries.len(), SETTING_DESCRIPTIONS.len());
+        assert!(entries
+            .iter()
+            .all(|(key, _)| SETTING_DESCRIPTIONS.iter().any(|(k, _)| k == key)));
+        assert!(entries.contains(&("imap.host".to_string(), "imap-mail.outlook.com".to_string())));
+
+        let rows: HashMap<String, String> = entries.into_iter().collect();
+        assert_eq!(from_entries(&rows), settings);
+    }
+
+    #[test]
+    fn test_from_entries_ignores_invalid() {
+        let rows = HashMap::from([
+            ("imap.port".to_string(), "abc".to_string()),
+            ("sync.fetch_window".to_string(), "200".to_string()),
+        ]);
+        let settings = from_entries(&rows);
+        assert_eq!(settings.imap.port, 993);
+        assert_eq!(settings.sync.fetch_window, 200);
+
+        let rows = HashMap::from([("sync.fetch_window".to_string(), "5".to_string())]);
+        assert_eq!(from_entries(&rows), AppSettings::default());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&AppSettings::default()).is_ok());
+        let mut settings = AppSettings::default();
+        settings.imap.host = "imap.attacker.example".to_string();
+        assert!(validate(&settings).is_err());
+        settings.imap.host = "imap-mail.outlook.com".to_string();
+        assert!(validate(&settings).is_ok());
+        let mut settings = AppSettings::default();
+        settings.network.request_timeout_secs = 1;
+        assert!(validate(&settings).is_err());
+    }
+}
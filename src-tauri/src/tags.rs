@@ -0,0 +1,358 @@
+//! 账号标签模块
+//! 提供账号标签（分组）与备注管理，标签与账号为多对多关系；标签归属用户，只能关联到自己的账号
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+use std::collections::HashMap;
+
+/// 标签信息
+#[derive(Debug, Clone, sqlx::FromRow, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub color: Option<String>,
+    /// 关联的账号数量
+    pub account_count: i64,
+}
+
+/// 账号上的标签（随邮箱列表返回）
+#[derive(Debug, Clone, sqlx::FromRow, Serialize, Deserialize)]
+pub struct AccountTag {
+    pub id: i64,
+    pub name: String,
+    pub color: Option<String>,
+}
+
+/// 批量关联结果
+#[derive(Debug, Serialize)]
+pub struct TagAssignResult {
+    /// 实际新增或移除的关联数量
+    pub affected: u64,
+}
+
+/// 创建标签
+pub async fn create_tag(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    name: &str,
+    color: Option<&str>,
+) -> Result<i64> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("标签名称不能为空"));
+    }
+
+    let id: Option<i64> = sqlx::query_scalar(
+        "INSERT INTO tags (user_id, name, color) VALUES (?, ?, ?) ON CONFLICT(user_id, name) DO NOTHING RETURNING id",
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(color)
+    .fetch_optional(pool)
+    .await?;
+
+    id.ok_or_else(|| anyhow!("标签已存在"))
+}
+
+/// 修改标签名称与颜色
+pub async fn update_tag(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    tag_id: i64,
+    name: &str,
+    color: Option<&str>,
+) -> Result<bool> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("标签名称不能为空"));
+    }
+
+    let result = sqlx::query("UPDATE tags SET name = ?, color = ? WHERE id = ? AND user_id = ?")
+        .bind(name)
+        .bind(color)
+        .bind(tag_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// 删除标签（关联记录随之级联删除）
+pub async fn delete_tag(pool: &Pool<Sqlite>, user_id: i64, tag_id: i64) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM tags WHERE id = ? AND user_id = ?")
+        .bind(tag_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// 获取标签列表（含账号数量）
+pub async fn list_tags(pool: &Pool<Sqlite>, user_id: i64) -> Result<Vec<Tag>> {
+    let tags = sqlx::query_as::<_, Tag>(
+        r#"SELECT t.id, t.name, t.color, COUNT(e.id) AS account_count
+FROM tags t
+LEFT JOIN email_tags et ON et.tag_id = t.id
+LEFT JOIN emails e ON e.id = et.email_id AND e.deleted_at IS NULL AND e.user_id = t.user_id
+WHERE t.user_id = ?
+GROUP BY t.id
+ORDER BY t.name"#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tags)
+}
+
+/// 批量为账号添加标签（账号需已确认属于该用户）
+pub async fn assign_tags(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    email_ids: &[i64],
+    tag_ids: &[i64],
+) -> Result<TagAssignResult> {
+    ensure_owned(pool, user_id, tag_ids).await?;
+    let mut affected = 0u64;
+    let mut tx = pool.begin().await?;
+
+    for email_id in email_ids {
+        for tag_id in tag_ids {
+            let result =
+                sqlx::query("INSERT OR IGNORE INTO email_tags (email_id, tag_id) VALUES (?, ?)")
+                    .bind(email_id)
+                    .bind(tag_id)
+                    .execute(&mut *tx)
+                    .await?;
+            affected += result.rows_affected();
+        }
+    }
+
+    tx.commit().await?;
+    Ok(TagAssignResult { affected })
+}
+
+/// 批量移除账号上的标签（账号需已确认属于该用户）
+pub async fn unassign_tags(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    email_ids: &[i64],
+    tag_ids: &[i64],
+) -> Result<TagAssignResult> {
+    ensure_owned(pool, user_id, tag_ids).await?;
+    let mut affected = 0u64;
+    let mut tx = pool.begin().await?;
+
+    for email_id in email_ids {
+        for tag_id in tag_ids {
+            let result = sqlx::query("DELETE FROM email_tags WHERE email_id = ? AND tag_id = ?")
+                .bind(email_id)
+                .bind(tag_id)
+                .execute(&mut *tx)
+                .await?;
+            affected += result.rows_affected();
+        }
+    }
+
+    tx.commit().await?;
+    Ok(TagAssignResult { affected })
+}
+
+/// 确认标签均属于该用户
+pub async fn ensure_owned(pool: &Pool<Sqlite>, user_id: i64, tag_ids: &[i64]) -> Result<()> {
+    if tag_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT id FROM tags WHERE user_id = ");
+    qb.push_bind(user_id);
+    qb.push(" AND id IN (");
+    let mut separated = qb.separated(", ");
+    for id in tag_ids {
+        separated.push_bind(id);
+    }
+    qb.push(")");
+    let owned: Vec<i64> = qb.build_query_scalar().fetch_all(pool).await?;
+    if tag_ids.iter().any(|id| !owned.contains(id)) {
+        return Err(anyhow!("标签不存在"));
+    }
+    Ok(())
+}
+
+/// 获取多个账号的标签 (email_id -> tags)
+pub async fn get_tags_for_emails(
+    pool: &Pool<Sqlite>,
+    email_ids: &[i64],
+) -> Result<HashMap<i64, Vec<AccountTag>>> {
+    let mut map: HashMap<i64, Vec<AccountTag>> = HashMap::new();
+    if email_ids.is_empty() {
+        return Ok(map);
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT et.email_id, t.id, t.name, t.color FROM email_tags et JOIN emails e ON e.id = et.email_id JOIN tags t ON t.id = et.tag_id AND t.user_id = e.user_id WHERE et.email_id IN (",
+    );
+    let mut separated = qb.separated(", ");
+    for id in email_ids {
+        separated.push_bind(id);
+    }
+    qb.push(") ORDER BY t.name");
+
+    let rows = qb
+        .build_query_as::<(i64, i64, String, Option<String>)>()
+        .fetch_all(pool)
+        .await?;
+
+    for (email_id, id, name, color) in rows {
+        map.entry(email_id)
+            .or_default()
+            .push(AccountTag { id, name, color });
+    }
+
+    Ok(map)
+}
+
+/// 设置账号备注
+pub async fn set_email_notes(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    notes: Option<&str>,
+) -> Result<bool> {
+    let notes = notes.map(str::trim).filter(|n| !n.is_empty());
+    let result =
+        sqlx::query("UPDATE emails SET notes = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(notes)
+            .bind(email_id)
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::email::{self, EmailFilter};
+
+    async fn insert_email(pool: &Pool<Sqlite>, email: &str) -> i64 {
+        sqlx::query_scalar(
+            "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES (?, '', 'cid', '', 1) RETURNING id",
+        )
+        .bind(email)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tags_scoped_to_user() {
+        let pool = db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO users (id, username, password_hash, salt, rounds) VALUES (2, 'other', '', '', 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let mine = create_tag(&pool, 1, "work", None).await.unwrap();
+        let theirs = create_tag(&pool, 2, "work", Some("#f00")).await.unwrap();
+        assert!(create_tag(&pool, 1, "work", None).await.is_err());
+
+        let tags = list_tags(&pool, 1).await.unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].id, mine);
+
+        assert!(!update_tag(&pool, 1, theirs, "x", None).await.unwrap());
+        assert!(!delete_tag(&pool, 1, theirs).await.unwrap());
+        assert!(ensure_owned(&pool, 1, &[mine, theirs]).await.is_err());
+        assert!(assign_tags(&pool, 1, &[], &[theirs]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_assign_and_unassign() {
+        let pool = db::test_pool().await;
+        let a = insert_email(&pool, "a@x.com").await;
+        let b = insert_email(&pool, "b@x.com").await;
+        let work = create_tag(&pool, 1, "work", None).await.unwrap();
+        let vip = create_tag(&pool, 1, "vip", Some("#f00")).await.unwrap();
+
+        let result = assign_tags(&pool, 1, &[a, b], &[work, vip]).await.unwrap();
+        assert_eq!(result.affected, 4);
+        // 已存在的关联不重复计数
+        let result = assign_tags(&pool, 1, &[a, b], &[work]).await.unwrap();
+        assert_eq!(result.affected, 0);
+
+        let counts: Vec<(String, i64)> = list_tags(&pool, 1)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.name, t.account_count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("vip".to_string(), 2), ("work".to_string(), 2)]
+        );
+
+        let result = unassign_tags(&pool, 1, &[a], &[work, vip]).await.unwrap();
+        assert_eq!(result.affected, 2);
+        let map = get_tags_for_emails(&pool, &[a, b]).await.unwrap();
+        assert!(!map.contains_key(&a));
+        let names: Vec<&str> = map[&b].iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["vip", "work"]);
+
+        // 删除标签后关联随之删除
+        assert!(delete_tag(&pool, 1, vip).await.unwrap());
+        let map = get_tags_for_emails(&pool, &[b]).await.unwrap();
+        assert_eq!(map[&b].len(), 1);
+        assert_eq!(map[&b][0].id, work);
+    }
+
+    #[tokio::test]
+    async fn test_filter_by_tag() {
+        let pool = db::test_pool().await;
+        let a = insert_email(&pool, "a@x.com").await;
+        let b = insert_email(&pool, "b@x.com").await;
+        let c = insert_email(&pool, "c@x.com").await;
+        let work = create_tag(&pool, 1, "work", None).await.unwrap();
+        assign_tags(&pool, 1, &[a, c], &[work]).await.unwrap();
+
+        let filter = EmailFilter {
+            tag_id: Some(work),
+            user_id: Some(1),
+            ..Default::default()
+        };
+        let mut ids: Vec<i64> = email::get_emails(&pool, &filter)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![a, c]);
+
+        // 显式 ID 与标签筛选取交集
+        let ids = email::resolve_email_ids(&pool, &[b, c], &filter)
+            .await
+            .unwrap();
+        assert_eq!(ids, vec![c]);
+
+        // 已删除的账号不计入标签账号数，也不出现在筛选结果中
+        sqlx::query("UPDATE emails SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(a)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(list_tags(&pool, 1).await.unwrap()[0].account_count, 1);
+        let ids: Vec<i64> = email::get_emails(&pool, &filter)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![c]);
+    }
+}
@@ -9,6 +9,8 @@ use sqlx::{Pool, Sqlite};
 use std::collections::HashMap;
 use std::sync::RwLock;
 
+use crate::vault;
+
 /// 内存缓存结构
 static TOKEN_CACHE: RwLock<Option<HashMap<i64, CachedToken>>> = RwLock::new(None);
 
@@ -78,6 +80,10 @@ pub async fn get_cached_token_db(
     .await?;
 
     if let (Some(token), Some(expires_str)) = row {
+        // 保险库锁定时无法解密，视为没有缓存
+        let Ok(token) = vault::open(&token) else {
+            return Ok(None);
+        };
         if let Ok(expires_at) = DateTime::parse_from_rfc3339(&expires_str) {
             let cached = CachedToken {
                 access_token: token,
@@ -102,10 +108,11 @@ pub async fn set_cached_token_db(
     let expires_at = Utc::now() + Duration::seconds(expires_in_secs);
     let expires_str = expires_at.to_rfc3339();
 
+    let _seal_guard = vault::seal_guard().await;
     sqlx::query(
         "UPDATE emails SET cached_token = ?, token_expires_at = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
     )
-    .bind(access_token)
+    .bind(vault::seal(access_token)?)
     .bind(&expires_str)
     .bind(email_id)
     .execute(pool)
@@ -158,3 +165,12 @@ pub fn clear_token_cache(email_id: i64) {
         map.remove(&email_id);
     }
 }
+
+/// 清除全部邮箱的 Token 缓存（保险库锁定时调用）
+pub fn clear_all_token_cache() {
+    init_cache();
+    let mut cache = TOKEN_CACHE.write().unwrap();
+    if let Some(ref mut map) = *cache {
+        map.clear();
+    }
+}
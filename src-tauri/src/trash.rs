@@ -0,0 +1,593 @@
+//! 本地回收站模块
+//! 删除邮箱账号、在服务器上彻底删除邮件以及同步时发现服务器已删除的邮件，都只在本地标记删除时间与原因；
+//! 回收站中的记录可以恢复，超过保留期限后由定时任务连同附件内容一并彻底清除
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sqlx::{Pool, QueryBuilder, Sqlite};
+
+use crate::audit::{self, AuditEvent};
+use crate::{blob_store, db};
+
+/// 用户删除
+pub const REASON_USER: &str = "user";
+/// 同步时服务器上已不存在
+pub const REASON_SYNC: &str = "sync";
+
+const CONFIG_RETENTION_DAYS: &str = "trash.retention_days";
+
+/// 默认保留天数
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// 最长保留天数
+const MAX_RETENTION_DAYS: i64 = 3650;
+
+/// 清理过期记录的间隔（秒）
+const PURGE_CHECK_SECS: u64 = 3600;
+
+/// 回收站中的邮箱账号
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TrashedAccount {
+    pub id: i64,
+    pub email: String,
+    pub mail_type: Option<String>,
+    pub deleted_at: String,
+    pub deleted_reason: Option<String>,
+    /// 账号下的邮件数（随账号一起恢复）
+    pub mail_count: i64,
+}
+
+/// 回收站中的邮件
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TrashedMail {
+    pub id: i64,
+    pub email_id: i64,
+    pub email: String,
+    pub subject: Option<String>,
+    pub sender: Option<String>,
+    pub received_time: Option<String>,
+    pub folder: Option<String>,
+    pub has_attachments: i64,
+    pub deleted_at: String,
+    pub deleted_reason: Option<String>,
+    /// 所属账号也在回收站中（恢复后需等账号恢复才可见）
+    pub account_deleted: bool,
+}
+
+/// 彻底清除的结果
+#[derive(Debug, Default, Serialize)]
+pub struct PurgeResult {
+    pub accounts: u64,
+    pub mails: u64,
+}
+
+/// 清除范围
+enum PurgeScope {
+    /// 指定用户回收站中的全部记录
+    User(i64),
+    /// 删除时间早于指定天数的记录
+    Expired(i64),
+}
+
+/// 将邮件移入回收站，返回实际移入的邮件 ID（已在回收站中的跳过）
+pub async fn trash_mails(pool: &Pool<Sqlite>, mail_ids: &[i64], reason: &str) -> Result<Vec<i64>> {
+    if mail_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "UPDATE mail_records SET deleted_at = CURRENT_TIMESTAMP, deleted_reason = ",
+    );
+    qb.push_bind(reason);
+    qb.push(" WHERE deleted_at IS NULL AND id IN (");
+    let mut separated = qb.separated(", ");
+    for id in mail_ids {
+        separated.push_bind(id);
+    }
+    qb.push(") RETURNING id");
+
+    let trashed = qb.build_query_scalar::<i64>().fetch_all(pool).await?;
+    Ok(trashed)
+}
+
+/// 将邮箱账号移入回收站，返回邮箱地址（账号不存在或已在回收站中时返回 None）
+pub async fn trash_account(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    reason: &str,
+) -> Result<Option<String>> {
+    let email = sqlx::query_scalar::<_, String>(
+        "UPDATE emails SET deleted_at = CURRENT_TIMESTAMP, deleted_reason = ? WHERE id = ? AND deleted_at IS NULL RETURNING email",
+    )
+    .bind(reason)
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(email)
+}
+
+/// 列出用户回收站中的邮箱账号
+pub async fn list_accounts(pool: &Pool<Sqlite>, user_id: i64) -> Result<Vec<TrashedAccount>> {
+    let accounts = sqlx::query_as::<_, TrashedAccount>(
+        r#"SELECT e.id, e.email, e.mail_type, e.deleted_at, e.deleted_reason,
+    (SELECT COUNT(*) FROM mail_records m WHERE m.email_id = e.id) AS mail_count
+FROM emails e
+WHERE e.user_id = ? AND e.deleted_at IS NOT NULL
+ORDER BY e.deleted_at DESC, e.id DESC"#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(accounts)
+}
+
+/// 列出用户回收站中的邮件，可按账号过滤
+pub async fn list_mails(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    email_id: Option<i64>,
+) -> Result<Vec<TrashedMail>> {
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        r#"SELECT m.id, m.email_id, e.email, m.subject, m.sender, m.received_time, m.folder, m.has_attachments,
+    m.deleted_at, m.deleted_reason, e.deleted_at IS NOT NULL AS account_deleted
+FROM mail_records m JOIN emails e ON e.id = m.email_id
+WHERE m.deleted_at IS NOT NULL AND e.user_id = "#,
+    );
+    qb.push_bind(user_id);
+    if let Some(email_id) = email_id {
+        qb.push(" AND m.email_id = ");
+        qb.push_bind(email_id);
+    }
+    qb.push(" ORDER BY m.deleted_at DESC, m.id DESC");
+
+    let mails = qb.build_query_as::<TrashedMail>().fetch_all(pool).await?;
+    Ok(mails)
+}
+
+/// 从回收站恢复邮箱账号，返回恢复的账号 ID
+pub async fn restore_accounts(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    email_ids: &[i64],
+) -> Result<Vec<i64>> {
+    if email_ids.is_empty() {
+        return Err(anyhow!("未选择邮箱"));
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "UPDATE emails SET deleted_at = NULL, deleted_reason = NULL WHERE deleted_at IS NOT NULL AND user_id = ",
+    );
+    qb.push_bind(user_id);
+    qb.push(" AND id IN (");
+    let mut separated = qb.separated(", ");
+    for id in email_ids {
+        separated.push_bind(id);
+    }
+    qb.push(") RETURNING id");
+    let restored = qb.build_query_scalar::<i64>().fetch_all(pool).await?;
+
+    if !restored.is_empty() {
+        audit::record(
+            pool,
+            audit::ACTION_ACCOUNT_RESTORE,
+            AuditEvent {
+                user_id: Some(user_id),
+                email_id: (restored.len() == 1).then(|| restored[0]),
+                target_ids: &restored,
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+    Ok(restored)
+}
+
+/// 从回收站恢复邮件，返回恢复的邮件 ID
+/// 仅恢复本地记录，服务器上已删除的邮件不会重新上传
+pub async fn restore_mails(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    mail_ids: &[i64],
+) -> Result<Vec<i64>> {
+    if mail_ids.is_empty() {
+        return Err(anyhow!("未选择邮件"));
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "UPDATE mail_records SET deleted_at = NULL, deleted_reason = NULL WHERE deleted_at IS NOT NULL AND email_id IN (SELECT id FROM emails WHERE user_id = ",
+    );
+    qb.push_bind(user_id);
+    qb.push(") AND id IN (");
+    let mut separated = qb.separated(", ");
+    for id in mail_ids {
+        separated.push_bind(id);
+    }
+    qb.push(") RETURNING id");
+    let restored = qb.build_query_scalar::<i64>().fetch_all(pool).await?;
+
+    if !restored.is_empty() {
+        audit::record(
+            pool,
+            audit::ACTION_MAIL_RESTORE,
+            AuditEvent {
+                user_id: Some(user_id),
+                target_ids: &restored,
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+    Ok(restored)
+}
+
+/// 恢复因同步移入回收站的邮件（服务器上重新出现时调用），用户删除的邮件不受影响
+/// 返回恢复的邮件 ID
+pub async fn restore_synced_mails(pool: &Pool<Sqlite>, mail_ids: &[i64]) -> Result<Vec<i64>> {
+    if mail_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "UPDATE mail_records SET deleted_at = NULL, deleted_reason = NULL WHERE deleted_reason = ",
+    );
+    qb.push_bind(REASON_SYNC);
+    qb.push(" AND id IN (");
+    let mut separated = qb.separated(", ");
+    for id in mail_ids {
+        separated.push_bind(id);
+    }
+    qb.push(") RETURNING id");
+
+    let restored = qb.build_query_scalar::<i64>().fetch_all(pool).await?;
+    Ok(restored)
+}
+
+/// 清空用户的回收站
+pub async fn empty_trash(pool: &Pool<Sqlite>, user_id: i64) -> Result<PurgeResult> {
+    let result = purge(pool, PurgeScope::User(user_id)).await?;
+    audit::record(
+        pool,
+        audit::ACTION_TRASH_PURGE,
+        AuditEvent {
+            user_id: Some(user_id),
+            detail: Some(format!(
+                "accounts={},mails={}",
+                result.accounts, result.mails
+            )),
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(result)
+}
+
+/// 彻底清除超过保留期限的回收站记录，0 表示永久保留
+pub async fn purge_expired(pool: &Pool<Sqlite>) -> Result<PurgeResult> {
+    let days = get_retention_days(pool).await?;
+    if days <= 0 {
+        return Ok(PurgeResult::default());
+    }
+
+    let result = purge(pool, PurgeScope::Expired(days)).await?;
+    if result.accounts > 0 || result.mails > 0 {
+        log::info!(
+            "已清除过期回收站记录: accounts={}, mails={}",
+            result.accounts,
+            result.mails
+        );
+        audit::record(
+            pool,
+            audit::ACTION_TRASH_PURGE,
+            AuditEvent {
+                detail: Some(format!(
+                    "expired,accounts={},mails={}",
+                    result.accounts, result.mails
+                )),
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+    Ok(result)
+}
+
+/// 读取回收站保留天数，0 表示永久保留
+pub async fn get_retention_days(pool: &Pool<Sqlite>) -> Result<i64> {
+    Ok(db::get_config(pool, CONFIG_RETENTION_DAYS)
+        .await?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS))
+}
+
+/// 设置回收站保留天数，0 表示永久保留
+pub async fn set_retention_days(pool: &Pool<Sqlite>, user_id: i64, days: i64) -> Result<()> {
+    if !(0..=MAX_RETENTION_DAYS).contains(&days) {
+        return Err(anyhow!("保留天数需在 0 到 {} 之间", MAX_RETENTION_DAYS));
+    }
+
+    db::set_config(
+        pool,
+        CONFIG_RETENTION_DAYS,
+        &days.to_string(),
+        Some("回收站保留天数（0 表示永久保留）"),
+    )
+    .await?;
+    audit::record(
+        pool,
+        audit::ACTION_TRASH_RETENTION,
+        AuditEvent {
+            user_id: Some(user_id),
+            detail: Some(format!("days={days}")),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// 后台定期清除过期回收站记录
+pub async fn run_purge(pool: Pool<Sqlite>) {
+    loop {
+        if let Err(e) = purge_expired(&pool).await {
+            log::warn!("清除过期回收站记录失败: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(PURGE_CHECK_SECS)).await;
+    }
+}
+
+/// 删除回收站记录：先删除邮件（及附件），再删除账号（邮件与附件随账号级联删除），最后回收附件内容
+async fn purge(pool: &Pool<Sqlite>, scope: PurgeScope) -> Result<PurgeResult> {
+    let (mail_scope, account_scope, value) = match scope {
+        PurgeScope::User(user_id) => (
+            "email_id IN (SELECT id FROM emails WHERE user_id = ?)",
+            "user_id = ?",
+            user_id,
+        ),
+        PurgeScope::Expired(days) => (
+            "deleted_at < datetime('now', '-' || ? || ' days')",
+            "deleted_at < datetime('now', '-' || ? || ' days')",
+            days,
+        ),
+    };
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!(
+        "DELETE FROM attachments WHERE mail_id IN (SELECT id FROM mail_records WHERE deleted_at IS NOT NULL AND {mail_scope})"
+    ))
+    .bind(value)
+    .execute(&mut *tx)
+    .await?;
+    let mails = sqlx::query(&format!(
+        "DELETE FROM mail_records WHERE deleted_at IS NOT NULL AND {mail_scope}"
+    ))
+    .bind(value)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    let accounts = sqlx::query(&format!(
+        "DELETE FROM emails WHERE deleted_at IS NOT NULL AND {account_scope}"
+    ))
+    .bind(value)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    tx.commit().await?;
+
+    if mails > 0 || accounts > 0 {
+        blob_store::collect_garbage_quietly(pool).await;
+    }
+    Ok(PurgeResult { accounts, mails })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn insert_account(pool: &Pool<Sqlite>, user_id: i64, email: &str) -> i64 {
+        sqlx::query_scalar(
+            "INSERT INTO emails (email, password, client_id, refresh_token, user_id) VALUES (?, '', 'cid', '', ?) RETURNING id",
+        )
+        .bind(email)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_mail(pool: &Pool<Sqlite>, email_id: i64, subject: &str) -> i64 {
+        sqlx::query_scalar(
+            "INSERT INTO mail_records (email_id, subject, folder) VALUES (?, ?, 'inbox') RETURNING id",
+        )
+        .bind(email_id)
+        .bind(subject)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn count(pool: &Pool<Sqlite>, sql: &str) -> i64 {
+        sqlx::query_scalar(sql).fetch_one(pool).await.unwrap()
+    }
+
+    /// 两个用户各一个账号、一封邮件，返回 (用户 1 的账号, 用户 1 的邮件, 用户 2 的账号, 用户 2 的邮件)
+    async fn setup() -> (Pool<Sqlite>, i64, i64, i64, i64) {
+        let pool = db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO users (id, username, password_hash, salt, rounds) VALUES (2, 'other', '', '', 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let own_account = insert_account(&pool, 1, "a@x.com").await;
+        let own_mail = insert_mail(&pool, own_account, "mine").await;
+        let other_account = insert_account(&pool, 2, "b@x.com").await;
+        let other_mail = insert_mail(&pool, other_account, "theirs").await;
+        (pool, own_account, own_mail, other_account, other_mail)
+    }
+
+    #[tokio::test]
+    async fn test_trash_and_restore_account() {
+        let (pool, own_account, _, other_account, _) = setup().await;
+
+        let email = trash_account(&pool, own_account, REASON_USER)
+            .await
+            .unwrap();
+        assert_eq!(email.as_deref(), Some("a@x.com"));
+        // 已在回收站中的账号不重复移入
+        assert!(trash_account(&pool, own_account, REASON_USER)
+            .await
+            .unwrap()
+            .is_none());
+        trash_account(&pool, other_account, REASON_USER)
+            .await
+            .unwrap();
+
+        let accounts = list_accounts(&pool, 1).await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!((accounts[0].id, accounts[0].mail_count), (own_account, 1));
+        assert_eq!(accounts[0].deleted_reason.as_deref(), Some(REASON_USER));
+
+        // 只能恢复自己的账号
+        let restored = restore_accounts(&pool, 1, &[own_account, other_account])
+            .await
+            .unwrap();
+        assert_eq!(restored, vec![own_account]);
+        assert!(list_accounts(&pool, 1).await.unwrap().is_empty());
+        assert_eq!(list_accounts(&pool, 2).await.unwrap().len(), 1);
+        assert!(restore_accounts(&pool, 1, &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_trash_and_restore_mails() {
+        let (pool, own_account, own_mail, _, other_mail) = setup().await;
+        let kept = insert_mail(&pool, own_account, "kept").await;
+
+        let trashed = trash_mails(&pool, &[own_mail, other_mail], REASON_USER)
+            .await
+            .unwrap();
+        assert_eq!(trashed, vec![own_mail, other_mail]);
+        assert!(trash_mails(&pool, &[own_mail], REASON_USER)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mails = list_mails(&pool, 1, None).await.unwrap();
+        assert_eq!(mails.len(), 1);
+        assert_eq!(mails[0].id, own_mail);
+        assert!(!mails[0].account_deleted);
+        assert!(list_mails(&pool, 1, Some(own_account + 100))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // 只能恢复自己账号下的邮件，未删除的邮件不受影响
+        let restored = restore_mails(&pool, 1, &[own_mail, other_mail, kept])
+            .await
+            .unwrap();
+        assert_eq!(restored, vec![own_mail]);
+        assert!(list_mails(&pool, 1, None).await.unwrap().is_empty());
+        assert_eq!(list_mails(&pool, 2, None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_restore_synced_mails_keeps_user_deletions() {
+        let (pool, own_account, synced, _, _) = setup().await;
+        let deleted = insert_mail(&pool, own_account, "deleted by user").await;
+        trash_mails(&pool, &[synced], REASON_SYNC).await.unwrap();
+        trash_mails(&pool, &[deleted], REASON_USER).await.unwrap();
+
+        let restored = restore_synced_mails(&pool, &[synced, deleted])
+            .await
+            .unwrap();
+        assert_eq!(restored, vec![synced]);
+        let mails = list_mails(&pool, 1, None).await.unwrap();
+        assert_eq!(mails.len(), 1);
+        assert_eq!(mails[0].id, deleted);
+        assert!(restore_synced_mails(&pool, &[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_empty_trash_only_purges_own_records() {
+        let (pool, own_account, own_mail, other_account, other_mail) = setup().await;
+        let kept = insert_mail(&pool, own_account, "kept").await;
+        trash_mails(&pool, &[own_mail, other_mail], REASON_USER)
+            .await
+            .unwrap();
+        trash_account(&pool, other_account, REASON_USER)
+            .await
+            .unwrap();
+
+        let result = empty_trash(&pool, 1).await.unwrap();
+        assert_eq!((result.accounts, result.mails), (0, 1));
+        let remaining: Vec<i64> = sqlx::query_scalar("SELECT id FROM mail_records ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![other_mail, kept]);
+
+        let result = empty_trash(&pool, 2).await.unwrap();
+        assert_eq!((result.accounts, result.mails), (1, 1));
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM emails").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired() {
+        let (pool, own_account, own_mail, other_account, other_mail) = setup().await;
+        trash_mails(&pool, &[own_mail, other_mail], REASON_USER)
+            .await
+            .unwrap();
+        trash_account(&pool, other_account, REASON_SYNC)
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE mail_records SET deleted_at = datetime('now', '-40 days') WHERE id = ?",
+        )
+        .bind(own_mail)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // 默认保留 30 天，只清除过期的邮件
+        let result = purge_expired(&pool).await.unwrap();
+        assert_eq!((result.accounts, result.mails), (0, 1));
+        assert_eq!(list_mails(&pool, 2, None).await.unwrap().len(), 1);
+
+        // 0 表示永久保留
+        sqlx::query("UPDATE emails SET deleted_at = datetime('now', '-400 days') WHERE id = ?")
+            .bind(other_account)
+            .execute(&pool)
+            .await
+            .unwrap();
+        set_retention_days(&pool, 1, 0).await.unwrap();
+        let result = purge_expired(&pool).await.unwrap();
+        assert_eq!((result.accounts, result.mails), (0, 0));
+
+        // 过期账号连同其邮件一起清除
+        set_retention_days(&pool, 1, 365).await.unwrap();
+        let result = purge_expired(&pool).await.unwrap();
+        assert_eq!((result.accounts, result.mails), (1, 0));
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM mail_records").await, 0);
+        let accounts: Vec<i64> = sqlx::query_scalar("SELECT id FROM emails")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(accounts, vec![own_account]);
+    }
+
+    #[tokio::test]
+    async fn test_retention_days() {
+        let pool = db::test_pool().await;
+        assert_eq!(
+            get_retention_days(&pool).await.unwrap(),
+            DEFAULT_RETENTION_DAYS
+        );
+        set_retention_days(&pool, 1, 7).await.unwrap();
+        assert_eq!(get_retention_days(&pool).await.unwrap(), 7);
+        assert!(set_retention_days(&pool, 1, -1).await.is_err());
+        assert!(set_retention_days(&pool, 1, MAX_RETENTION_DAYS + 1)
+            .await
+            .is_err());
+        assert_eq!(get_retention_days(&pool).await.unwrap(), 7);
+    }
+}
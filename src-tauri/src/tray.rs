@@ -0,0 +1,435 @@
+//! 系统托盘模块
+//! 托盘图标显示已登录用户的未读数与监听状态，菜单提供显示窗口、立即检查、暂停/恢复全部监听、
+//! 后台运行与开机启动开关。托盘没有会话令牌，与对应命令保持一致：立即检查只作用于已登录用户的账号，
+//! 暂停/恢复全部监听、后台运行与开机启动需要有管理员已登录。开启后台运行（默认关闭）时关闭主窗口只隐藏窗口，监听、备份等后台任务继续运行；
+//! 开机启动时带 --background 参数，直接在托盘中运行并恢复已持久化的监听。
+//! 托盘创建失败时不隐藏窗口，避免窗口关闭后无法再打开
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager, Window, WindowEvent, Wry};
+use tauri_plugin_autostart::ManagerExt;
+
+use crate::auth;
+use crate::db::{self, AppState};
+use crate::email::{self, EmailFilter};
+use crate::mail_state;
+use crate::mail_watcher::MailWatcherManager;
+
+/// 开机启动时传入的参数：启动后不显示主窗口
+pub const BACKGROUND_ARG: &str = "--background";
+
+/// 关闭窗口时是否后台运行
+const CONFIG_RUN_IN_BACKGROUND: &str = "tray.run_in_background";
+
+/// 托盘图标 ID
+const TRAY_ID: &str = "main";
+
+/// 主窗口标签
+const MAIN_WINDOW: &str = "main";
+
+/// 托盘状态刷新间隔（秒）
+const REFRESH_SECS: u64 = 30;
+
+const MENU_UNREAD: &str = "unread";
+const MENU_SHOW: &str = "show";
+const MENU_CHECK_NOW: &str = "check_now";
+const MENU_TOGGLE_WATCHERS: &str = "toggle_watchers";
+const MENU_BACKGROUND: &str = "run_in_background";
+const MENU_AUTOSTART: &str = "autostart";
+const MENU_QUIT: &str = "quit";
+
+/// 关闭窗口时是否后台运行（窗口事件回调是同步的，缓存配置值）
+static RUN_IN_BACKGROUND: AtomicBool = AtomicBool::new(false);
+
+/// 托盘图标是否已创建
+static TRAY_READY: AtomicBool = AtomicBool::new(false);
+
+/// 上次显示的托盘状态，未变化时不重建菜单
+static LAST_STATE: Mutex<Option<TrayState>> = Mutex::new(None);
+
+/// 后台运行设置
+#[derive(Debug, Serialize)]
+pub struct BackgroundSettings {
+    /// 关闭窗口时隐藏到托盘继续运行
+    pub run_in_background: bool,
+    /// 开机启动（启动后在托盘中运行）
+    pub autostart: bool,
+    /// 是否已暂停全部监听
+    pub watchers_suspended: bool,
+}
+
+/// 托盘显示的状态
+#[derive(Debug, Clone, PartialEq)]
+struct TrayState {
+    /// 已登录用户的未读数，无人登录时为 None
+    unread: Option<i64>,
+    /// 是否有管理员已登录（可使用全局操作）
+    admin: bool,
+    watchers: usize,
+    suspended: bool,
+    run_in_background: bool,
+    autostart: bool,
+}
+
+/// 读取后台运行配置（应用启动时调用）
+pub async fn load_settings(pool: &Pool<Sqlite>) -> Result<()> {
+    let enabled = db::get_config(pool, CONFIG_RUN_IN_BACKGROUND)
+        .await?
+        .map(|v| v == "1")
+        .unwrap_or(false);
+    RUN_IN_BACKGROUND.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// 创建托盘图标
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let state = TrayState {
+        unread: None,
+        admin: false,
+        watchers: 0,
+        suspended: false,
+        run_in_background: RUN_IN_BACKGROUND.load(Ordering::Relaxed),
+        autostart: app.autolaunch().is_enabled().unwrap_or(false),
+    };
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(tooltip(&state))
+        .menu(&build_menu(app, &state)?)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| on_menu_event(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    TRAY_READY.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// 是否可以隐藏到托盘：托盘已创建
+pub fn is_ready() -> bool {
+    TRAY_READY.load(Ordering::Relaxed)
+}
+
+/// 定期刷新托盘的未读数与监听状态
+pub async fn run_refresh(app: AppHandle) {
+    loop {
+        refresh(&app).await;
+        tokio::time::sleep(Duration::from_secs(REFRESH_SECS)).await;
+    }
+}
+
+/// 刷新托盘提示与菜单
+pub async fn refresh(app: &AppHandle) {
+    let state = match current_state(app).await {
+        Ok(state) => state,
+        Err(e) => {
+            log::warn!("获取托盘状态失败: {}", e);
+            return;
+        }
+    };
+    {
+        let mut last = LAST_STATE.lock().unwrap_or_else(|e| e.into_inner());
+        if last.as_ref() == Some(&state) {
+            return;
+        }
+        *last = Some(state.clone());
+    }
+
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let _ = tray.set_tooltip(Some(tooltip(&state)));
+    match build_menu(app, &state) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => log::warn!("更新托盘菜单失败: {}", e),
+    }
+}
+
+/// 关闭主窗口时，开启后台运行则隐藏窗口而不退出
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if let WindowEvent::CloseRequested { api, .. } = event {
+        if window.label() == MAIN_WINDOW && is_ready() && RUN_IN_BACKGROUND.load(Ordering::Relaxed)
+        {
+            api.prevent_close();
+            let _ = window.hide();
+        }
+    }
+}
+
+/// 隐藏主窗口（以后台模式启动时调用）
+pub fn hide_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = window.hide();
+    }
+}
+
+/// 显示并聚焦主窗口
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// 获取后台运行设置
+pub fn get_settings(app: &AppHandle) -> BackgroundSettings {
+    BackgroundSettings {
+        run_in_background: RUN_IN_BACKGROUND.load(Ordering::Relaxed),
+        autostart: app.autolaunch().is_enabled().unwrap_or(false),
+        watchers_suspended: app.state::<Arc<MailWatcherManager>>().is_suspended(),
+    }
+}
+
+/// 设置关闭窗口时是否后台运行
+pub async fn set_run_in_background(pool: &Pool<Sqlite>, enabled: bool) -> Result<()> {
+    db::set_config(
+        pool,
+        CONFIG_RUN_IN_BACKGROUND,
+        if enabled { "1" } else { "0" },
+        Some("关闭窗口时隐藏到托盘继续运行"),
+    )
+    .await?;
+    RUN_IN_BACKGROUND.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// 开启或关闭开机启动
+pub fn set_autostart(app: &AppHandle, enabled: bool) -> Result<()> {
+    let autolaunch = app.autolaunch();
+    if enabled {
+        autolaunch.enable()?;
+    } else {
+        autolaunch.disable()?;
+    }
+    Ok(())
+}
+
+/// 暂停或恢复全部监听，并通知前端
+pub fn set_watchers_suspended(app: &AppHandle, suspended: bool) {
+    let manager = app.state::<Arc<MailWatcherManager>>();
+    if suspended {
+        manager.suspend_all();
+    } else {
+        manager.resume_all();
+    }
+    log::info!("全部监听已{}", if suspended { "暂停" } else { "恢复" });
+    let _ = app.emit(
+        "mail-watchers-suspended",
+        serde_json::json!({ "suspended": suspended }),
+    );
+}
+
+/// 处理托盘菜单点击
+fn on_menu_event(app: &AppHandle, id: &str) {
+    match id {
+        MENU_SHOW => show_main_window(app),
+        MENU_QUIT => app.exit(0),
+        MENU_CHECK_NOW | MENU_TOGGLE_WATCHERS | MENU_BACKGROUND | MENU_AUTOSTART => {
+            let app = app.clone();
+            let id = id.to_string();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = handle_action(&app, &id).await {
+                    log::warn!("托盘操作失败: action={}, error={}", id, e);
+                }
+                refresh(&app).await;
+            });
+        }
+        _ => {}
+    }
+}
+
+async fn handle_action(app: &AppHandle, id: &str) -> Result<()> {
+    let pool = app.state::<AppState>().db.clone();
+    let manager = app.state::<Arc<MailWatcherManager>>().inner().clone();
+    if id == MENU_CHECK_NOW {
+        // 只检查已登录用户的账号
+        let mut email_ids = Vec::new();
+        for user_id in auth::active_user_ids() {
+            let filter = EmailFilter {
+                user_id: Some(user_id),
+                remote_only: true,
+                ..Default::default()
+            };
+            email_ids.extend(email::resolve_email_ids(&pool, &[], &filter).await?);
+        }
+        let count = manager.check_now(&email_ids).await;
+        log::info!("托盘触发立即检查: watchers={}", count);
+        return Ok(());
+    }
+
+    if !auth::admin_logged_in(&pool).await? {
+        return Err(anyhow!(auth::ADMIN_REQUIRED));
+    }
+    match id {
+        MENU_TOGGLE_WATCHERS => set_watchers_suspended(app, !manager.is_suspended()),
+        MENU_BACKGROUND => {
+            set_run_in_background(&pool, !RUN_IN_BACKGROUND.load(Ordering::Relaxed)).await?;
+        }
+        MENU_AUTOSTART => {
+            let enabled = app.autolaunch().is_enabled().unwrap_or(false);
+            set_autostart(app, !enabled)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 收集托盘状态
+async fn current_state(app: &AppHandle) -> Result<TrayState> {
+    let pool = app.state::<AppState>().db.clone();
+    let manager = app.state::<Arc<MailWatcherManager>>().inner().clone();
+
+    // 只统计已登录用户的账号，避免在托盘泄露其他本地用户的信息
+    let user_ids = auth::active_user_ids();
+    let unread = if user_ids.is_empty() {
+        None
+    } else {
+        let mut total = 0;
+        for user_id in user_ids {
+            total += mail_state::get_unread_counts(&pool, user_id, None)
+                .await?
+                .iter()
+                .map(|c| c.unread_count)
+                .sum::<i64>();
+        }
+        Some(total)
+    };
+
+    Ok(TrayState {
+        unread,
+        admin: auth::admin_logged_in(&pool).await?,
+        watchers: manager.list_watchers().await.len(),
+        suspended: manager.is_suspended(),
+        run_in_background: RUN_IN_BACKGROUND.load(Ordering::Relaxed),
+        autostart: app.autolaunch().is_enabled().unwrap_or(false),
+    })
+}
+
+fn unread_label(state: &TrayState) -> String {
+    match state.unread {
+        Some(unread) => format!("未读邮件: {unread}"),
+        None => "未登录".to_string(),
+    }
+}
+
+fn tooltip(state: &TrayState) -> String {
+    let watchers = if state.suspended {
+        "监听已暂停".to_string()
+    } else {
+        format!("{} 个监听运行中", state.watchers)
+    };
+    format!("FlareMail - {} · {}", unread_label(state), watchers)
+}
+
+fn build_menu(app: &AppHandle, state: &TrayState) -> tauri::Result<Menu<Wry>> {
+    let unread = MenuItem::with_id(app, MENU_UNREAD, unread_label(state), false, None::<&str>)?;
+    let show = MenuItem::with_id(app, MENU_SHOW, "显示主窗口", true, None::<&str>)?;
+    let check_now = MenuItem::with_id(
+        app,
+        MENU_CHECK_NOW,
+        "立即检查新邮件",
+        state.unread.is_some() && !state.suspended,
+        None::<&str>,
+    )?;
+    let toggle_watchers = MenuItem::with_id(
+        app,
+        MENU_TOGGLE_WATCHERS,
+        if state.suspended {
+            "恢复全部监听"
+        } else {
+            "暂停全部监听"
+        },
+        state.admin,
+        None::<&str>,
+    )?;
+    let background = CheckMenuItem::with_id(
+        app,
+        MENU_BACKGROUND,
+        "关闭窗口时后台运行",
+        state.admin,
+        state.run_in_background,
+        None::<&str>,
+    )?;
+    let autostart = CheckMenuItem::with_id(
+        app,
+        MENU_AUTOSTART,
+        "开机启动",
+        state.admin,
+        state.autostart,
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, MENU_QUIT, "退出", true, None::<&str>)?;
+
+    Menu::with_items(
+        app,
+        &[
+            &unread,
+            &PredefinedMenuItem::separator(app)?,
+            &show,
+            &check_now,
+            &toggle_watchers,
+            &PredefinedMenuItem::separator(app)?,
+            &background,
+            &autostart,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_in_background_is_opt_in() {
+        let pool = db::test_pool().await;
+        load_settings(&pool).await.unwrap();
+        assert!(!RUN_IN_BACKGROUND.load(Ordering::Relaxed));
+
+        set_run_in_background(&pool, true).await.unwrap();
+        RUN_IN_BACKGROUND.store(false, Ordering::Relaxed);
+        load_settings(&pool).await.unwrap();
+        assert!(RUN_IN_BACKGROUND.load(Ordering::Relaxed));
+
+        set_run_in_background(&pool, false).await.unwrap();
+        load_settings(&pool).await.unwrap();
+        assert!(!RUN_IN_BACKGROUND.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_tooltip() {
+        let mut state = TrayState {
+            unread: None,
+            admin: false,
+            watchers: 2,
+            suspended: false,
+            run_in_background: false,
+            autostart: false,
+        };
+        assert_eq!(tooltip(&state), "FlareMail - 未登录 · 2 个监听运行中");
+        state.unread = Some(5);
+        state.suspended = true;
+        assert_eq!(tooltip(&state), "FlareMail - 未读邮件: 5 · 监听已暂停");
+    }
+}
@@ -0,0 +1,622 @@
+//! 凭据保险库模块
+//! 可选的主密码模式：以 PBKDF2-HMAC-SHA256 从主密码派生密钥，使用 AES-256-GCM 加密
+//! emails 表中的 password、refresh_token、access_token、cached_token。
+//! 密文以 `enc:v1:` 前缀保存在原字段中；启动后需解锁，用户操作空闲超时自动锁定。
+//! 启用或修改主密码后整理数据库文件，并重新加密 app_data 中的备份和恢复前副本；
+//! 导出到其他位置的备份需用户自行处理
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::{ConnectOptions, Connection, Pool, Sqlite};
+use tauri::{AppHandle, Emitter};
+use zeroize::Zeroizing;
+
+use crate::backup;
+use crate::db;
+use crate::token_cache;
+
+/// 密文前缀
+const CIPHER_PREFIX: &str = "enc:v1:";
+
+/// AES-GCM 随机数长度
+const NONCE_LEN: usize = 12;
+
+/// PBKDF2 迭代次数
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// 用于校验主密码的明文
+const CHECK_PLAINTEXT: &str = "flaremail-vault";
+
+/// 默认空闲自动锁定时间（分钟）
+const DEFAULT_AUTO_LOCK_MINUTES: i64 = 15;
+
+/// 检查是否需要自动锁定的间隔（秒）
+const AUTO_LOCK_CHECK_SECS: u64 = 30;
+
+/// 主密码最短长度
+const MIN_PASSWORD_LEN: usize = 8;
+
+const CONFIG_SALT: &str = "vault.salt";
+const CONFIG_ROUNDS: &str = "vault.rounds";
+const CONFIG_CHECK: &str = "vault.check";
+const CONFIG_AUTO_LOCK: &str = "vault.auto_lock_minutes";
+
+/// 锁定时通知前端的事件
+pub const VAULT_LOCKED_EVENT: &str = "vault-locked";
+
+type VaultKey = Zeroizing<[u8; 32]>;
+
+/// 保险库运行状态
+struct VaultState {
+    enabled: bool,
+    key: Option<VaultKey>,
+    last_used: Option<Instant>,
+    /// 空闲自动锁定时间（分钟），0 表示不自动锁定
+    auto_lock_minutes: i64,
+}
+
+static VAULT: Mutex<VaultState> = Mutex::new(VaultState {
+    enabled: false,
+    key: None,
+    last_used: None,
+    auto_lock_minutes: DEFAULT_AUTO_LOCK_MINUTES,
+});
+
+/// 写入凭据时持有读锁，修改主密码、启用或关闭保险库时持有写锁，
+/// 避免重新加密期间写入旧密钥加密或未加密的凭据
+static REKEY_LOCK: tokio::sync::RwLock<()> = tokio::sync::RwLock::const_new(());
+
+/// 保险库状态（供前端展示）
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+    pub auto_lock_minutes: i64,
+}
+
+fn state() -> std::sync::MutexGuard<'static, VaultState> {
+    VAULT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 启动时读取保险库配置
+pub async fn load(pool: &Pool<Sqlite>) -> Result<()> {
+    let enabled = db::get_config(pool, CONFIG_CHECK).await?.is_some();
+    let auto_lock_minutes = db::get_config(pool, CONFIG_AUTO_LOCK)
+        .await?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_AUTO_LOCK_MINUTES);
+
+    let mut state = state();
+    state.enabled = enabled;
+    state.auto_lock_minutes = auto_lock_minutes;
+    Ok(())
+}
+
+/// 当前状态
+pub fn status() -> VaultStatus {
+    lock_if_idle();
+    let state = state();
+    VaultStatus {
+        enabled: state.enabled,
+        unlocked: !state.enabled || state.key.is_some(),
+        auto_lock_minutes: state.auto_lock_minutes,
+    }
+}
+
+/// 写入凭据前获取，持有到写入数据库完成，与重新加密全部凭据互斥
+pub async fn seal_guard() -> tokio::sync::RwLockReadGuard<'static, ()> {
+    REKEY_LOCK.read().await
+}
+
+/// 加密凭据；未启用保险库或值为空时原样返回
+pub fn seal(value: &str) -> Result<String> {
+    if value.is_empty() || is_sealed(value) {
+        return Ok(value.to_string());
+    }
+    match current_key()? {
+        Some(key) => encrypt(&key, value),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// 解密凭据；未加密的值原样返回
+pub fn open(value: &str) -> Result<String> {
+    if !is_sealed(value) {
+        return Ok(value.to_string());
+    }
+    let key = current_key()?.ok_or_else(|| anyhow!("保险库未启用，无法解密凭据"))?;
+    decrypt(&key, value)
+}
+
+/// 确认保险库已解锁（或未启用）
+pub fn ensure_unlocked() -> Result<()> {
+    current_key().map(|_| ())
+}
+
+fn is_sealed(value: &str) -> bool {
+    value.starts_with(CIPHER_PREFIX)
+}
+
+/// 获取当前密钥：未启用时为 None，已启用但锁定时报错。
+/// 不刷新空闲计时，后台同步使用凭据不会阻止自动锁定
+fn current_key() -> Result<Option<VaultKey>> {
+    lock_if_idle();
+    let state = state();
+    if !state.enabled {
+        return Ok(None);
+    }
+    let key = state
+        .key
+        .clone()
+        .ok_or_else(|| anyhow!("保险库已锁定，请先输入主密码解锁"))?;
+    Ok(Some(key))
+}
+
+/// 用户操作时刷新空闲计时（已超时锁定的不会重新解锁）
+pub fn touch() {
+    lock_if_idle();
+    let mut state = state();
+    if state.key.is_some() {
+        state.last_used = Some(Instant::now());
+    }
+}
+
+/// 空闲超时则锁定，返回是否由本次调用锁定
+fn lock_if_idle() -> bool {
+    let mut state = state();
+    if state.key.is_none() || state.auto_lock_minutes <= 0 {
+        return false;
+    }
+    let timeout = Duration::from_secs(state.auto_lock_minutes as u64 * 60);
+    let idle = state
+        .last_used
+        .map(|t| t.elapsed() >= timeout)
+        .unwrap_or(true);
+    if idle {
+        state.key = None;
+        state.last_used = None;
+        drop(state);
+        token_cache::clear_all_token_cache();
+        log::info!("保险库空闲超时，已自动锁定");
+    }
+    idle
+}
+
+/// 后台自动锁定，锁定时通知前端
+pub async fn run_auto_lock(app_handle: AppHandle) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(AUTO_LOCK_CHECK_SECS)).await;
+        if lock_if_idle() {
+            let _ = app_handle.emit(VAULT_LOCKED_EVENT, status());
+        }
+    }
+}
+
+/// 用主密码解锁
+pub async fn unlock(pool: &Pool<Sqlite>, password: &str) -> Result<VaultStatus> {
+    let key = verify_password(pool, password).await?;
+    {
+        let mut state = state();
+        state.key = Some(key);
+        state.last_used = Some(Instant::now());
+    }
+    log::info!("保险库已解锁");
+    Ok(status())
+}
+
+/// 锁定保险库，并清除内存中的令牌缓存
+pub fn lock() -> VaultStatus {
+    {
+        let mut state = state();
+        state.key = None;
+        state.last_used = None;
+    }
+    token_cache::clear_all_token_cache();
+    status()
+}
+
+/// 启用保险库：设置主密码并加密已有的凭据
+pub async fn enable(pool: &Pool<Sqlite>, password: &str) -> Result<VaultStatus> {
+    let _guard = REKEY_LOCK.write().await;
+    if state().enabled {
+        return Err(anyhow!("保险库已启用"));
+    }
+    validate_new_password(password)?;
+
+    let (key, salt) = new_key(password).await?;
+    rekey(pool, None, Some((&key, &salt))).await?;
+    {
+        let mut state = state();
+        state.enabled = true;
+        state.key = Some(key.clone());
+        state.last_used = Some(Instant::now());
+    }
+    log::info!("保险库已启用，已有凭据已加密");
+    scrub(pool, None, &key, &salt).await;
+    Ok(status())
+}
+
+/// 修改主密码并重新加密全部凭据
+pub async fn change_password(
+    pool: &Pool<Sqlite>,
+    old_password: &str,
+    new_password: &str,
+) -> Result<VaultStatus> {
+    let _guard = REKEY_LOCK.write().await;
+    if !state().enabled {
+        return Err(anyhow!("保险库未启用"));
+    }
+    let old_key = verify_password(pool, old_password).await?;
+    validate_new_password(new_password)?;
+
+    let (key, salt) = new_key(new_password).await?;
+    rekey(pool, Some(&old_key), Some((&key, &salt))).await?;
+    {
+        let mut state = state();
+        state.key = Some(key.clone());
+        state.last_used = Some(Instant::now());
+    }
+    log::info!("保险库主密码已修改");
+    scrub(pool, Some(&old_key), &key, &salt).await;
+    Ok(status())
+}
+
+/// 关闭保险库：解密全部凭据并删除主密码
+pub async fn disable(pool: &Pool<Sqlite>, password: &str) -> Result<VaultStatus> {
+    let _guard = REKEY_LOCK.write().await;
+    if !state().enabled {
+        return Err(anyhow!("保险库未启用"));
+    }
+    let key = verify_password(pool, password).await?;
+
+    rekey(pool, Some(&key), None).await?;
+    {
+        let mut state = state();
+        state.enabled = false;
+        state.key = None;
+        state.last_used = None;
+    }
+    log::info!("保险库已关闭，凭据已解密");
+    Ok(status())
+}
+
+/// 设置空闲自动锁定时间（分钟），0 表示不自动锁定
+pub async fn set_auto_lock(pool: &Pool<Sqlite>, minutes: i64) -> Result<VaultStatus> {
+    if minutes < 0 {
+        return Err(anyhow!("自动锁定时间不能为负数"));
+    }
+    db::set_config(
+        pool,
+        CONFIG_AUTO_LOCK,
+        &minutes.to_string(),
+        Some("保险库空闲自动锁定时间（分钟），0 表示不自动锁定"),
+    )
+    .await?;
+    state().auto_lock_minutes = minutes;
+    Ok(status())
+}
+
+fn validate_new_password(password: &str) -> Result<()> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(anyhow!("主密码至少需要 {} 个字符", MIN_PASSWORD_LEN));
+    }
+    Ok(())
+}
+
+/// 校验主密码，返回派生的密钥
+async fn verify_password(pool: &Pool<Sqlite>, password: &str) -> Result<VaultKey> {
+    let salt = db::get_config(pool, CONFIG_SALT)
+        .await?
+        .ok_or_else(|| anyhow!("保险库未启用"))?;
+    let check = db::get_config(pool, CONFIG_CHECK)
+        .await?
+        .ok_or_else(|| anyhow!("保险库未启用"))?;
+    let rounds = db::get_config(pool, CONFIG_ROUNDS)
+        .await?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(PBKDF2_ROUNDS);
+
+    let key = derive_key(password, hex::decode(salt)?, rounds).await?;
+    match decrypt(&key, &check) {
+        Ok(plaintext) if plaintext == CHECK_PLAINTEXT => Ok(key),
+        _ => Err(anyhow!("主密码错误")),
+    }
+}
+
+/// 生成新的盐并派生密钥
+async fn new_key(password: &str) -> Result<(VaultKey, Vec<u8>)> {
+    let salt = rand::random::<[u8; 16]>().to_vec();
+    let key = derive_key(password, salt.clone(), PBKDF2_ROUNDS).await?;
+    Ok((key, salt))
+}
+
+/// PBKDF2 派生密钥（在阻塞线程中计算）
+async fn derive_key(password: &str, salt: Vec<u8>, rounds: u32) -> Result<VaultKey> {
+    let password = Zeroizing::new(password.as_bytes().to_vec());
+    let key = tokio::task::spawn_blocking(move || {
+        let mut key = Zeroizing::new([0u8; 32]);
+        pbkdf2::pbkdf2_hmac::<Sha256>(&password, &salt, rounds, key.as_mut());
+        key
+    })
+    .await?;
+    Ok(key)
+}
+
+/// 使用旧密钥解密、新密钥加密全部凭据，并在同一事务中更新保险库配置
+///
+/// from 为 None 表示当前为明文；to 为 None 表示解密为明文并删除保险库配置
+async fn rekey(
+    pool: &Pool<Sqlite>,
+    from: Option<&VaultKey>,
+    to: Option<(&VaultKey, &[u8])>,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    rekey_rows(&mut tx, from, to).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 在已开启的事务中转换凭据并写入保险库配置
+async fn rekey_rows(
+    tx: &mut SqliteConnection,
+    from: Option<&VaultKey>,
+    to: Option<(&VaultKey, &[u8])>,
+) -> Result<()> {
+    let rows = sqlx::query_as::<_, (i64, String, String, Option<String>, Option<String>)>(
+        "SELECT id, password, refresh_token, access_token, cached_token FROM emails",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let convert = |value: &str| -> Result<String> {
+        let plaintext = match (from, is_sealed(value)) {
+            (Some(key), true) => Zeroizing::new(decrypt(key, value)?),
+            (None, true) => return Err(anyhow!("发现无法解密的凭据")),
+            (_, false) => Zeroizing::new(value.to_string()),
+        };
+        match to {
+            Some((key, _)) if !plaintext.is_empty() => encrypt(key, &plaintext),
+            _ => Ok(plaintext.to_string()),
+        }
+    };
+
+    for (id, password, refresh_token, access_token, cached_token) in rows {
+        sqlx::query(
+            "UPDATE emails SET password = ?, refresh_token = ?, access_token = ?, cached_token = ? WHERE id = ?",
+        )
+        .bind(convert(&password)?)
+        .bind(convert(&refresh_token)?)
+        .bind(access_token.as_deref().map(convert).transpose()?)
+        .bind(cached_token.as_deref().map(convert).transpose()?)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // 旧版本的备份中没有 webhooks 表
+    let has_webhooks = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'webhooks'",
+    )
+    .fetch_one(&mut *tx)
+    .await?
+        > 0;
+    let secrets = if has_webhooks {
+        sqlx::query_as::<_, (i64, String)>("SELECT id, secret FROM webhooks")
+            .fetch_all(&mut *tx)
+            .await?
+    } else {
+        Vec::new()
+    };
+    for (id, secret) in secrets {
+        sqlx::query("UPDATE webhooks SET secret = ? WHERE id = ?")
+            .bind(convert(&secret)?)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    match to {
+        Some((key, salt)) => {
+            for (config_key, value, description) in [
+                (CONFIG_SALT, hex::encode(salt), "保险库密钥派生盐"),
+                (
+                    CONFIG_ROUNDS,
+                    PBKDF2_ROUNDS.to_string(),
+                    "保险库 PBKDF2 迭代次数",
+                ),
+                (
+                    CONFIG_CHECK,
+                    encrypt(key, CHECK_PLAINTEXT)?,
+                    "保险库主密码校验值",
+                ),
+            ] {
+                sqlx::query(
+                    r#"INSERT INTO system_config (key, value, description) VALUES (?, ?, ?)
+ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP"#,
+                )
+                .bind(config_key)
+                .bind(value)
+                .bind(description)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        None => {
+            sqlx::query("DELETE FROM system_config WHERE key IN (?, ?, ?)")
+                .bind(CONFIG_SALT)
+                .bind(CONFIG_ROUNDS)
+                .bind(CONFIG_CHECK)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 重新加密后清除明文或旧密钥密文的残留：回写 WAL 并整理数据库文件以覆盖空闲页，
+/// 再重新加密备份目录中的备份和恢复前保留的副本；失败只记录日志，不影响已完成的重新加密
+async fn scrub(pool: &Pool<Sqlite>, from: Option<&VaultKey>, key: &VaultKey, salt: &[u8]) {
+    if let Err(e) = compact(pool).await {
+        log::warn!("整理数据库文件失败，旧凭据可能残留在空闲页中: {}", e);
+    }
+
+    let _backup_guard = backup::lock().await;
+    let copies = match backup::local_copies().await {
+        Ok(copies) => copies,
+        Err(e) => {
+            log::warn!("读取本地备份失败，备份中的凭据未重新加密: {}", e);
+            return;
+        }
+    };
+    for path in copies {
+        match rekey_copy(&path, from, key, salt).await {
+            Ok(()) => log::info!("已重新加密备份中的凭据: {}", path.display()),
+            Err(e) => log::warn!(
+                "备份中的凭据无法重新加密，请确认后手动删除: {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// 回写 WAL 并 VACUUM，使已删除的页不再保留旧内容
+async fn compact(pool: &Pool<Sqlite>) -> Result<()> {
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool)
+        .await?;
+    sqlx::query("VACUUM").execute(pool).await?;
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 重新加密数据库副本中的凭据并整理文件
+async fn rekey_copy(
+    path: &Path,
+    from: Option<&VaultKey>,
+    key: &VaultKey,
+    salt: &[u8],
+) -> Result<()> {
+    let mut conn = SqliteConnectOptions::new().filename(path).connect().await?;
+    let mut tx = conn.begin().await?;
+    rekey_rows(&mut tx, from, Some((key, salt))).await?;
+    tx.commit().await?;
+    sqlx::query("VACUUM").execute(&mut conn).await?;
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+    Ok(())
+}
+
+/// AES-256-GCM 加密，输出 `enc:v1:` + base64(随机数 || 密文)
+fn encrypt(key: &VaultKey, plaintext: &str) -> Result<String> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_ref()));
+    let nonce_bytes = rand::random::<[u8; NONCE_LEN]>();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_bytes())
+        .map_err(|_| anyhow!("加密凭据失败"))?;
+
+    let mut payload = nonce_bytes.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", CIPHER_PREFIX, STANDARD.encode(payload)))
+}
+
+/// 解密 [`encrypt`] 的输出
+fn decrypt(key: &VaultKey, value: &str) -> Result<String> {
+    let encoded = value
+        .strip_prefix(CIPHER_PREFIX)
+        .ok_or_else(|| anyhow!("不是加密的凭据"))?;
+    let payload = STANDARD.decode(encoded)?;
+    if payload.len() <= NONCE_LEN {
+        return Err(anyhow!("密文长度无效"));
+    }
+
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_ref()));
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("解密凭据失败"))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let key = Zeroizing::new([7u8; 32]);
+        let sealed = encrypt(&key, "refresh-token").unwrap();
+        assert!(is_sealed(&sealed));
+        assert_ne!(sealed, encrypt(&key, "refresh-token").unwrap());
+        assert_eq!(decrypt(&key, &sealed).unwrap(), "refresh-token");
+
+        let other = Zeroizing::new([8u8; 32]);
+        assert!(decrypt(&other, &sealed).is_err());
+        assert!(decrypt(&key, "enc:v1:AAAA").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rekey_copy_leaves_no_plaintext() {
+        let path = std::env::temp_dir().join(format!("vault-copy-{}.db", rand::random::<u32>()));
+        let mut conn = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .connect()
+            .await
+            .unwrap();
+        for sql in [
+            "CREATE TABLE emails (id INTEGER PRIMARY KEY, password TEXT NOT NULL, refresh_token TEXT NOT NULL, access_token TEXT, cached_token TEXT)",
+            "CREATE TABLE system_config (key TEXT PRIMARY KEY, value TEXT, description TEXT, updated_at TEXT)",
+            "INSERT INTO emails (password, refresh_token) VALUES ('plain-password-1234', 'plain-refresh-5678')",
+        ] {
+            sqlx::query(sql).execute(&mut conn).await.unwrap();
+        }
+        conn.close().await.unwrap();
+
+        // 没有 webhooks 表的旧备份也能处理
+        let key = Zeroizing::new([3u8; 32]);
+        rekey_copy(&path, None, &key, &[1u8; 16]).await.unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        let contains = |needle: &str| bytes.windows(needle.len()).any(|w| w == needle.as_bytes());
+        assert!(!contains("plain-password-1234"));
+        assert!(!contains("plain-refresh-5678"));
+
+        let mut conn = SqliteConnectOptions::new()
+            .filename(&path)
+            .connect()
+            .await
+            .unwrap();
+        let (password, check) = sqlx::query_as::<_, (String, String)>(
+            "SELECT password, (SELECT value FROM system_config WHERE key = 'vault.check') FROM emails",
+        )
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(decrypt(&key, &password).unwrap(), "plain-password-1234");
+        assert_eq!(decrypt(&key, &check).unwrap(), CHECK_PLAINTEXT);
+        conn.close().await.unwrap();
+
+        // 用其他密钥加密的副本不会被修改
+        let other = Zeroizing::new([4u8; 32]);
+        assert!(rekey_copy(&path, Some(&other), &key, &[1u8; 16])
+            .await
+            .is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
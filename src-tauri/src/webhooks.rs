@@ -0,0 +1,1031 @@
+//! Webhook 模块
+//! 新邮件、收件规则提取到验证码等事件以 JSON 推送到用户配置的端点，端点可按账号、账号标签或规则限定范围。
+//! 请求体使用端点密钥做 HMAC-SHA256 签名；投递先写入队列，由后台任务发送（不同端点并发，
+//! 同一端点按顺序），失败按指数退避重试，重试耗尽后转入死信列表，可手动重新投递
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{Pool, QueryBuilder, Sqlite};
+
+use crate::audit::{self, AuditEvent};
+use crate::proxy::{create_http_client, ProxyConfig};
+use crate::settings;
+use crate::tags;
+use crate::vault;
+
+/// 收到新邮件
+pub const EVENT_MAIL_RECEIVED: &str = "mail.received";
+/// 收件规则提取到验证码
+pub const EVENT_CODE_EXTRACTED: &str = "code.extracted";
+/// 收件规则的 Webhook 动作
+pub const EVENT_RULE_MATCHED: &str = "rule.matched";
+/// 测试投递
+pub const EVENT_PING: &str = "ping";
+
+/// 可订阅的事件
+const SUBSCRIBABLE_EVENTS: [&str; 2] = [EVENT_MAIL_RECEIVED, EVENT_CODE_EXTRACTED];
+
+/// 签名请求头，值为 sha256=<hex>，签名内容为 "{timestamp}.{body}"
+pub const SIGNATURE_HEADER: &str = "X-Flaremail-Signature";
+/// 签名时间戳（Unix 秒）请求头
+pub const TIMESTAMP_HEADER: &str = "X-Flaremail-Timestamp";
+/// 事件类型请求头
+pub const EVENT_HEADER: &str = "X-Flaremail-Event";
+/// 投递 ID 请求头（重试时不变，可用于去重）
+pub const DELIVERY_HEADER: &str = "X-Flaremail-Delivery";
+
+const STATUS_PENDING: &str = "pending";
+const STATUS_DELIVERED: &str = "delivered";
+const STATUS_DEAD: &str = "dead";
+
+/// 最多尝试次数，超过后转入死信
+const MAX_ATTEMPTS: i64 = 6;
+
+/// 首次重试的等待时间（秒），之后每次翻倍
+const RETRY_BASE_SECS: i64 = 30;
+
+/// 重试等待时间上限（秒）
+const RETRY_MAX_SECS: i64 = 3600;
+
+/// 后台检查待投递队列的间隔（秒）
+const DELIVERY_POLL_SECS: u64 = 10;
+
+/// 每轮最多投递的条数
+const DELIVERY_BATCH: i64 = 50;
+
+/// 已送达记录保留天数
+const DELIVERED_RETENTION_DAYS: i64 = 7;
+
+/// 错误信息中保留的响应体长度
+const MAX_ERROR_BODY: usize = 500;
+
+/// 投递记录默认返回的条数
+const DEFAULT_DELIVERY_LIMIT: i64 = 200;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 共用的 HTTP 客户端及创建时的 (请求超时, 连接超时)，网络设置变化后重建
+static HTTP_CLIENT: Mutex<Option<((u64, u64), reqwest::Client)>> = Mutex::new(None);
+
+/// Webhook 端点（不含密钥）
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Webhook {
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    pub enabled: bool,
+    /// 仅限该账号
+    pub email_id: Option<i64>,
+    /// 仅限带有该标签的账号
+    pub tag_id: Option<i64>,
+    /// 仅限该收件规则产生的事件
+    pub rule_id: Option<i64>,
+    #[sqlx(json)]
+    pub events: Vec<String>,
+    pub pending_count: i64,
+    pub dead_count: i64,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// 新建或修改端点的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookInput {
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub email_id: Option<i64>,
+    pub tag_id: Option<i64>,
+    pub rule_id: Option<i64>,
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_events() -> Vec<String> {
+    SUBSCRIBABLE_EVENTS.iter().map(|e| e.to_string()).collect()
+}
+
+/// 新建端点或重新生成密钥的结果（密钥只在此时返回）
+#[derive(Debug, Serialize)]
+pub struct WebhookSecret {
+    pub webhook_id: i64,
+    pub secret: String,
+}
+
+/// 投递记录
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub webhook_name: String,
+    pub event: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i64,
+    pub next_attempt_at: Option<String>,
+    pub last_status: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: Option<String>,
+    pub delivered_at: Option<String>,
+}
+
+/// 投递记录查询条件
+#[derive(Debug, Default, Deserialize)]
+pub struct DeliveryQuery {
+    pub webhook_id: Option<i64>,
+    /// pending / delivered / dead
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// 测试投递结果
+#[derive(Debug, Serialize)]
+pub struct WebhookTestResult {
+    pub success: bool,
+    pub status: Option<u16>,
+    pub duration_ms: u64,
+    pub message: String,
+}
+
+/// 事件推送中的邮件摘要
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct MailSummary {
+    pub id: i64,
+    pub email_id: i64,
+    pub account: String,
+    pub subject: Option<String>,
+    pub sender: Option<String>,
+    pub received_time: Option<String>,
+    pub folder: Option<String>,
+}
+
+/// 事件来源，用于匹配端点范围
+#[derive(Debug, Clone, Copy)]
+pub struct EventSource {
+    pub user_id: i64,
+    pub email_id: i64,
+    pub rule_id: Option<i64>,
+}
+
+/// 待投递的记录
+#[derive(sqlx::FromRow)]
+struct DueDelivery {
+    id: i64,
+    webhook_id: i64,
+    event: String,
+    payload: String,
+    attempts: i64,
+    url: String,
+    secret: String,
+}
+
+/// 获取用户的端点
+pub async fn list_webhooks(pool: &Pool<Sqlite>, user_id: i64) -> Result<Vec<Webhook>> {
+    let webhooks = sqlx::query_as::<_, Webhook>(
+        r#"SELECT w.id, w.name, w.url, w.enabled, w.email_id, w.tag_id, w.rule_id, w.events,
+    (SELECT COUNT(*) FROM webhook_deliveries d WHERE d.webhook_id = w.id AND d.status = 'pending') AS pending_count,
+    (SELECT COUNT(*) FROM webhook_deliveries d WHERE d.webhook_id = w.id AND d.status = 'dead') AS dead_count,
+    w.created_at, w.updated_at
+FROM webhooks w
+WHERE w.user_id = ?
+ORDER BY w.id"#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(webhooks)
+}
+
+/// 新建端点并生成签名密钥
+pub async fn create_webhook(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    input: &WebhookInput,
+) -> Result<WebhookSecret> {
+    validate(pool, user_id, input).await?;
+    let secret = generate_secret();
+    let seal_guard = vault::seal_guard().await;
+    let webhook_id: i64 = sqlx::query_scalar(
+        "INSERT INTO webhooks (user_id, name, url, secret, enabled, email_id, tag_id, rule_id, events) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(input.name.trim())
+    .bind(input.url.trim())
+    .bind(vault::seal(&secret)?)
+    .bind(input.enabled)
+    .bind(input.email_id)
+    .bind(input.tag_id)
+    .bind(input.rule_id)
+    .bind(serde_json::to_string(&input.events)?)
+    .fetch_one(pool)
+    .await?;
+    drop(seal_guard);
+
+    audit::record(
+        pool,
+        audit::ACTION_WEBHOOK_CREATE,
+        AuditEvent {
+            user_id: Some(user_id),
+            email_id: input.email_id,
+            target_ids: &[webhook_id],
+            detail: Some(input.url.trim().to_string()),
+        },
+    )
+    .await?;
+
+    Ok(WebhookSecret { webhook_id, secret })
+}
+
+/// 修改端点（密钥不变），端点不存在时返回 false
+pub async fn update_webhook(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    webhook_id: i64,
+    input: &WebhookInput,
+) -> Result<bool> {
+    validate(pool, user_id, input).await?;
+    let result = sqlx::query(
+        r#"UPDATE webhooks
+SET name = ?, url = ?, enabled = ?, email_id = ?, tag_id = ?, rule_id = ?, events = ?, updated_at = CURRENT_TIMESTAMP
+WHERE id = ? AND user_id = ?"#,
+    )
+    .bind(input.name.trim())
+    .bind(input.url.trim())
+    .bind(input.enabled)
+    .bind(input.email_id)
+    .bind(input.tag_id)
+    .bind(input.rule_id)
+    .bind(serde_json::to_string(&input.events)?)
+    .bind(webhook_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    audit::record(
+        pool,
+        audit::ACTION_WEBHOOK_UPDATE,
+        AuditEvent {
+            user_id: Some(user_id),
+            email_id: input.email_id,
+            target_ids: &[webhook_id],
+            detail: Some(input.url.trim().to_string()),
+        },
+    )
+    .await?;
+    Ok(true)
+}
+
+/// 删除端点（投递记录一并删除）
+pub async fn delete_webhook(pool: &Pool<Sqlite>, user_id: i64, webhook_id: i64) -> Result<bool> {
+    let Some(url) = sqlx::query_scalar::<_, String>(
+        "DELETE FROM webhooks WHERE id = ? AND user_id = ? RETURNING url",
+    )
+    .bind(webhook_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    audit::record(
+        pool,
+        audit::ACTION_WEBHOOK_DELETE,
+        AuditEvent {
+            user_id: Some(user_id),
+            target_ids: &[webhook_id],
+            detail: Some(url),
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(true)
+}
+
+/// 重新生成端点密钥，旧密钥立即失效
+pub async fn rotate_secret(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    webhook_id: i64,
+) -> Result<WebhookSecret> {
+    let secret = generate_secret();
+    let seal_guard = vault::seal_guard().await;
+    let result = sqlx::query(
+        "UPDATE webhooks SET secret = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND user_id = ?",
+    )
+    .bind(vault::seal(&secret)?)
+    .bind(webhook_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    drop(seal_guard);
+    if result.rows_affected() == 0 {
+        return Err(anyhow!("Webhook 不存在"));
+    }
+
+    audit::record(
+        pool,
+        audit::ACTION_WEBHOOK_SECRET,
+        AuditEvent {
+            user_id: Some(user_id),
+            target_ids: &[webhook_id],
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(WebhookSecret { webhook_id, secret })
+}
+
+/// 立即向端点发送一条签名的 ping 事件（不进入队列，不重试）
+pub async fn send_test(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    webhook_id: i64,
+) -> Result<WebhookTestResult> {
+    let (url, secret) = sqlx::query_as::<_, (String, String)>(
+        "SELECT url, secret FROM webhooks WHERE id = ? AND user_id = ?",
+    )
+    .bind(webhook_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("Webhook 不存在"))?;
+    let secret = vault::open(&secret)?;
+
+    let payload = build_payload(EVENT_PING, serde_json::json!({ "webhook_id": webhook_id }))?;
+    let started = Instant::now();
+    let client = http_client()?;
+    let result = send(&client, &url, &secret, EVENT_PING, "test", &payload).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    Ok(match result {
+        Ok(status) => WebhookTestResult {
+            success: true,
+            status: Some(status),
+            duration_ms,
+            message: format!("HTTP {status}"),
+        },
+        Err(e) => WebhookTestResult {
+            success: false,
+            status: e.status,
+            duration_ms,
+            message: e.message,
+        },
+    })
+}
+
+/// 把新邮件事件加入匹配端点的投递队列，返回入队条数
+pub async fn dispatch_new_mails(
+    pool: &Pool<Sqlite>,
+    email_id: i64,
+    mail_ids: &[i64],
+) -> Result<usize> {
+    if mail_ids.is_empty() {
+        return Ok(0);
+    }
+    let owner: Option<i64> = sqlx::query_scalar("SELECT user_id FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+    let Some(user_id) = owner else {
+        return Ok(0);
+    };
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT m.id, m.email_id, e.email AS account, m.subject, m.sender, m.received_time, m.folder FROM mail_records m JOIN emails e ON e.id = m.email_id WHERE m.id IN (",
+    );
+    let mut separated = qb.separated(", ");
+    for id in mail_ids {
+        separated.push_bind(id);
+    }
+    qb.push(") ORDER BY m.id");
+    let mails = qb.build_query_as::<MailSummary>().fetch_all(pool).await?;
+
+    let source = EventSource {
+        user_id,
+        email_id,
+        rule_id: None,
+    };
+    let mut queued = 0;
+    for mail in mails {
+        queued += dispatch(
+            pool,
+            EVENT_MAIL_RECEIVED,
+            source,
+            serde_json::json!({ "mail": mail }),
+        )
+        .await?;
+    }
+    Ok(queued)
+}
+
+/// 把事件加入所有匹配端点的投递队列，返回入队条数
+///
+/// 端点需订阅该事件，且账号、标签、规则范围（设置了的）均与事件来源一致
+pub async fn dispatch(
+    pool: &Pool<Sqlite>,
+    event: &str,
+    source: EventSource,
+    data: serde_json::Value,
+) -> Result<usize> {
+    let webhook_ids: Vec<i64> = sqlx::query_scalar(
+        r#"SELECT w.id FROM webhooks w
+WHERE w.user_id = ? AND w.enabled = 1
+    AND EXISTS (SELECT 1 FROM json_each(w.events) WHERE json_each.value = ?)
+    AND (w.email_id IS NULL OR w.email_id = ?)
+    AND (w.tag_id IS NULL OR EXISTS (SELECT 1 FROM email_tags et WHERE et.email_id = ? AND et.tag_id = w.tag_id))
+    AND (w.rule_id IS NULL OR w.rule_id = ?)"#,
+    )
+    .bind(source.user_id)
+    .bind(event)
+    .bind(source.email_id)
+    .bind(source.email_id)
+    .bind(source.rule_id)
+    .fetch_all(pool)
+    .await?;
+    if webhook_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let payload = build_payload(event, data)?;
+    for webhook_id in &webhook_ids {
+        insert_delivery(pool, *webhook_id, event, &payload).await?;
+    }
+    Ok(webhook_ids.len())
+}
+
+/// 把事件加入指定端点的投递队列（收件规则的 Webhook 动作），返回投递 ID
+pub async fn enqueue(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    webhook_id: i64,
+    event: &str,
+    data: serde_json::Value,
+) -> Result<i64> {
+    let enabled: bool =
+        sqlx::query_scalar("SELECT enabled FROM webhooks WHERE id = ? AND user_id = ?")
+            .bind(webhook_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| anyhow!("Webhook 不存在"))?;
+    if !enabled {
+        return Err(anyhow!("Webhook 已停用"));
+    }
+
+    let payload = build_payload(event, data)?;
+    insert_delivery(pool, webhook_id, event, &payload).await
+}
+
+/// 确认端点属于用户
+pub async fn ensure_owned(pool: &Pool<Sqlite>, user_id: i64, webhook_id: i64) -> Result<()> {
+    sqlx::query_scalar::<_, i64>("SELECT id FROM webhooks WHERE id = ? AND user_id = ?")
+        .bind(webhook_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .map(|_| ())
+        .ok_or_else(|| anyhow!("Webhook 不存在"))
+}
+
+/// 查询投递记录（最新在前），status 为 dead 时即死信列表
+pub async fn list_deliveries(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    query: &DeliveryQuery,
+) -> Result<Vec<WebhookDelivery>> {
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT d.id, d.webhook_id, w.name AS webhook_name, d.event, d.payload, d.status, d.attempts, d.next_attempt_at, d.last_status, d.last_error, d.created_at, d.delivered_at FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id WHERE w.user_id = ",
+    );
+    qb.push_bind(user_id);
+    if let Some(webhook_id) = query.webhook_id {
+        qb.push(" AND d.webhook_id = ");
+        qb.push_bind(webhook_id);
+    }
+    if let Some(status) = query.status.as_deref().filter(|s| !s.is_empty()) {
+        qb.push(" AND d.status = ");
+        qb.push_bind(status.to_string());
+    }
+    qb.push(" ORDER BY d.id DESC LIMIT ");
+    qb.push_bind(query.limit.unwrap_or(DEFAULT_DELIVERY_LIMIT).max(1));
+
+    let deliveries = qb
+        .build_query_as::<WebhookDelivery>()
+        .fetch_all(pool)
+        .await?;
+    Ok(deliveries)
+}
+
+/// 重新投递（死信或等待重试中的记录），重置尝试次数，返回重新入队的条数
+pub async fn retry_deliveries(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    delivery_ids: &[i64],
+) -> Result<u64> {
+    if delivery_ids.is_empty() {
+        return Err(anyhow!("未选择投递记录"));
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "UPDATE webhook_deliveries SET status = 'pending', attempts = 0, next_attempt_at = CURRENT_TIMESTAMP, last_error = NULL WHERE status != 'delivered' AND webhook_id IN (SELECT id FROM webhooks WHERE user_id = ",
+    );
+    qb.push_bind(user_id);
+    qb.push(") AND id IN (");
+    let mut separated = qb.separated(", ");
+    for id in delivery_ids {
+        separated.push_bind(id);
+    }
+    qb.push(")");
+    Ok(qb.build().execute(pool).await?.rows_affected())
+}
+
+/// 删除投递记录（如清理死信），返回删除的条数
+pub async fn delete_deliveries(
+    pool: &Pool<Sqlite>,
+    user_id: i64,
+    delivery_ids: &[i64],
+) -> Result<u64> {
+    if delivery_ids.is_empty() {
+        return Err(anyhow!("未选择投递记录"));
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "DELETE FROM webhook_deliveries WHERE webhook_id IN (SELECT id FROM webhooks WHERE user_id = ",
+    );
+    qb.push_bind(user_id);
+    qb.push(") AND id IN (");
+    let mut separated = qb.separated(", ");
+    for id in delivery_ids {
+        separated.push_bind(id);
+    }
+    qb.push(")");
+    Ok(qb.build().execute(pool).await?.rows_affected())
+}
+
+/// 投递到期的队列记录，返回本轮处理的条数
+///
+/// 不同端点并发投递，同一端点按入队顺序依次投递；保险库锁定时无法解密密钥，暂不投递
+pub async fn deliver_due(pool: &Pool<Sqlite>) -> Result<usize> {
+    if vault::ensure_unlocked().is_err() {
+        return Ok(0);
+    }
+
+    let due = sqlx::query_as::<_, DueDelivery>(
+        r#"SELECT d.id, d.webhook_id, d.event, d.payload, d.attempts, w.url, w.secret
+FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id
+WHERE d.status = 'pending' AND w.enabled = 1 AND d.next_attempt_at <= CURRENT_TIMESTAMP
+ORDER BY d.id
+LIMIT ?"#,
+    )
+    .bind(DELIVERY_BATCH)
+    .fetch_all(pool)
+    .await?;
+    let count = due.len();
+    if count == 0 {
+        return Ok(0);
+    }
+
+    let client = http_client()?;
+    let mut by_endpoint: BTreeMap<i64, Vec<DueDelivery>> = BTreeMap::new();
+    for delivery in due {
+        by_endpoint
+            .entry(delivery.webhook_id)
+            .or_default()
+            .push(delivery);
+    }
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for deliveries in by_endpoint.into_values() {
+        let pool = pool.clone();
+        let client = client.clone();
+        tasks.spawn(async move {
+            for delivery in &deliveries {
+                deliver(&pool, &client, delivery).await?;
+            }
+            anyhow::Ok(())
+        });
+    }
+    let mut first_error = None;
+    while let Some(result) = tasks.join_next().await {
+        if let Err(e) = result.map_err(anyhow::Error::from).and_then(|r| r) {
+            first_error.get_or_insert(e);
+        }
+    }
+    first_error.map_or(Ok(count), Err)
+}
+
+/// 投递一条记录并更新状态：成功标记为已送达，失败按退避时间重试，次数耗尽转入死信
+async fn deliver(
+    pool: &Pool<Sqlite>,
+    client: &reqwest::Client,
+    delivery: &DueDelivery,
+) -> Result<()> {
+    let attempts = delivery.attempts + 1;
+    let result = match vault::open(&delivery.secret) {
+        Ok(secret) => {
+            send(
+                client,
+                &delivery.url,
+                &secret,
+                &delivery.event,
+                &delivery.id.to_string(),
+                &delivery.payload,
+            )
+            .await
+        }
+        Err(e) => Err(SendError {
+            status: None,
+            message: e.to_string(),
+        }),
+    };
+
+    match result {
+        Ok(status) => {
+            sqlx::query(
+                "UPDATE webhook_deliveries SET status = ?, attempts = ?, last_status = ?, last_error = NULL, delivered_at = CURRENT_TIMESTAMP WHERE id = ?",
+            )
+            .bind(STATUS_DELIVERED)
+            .bind(attempts)
+            .bind(i64::from(status))
+            .bind(delivery.id)
+            .execute(pool)
+            .await?;
+        }
+        Err(e) => {
+            let status = if attempts >= MAX_ATTEMPTS {
+                log::warn!(
+                    "Webhook 投递失败，已转入死信: delivery_id={}, error={}",
+                    delivery.id,
+                    e.message
+                );
+                STATUS_DEAD
+            } else {
+                STATUS_PENDING
+            };
+            sqlx::query(
+                "UPDATE webhook_deliveries SET status = ?, attempts = ?, last_status = ?, last_error = ?, next_attempt_at = datetime('now', ?) WHERE id = ?",
+            )
+            .bind(status)
+            .bind(attempts)
+            .bind(e.status.map(i64::from))
+            .bind(&e.message)
+            .bind(format!("+{} seconds", retry_delay_secs(attempts)))
+            .bind(delivery.id)
+            .execute(pool)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// 后台投递队列，并清理过期的已送达记录
+pub async fn run_delivery(pool: Pool<Sqlite>) {
+    loop {
+        if let Err(e) = deliver_due(&pool).await {
+            log::warn!("Webhook 投递失败: {}", e);
+        }
+        if let Err(e) = sqlx::query(
+            "DELETE FROM webhook_deliveries WHERE status = 'delivered' AND delivered_at < datetime('now', ?)",
+        )
+        .bind(format!("-{DELIVERED_RETENTION_DAYS} days"))
+        .execute(&pool)
+        .await
+        {
+            log::warn!("清理 Webhook 投递记录失败: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(DELIVERY_POLL_SECS)).await;
+    }
+}
+
+/// 计算签名，返回 sha256=<hex>（接收端用同一密钥对 "{timestamp}.{body}" 计算后比对）
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC 接受任意长度的密钥");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// 第 attempts 次失败后的等待时间（秒）
+fn retry_delay_secs(attempts: i64) -> i64 {
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    (RETRY_BASE_SECS << exponent).min(RETRY_MAX_SECS)
+}
+
+/// 发送失败的原因
+struct SendError {
+    status: Option<u16>,
+    message: String,
+}
+
+/// 共用的 HTTP 客户端，使用设置中的请求与连接超时
+fn http_client() -> Result<reqwest::Client> {
+    let network = settings::current().network;
+    let timeouts = (network.request_timeout_secs, network.connect_timeout_secs);
+    let mut cached = HTTP_CLIENT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_timeouts, client)) = cached.as_ref() {
+        if *cached_timeouts == timeouts {
+            return Ok(client.clone());
+        }
+    }
+    let client = create_http_client(&ProxyConfig::default(), timeouts.0)?;
+    *cached = Some((timeouts, client.clone()));
+    Ok(client)
+}
+
+/// 发送签名请求，成功时返回 HTTP 状态码
+async fn send(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    event: &str,
+    delivery_id: &str,
+    payload: &str,
+) -> std::result::Result<u16, SendError> {
+    let timestamp = chrono::Utc::now().timestamp();
+    let request_error = |e: reqwest::Error| SendError {
+        status: None,
+        message: format!("请求失败: {e}"),
+    };
+
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, event)
+        .header(DELIVERY_HEADER, delivery_id)
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, sign(secret, timestamp, payload))
+        .body(payload.to_string())
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(status.as_u16());
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(SendError {
+        status: Some(status.as_u16()),
+        message: format!(
+            "HTTP {}: {}",
+            status.as_u16(),
+            body.chars().take(MAX_ERROR_BODY).collect::<String>()
+        ),
+    })
+}
+
+/// 构建请求体
+fn build_payload(event: &str, data: serde_json::Value) -> Result<String> {
+    Ok(serde_json::to_string(&serde_json::json!({
+        "event": event,
+        "created_at": chrono::Utc::now().to_rfc3339(),
+        "data": data,
+    }))?)
+}
+
+async fn insert_delivery(
+    pool: &Pool<Sqlite>,
+    webhook_id: i64,
+    event: &str,
+    payload: &str,
+) -> Result<i64> {
+    let id = sqlx::query_scalar(
+        "INSERT INTO webhook_deliveries (webhook_id, event, payload) VALUES (?, ?, ?) RETURNING id",
+    )
+    .bind(webhook_id)
+    .bind(event)
+    .bind(payload)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// 校验端点参数，范围中的规则必须属于当前用户（账号归属由命令层校验）
+async fn validate(pool: &Pool<Sqlite>, user_id: i64, input: &WebhookInput) -> Result<()> {
+    if input.name.trim().is_empty() {
+        return Err(anyhow!("名称不能为空"));
+    }
+    let url = reqwest::Url::parse(input.url.trim()).map_err(|_| anyhow!("Webhook 地址无效"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("Webhook 地址只支持 http/https"));
+    }
+    if let Some(event) = input
+        .events
+        .iter()
+        .find(|e| !SUBSCRIBABLE_EVENTS.contains(&e.as_str()))
+    {
+        return Err(anyhow!("不支持的事件: {}", event));
+    }
+
+    if let Some(rule_id) = input.rule_id {
+        let owned =
+            sqlx::query_scalar::<_, i64>("SELECT id FROM mail_rules WHERE id = ? AND user_id = ?")
+                .bind(rule_id)
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?;
+        if owned.is_none() {
+            return Err(anyhow!("收件规则不存在"));
+        }
+    }
+    if let Some(tag_id) = input.tag_id {
+        tags::ensure_owned(pool, user_id, &[tag_id]).await?;
+    }
+    Ok(())
+}
+
+/// 生成 32 字节随机密钥（十六进制）
+fn generate_secret() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("secret", 1700000000, r#"{"event":"ping"}"#),
+            "sha256=4d39bd2442f073b6bc62e95d0297ce25475582a17389ab860abdc778fe1d9f77"
+        );
+        assert_ne!(
+            sign("secret", 1700000001, r#"{"event":"ping"}"#),
+            sign("secret", 1700000000, r#"{"event":"ping"}"#)
+        );
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay_secs(1), 30);
+        assert_eq!(retry_delay_secs(3), 120);
+        assert_eq!(retry_delay_secs(20), RETRY_MAX_SECS);
+    }
+    /// 收到的请求：(小写的请求头, 请求体)
+    type Received = (std::collections::HashMap<String, String>, String);
+
+    /// 本地 HTTP 端点：按顺序返回给定的状态码，并转发收到的请求
+    async fn spawn_endpoint(
+        statuses: Vec<u16>,
+    ) -> (String, tokio::sync::mpsc::UnboundedReceiver<Received>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut data = Vec::new();
+                let mut buf = [0u8; 4096];
+                let (head, body) = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&data).into_owned();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|l| {
+                                l.to_lowercase()
+                                    .strip_prefix("content-length:")
+                                    .map(|v| v.trim().to_string())
+                            })
+                            .and_then(|v| v.parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if body.len() >= length {
+                            break (head.to_string(), body.to_string());
+                        }
+                    }
+                };
+                let headers = head
+                    .lines()
+                    .skip(1)
+                    .filter_map(|l| l.split_once(':'))
+                    .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+                    .collect();
+                tx.send((headers, body)).unwrap();
+                let response = format!(
+                    "HTTP/1.1 {status} Status\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    async fn delivery_state(
+        pool: &Pool<Sqlite>,
+        delivery_id: i64,
+    ) -> (String, i64, Option<i64>, i64) {
+        sqlx::query_as(
+            "SELECT status, attempts, last_status, CAST(strftime('%s', next_attempt_at) - strftime('%s', 'now') AS INTEGER) FROM webhook_deliveries WHERE id = ?",
+        )
+        .bind(delivery_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_deliver_due_retries_and_dead_letters() {
+        let pool = db::test_pool().await;
+        let (url, mut requests) = spawn_endpoint(vec![500, 200, 503]).await;
+        let webhook_id: i64 = sqlx::query_scalar(
+            "INSERT INTO webhooks (user_id, name, url, secret) VALUES (1, 'hook', ?, 'secret') RETURNING id",
+        )
+        .bind(&url)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let delivery_id = enqueue(
+            &pool,
+            1,
+            webhook_id,
+            EVENT_PING,
+            serde_json::json!({ "n": 1 }),
+        )
+        .await
+        .unwrap();
+
+        // 失败后保持待投递，按退避时间重试
+        assert_eq!(deliver_due(&pool).await.unwrap(), 1);
+        let (headers, body) = requests.recv().await.unwrap();
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(headers[&EVENT_HEADER.to_lowercase()], EVENT_PING);
+        assert_eq!(
+            headers[&DELIVERY_HEADER.to_lowercase()],
+            delivery_id.to_string()
+        );
+        let timestamp: i64 = headers[&TIMESTAMP_HEADER.to_lowercase()].parse().unwrap();
+        assert_eq!(
+            headers[&SIGNATURE_HEADER.to_lowercase()],
+            sign("secret", timestamp, &body)
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()["data"]["n"],
+            1
+        );
+
+        let (status, attempts, last_status, wait) = delivery_state(&pool, delivery_id).await;
+        assert_eq!(
+            (status.as_str(), attempts, last_status),
+            (STATUS_PENDING, 1, Some(500))
+        );
+        assert!((RETRY_BASE_SECS - 5..=RETRY_BASE_SECS).contains(&wait));
+        assert_eq!(deliver_due(&pool).await.unwrap(), 0);
+
+        // 到期后重试成功，投递 ID 不变
+        sqlx::query(
+            "UPDATE webhook_deliveries SET next_attempt_at = datetime('now', '-1 seconds')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert_eq!(deliver_due(&pool).await.unwrap(), 1);
+        let (headers, _) = requests.recv().await.unwrap();
+        assert_eq!(
+            headers[&DELIVERY_HEADER.to_lowercase()],
+            delivery_id.to_string()
+        );
+        let (status, attempts, last_status, _) = delivery_state(&pool, delivery_id).await;
+        assert_eq!(
+            (status.as_str(), attempts, last_status),
+            (STATUS_DELIVERED, 2, Some(200))
+        );
+
+        // 最后一次尝试仍失败时转入死信
+        let dead_id = enqueue(&pool, 1, webhook_id, EVENT_PING, serde_json::json!({}))
+            .await
+            .unwrap();
+        sqlx::query("UPDATE webhook_deliveries SET attempts = ? WHERE id = ?")
+            .bind(MAX_ATTEMPTS - 1)
+            .bind(dead_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(deliver_due(&pool).await.unwrap(), 1);
+        requests.recv().await.unwrap();
+        let (status, attempts, last_status, _) = delivery_state(&pool, dead_id).await;
+        assert_eq!(
+            (status.as_str(), attempts, last_status),
+            (STATUS_DEAD, MAX_ATTEMPTS, Some(503))
+        );
+    }
+}
@@ -1,18 +1,18 @@
 
 import { useState, useEffect, useRef, useCallback } from 'react';
 import { createPortal } from 'react-dom';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../lib/api';
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { open, save } from '@tauri-apps/plugin-dialog';
-import { readTextFile, writeTextFile } from '@tauri-apps/plugin-fs';
+import { readTextFile } from '@tauri-apps/plugin-fs';
 import {
     Upload, FileText, Trash2, Inbox, Trash,
     Download, Clipboard, RefreshCw, X,
-    Eye, EyeOff, Paperclip
+    Paperclip
 } from 'lucide-react';
 import { useAppStore } from '../store/app';
 import MailDetailModal from './MailDetailModal';
-import type { EmailAccount, MailRecord } from '../types';
+import type { CredentialField, EmailAccount, MailRecord } from '../types';
 
 type MailFolder = 'INBOX' | 'JUNK';
 
@@ -85,7 +85,6 @@ export default function EmailManagement() {
     const [currentPage, setCurrentPage] = useState(1);
     const [pageSize, setPageSize] = useState(10);
     // 密码显示状态（默认隐藏）
-    const [showPasswords, setShowPasswords] = useState(false);
     const [toastMessage, setToastMessage] = useState<string | null>(null);
     // 通过更新 key 重新触发 toast 动画，避免同文案时不显示
     const [toastId, setToastId] = useState(0);
@@ -279,32 +278,34 @@ export default function EmailManagement() {
                 return; // 用户取消
             }
 
-            // 根据当前分隔符格式化导出内容
-            const exportContent = emails.map(email =>
-                `${email.email}${separator}${email.password}${separator}${email.client_id}${separator}${email.refresh_token}`
-            ).join('\n');
-
-            // 写入文件
-            await writeTextFile(filePath, exportContent);
-            showToast(`成功导出 ${emails.length} 个邮箱`);
+            // 凭据由后端按当前分隔符直接写入文件（记录审计日志）
+            const count = await invoke<number>('export_emails', {
+                emailIds: emails.map(email => email.id),
+                separator,
+                target: filePath,
+            });
+            showToast(`成功导出 ${count} 个邮箱`);
         } catch (error) {
             console.error('导出失败:', error);
             alert(`导出失败: ${error}`);
         }
     };
 
-    const normalizeFolder = (folder?: string): MailFolder => {
+    const normalizeFolder = (folder?: string): MailFolder | null => {
         if (!folder) {
             return 'INBOX';
         }
 
+        // 后端以统一标识保存文件夹（inbox/junk/其他文件夹的小写路径）
         const normalized = folder.trim().toLowerCase();
-        // 匹配垃圾邮件文件夹（Junk/Spam）
-        if (normalized.includes('junk') || normalized.includes('spam') || normalized.includes('垃圾')) {
+        if (normalized === 'junk') {
             return 'JUNK';
         }
+        if (normalized === 'inbox') {
+            return 'INBOX';
+        }
 
-        return 'INBOX';
+        return null;
     };
 
     const filterMailRecordsByFolder = (records: MailRecord[], folder: MailFolder) =>
@@ -335,8 +336,9 @@ export default function EmailManagement() {
             mailUpdateUnlistenRef.current();
         }
         mailUpdateUnlistenRef.current = await listen<MailUpdateEvent>('mail-updated', (event) => {
-            const { email_id, records, new_count, deleted_count } = event.payload;
-            if (email_id === account.id) {
+            const { email_id, folder: eventFolder, records, new_count, deleted_count } = event.payload;
+            // 同一邮箱可能同时监听多个文件夹，只处理当前查看的文件夹
+            if (email_id === account.id && eventFolder.toLowerCase() === folder.toLowerCase()) {
                 setMailViewerRecords(records);
                 // 收到更新时重置倒计时
                 setRefreshCountdown(5);
@@ -365,6 +367,7 @@ export default function EmailManagement() {
                 emailId: account.id,
                 folder: folder,
                 intervalSecs: 5,
+                persist: false,
             });
             // 启动倒计时
             setRefreshCountdown(5);
@@ -383,7 +386,7 @@ export default function EmailManagement() {
         // 停止邮件监听器
         if (mailViewerEmail) {
             try {
-                await invoke('stop_mail_watcher', { emailId: mailViewerEmail.id });
+                await invoke('stop_mail_watcher', { emailId: mailViewerEmail.id, persist: false });
             } catch (error) {
                 console.error('停止监听失败:', error);
             }
@@ -484,6 +487,17 @@ export default function EmailManagement() {
         }
     };
 
+    // 复制凭据：由后端写入剪贴板，凭据不经过前端
+    const handleCopyCredential = async (emailId: number, field: CredentialField) => {
+        try {
+            await invoke('copy_credential', { emailId, field });
+            showToast(field === 'password' ? '密码已复制' : '刷新令牌已复制');
+        } catch (error) {
+            console.error(error);
+            alert(`复制失败: ${error}`);
+        }
+    };
+
     const filteredEmails = emails.filter(e => e.email.toLowerCase().includes(searchQuery.toLowerCase()));
     const totalPages = Math.max(1, Math.ceil(filteredEmails.length / pageSize));
     const pageStart = (currentPage - 1) * pageSize;
@@ -652,18 +666,7 @@ export default function EmailManagement() {
                                 <th style={{ width: '60px' }}>#</th>
                                 <th>邮箱地址</th>
                                 <th>
-                                    <div className="flex items-center gap-2">
-                                        <span>密码</span>
-                                        <button
-                                            className="btn-icon-action password-toggle"
-                                            onClick={() => setShowPasswords((prev) => !prev)}
-                                            title={showPasswords ? '隐藏密码' : '显示密码'}
-                                            aria-label={showPasswords ? '隐藏密码' : '显示密码'}
-                                            type="button"
-                                        >
-                                            {showPasswords ? <EyeOff size={16} /> : <Eye size={16} />}
-                                        </button>
-                                    </div>
+                                    <span>密码</span>
                                 </th>
                                 <th>客户端ID</th>
                                 <th>刷新令牌</th>
@@ -706,11 +709,11 @@ export default function EmailManagement() {
                                             {email.email}
                                         </td>
                                         <td
-                                            className={`mono copyable-cell ${showPasswords ? '' : 'disabled'}`}
-                                            title={showPasswords ? '点击复制' : '显示后可复制'}
-                                            onClick={showPasswords ? () => handleCopyValue(email.password) : undefined}
+                                            className={`mono copyable-cell ${email.has_password ? '' : 'disabled'}`}
+                                            title={email.has_password ? '点击复制' : '未保存密码'}
+                                            onClick={email.has_password ? () => handleCopyCredential(email.id, 'password') : undefined}
                                         >
-                                            {showPasswords ? email.password : '******'}
+                                            {email.has_password ? '******' : '-'}
                                         </td>
                                         <td
                                             className="mono text-muted truncate copyable-cell"
@@ -720,11 +723,11 @@ export default function EmailManagement() {
                                             {email.client_id}
                                         </td>
                                         <td
-                                            className="mono text-muted truncate copyable-cell"
-                                            title={`${email.refresh_token}（点击复制）`}
-                                            onClick={() => handleCopyValue(email.refresh_token, `${email.refresh_token.substring(0, 20)}...`)}
+                                            className={`mono text-muted truncate copyable-cell ${email.has_refresh_token ? '' : 'disabled'}`}
+                                            title={email.has_refresh_token ? '点击复制' : '未保存刷新令牌'}
+                                            onClick={email.has_refresh_token ? () => handleCopyCredential(email.id, 'refresh_token') : undefined}
                                         >
-                                            {email.refresh_token.substring(0, 20)}...
+                                            {email.has_refresh_token ? '******' : '-'}
                                         </td>
                                         <td>
                                             <div className="flex items-center gap-2">
@@ -1,8 +1,7 @@
 import { X, Download } from 'lucide-react';
 import { useState } from 'react';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../lib/api';
 import { save } from '@tauri-apps/plugin-dialog';
-import { writeFile } from '@tauri-apps/plugin-fs';
 import { useAppStore } from '../store/app';
 import type { MailRecord, AttachmentInfo } from '../types';
 
@@ -33,8 +32,6 @@ export default function MailDetailModal({ isOpen, onClose, mail }: MailDetailMod
     const handleDownloadAttachment = async (attId: number, filename?: string) => {
         setLoading(true);
         try {
-            const content = await invoke<{ id: number; filename?: string; content_type?: string; content_base64: string }>('get_attachment_content', { attachmentId: attId });
-
             const savePath = await save({
                 defaultPath: filename || 'attachment',
                 filters: [{
@@ -48,8 +45,8 @@ export default function MailDetailModal({ isOpen, onClose, mail }: MailDetailMod
                 return;
             }
 
-            const binaryData = Uint8Array.from(atob(content.content_base64), c => c.charCodeAt(0));
-            await writeFile(savePath, binaryData);
+            // 由后端直接写入文件，避免大附件经 base64 传输
+            await invoke<string>('save_attachment', { attachmentId: attId, path: savePath });
 
             alert(t.mail.checkSuccess);
         } catch (error) {
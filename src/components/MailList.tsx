@@ -1,6 +1,6 @@
 import { Mail, RefreshCw, Paperclip } from 'lucide-react';
 import { useState, useEffect } from 'react';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../lib/api';
 import { useAppStore } from '../store/app';
 import type { EmailAccount, MailRecord, CheckResult } from '../types';
 import MailDetailModal from './MailDetailModal';
@@ -0,0 +1,18 @@
+import { invoke as tauriInvoke, InvokeArgs } from '@tauri-apps/api/core';
+import { useAuthStore } from '../store/auth';
+
+// 与后端 auth::SESSION_EXPIRED 保持一致
+const SESSION_EXPIRED = '会话无效或已过期，请重新登录';
+
+// 调用后端命令并附带当前会话令牌；会话失效时退出登录
+export async function invoke<T>(cmd: string, args: InvokeArgs = {}): Promise<T> {
+    const { token, logout } = useAuthStore.getState();
+    try {
+        return await tauriInvoke<T>(cmd, { ...(args as Record<string, unknown>), token });
+    } catch (err) {
+        if (typeof err === 'string' && err.includes(SESSION_EXPIRED)) {
+            logout();
+        }
+        throw err;
+    }
+}
@@ -1,17 +1,19 @@
 import { useEffect, useState, useRef } from 'react';
 import { createPortal } from 'react-dom';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../lib/api';
 import { openUrl } from '@tauri-apps/plugin-opener';
-import { Users, Download, X } from 'lucide-react';
+import { Users, Download, X, LogOut } from 'lucide-react';
 import EmailManagement from '../components/EmailManagement';
 import About from '../components/About';
 import { ModeToggle } from '../components/mode-toggle';
 import { checkForUpdate, type UpdateInfo } from '../utils/updateChecker';
+import { useAuthStore } from '../store/auth';
 import type { EmailAccount } from '../types';
 
 export default function Dashboard() {
     const [view, setView] = useState<'dashboard' | 'management' | 'about'>('dashboard');
     const [accounts, setAccounts] = useState<EmailAccount[]>([]);
+    const { user, logout } = useAuthStore();
 
     // 更新提示 toast 状态
     const [updateInfo, setUpdateInfo] = useState<UpdateInfo | null>(null);
@@ -36,6 +38,12 @@ export default function Dashboard() {
         setShowUpdateToast(false);
     };
 
+    // 注销会话并返回登录页
+    const handleLogout = async () => {
+        await invoke('logout').catch(console.error);
+        logout();
+    };
+
     useEffect(() => {
         // 切换到仪表盘视图时加载邮箱列表
         if (view !== 'dashboard') return;
@@ -87,7 +95,16 @@ export default function Dashboard() {
                 </nav>
 
                 <div className="header-right-actions">
+                    <span className="text-sm text-muted-foreground">{user?.username}</span>
                     <ModeToggle />
+                    <button
+                        className="p-2 rounded-md hover:bg-muted"
+                        onClick={handleLogout}
+                        title="退出登录"
+                        type="button"
+                    >
+                        <LogOut size={18} />
+                    </button>
                 </div>
             </header>
 
@@ -2,7 +2,7 @@ import { useState } from 'react';
 import { useNavigate, Link } from 'react-router-dom';
 import { invoke } from '@tauri-apps/api/core';
 import { useAuthStore } from '../store/auth';
-import { User } from '../types';
+import { LoginSession } from '../types';
 import { Mail } from 'lucide-react';
 
 export default function Login() {
@@ -12,7 +12,7 @@ export default function Login() {
     const [loading, setLoading] = useState(false);
 
     const navigate = useNavigate();
-    const setUser = useAuthStore((state) => state.setUser);
+    const login = useAuthStore((state) => state.login);
 
     const handleLogin = async (e: React.FormEvent) => {
         e.preventDefault();
@@ -20,8 +20,8 @@ export default function Login() {
         setLoading(true);
 
         try {
-            const user = await invoke<User>('login', { username, password });
-            setUser(user);
+            const session = await invoke<LoginSession>('login', { username, password });
+            login(session.user, session.token);
             navigate('/');
         } catch (err) {
             setError(err as string);
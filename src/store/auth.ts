@@ -3,16 +3,16 @@ import { User } from '../types';
 
 interface AuthState {
     user: User | null;
+    token: string | null;
     isAuthenticated: boolean;
-    setUser: (user: User | null) => void;
-    login: (user: User) => void;
+    login: (user: User, token: string) => void;
     logout: () => void;
 }
 
 export const useAuthStore = create<AuthState>((set) => ({
     user: null,
+    token: null,
     isAuthenticated: false,
-    setUser: (user) => set({ user, isAuthenticated: !!user }),
-    login: (user) => set({ user, isAuthenticated: true }),
-    logout: () => set({ user: null, isAuthenticated: false }),
+    login: (user, token) => set({ user, token, isAuthenticated: true }),
+    logout: () => set({ user: null, token: null, isAuthenticated: false }),
 }));
@@ -1,16 +1,23 @@
 export interface User {
     id: number;
     username: string;
+    is_admin: boolean;
+    created_at?: string;
+}
+
+export interface LoginSession {
+    token: string;
+    user: User;
 }
 
 export interface EmailAccount {
     id: number;
     email: string;
-    // 邮箱密码（仅前端显示/复制使用）
-    password: string;
+    // 凭据不随列表返回，需通过 reveal_credentials / copy_credential 获取
+    has_password: boolean;
     mail_type: string;
     client_id: string;
-    refresh_token: string;
+    has_refresh_token: boolean;
     last_check_time?: string;
 }
 
@@ -23,6 +30,19 @@ export interface MailRecord {
     content?: string;
     folder?: string;
     has_attachments: number;
+    is_read: boolean;
+    is_flagged: boolean;
+    is_answered: boolean;
+    // low / normal / high
+    importance?: string;
+    categories: string[];
+}
+
+export interface UnreadCount {
+    email_id: number;
+    folder: string;
+    unread_count: number;
+    total_count: number;
 }
 
 export interface AttachmentInfo {
@@ -31,13 +51,55 @@ export interface AttachmentInfo {
     filename?: string;
     content_type?: string;
     size?: number;
+    // 内容是否已下载到本地（未下载的附件在查看时从服务器获取）
+    is_downloaded: boolean;
+    // file / item（嵌入邮件，.eml）/ reference（云附件链接）
+    kind: string;
+    source_url?: string;
+}
+
+export interface AttachmentExportFilter {
+    email_ids?: number[];
+    sender?: string;
+    // YYYY-MM-DD，包含首尾两天
+    date_from?: string;
+    date_to?: string;
+    extensions?: string[];
+}
+
+export interface AttachmentExportResult {
+    exported: number;
+    failed: number;
+    target: string;
+    errors: string[];
+}
+
+export interface MailboxSelection {
+    email_ids?: number[];
+    folders?: string[];
+}
+
+export interface MailboxExportResult {
+    exported: number;
+    failed: number;
+    target: string;
+    errors: string[];
+}
+
+export interface MailboxImportResult {
+    email_id: number;
+    imported: number;
+    skipped: number;
+    failed: number;
+    errors: string[];
 }
 
 export interface AttachmentContent {
     id: number;
     filename?: string;
     content_type?: string;
-    content_base64: string;
+    size: number;
+    path: string;
 }
 
 export interface CheckResult {
@@ -59,3 +121,321 @@ export interface ImportResult {
     failed_count: number;
     failed_lines: string[];
 }
+
+export interface BackupInfo {
+    path: string;
+    size: number;
+    created_at: string;
+    automatic: boolean;
+}
+
+export interface BackupSettings {
+    auto_enabled: boolean;
+    interval_hours: number;
+    keep: number;
+}
+
+export interface BackupValidation {
+    valid: boolean;
+    schema_version: number;
+    account_count: number;
+    mail_count: number;
+    errors: string[];
+}
+
+export interface IntegrityReport {
+    ok: boolean;
+    messages: string[];
+    foreign_key_violations: number;
+}
+
+export interface VacuumResult {
+    size_before: number;
+    size_after: number;
+}
+
+export interface AccountStorage {
+    email_id: number;
+    email: string;
+    mail_count: number;
+    content_bytes: number;
+    attachment_count: number;
+    attachment_bytes: number;
+    pending_attachment_bytes: number;
+}
+
+export interface StorageReport {
+    database_bytes: number;
+    free_bytes: number;
+    blob_bytes: number;
+    blob_count: number;
+    accounts: AccountStorage[];
+}
+
+export interface VaultStatus {
+    enabled: boolean;
+    unlocked: boolean;
+    auto_lock_minutes: number;
+}
+
+export interface AccountCredentials {
+    email_id: number;
+    email: string;
+    password: string;
+    client_id: string;
+    refresh_token: string;
+}
+
+export type CredentialField = 'password' | 'refresh_token';
+
+export interface AuditEntry {
+    id: number;
+    // 如 account.delete、mail.sync_delete
+    action: string;
+    user_id?: number;
+    username?: string;
+    email_id?: number;
+    email?: string;
+    target_ids: number[];
+    target_count: number;
+    detail?: string;
+    created_at?: string;
+}
+
+export interface AuditQuery {
+    // 可只写前缀，如 account
+    action?: string;
+    email_id?: number;
+    date_from?: string;
+    date_to?: string;
+    limit?: number;
+    offset?: number;
+}
+
+export type AuditExportFormat = 'csv' | 'json';
+
+// 删除原因：user 用户删除，sync 同步时服务器上已不存在
+export type TrashReason = 'user' | 'sync';
+
+export interface TrashedAccount {
+    id: number;
+    email: string;
+    mail_type?: string;
+    deleted_at: string;
+    deleted_reason?: TrashReason;
+    mail_count: number;
+}
+
+export interface TrashedMail {
+    id: number;
+    email_id: number;
+    email: string;
+    subject?: string;
+    sender?: string;
+    received_time?: string;
+    folder?: string;
+    has_attachments: number;
+    deleted_at: string;
+    deleted_reason?: TrashReason;
+    // 所属账号也在回收站中
+    account_deleted: boolean;
+}
+
+export interface PurgeResult {
+    accounts: number;
+    mails: number;
+}
+
+export type RuleCondition =
+    | { type: 'sender'; pattern: string }
+    | { type: 'subject'; pattern: string }
+    | { type: 'body'; pattern: string }
+    | { type: 'folder'; folder: string }
+    | { type: 'account_tag'; tag_id: number }
+    // 扩展名（pdf）、MIME 类型（application/pdf）或主类型（image）
+    | { type: 'attachment_type'; kind: string };
+
+export type RuleAction =
+    | { type: 'tag'; category: string }
+    | { type: 'mark_read' }
+    | { type: 'move'; folder: string }
+    | { type: 'delete' }
+    | { type: 'forward'; to: string[]; comment?: string }
+    | { type: 'extract_code'; pattern?: string }
+    | { type: 'webhook'; webhook_id: number }
+    | { type: 'notify'; title?: string };
+
+export interface MailRuleInput {
+    name: string;
+    enabled?: boolean;
+    // 数值越小越先执行
+    priority?: number;
+    // true 全部满足，false 任一满足
+    match_all?: boolean;
+    stop_processing?: boolean;
+    conditions: RuleCondition[];
+    actions: RuleAction[];
+}
+
+export interface MailRule extends Required<MailRuleInput> {
+    id: number;
+    created_at?: string;
+    updated_at?: string;
+}
+
+export interface RuleActionOutcome {
+    action: string;
+    success: boolean;
+    message: string;
+}
+
+export interface RuleHit {
+    id: number;
+    rule_id: number;
+    rule_name: string;
+    mail_id: number;
+    email_id: number;
+    subject?: string;
+    extracted_code?: string;
+    results: RuleActionOutcome[];
+    created_at?: string;
+}
+
+export interface RuleTestResult {
+    scanned: number;
+    matches: {
+        mail_id: number;
+        email_id: number;
+        account: string;
+        subject?: string;
+        sender?: string;
+        received_time?: string;
+        folder?: string;
+        extracted_code?: string;
+    }[];
+}
+
+// rule-notification 事件
+export interface RuleNotification {
+    rule_id: number;
+    mail_id: number;
+    email_id: number;
+    title: string;
+    body: string;
+    code?: string;
+}
+
+export type WebhookEvent = 'mail.received' | 'code.extracted';
+
+export interface Webhook {
+    id: number;
+    name: string;
+    url: string;
+    enabled: boolean;
+    email_id?: number;
+    tag_id?: number;
+    rule_id?: number;
+    events: WebhookEvent[];
+    pending_count: number;
+    dead_count: number;
+    created_at?: string;
+    updated_at?: string;
+}
+
+export interface WebhookInput {
+    name: string;
+    url: string;
+    enabled?: boolean;
+    email_id?: number;
+    tag_id?: number;
+    rule_id?: number;
+    events?: WebhookEvent[];
+}
+
+// 签名密钥只在新建与重新生成时返回
+export interface WebhookSecret {
+    webhook_id: number;
+    secret: string;
+}
+
+export type WebhookDeliveryStatus = 'pending' | 'delivered' | 'dead';
+
+export interface WebhookDelivery {
+    id: number;
+    webhook_id: number;
+    webhook_name: string;
+    event: string;
+    payload: string;
+    status: WebhookDeliveryStatus;
+    attempts: number;
+    next_attempt_at?: string;
+    last_status?: number;
+    last_error?: string;
+    created_at?: string;
+    delivered_at?: string;
+}
+
+export interface WebhookDeliveryQuery {
+    webhook_id?: number;
+    status?: WebhookDeliveryStatus;
+    limit?: number;
+}
+
+export interface WebhookTestResult {
+    success: boolean;
+    status?: number;
+    duration_ms: number;
+    message: string;
+}
+
+export interface NotificationSettings {
+    enabled: boolean;
+    quiet_start?: string; // HH:MM，本地时间
+    quiet_end?: string;
+    group_threshold: number;
+}
+
+export interface NotificationMute {
+    id: number;
+    email_id?: number;
+    account?: string;
+    sender?: string;
+    muted_until?: string;
+    created_at?: string;
+}
+
+// email_id 与 sender 二选一；sender 为完整地址或 @域名
+export interface NotificationMuteInput {
+    email_id?: number;
+    sender?: string;
+    minutes?: number;
+}
+
+export interface BackgroundSettings {
+    run_in_background: boolean;
+    autostart: boolean;
+    watchers_suspended: boolean;
+}
+
+export interface AppSettings {
+    watcher: {
+        default_interval_secs: number;
+    };
+    sync: {
+        fetch_window: number;
+    };
+    network: {
+        request_timeout_secs: number;
+        connect_timeout_secs: number;
+    };
+    imap: {
+        host: string;
+        port: number;
+    };
+}
+
+// settings-changed 事件的 payload
+export interface SettingsChange {
+    settings: AppSettings;
+    changed: string[];
+}